@@ -0,0 +1,36 @@
+//! Thresholding and packing a large atlas, on one thread and on the whole
+//! pool, so `--jobs` has numbers behind it. Run with `cargo bench`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use mycp437generator::gray::GrayImage;
+use mycp437generator::packing::{BitOrder, WordSize, pack_bits};
+
+/// A 16x16 grid of 32x64 cells with a diagonal stroke in each, about the
+/// size of a 32px-wide CP437 atlas.
+fn atlas() -> GrayImage {
+    let mut image = GrayImage::new(512, 1024, 255);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            if (x % 32).abs_diff(y % 64 / 2) < 3 {
+                image.set(x, y, 0);
+            }
+        }
+    }
+    image
+}
+
+fn packing(c: &mut Criterion) {
+    let image = atlas();
+    let pack = || pack_bits(&image, 128, WordSize::Bits32, BitOrder::LsbFirst);
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    c.bench_function("pack 512x1024, 1 thread", |b| {
+        b.iter(|| single.install(pack))
+    });
+    c.bench_function("pack 512x1024, all threads", |b| b.iter(pack));
+}
+
+criterion_group!(benches, packing);
+criterion_main!(benches);
@@ -0,0 +1,101 @@
+//! `--emit-asm nasm|masm|gas`: the glyphs as assembler data to include in
+//! real-mode code, each row packed with its leftmost pixel in the top bit
+//! the way the VGA reads font memory, one line per glyph under a label,
+//! with equates for the glyph size.
+
+use anyhow::{Result, bail};
+use std::fmt::Write as _;
+
+use crate::tiles::AsmSyntax;
+use mycp437generator::packing::{self, BitOrder, WordSize};
+use mycp437generator::{Atlas, INK_THRESHOLD};
+
+/// How a dialect spells each part of the file.
+struct Dialect {
+    comment: &'static str,
+    /// Data directives for 8, 16, and 32-bit values.
+    directives: [&'static str; 3],
+}
+
+impl Dialect {
+    fn of(syntax: AsmSyntax) -> Dialect {
+        match syntax {
+            AsmSyntax::Nasm => Dialect {
+                comment: ";",
+                directives: ["db", "dw", "dd"],
+            },
+            AsmSyntax::Masm => Dialect {
+                comment: ";",
+                directives: ["DB", "DW", "DD"],
+            },
+            AsmSyntax::Gas => Dialect {
+                comment: "#",
+                directives: [".byte", ".2byte", ".4byte"],
+            },
+            AsmSyntax::Rgbds => unreachable!("RGBDS source is written from the Game Boy tiles"),
+        }
+    }
+}
+
+/// Format every glyph of `atlas` for `syntax`, its rows packed into the
+/// smallest of bytes, words, and doublewords that holds the glyph width.
+pub fn format_asm(syntax: AsmSyntax, atlas: &Atlas, name: &str) -> Result<String> {
+    let grid = &atlas.grid;
+    let (word_size, size) = match grid.glyph_width {
+        0..=8 => (WordSize::Bits8, 0),
+        9..=16 => (WordSize::Bits16, 1),
+        17..=32 => (WordSize::Bits32, 2),
+        width => bail!(
+            "Error: --emit-asm packs rows into at most 32 bits, and the glyphs are {} pixels wide",
+            width
+        ),
+    };
+    let dialect = Dialect::of(syntax);
+    let digits = word_size.bits() as usize / 4;
+    let hex = |value: u32| match syntax {
+        AsmSyntax::Masm => format!("0{:0digits$X}h", value),
+        _ => format!("0x{:0digits$X}", value),
+    };
+    let equate = |symbol: &str, value: u32| match syntax {
+        AsmSyntax::Nasm => format!("{}_{} equ {}", name, symbol, value),
+        AsmSyntax::Masm => format!("{}_{} EQU {}", name, symbol, value),
+        _ => format!(".equ {}_{}, {}", name, symbol, value),
+    };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "{} {} glyphs of {}x{}, one {} per row, leftmost pixel in the top bit",
+        dialect.comment,
+        atlas.glyphs.len(),
+        grid.glyph_width,
+        grid.glyph_height,
+        dialect.directives[size]
+    )
+    .unwrap();
+    writeln!(out, "{}", equate("width", grid.glyph_width)).unwrap();
+    writeln!(out, "{}", equate("height", grid.glyph_height)).unwrap();
+    writeln!(out, "{}", equate("glyphs", atlas.glyphs.len() as u32)).unwrap();
+    writeln!(out).unwrap();
+    match syntax {
+        AsmSyntax::Masm => writeln!(out, "{} LABEL BYTE", name).unwrap(),
+        _ => writeln!(out, "{}:", name).unwrap(),
+    }
+    for (i, glyph) in atlas.glyphs.iter().enumerate() {
+        let (x, y) = grid.glyph_origin(i);
+        let cell = atlas.image.crop(x, y, grid.glyph_width, grid.glyph_height);
+        let rows = packing::pack_bits(&cell, INK_THRESHOLD, word_size, BitOrder::MsbFirst);
+        let values: Vec<String> = rows.words.iter().map(|&row| hex(row)).collect();
+        writeln!(
+            out,
+            "    {} {} {} {} U+{:04X}",
+            dialect.directives[size],
+            values.join(", "),
+            dialect.comment,
+            i,
+            glyph.ch as u32
+        )
+        .unwrap();
+    }
+    Ok(out)
+}
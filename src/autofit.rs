@@ -0,0 +1,75 @@
+//! `--atlas-size`: the largest cell whose atlas fits a fixed texture
+//! budget, found by sizing, without drawing, one candidate width after
+//! another. The width and the height the font gives it must both fit, so
+//! each candidate is laid out in full rather than predicted.
+
+use anyhow::{Result, anyhow, bail};
+use log::debug;
+
+use mycp437generator::failure::{Categorize, Failure};
+use mycp437generator::gray::GrayImage;
+use mycp437generator::{Atlas, AtlasConfig};
+
+/// Parse a `<width>x<height>` budget.
+pub fn parse_budget(spec: &str) -> Result<(u32, u32)> {
+    let parsed = spec
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)));
+    match parsed {
+        Some((width, height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => bail!(
+            "Error: invalid --atlas-size '{}', expected <width>x<height> such as 256x256",
+            spec
+        ),
+    }
+}
+
+/// The widest cell, up to `config.cell_width`, whose atlas fits in
+/// `budget`, laying each candidate out with `generate`. Wider cells never
+/// make the atlas smaller, so the widths are binary searched.
+pub fn fit(
+    generate: impl Fn(&AtlasConfig) -> Result<Atlas>,
+    config: &AtlasConfig,
+    budget: (u32, u32),
+) -> Result<u32> {
+    let mut probe = config.clone();
+    probe.layout_only = true;
+    let (mut low, mut high) = (2 * config.padding + 1, config.cell_width);
+    let mut best = None;
+    while low <= high {
+        let width = low + (high - low) / 2;
+        probe.cell_width = width;
+        let layout = generate(&probe)?;
+        let fits = layout.content_width <= budget.0 && layout.content_height <= budget.1;
+        debug!(
+            "--atlas-size: {}px cells make a {}x{} atlas, which {}",
+            width,
+            layout.content_width,
+            layout.content_height,
+            if fits { "fits" } else { "doesn't fit" }
+        );
+        if fits {
+            best = Some(width);
+            low = width + 1;
+        } else {
+            high = width - 1;
+        }
+    }
+    best.ok_or_else(|| {
+        anyhow!(
+            "Error: no cell fits {} characters in a {}x{} atlas",
+            config.charmap.len(),
+            budget.0,
+            budget.1
+        )
+    })
+    .failure(Failure::Sizing)
+}
+
+/// Pad `atlas` with background out to `budget`, the slack left as a
+/// trailing margin right of and below the grid.
+pub fn pad(atlas: &mut Atlas, budget: (u32, u32)) {
+    let mut image = GrayImage::new(budget.0, budget.1, 255);
+    image.paste(&atlas.image, 0, 0);
+    atlas.image = image;
+}
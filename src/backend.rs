@@ -0,0 +1,1635 @@
+//! The boundary `--backend` swaps renderers across: measuring a glyph,
+//! rasterizing it, and saving an image. `generate` sizes the font, renders
+//! every cell, and lays out the atlas through it alone, so each option
+//! works the same whichever backend opened the fonts.
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use log::{debug, info};
+use std::path::{Path, PathBuf};
+
+use crate::coverage::{self, Coverage};
+use crate::failure::{Categorize, Failure};
+use crate::gray::{GrayImage, RgbImage};
+use crate::grid::Grid;
+use crate::warning::{self, Code, Warning};
+use crate::{
+    Atlas, AtlasConfig, CacheStats, CellStatus, GlyphInfo, HAlign, LcdOrder, Measure, MissingGlyph,
+    Overflow, Placement, RenderMode, ScaleAlgorithm, VAlign, boxes, check_atlas_size, check_gamma,
+    check_stretch, morphology, overrides, parse_halign_ranges, parse_index_set, parse_nudges,
+    pixel_art, sdf, stretch_glyphs, vertical_layout,
+};
+
+/// Font-wide metrics at one size, in pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct LineMetrics {
+    /// Baseline to the top of the line.
+    pub ascent: i32,
+    /// Baseline to the bottom of the line; negative below it.
+    pub descent: i32,
+}
+
+/// A glyph's ink box and advance in pixels, relative to the pen on the
+/// baseline with y pointing up, as SDL_ttf reports them.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphBox {
+    pub minx: i32,
+    pub maxx: i32,
+    pub miny: i32,
+    pub maxy: i32,
+    pub advance: i32,
+}
+
+/// A rasterized glyph: brightness (0 = full ink) over an image whose
+/// top-left is `left`, `top` from the pen on the baseline, y pointing down.
+/// The image may hold background around the ink, which
+/// `AtlasConfig::ink_crop` leaves out.
+pub struct Raster {
+    pub image: GrayImage,
+    /// For `RenderMode::Lcd`, the coverage of each subpixel, laid out as
+    /// `image`, which holds its luminance.
+    pub subpixel: Option<RgbImage>,
+    pub left: i32,
+    pub top: i32,
+}
+
+/// One font at any size.
+pub trait Backend {
+    fn line_metrics(&self, size: f32) -> Result<LineMetrics>;
+
+    /// The font's line height, which fallback fonts are sized to match.
+    fn line_height(&self, size: f32) -> Result<i32> {
+        let line = self.line_metrics(size)?;
+        Ok(line.ascent - line.descent)
+    }
+
+    /// Height of `text` set on one line, which the glyph box is given.
+    fn text_height(&self, _text: &str, size: f32) -> Result<u32> {
+        Ok(self.line_height(size)?.max(0) as u32)
+    }
+
+    /// `None` when the font has no glyph for `ch`.
+    fn measure_glyph(&self, ch: char, size: f32) -> Result<Option<GlyphBox>>;
+
+    /// `None` when the font can't draw `ch`; a glyph with nothing to draw,
+    /// such as a space, comes back as an empty image.
+    fn rasterize_glyph(&self, ch: char, size: f32) -> Result<Option<Raster>>;
+
+    /// Save `image` as a PNG at `path`.
+    fn save_image(&self, image: &GrayImage, path: &Path) -> Result<()>;
+
+    /// Counters of the font cache behind the backend, if it keeps one.
+    fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            open: 0,
+            loads: 0,
+            hits: 0,
+        }
+    }
+}
+
+/// Opens face `face_index` of the font file at a path, for `generate` to
+/// draw from: the primary font, then any fallback and secondary fonts.
+pub type Open<'a> = dyn Fn(&Path, u32) -> Result<Box<dyn Backend + 'a>> + 'a;
+
+/// An open font and the size it draws at.
+struct Face<'a> {
+    path: PathBuf,
+    backend: Box<dyn Backend + 'a>,
+    size: f32,
+}
+
+/// A glyph rendered for the atlas, along with the font that supplied it.
+struct RenderedGlyph {
+    index: usize,
+    ch: char,
+    /// The character actually rendered: `ch`, or the `--missing-glyph`
+    /// replacement when no font could render it
+    drawn: char,
+    raster: Option<Raster>,
+    /// Position in the font chain; 0 is the primary font
+    source: usize,
+}
+
+/// The atlas being drawn, with the subpixel coverage alongside it for
+/// `RenderMode::Lcd`.
+struct Canvas {
+    gray: GrayImage,
+    subpixel: Option<RgbImage>,
+}
+
+impl Canvas {
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, value: u8) {
+        self.gray.fill_rect(x, y, width, height, value);
+        if let Some(rgb) = &mut self.subpixel {
+            rgb.fill_rect(x, y, width, height, [value; 3]);
+        }
+    }
+
+    /// Copy the `source` rectangle (left, top, width, height) of `image`
+    /// to `at` in the glyph box whose top-left is `origin`, clipped to the
+    /// box's `size` so nothing spills into the next cell. Negative offsets
+    /// trim the overhang off the left and top edges.
+    fn blit(
+        &mut self,
+        image: &GrayImage,
+        subpixel: Option<&RgbImage>,
+        source: (u32, u32, u32, u32),
+        origin: (u32, u32),
+        size: (u32, u32),
+        at: (i32, i32),
+    ) {
+        let (x_offset, y_offset) = at;
+        let (dst_x, dst_y) = (x_offset.max(0), y_offset.max(0));
+        let width = (source.2 as i32 - (-x_offset).max(0))
+            .min(size.0 as i32 - dst_x)
+            .max(0) as u32;
+        let height = (source.3 as i32 - (-y_offset).max(0))
+            .min(size.1 as i32 - dst_y)
+            .max(0) as u32;
+        let (src_x, src_y) = (
+            source.0 + (-x_offset).max(0) as u32,
+            source.1 + (-y_offset).max(0) as u32,
+        );
+        let (dst_x, dst_y) = (origin.0 + dst_x as u32, origin.1 + dst_y as u32);
+        for y in 0..height {
+            for x in 0..width {
+                let value = image.get(src_x + x, src_y + y);
+                self.gray.set(dst_x + x, dst_y + y, value);
+                if let Some(rgb) = &mut self.subpixel {
+                    let pixel = subpixel.map_or([value; 3], |s| s.get(src_x + x, src_y + y));
+                    rgb.set(dst_x + x, dst_y + y, pixel);
+                }
+            }
+        }
+    }
+
+    /// Copy the whole of `image` to `origin`.
+    fn paste(&mut self, image: &GrayImage, origin: (u32, u32)) {
+        let size = (image.width, image.height);
+        self.blit(image, None, (0, 0, size.0, size.1), origin, size, (0, 0));
+    }
+}
+
+/// Where a glyph goes in its glyph box, worked out from its metrics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Position {
+    /// Pen position the glyph is drawn from, from the box's left.
+    pen_x: i32,
+    /// Top-left of what is blitted, from the box's top-left.
+    x_offset: i32,
+    y_offset: i32,
+    /// How far the ink reached left of the box before it was shifted back.
+    overhang: i32,
+    /// How much of that overhang the slack on the right couldn't take in.
+    clipped: i32,
+    /// Where the ink lands: left, top, right, and bottom, exclusive.
+    ink: (i32, i32, i32, i32),
+}
+
+/// Place a glyph with `metrics` in a `size` glyph box whose baseline is
+/// `baseline` rows down. The raster starts at `left`, `top` from the pen
+/// on the baseline; with `cropped` only its ink box is blitted, placed by
+/// the metrics, so renderer padding around the glyph can't shift it.
+///
+/// The pen is set by `align`, honoring the glyph's bearing (minx) rather
+/// than guessing from the raster's width. Ink left of the box (negative
+/// minx, as in 'j') is shifted right as far as the slack on the right
+/// allows. Vertically the glyph only moves when its ink sticks out of the
+/// box, and then only as far as the room on the opposite side allows.
+#[allow(clippy::too_many_arguments)]
+fn place(
+    metrics: &GlyphBox,
+    left: i32,
+    top: i32,
+    cropped: bool,
+    align: HAlign,
+    measure: Measure,
+    size: (u32, u32),
+    baseline: i32,
+) -> Position {
+    let (width, height) = (size.0 as i32, size.1 as i32);
+    let ink_width = metrics.maxx - metrics.minx;
+    let mut pen_x = match (align, measure, cropped) {
+        // Center the glyph's advance box in the cell
+        (HAlign::Center, Measure::Ink, false) => ((width - metrics.advance) / 2).max(0),
+        // Center the ink itself; if it is wider than the cell the overflow
+        // is split between both edges
+        (HAlign::Center, Measure::Ink, true) => (width - ink_width) / 2 - metrics.minx,
+        // Pen at the cell's left edge, exactly where a text renderer would
+        // put it, so box-drawing strokes meet across cells
+        (HAlign::Center, Measure::Advance, _) | (HAlign::Bearing, _, _) => 0,
+        (HAlign::Left, _, _) => -metrics.minx,
+    };
+    let overhang = -(pen_x + metrics.minx);
+    let mut clipped = 0;
+    if overhang > 0 {
+        let slack = (width - (pen_x + metrics.maxx)).max(0);
+        let shift = overhang.min(slack);
+        pen_x += shift;
+        clipped = overhang - shift;
+    }
+    // The raster starts where the backend put it, or at the ink when
+    // cropped
+    let x_offset = if cropped {
+        pen_x + metrics.minx
+    } else {
+        pen_x + left
+    };
+    let ink_top = baseline - metrics.maxy;
+    let ink_bottom = baseline - metrics.miny;
+    let shift_y = if ink_top < 0 {
+        (-ink_top).min((height - ink_bottom).max(0))
+    } else if ink_bottom > height {
+        -(ink_bottom - height).min(ink_top)
+    } else {
+        0
+    };
+    let y_offset = if cropped {
+        ink_top + shift_y
+    } else {
+        baseline + top + shift_y
+    };
+    Position {
+        pen_x,
+        x_offset,
+        y_offset,
+        overhang: overhang.max(0),
+        clipped,
+        ink: (
+            pen_x + metrics.minx,
+            ink_top + shift_y,
+            pen_x + metrics.maxx,
+            ink_bottom + shift_y,
+        ),
+    }
+}
+
+/// What the font draws for a codepoint no real font maps (the last
+/// private-use plane character), so glyphs that come back as its .notdef
+/// box can be told apart. `None` when that draws nothing visible, as some
+/// fonts leave .notdef empty.
+fn notdef_reference(face: &Face) -> Result<Option<GrayImage>> {
+    let raster = face.backend.rasterize_glyph('\u{10FFFD}', face.size)?;
+    Ok(raster
+        .map(|r| r.image)
+        .filter(|image| image.ink_bounds().is_some()))
+}
+
+/// Largest integer size at which `backend` has a line height no taller
+/// than `line_height`.
+fn size_for_line_height(backend: &dyn Backend, line_height: i32) -> Result<f32> {
+    let mut best = 1.0_f32;
+    for size in 1..128 {
+        let size = size as f32;
+        if backend.line_height(size)? > line_height {
+            break;
+        }
+        best = size;
+    }
+    Ok(best)
+}
+
+/// Size the font, render every character of `config.charmap` with the
+/// fonts `open` opens, and lay out, scale, and pad the atlas.
+pub fn generate(config: &AtlasConfig, open: &Open) -> Result<Atlas> {
+    if config.render_mode == RenderMode::Lcd {
+        let unsupported = [
+            (config.sdf_spread.is_some(), "--sdf"),
+            (config.scale > 1, "--scale"),
+            (
+                config.stretch_width > 1 || config.stretch_height > 1,
+                "--stretch-width or --stretch-height",
+            ),
+            (config.gamma != 1.0, "--gamma"),
+            (config.embolden > 0, "--embolden"),
+            (config.thin > 0, "--thin"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!(
+                "Error: {} works on gray glyphs and can't be combined with --render-mode lcd",
+                option
+            );
+        }
+    }
+    if let Some(spread) = config.sdf_spread {
+        return generate_sdf(config, spread, open);
+    }
+    let charmap = &config.charmap;
+    // Glyphs are sized to the box left inside the padding
+    let Some(requested_width) = config
+        .cell_width
+        .checked_sub(2 * config.padding)
+        .filter(|&w| w > 0)
+    else {
+        bail!(
+            "Error: --padding {} leaves no room for glyphs in a {}px cell",
+            config.padding,
+            config.cell_width
+        );
+    };
+
+    // Index -> CP437 shape for every cell --synthesize-boxes draws
+    let synthesized: Vec<Option<u8>> = charmap
+        .chars()
+        .iter()
+        .map(|&ch| {
+            config
+                .synthesize_boxes
+                .then(|| boxes::synthesized_as(ch))
+                .flatten()
+        })
+        .collect();
+    let overrides = match &config.override_dir {
+        Some(dir) => overrides::load(dir, charmap.len())?,
+        None => (0..charmap.len()).map(|_| None).collect(),
+    };
+    let secondary_set = match &config.secondary_font {
+        Some(_) if config.secondary_set.trim().is_empty() => {
+            bail!("Error: --secondary-font needs at least one --secondary-range")
+        }
+        Some(_) => parse_index_set(&config.secondary_set, charmap.len())
+            .context("Invalid --secondary-range")?,
+        None if !config.secondary_set.trim().is_empty() => {
+            bail!("Error: --secondary-range needs a --secondary-font")
+        }
+        None => vec![false; charmap.len()],
+    };
+    let mut measure_set =
+        parse_index_set(&config.measure_set, charmap.len()).context("Invalid --measure-set")?;
+    let nudges = parse_nudges(&config.nudges, charmap.len())?;
+    let halign = parse_halign_ranges(config.halign, &config.halign_ranges, charmap.len())?;
+    // Synthesized and overridden characters never come from the font, so
+    // they must not influence its size either, and the secondary font's
+    // are sized separately.
+    for i in 0..charmap.len() {
+        if synthesized[i].is_some() || overrides[i].is_some() || secondary_set[i] {
+            measure_set[i] = false;
+        }
+    }
+    if !(1..=16).contains(&config.scale) {
+        bail!(
+            "Error: --scale must be between 1 and 16, got {}",
+            config.scale
+        );
+    }
+    // The EPX filters scale by a fixed factor, so they are applied repeatedly
+    let filter_passes = match config.scale_algorithm {
+        ScaleAlgorithm::Nearest => 0,
+        ScaleAlgorithm::Scale2x => match config.scale {
+            2 => 1,
+            4 => 2,
+            8 => 3,
+            16 => 4,
+            _ => bail!("Error: --scale-algorithm scale2x needs --scale 2, 4, 8, or 16"),
+        },
+        ScaleAlgorithm::Scale3x => match config.scale {
+            3 => 1,
+            9 => 2,
+            _ => bail!("Error: --scale-algorithm scale3x needs --scale 3 or 9"),
+        },
+    };
+    check_stretch(config)?;
+    check_gamma(config)?;
+    if config.columns == 0 {
+        bail!("Error: --columns must be at least 1");
+    }
+    // Offsets are i32, so the limit also bounds every coordinate
+    if config.max_atlas_dimension == 0 || config.max_atlas_dimension > i32::MAX as u32 {
+        bail!(
+            "Error: --max-atlas-dimension must be between 1 and {}, got {}",
+            i32::MAX,
+            config.max_atlas_dimension
+        );
+    }
+    // The width is fixed by the request alone, so an impossible one fails
+    // before the size search rather than after rendering every glyph
+    let glyph_width = config.cell_width.saturating_sub(2 * config.padding) as u64;
+    let stretch_extra = glyph_width * (config.stretch_width as u64 - 1);
+    check_atlas_size(
+        config.columns as u64 * (config.cell_width as u64 + stretch_extra + config.gutter as u64),
+        0,
+        config,
+    )?;
+    if !(0.0..=1.0).contains(&config.max_blank_fraction) {
+        bail!(
+            "Error: --max-blank-fraction must be between 0 and 1, got {}",
+            config.max_blank_fraction
+        );
+    }
+
+    let primary = open(&config.font_path, config.face_index)?;
+    if !config.variations.is_empty() {
+        info!("Variation: {}", config.variations.join(", "));
+    }
+
+    // Find the widest glyph across the characters in the measure set
+    let widest = |font_size: f32| -> Result<u32> {
+        let mut max_width = 0;
+        for i in (0..charmap.len()).filter(|&i| measure_set[i]) {
+            let Some(metrics) = primary.measure_glyph(charmap.get(i), font_size)? else {
+                continue; // Character not in font, skip
+            };
+            max_width = max_width.max(config.measure.width(&metrics).max(0) as u32);
+        }
+        Ok(max_width)
+    };
+
+    // --- Step 1: Find the right font size ---
+    // Iterate font sizes until the widest measured glyph's width == font_width.
+    let mut font_size = 1.0_f32;
+    let mut max_width: u32 = 0;
+    if let Some(size) = config.font_size {
+        // A pinned size keeps the requested width as a minimum
+        font_size = size;
+        max_width = widest(size)?.max(requested_width);
+    }
+    for iteration in (1..128).take_while(|_| config.font_size.is_none()) {
+        font_size = iteration as f32; // Start with integer sizes for faster convergence
+        max_width = widest(font_size)?;
+
+        if max_width >= requested_width {
+            debug!(
+                "Iteration {}: font_size={:.4}pt, max_width={} == font_width={} — done",
+                iteration, font_size, max_width, requested_width
+            );
+            break;
+        }
+        debug!(
+            "Iteration {}: font_size={:.4}pt, max_width={} < font_width={}",
+            iteration, font_size, max_width, requested_width
+        );
+    }
+    if max_width == 0 {
+        return Err(anyhow!(
+            "Error: no glyph in the measure set has any width; is the font valid?"
+        ))
+        .failure(Failure::Sizing);
+    }
+
+    // --- Step 2: Derive cell dimensions at the final size ---
+    let line = primary
+        .line_metrics(font_size)
+        .context("Failed to load font with adjusted size")?;
+    let line_height = primary.line_height(font_size)?;
+    let font_width = max_width;
+
+    // Fallback fonts are loaded once, at the largest size whose line height
+    // fits the primary font's, so substituted glyphs match the cell.
+    let mut fonts = vec![Face {
+        path: config.font_path.clone(),
+        backend: primary,
+        size: font_size,
+    }];
+    for path in &config.fallback_fonts {
+        let backend = open(path, 0)?;
+        let size = size_for_line_height(backend.as_ref(), line_height)?;
+        debug!("Fallback font {} at {:.4}pt", path.display(), size);
+        fonts.push(Face {
+            path: path.clone(),
+            backend,
+            size,
+        });
+    }
+    // The secondary font goes last, where the fallback search skips it; it
+    // is sized the same way so its glyphs span the same cell height.
+    let secondary = match &config.secondary_font {
+        Some(path) => {
+            let backend = open(path, 0)?;
+            let size = size_for_line_height(backend.as_ref(), line_height)?;
+            info!("Secondary font {} at {:.4}pt", path.display(), size);
+            fonts.push(Face {
+                path: path.clone(),
+                backend,
+                size,
+            });
+            Some(fonts.len() - 1)
+        }
+        None => None,
+    };
+    let primary = &fonts[0];
+
+    // --- Step 3: Render every glyph, find true cell height, build atlas ---
+    // Each raster is placed from the pen on the baseline; the blit below
+    // puts that baseline on the cell's shared baseline row. The cell is as
+    // tall as all the chars set in one string would be, which the backend
+    // measures without rendering the string.
+    let mut rendered: Vec<RenderedGlyph> = Vec::with_capacity(charmap.len());
+
+    let all_chars_string = charmap.chars().iter().collect::<String>();
+    let text_height = primary
+        .backend
+        .text_height(&all_chars_string, font_size)
+        .context("Failed to measure all chars in one string")?;
+    if text_height == 0 {
+        bail!(
+            "Error: all rendered glyphs have zero height. This likely means the font size is too small or the font file is invalid."
+        );
+    }
+    // Rows the primary font's baseline sits below the top of its line
+    let (font_height, baseline_row) =
+        vertical_layout(config, text_height, line.ascent, line.descent)?;
+    let line_offset = baseline_row - line.ascent;
+    let mut grid = Grid {
+        padding: config.padding,
+        gutter: config.gutter,
+        ..Grid::new(
+            charmap.len(),
+            config.columns,
+            config.column_major,
+            font_width,
+            font_height,
+        )
+    };
+    let (stretched_width, stretched_height) = (
+        grid.glyph_width as u64 * config.stretch_width as u64,
+        grid.glyph_height as u64 * config.stretch_height as u64,
+    );
+    check_atlas_size(
+        grid.columns as u64 * (stretched_width + 2 * grid.padding as u64 + grid.gutter as u64),
+        grid.rows as u64 * (stretched_height + 2 * grid.padding as u64 + grid.gutter as u64),
+        config,
+    )?;
+
+    // What each font in the chain draws for a codepoint it lacks, so glyphs
+    // that come back as the .notdef box can be told apart from real ones
+    let notdef = fonts
+        .iter()
+        .map(notdef_reference)
+        .collect::<Result<Vec<_>>>()?;
+    let mut tofu = Vec::new();
+
+    for (i, &ch) in charmap.chars().iter().enumerate() {
+        // Take the glyph from the first font in the chain that really has
+        // it; fonts that answer with their .notdef box don't count. The
+        // secondary font comes first for its own indices, and is not part
+        // of the chain for any others.
+        let chain_len = secondary.unwrap_or(fonts.len());
+        let chain = secondary
+            .filter(|_| secondary_set[i])
+            .into_iter()
+            .chain(0..chain_len);
+        let mut found = None;
+        for source in chain {
+            let face = &fonts[source];
+            if face.backend.measure_glyph(ch, face.size)?.is_none() {
+                continue;
+            }
+            let Some(raster) = face.backend.rasterize_glyph(ch, face.size)? else {
+                continue;
+            };
+            if let Some(reference) = &notdef[source]
+                && raster.image == *reference
+            {
+                debug!(
+                    "'{}' (index {}) renders as .notdef in {}",
+                    ch,
+                    i,
+                    face.path.display()
+                );
+                continue;
+            }
+            found = Some((raster, source));
+            break;
+        }
+        // Nothing in the chain covers it: keep whatever the primary font
+        // draws, unless that is tofu, which would be mistaken for a glyph
+        let (mut raster, mut source) = match found {
+            Some((raster, source)) => (Some(raster), source),
+            None => {
+                let raster = primary
+                    .backend
+                    .rasterize_glyph(ch, font_size)?
+                    .filter(|r| r.image.width > 0 && r.image.height > 0);
+                match (&raster, &notdef[0]) {
+                    (Some(r), Some(reference)) if r.image == *reference => {
+                        tofu.push(i);
+                        (None, 0)
+                    }
+                    _ => (raster, 0),
+                }
+            }
+        };
+        // A `char:` placeholder comes from the first font that has it
+        let mut drawn = ch;
+        if raster.is_none()
+            && let MissingGlyph::Char(replacement) = config.missing_glyph
+        {
+            for (n, face) in fonts.iter().enumerate() {
+                if face
+                    .backend
+                    .measure_glyph(replacement, face.size)?
+                    .is_none()
+                {
+                    continue;
+                }
+                if let Some(r) = face.backend.rasterize_glyph(replacement, face.size)? {
+                    (raster, source, drawn) = (Some(r), n, replacement);
+                    break;
+                }
+            }
+        }
+        if source > 0 && Some(source) != secondary {
+            debug!(
+                "'{}' (index {}) supplied by fallback font {}",
+                ch,
+                i,
+                fonts[source].path.display()
+            );
+        }
+        rendered.push(RenderedGlyph {
+            index: i,
+            ch,
+            drawn,
+            raster,
+            source,
+        });
+    }
+    let mut supplied = vec![0; fonts.len()];
+    let mut from_fallback = vec![false; charmap.len()];
+    for glyph in rendered.iter().filter(|g| g.raster.is_some()) {
+        if glyph.drawn == glyph.ch {
+            supplied[glyph.source] += 1;
+        }
+        from_fallback[glyph.index] = glyph.source > 0 && Some(glyph.source) != secondary;
+    }
+    if fonts.len() > 1 {
+        let list = fonts
+            .iter()
+            .zip(&supplied)
+            .enumerate()
+            .map(|(n, (face, count))| {
+                let role = match n {
+                    0 => "primary",
+                    _ if Some(n) == secondary => "secondary",
+                    _ => "fallback",
+                };
+                format!("{} from {} ({})", count, face.path.display(), role)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        info!("Glyph sources: {}", list);
+    }
+    if !tofu.is_empty() {
+        let list = tofu
+            .iter()
+            .map(|&i| format!("{} '{}'", i, charmap.get(i)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let fate = if config.missing_glyph == MissingGlyph::Blank {
+            "left blank"
+        } else {
+            "given the --missing-glyph placeholder"
+        };
+        warning::emit_all(
+            format!(
+                "{} glyph(s) render as the font's .notdef box and were {}: {}",
+                tofu.len(),
+                fate,
+                list
+            ),
+            tofu.iter()
+                .map(|&i| {
+                    let ch = charmap.get(i);
+                    Warning::new(
+                        Code::GlyphNotdef,
+                        format!(
+                            "'{}' (index {}) renders as the font's .notdef box and was {}",
+                            ch, i, fate
+                        ),
+                    )
+                    .glyph(i, ch)
+                })
+                .collect(),
+        );
+    }
+
+    // Some broken fonts report success for every render but produce rasters
+    // that are entirely background. Printable ASCII must have ink, so use it
+    // as the canary before emitting a blank atlas with a success exit code.
+    // Tables without any ASCII (a Hiragana range, say) skip the check.
+    let canary = '!'..='~';
+    let canary_count = charmap
+        .chars()
+        .iter()
+        .filter(|c| canary.contains(c))
+        .count();
+    let blank_glyphs: Vec<char> = rendered
+        .iter()
+        .filter(|glyph| canary.contains(&glyph.ch))
+        .filter(|glyph| {
+            glyph
+                .raster
+                .as_ref()
+                .is_none_or(|r| r.image.ink_bounds().is_none())
+        })
+        .map(|glyph| glyph.ch)
+        .collect();
+    let blank_fraction = blank_glyphs.len() as f32 / canary_count.max(1) as f32;
+    if blank_fraction > config.max_blank_fraction {
+        let message = format!(
+            "{} of {} printable ASCII glyphs rendered blank ({:.0}% > {:.0}%): {}",
+            blank_glyphs.len(),
+            canary_count,
+            blank_fraction * 100.0,
+            config.max_blank_fraction * 100.0,
+            blank_glyphs.iter().collect::<String>()
+        );
+        if !config.allow_blank_atlas {
+            return Err(anyhow!(
+                "Error: {}. The font's outlines may be broken for this renderer; try a different font, or pass --allow-blank-atlas to write the atlas anyway.",
+                message
+            ))
+            .failure(Failure::Render);
+        }
+        warning::emit(
+            Warning::new(
+                Code::BlankAtlas,
+                format!("{} (continuing due to --allow-blank-atlas)", message),
+            )
+            .data(serde_json::json!({
+                "blank": blank_glyphs.len(),
+                "printable": canary_count,
+                "max_blank_fraction": config.max_blank_fraction,
+            })),
+        );
+    }
+
+    info!(
+        "Final: font_size={:.4}pt, ascent={}, descent={}, height={}, max_width={}",
+        font_size, line.ascent, line.descent, line_height, max_width
+    );
+    info!(
+        "Cell: {}x{} (width specified, height derived)",
+        grid.cell_width(),
+        grid.cell_height()
+    );
+    if config.padding > 0 || config.gutter > 0 {
+        info!(
+            "Glyph box: {}x{}, padding {}, gutter {}, stride {}x{}",
+            font_width,
+            font_height,
+            config.padding,
+            config.gutter,
+            grid.stride_x(),
+            grid.stride_y()
+        );
+    }
+    info!(
+        "Characters: {} ({}), grid {}x{}{}",
+        charmap.len(),
+        charmap.label(),
+        grid.columns,
+        grid.rows,
+        if grid.column_major {
+            ", column-major"
+        } else {
+            ""
+        }
+    );
+    if config.layout_only {
+        let grid = grid
+            .stretched(config.stretch_width, config.stretch_height)
+            .scaled(config.scale);
+        let (content_width, content_height) = (grid.width(), grid.height());
+        let (atlas_width, atlas_height) = if config.pot {
+            (
+                content_width.next_power_of_two(),
+                content_height.next_power_of_two(),
+            )
+        } else {
+            (content_width, content_height)
+        };
+        let glyphs = charmap
+            .chars()
+            .iter()
+            .enumerate()
+            .map(|(i, &ch)| {
+                let (x, y) = grid.glyph_origin(i);
+                GlyphInfo {
+                    ch,
+                    rect: (x, y, grid.glyph_width, grid.glyph_height),
+                    status: CellStatus::Skipped,
+                    synthesized: false,
+                    fallback: false,
+                    placement: None,
+                }
+            })
+            .collect();
+        return Ok(Atlas {
+            image: GrayImage::new(atlas_width, atlas_height, 255),
+            grid,
+            content_width,
+            content_height,
+            font_size,
+            baseline: (baseline_row + config.baseline_shift).max(0) as u32
+                * config.stretch_height
+                * config.scale,
+            line: Some(line),
+            width_fit: Some((font_width, requested_width)),
+            glyphs,
+            coverage: Coverage::default(),
+            cache_stats: primary.backend.cache_stats(),
+            subpixel: None,
+        });
+    }
+    if !charmap.overrides().is_empty() {
+        let list = charmap
+            .overrides()
+            .iter()
+            .map(|&i| format!("{} '{}'", i, charmap.get(i)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        info!("Charmap overrides: {}", list);
+    }
+
+    let overflow_name = config
+        .overflow
+        .to_possible_value()
+        .expect("no skipped variants")
+        .get_name()
+        .to_string();
+
+    // Glyphs excluded from the measurement may be wider than the cell; they
+    // are handled by the overflow policy during the blit, so name them here.
+    let mut oversized: Vec<(usize, char, i32)> = Vec::new();
+    for i in (0..charmap.len())
+        .filter(|&i| !measure_set[i])
+        .filter(|&i| synthesized[i].is_none() && overrides[i].is_none() && !secondary_set[i])
+    {
+        let ch = charmap.get(i);
+        let Some(metrics) = primary.backend.measure_glyph(ch, font_size)? else {
+            continue;
+        };
+        let width = config.measure.width(&metrics);
+        if width > font_width as i32 {
+            oversized.push((i, ch, width));
+        }
+    }
+    if !oversized.is_empty() {
+        let list = oversized
+            .iter()
+            .map(|(i, ch, _)| format!("'{}' ({})", ch, i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warning::emit_all(
+            format!(
+                "{} glyph(s) outside the measure set exceed the cell width ({}), --overflow {} applies: {}",
+                oversized.len(),
+                font_width,
+                overflow_name,
+                list
+            ),
+            oversized
+                .iter()
+                .map(|&(i, ch, width)| {
+                    Warning::new(
+                        Code::GlyphOversized,
+                        format!(
+                            "'{}' (index {}) is {}px wide, over the {}px cell; --overflow {} applies",
+                            ch, i, width, font_width, overflow_name
+                        ),
+                    )
+                    .glyph(i, ch)
+                    .data(serde_json::json!({ "width": width, "cell_width": font_width }))
+                })
+                .collect(),
+        );
+    }
+
+    // --- Step 3: Draw each character into the grid atlas ---
+    let mut atlas = Canvas {
+        gray: GrayImage::new(grid.width(), grid.height(), 255),
+        subpixel: (config.render_mode == RenderMode::Lcd)
+            .then(|| RgbImage::new(grid.width(), grid.height(), [255; 3])),
+    };
+
+    let overridden = overrides.iter().flatten().count();
+    if let Some(dir) = &config.override_dir
+        && overridden > 0
+    {
+        info!(
+            "Overrides: {} glyph(s) drawn from {} (--override-fit {})",
+            overridden,
+            dir.display(),
+            config
+                .override_fit
+                .to_possible_value()
+                .expect("no skipped variants")
+                .get_name()
+        );
+    }
+
+    let mut overflowed: Vec<(usize, char, &str)> = Vec::new();
+    let mut status = vec![CellStatus::Ok; charmap.len()];
+    let mut coverage = coverage::Coverage::default();
+    let mut placement: Vec<Option<Placement>> = vec![None; charmap.len()];
+    let glyph_box = (font_width, font_height);
+
+    // Second pass: blit every rendered glyph into the atlas, each moved so
+    // its baseline lands on the cell's baseline row and shifted only when
+    // its ink would otherwise leave the cell.
+    for glyph in &rendered {
+        let (i, ch) = (glyph.index, glyph.ch);
+        let origin = grid.glyph_origin(i);
+
+        if let Some(item) = &overrides[i] {
+            atlas.paste(
+                &overrides::fit(item, config.override_fit, font_width, font_height),
+                origin,
+            );
+            status[i] = CellStatus::Overridden;
+            continue;
+        }
+
+        if let Some(shape) = synthesized[i]
+            && let Some(rects) = boxes::shape(shape, font_width, font_height)
+        {
+            for (x, y, w, h) in rects {
+                atlas.fill(origin.0 + x, origin.1 + y, w, h, 0);
+            }
+            continue;
+        }
+
+        // Cells no font could render get the drawn placeholder, if any
+        let mut missing = |status: &mut CellStatus, atlas: &mut Canvas| {
+            coverage.missing.push(coverage::Entry::new(i, ch));
+            *status = CellStatus::Skipped;
+            if let Some(pattern) = config.missing_glyph.pattern(font_width, font_height) {
+                atlas.paste(&pattern, origin);
+                coverage.placeholder.push(coverage::Entry::new(i, ch));
+                *status = CellStatus::Placeholder;
+            }
+        };
+
+        let Some(raster) = &glyph.raster else {
+            debug!("Skipping '{}' (index {}) — not in font", ch, i);
+            missing(&mut status[i], &mut atlas);
+            continue;
+        };
+
+        let face = &fonts[glyph.source];
+        let Some(metrics) = face.backend.measure_glyph(glyph.drawn, face.size)? else {
+            debug!(
+                "Failed to get metrics for char '{}' (index {}) — skipping",
+                ch, i
+            );
+            missing(&mut status[i], &mut atlas);
+            continue;
+        };
+        if glyph.drawn != ch {
+            coverage.missing.push(coverage::Entry::new(i, ch));
+            coverage.placeholder.push(coverage::Entry::new(i, ch));
+            status[i] = CellStatus::Placeholder;
+        }
+
+        if metrics.miny == metrics.maxy || metrics.minx == metrics.maxx {
+            debug!(
+                "Char '{}' (index {}) has a zero dimension (miny == maxy == {} or minx == maxx == {}) — skipping",
+                ch, i, metrics.miny, metrics.minx
+            );
+            if !ch.is_whitespace() && !ch.is_control() {
+                coverage.zero_sized.push(coverage::Entry::new(i, ch));
+            }
+            continue;
+        }
+
+        // With --ink-crop the blit source is the tight ink box and placement
+        // comes from the metrics, so renderer padding around the glyph (some
+        // SDL_ttf builds add a 1px border) can't shift it.
+        let ink_box = if config.ink_crop {
+            match raster.image.ink_bounds() {
+                Some(r) => Some(r),
+                None => {
+                    debug!("Skipping '{}' (index {}) — no ink after cropping", ch, i);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        let source = ink_box.unwrap_or((0, 0, raster.image.width, raster.image.height));
+
+        // Secondary glyphs keep their own font's baseline, so a font drawn
+        // edge to edge fills the cell as designed; every other glyph sits on
+        // the primary font's, so descenders and underscores keep their place
+        // below it.
+        let baseline = if Some(glyph.source) == secondary {
+            face.backend.line_metrics(face.size)?.ascent
+        } else {
+            line.ascent
+        } + line_offset
+            + config.baseline_shift;
+        let position = place(
+            &metrics,
+            raster.left,
+            raster.top,
+            ink_box.is_some(),
+            halign[i],
+            config.measure,
+            glyph_box,
+            baseline,
+        );
+        if position.clipped > 0 && config.overflow == Overflow::Clip {
+            status[i] = CellStatus::Clipped;
+            warning::emit(
+                Warning::new(
+                    Code::GlyphClipped,
+                    format!(
+                        "char '{}' (index {}) extends {}px left of the cell (minx={}); {}px clipped",
+                        ch, i, position.overhang, metrics.minx, position.clipped
+                    ),
+                )
+                .glyph(i, ch)
+                .data(serde_json::json!({
+                    "edge": "left",
+                    "overhang": position.overhang,
+                    "clipped": position.clipped,
+                })),
+            );
+        }
+        let Position {
+            mut pen_x,
+            mut x_offset,
+            mut y_offset,
+            ink: (mut ink_left, mut ink_top, mut ink_right, mut ink_bottom),
+            ..
+        } = position;
+        placement[i] = Some(Placement {
+            minx: metrics.minx,
+            maxx: metrics.maxx,
+            miny: metrics.miny,
+            maxy: metrics.maxy,
+            advance: metrics.advance,
+            pen_x,
+            x_offset,
+            y_offset,
+            surface_width: source.2,
+            surface_height: source.3,
+        });
+
+        // Judge overflow by where the ink lands, so a raster that is merely
+        // padded wider than the cell doesn't count.
+        let mut source = source;
+        // With --overflow scale, the shrunken glyph blitted in place of
+        // the rendered one
+        let mut scaled = None;
+        if ink_left < 0
+            || ink_right > font_width as i32
+            || ink_top < 0
+            || ink_bottom > font_height as i32
+        {
+            overflowed.push((i, ch, ""));
+            coverage.oversized.push(coverage::Entry::new(i, ch));
+            match config.overflow {
+                Overflow::Clip => status[i] = CellStatus::Clipped,
+                Overflow::Error => {
+                    status[i] = CellStatus::Skipped;
+                    continue;
+                }
+                Overflow::Scale => {
+                    let Some(ink) = ink_box.or_else(|| raster.image.ink_bounds()) else {
+                        continue;
+                    };
+                    let (ink_x, ink_y, ink_width, ink_height) = ink;
+                    let glyph = raster.image.crop(ink_x, ink_y, ink_width, ink_height);
+                    let factor = (font_width as f32 / ink_width as f32)
+                        .min(font_height as f32 / ink_height as f32)
+                        .min(1.0);
+                    let w = ((ink_width as f32 * factor).round() as u32).clamp(1, font_width);
+                    let h = ((ink_height as f32 * factor).round() as u32).clamp(1, font_height);
+                    let shrink = |v: i32| (v as f32 * factor).round() as i32;
+                    let (minx, maxy) = (shrink(metrics.minx), shrink(metrics.maxy));
+                    // The shrunken ink is placed by the same --halign
+                    // policy as a cropped glyph, then kept inside the cell
+                    pen_x = match (halign[i], config.measure) {
+                        (HAlign::Center, Measure::Ink) => (font_width - w) as i32 / 2 - minx,
+                        (HAlign::Center, Measure::Advance) | (HAlign::Bearing, _) => 0,
+                        (HAlign::Left, _) => -minx,
+                    };
+                    x_offset = (pen_x + minx).clamp(0, (font_width - w) as i32);
+                    pen_x = x_offset - minx;
+                    // Keep the scaled glyph's relation to the baseline
+                    y_offset = (baseline - maxy).clamp(0, (font_height - h) as i32);
+                    (ink_left, ink_right) = (x_offset, x_offset + w as i32);
+                    (ink_top, ink_bottom) = (y_offset, y_offset + h as i32);
+                    source = (0, 0, w, h);
+                    scaled = Some(glyph.resize_linear(w, h, config.gamma));
+                    placement[i] = Some(Placement {
+                        minx,
+                        maxx: minx + w as i32,
+                        miny: maxy - h as i32,
+                        maxy,
+                        advance: shrink(metrics.advance),
+                        pen_x,
+                        x_offset,
+                        y_offset,
+                        surface_width: w,
+                        surface_height: h,
+                    });
+                    status[i] = CellStatus::Scaled;
+                }
+            }
+        }
+
+        if y_offset + source.3 as i32 > font_height as i32 {
+            warning::emit(
+                Warning::new(
+                    Code::GlyphTooTall,
+                    format!(
+                        "char '{}' (index {}) has a y_offset={} that causes it to exceed cell height ({} + {} > {})",
+                        ch, i, y_offset, y_offset, source.3, font_height
+                    ),
+                )
+                .glyph(i, ch)
+                .data(serde_json::json!({
+                    "y_offset": y_offset,
+                    "height": source.3,
+                    "cell_height": font_height,
+                })),
+            );
+        }
+
+        // Nudges come last, and whatever they push out of the cell is
+        // clipped rather than handled by the overflow policy
+        let (dx, dy) = nudges[i];
+        if (dx, dy) != (0, 0) {
+            pen_x += dx;
+            x_offset += dx;
+            y_offset += dy;
+            if let Some(p) = placement[i].as_mut() {
+                (p.pen_x, p.x_offset, p.y_offset) = (pen_x, x_offset, y_offset);
+            }
+            let inside = ink_left + dx >= 0
+                && ink_right + dx <= font_width as i32
+                && ink_top + dy >= 0
+                && ink_bottom + dy <= font_height as i32;
+            if !inside && status[i] != CellStatus::Clipped {
+                status[i] = CellStatus::Clipped;
+                warning::emit(
+                    Warning::new(
+                        Code::GlyphClipped,
+                        format!(
+                            "--nudge {},{} moves char '{}' (index {}) out of the cell; it is clipped",
+                            dx, dy, ch, i
+                        ),
+                    )
+                    .glyph(i, ch)
+                    .data(serde_json::json!({ "nudge": [dx, dy] })),
+                );
+            }
+        }
+
+        debug!(
+            "{}  minx={}, maxx={}, miny={}, maxy={}, asc={}, dsc={}, intern={}, tex_height={}, font_height={}, x_offset={}, y_offset={}",
+            ch,
+            metrics.minx,
+            metrics.maxx,
+            metrics.miny,
+            metrics.maxy,
+            line.ascent,
+            line.descent,
+            line_height,
+            raster.image.height,
+            font_height,
+            x_offset,
+            y_offset
+        );
+        match &scaled {
+            Some(image) => atlas.blit(image, None, source, origin, glyph_box, (x_offset, y_offset)),
+            None => atlas.blit(
+                &raster.image,
+                raster.subpixel.as_ref(),
+                source,
+                origin,
+                glyph_box,
+                (x_offset, y_offset),
+            ),
+        }
+    }
+    let Canvas {
+        gray: mut image,
+        subpixel,
+    } = atlas;
+
+    // Coverage is decoded before anything thresholds it, so morphology,
+    // block fixes, and every output judge ink in linear light
+    if config.gamma != 1.0 {
+        image.linearize(config.gamma);
+    }
+
+    // Strokes are grown or thinned in place, so the overflow policy and
+    // the block fix below see the final glyphs
+    if config.embolden > 0 || config.thin > 0 {
+        for (i, &ch) in charmap.chars().iter().enumerate() {
+            if placement[i].is_none() {
+                continue;
+            }
+            let (x, y) = grid.glyph_origin(i);
+            let mut glyph = image.crop(x, y, font_width, font_height);
+            if config.thin > 0 {
+                glyph = morphology::thin(&glyph, config.thin, config.embolden_dir);
+            }
+            if config.embolden > 0 {
+                let grew_out;
+                (glyph, grew_out) = morphology::embolden(
+                    &glyph,
+                    config.embolden,
+                    config.embolden_dir,
+                    config.overflow,
+                );
+                if grew_out && status[i] != CellStatus::Clipped {
+                    overflowed.push((i, ch, " once emboldened"));
+                    coverage.oversized.push(coverage::Entry::new(i, ch));
+                    if config.overflow == Overflow::Clip {
+                        status[i] = CellStatus::Clipped;
+                    }
+                }
+            }
+            image.paste(&glyph, x, y);
+        }
+    }
+    let mut atlas = Canvas {
+        gray: image,
+        subpixel,
+    };
+
+    // Block elements must tile perfectly, whatever the font drew for them.
+    if config.fix_blocks {
+        for (i, &ch) in charmap.chars().iter().enumerate() {
+            let Some(block) = boxes::synthesized_as(ch).filter(|b| boxes::BLOCKS.contains(b))
+            else {
+                continue;
+            };
+            // A hand-drawn override is what the user asked for
+            if overrides[i].is_some() {
+                continue;
+            }
+            let (x, y) = grid.glyph_origin(i);
+            atlas.fill(x, y, font_width, font_height, 255);
+            for (dx, dy, w, h) in boxes::shape(block, font_width, font_height).unwrap_or_default() {
+                atlas.fill(x + dx, y + dy, w, h, 0);
+            }
+        }
+    }
+
+    if !overflowed.is_empty() {
+        let list = overflowed
+            .iter()
+            .map(|(i, ch, when)| format!("'{}' ({}){}", ch, i, when))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let listing = format!(
+            "{} glyph(s) exceed the {}x{} cell: {}",
+            overflowed.len(),
+            font_width,
+            font_height,
+            list
+        );
+        if config.overflow == Overflow::Error {
+            return Err(anyhow!(
+                "Error: {} (use --overflow clip or scale to continue)",
+                listing
+            ))
+            .failure(Failure::Render);
+        }
+        let code = match config.overflow {
+            Overflow::Clip => Code::GlyphClipped,
+            _ => Code::GlyphScaled,
+        };
+        warning::emit_all(
+            format!("overflow ({}): {}", overflow_name, listing),
+            overflowed
+                .iter()
+                .map(|&(i, ch, when)| {
+                    Warning::new(
+                        code,
+                        format!(
+                            "'{}' (index {}){} exceeds the {}x{} cell; --overflow {} applies",
+                            ch, i, when, font_width, font_height, overflow_name
+                        ),
+                    )
+                    .glyph(i, ch)
+                    .data(serde_json::json!({
+                        "cell_width": font_width,
+                        "cell_height": font_height,
+                        "overflow": overflow_name,
+                    }))
+                })
+                .collect(),
+        );
+    }
+    let Canvas {
+        gray: mut image,
+        subpixel,
+    } = atlas;
+
+    // --- Step 4: Stretch, scale, and pad the finished atlas ---
+    // Everything downstream (outputs, macros, reported sizes) sees the
+    // stretched and scaled atlas and grid.
+    if config.stretch_width > 1 || config.stretch_height > 1 {
+        (image, grid) = stretch_glyphs(&image, &grid, charmap.len(), config);
+    }
+    if config.scale > 1 {
+        image = match config.scale_algorithm {
+            ScaleAlgorithm::Nearest => image.upscale(config.scale),
+            algorithm => {
+                let mut mask = pixel_art::Mask::from_gray(&image);
+                for _ in 0..filter_passes {
+                    mask = if algorithm == ScaleAlgorithm::Scale2x {
+                        mask.scale2x()
+                    } else {
+                        mask.scale3x()
+                    };
+                }
+                mask.to_gray()
+            }
+        };
+        grid = grid.scaled(config.scale);
+        info!(
+            "Scaled {}x: glyph box {}x{}",
+            config.scale, grid.glyph_width, grid.glyph_height
+        );
+    }
+    let (content_width, content_height) = (grid.width(), grid.height());
+    // The grid stays anchored at the top left; the extra area is background
+    let (atlas_width, atlas_height) = if config.pot {
+        (
+            content_width.next_power_of_two(),
+            content_height.next_power_of_two(),
+        )
+    } else {
+        (content_width, content_height)
+    };
+    if (atlas_width, atlas_height) != (content_width, content_height) {
+        let mut padded = GrayImage::new(atlas_width, atlas_height, 255);
+        padded.paste(&image, 0, 0);
+        image = padded;
+        info!(
+            "Atlas: {}x{} (content {}x{}, padded to powers of two)",
+            atlas_width, atlas_height, content_width, content_height
+        );
+    } else {
+        info!("Atlas: {}x{}", atlas_width, atlas_height);
+    }
+    let subpixel = subpixel.map(|mut rgb| {
+        if config.lcd_order == LcdOrder::Bgr {
+            rgb.swap_red_blue();
+        }
+        rgb.padded(atlas_width, atlas_height)
+    });
+
+    let glyphs = charmap
+        .chars()
+        .iter()
+        .enumerate()
+        .map(|(i, &ch)| {
+            let (x, y) = grid.glyph_origin(i);
+            GlyphInfo {
+                ch,
+                rect: (x, y, grid.glyph_width, grid.glyph_height),
+                status: status[i],
+                synthesized: synthesized[i].is_some(),
+                fallback: from_fallback[i],
+                placement: placement[i],
+            }
+        })
+        .collect();
+    Ok(Atlas {
+        image,
+        grid,
+        content_width,
+        content_height,
+        font_size,
+        baseline: (baseline_row + config.baseline_shift).max(0) as u32
+            * config.stretch_height
+            * config.scale,
+        line: Some(line),
+        width_fit: Some((font_width, requested_width)),
+        glyphs,
+        coverage,
+        cache_stats: fonts[0].backend.cache_stats(),
+        subpixel,
+    })
+}
+
+/// Render `config` and its bold variant at the same point size with
+/// `generate`, in glyph boxes large enough for either, so the bold glyphs
+/// sit in matching cells without clipping.
+pub fn generate_with_bold(
+    config: &AtlasConfig,
+    generate: impl Fn(&AtlasConfig) -> Result<Atlas>,
+) -> Result<(Atlas, Atlas)> {
+    if config.sdf_spread.is_some() {
+        bail!("Error: --with-bold can't be combined with --sdf");
+    }
+    let normal = generate(config)?;
+    let pinned = AtlasConfig {
+        font_size: Some(normal.font_size),
+        ..config.clone()
+    };
+    let bold = generate(&AtlasConfig {
+        bold: true,
+        ..pinned.clone()
+    })?;
+
+    // The boxes before --scale and --stretch, which are what the config
+    // asks for
+    let unscaled = |grid: &Grid| {
+        (
+            grid.glyph_width / (config.scale * config.stretch_width),
+            grid.glyph_height / (config.scale * config.stretch_height),
+        )
+    };
+    let (normal_box, bold_box) = (unscaled(&normal.grid), unscaled(&bold.grid));
+    if normal_box == bold_box {
+        return Ok((normal, bold));
+    }
+    let (width, height) = (normal_box.0.max(bold_box.0), normal_box.1.max(bold_box.1));
+    info!(
+        "Bold glyphs need a {}x{} box where regular ones need {}x{}; rendering both at {}x{}",
+        bold_box.0, bold_box.1, normal_box.0, normal_box.1, width, height
+    );
+    // Extra height goes below, leaving the baseline where it was
+    let union = AtlasConfig {
+        cell_width: width + 2 * config.padding,
+        cell_height: Some(height + 2 * config.padding),
+        valign: if config.cell_height.is_some() {
+            config.valign
+        } else {
+            VAlign::Top
+        },
+        ..pinned
+    };
+    let normal = if normal_box == (width, height) {
+        normal
+    } else {
+        generate(&union)?
+    };
+    let bold = if bold_box == (width, height) {
+        bold
+    } else {
+        generate(&AtlasConfig {
+            bold: true,
+            ..union
+        })?
+    };
+    Ok((normal, bold))
+}
+
+/// Render `config` `sdf_supersample` times larger, then average its
+/// distance field, `spread` pixels deep, down to the requested size.
+fn generate_sdf(config: &AtlasConfig, spread: u32, open: &Open) -> Result<Atlas> {
+    let factor = config.sdf_supersample;
+    if spread == 0 {
+        bail!("Error: --sdf needs a spread of at least 1 pixel");
+    }
+    if !(1..=16).contains(&factor) {
+        bail!(
+            "Error: --sdf-supersample must be between 1 and 16, got {}",
+            factor
+        );
+    }
+    if config.scale > 1 {
+        bail!("Error: --sdf can't be combined with --scale; raise --font-width instead");
+    }
+    if config.stretch_width > 1 || config.stretch_height > 1 {
+        bail!("Error: --sdf can't be combined with --stretch-width or --stretch-height");
+    }
+    if config.embolden > 0 || config.thin > 0 {
+        bail!("Error: --sdf can't be combined with --embolden or --thin");
+    }
+    if !config.nudges.is_empty() {
+        bail!("Error: --sdf can't be combined with --nudge");
+    }
+    // Without room around the glyph the field is cut off at the cell edge
+    let padding = config.padding.max(spread);
+    if padding > config.padding {
+        info!(
+            "Padding raised from {} to {} to hold the --sdf spread",
+            config.padding, padding
+        );
+    }
+    let Some(cell_width) = config.cell_width.checked_mul(factor) else {
+        bail!(
+            "Error: a {}px cell is too wide to supersample {}x",
+            config.cell_width,
+            factor
+        );
+    };
+    let cell_height = match config.cell_height {
+        Some(height) => match height.checked_mul(factor) {
+            Some(height) => Some(height),
+            None => {
+                return Err(anyhow!(
+                    "Error: a {}px cell is too tall to supersample {}x",
+                    height,
+                    factor
+                ))
+                .failure(Failure::Sizing);
+            }
+        },
+        None => None,
+    };
+    let supersampled = AtlasConfig {
+        cell_width,
+        cell_height,
+        baseline_row: config.baseline_row.map(|row| row * factor),
+        font_size: config.font_size.map(|size| size * factor as f32),
+        baseline_shift: config.baseline_shift * factor as i32,
+        padding: padding * factor,
+        gutter: config.gutter * factor,
+        pot: false,
+        max_atlas_dimension: config
+            .max_atlas_dimension
+            .saturating_mul(factor)
+            .min(i32::MAX as u32),
+        sdf_spread: None,
+        ..config.clone()
+    };
+    let mut atlas = sdf::distance_field(generate(&supersampled, open)?, factor, spread);
+
+    let grid = atlas.grid;
+    check_atlas_size(grid.width() as u64, grid.height() as u64, config)?;
+    if config.pot {
+        let mut padded = GrayImage::new(
+            grid.width().next_power_of_two(),
+            grid.height().next_power_of_two(),
+            255,
+        );
+        padded.paste(&atlas.image, 0, 0);
+        atlas.image = padded;
+    }
+    info!(
+        "Distance field: {}x{} atlas, glyph box {}x{}, spread {}px, rendered {}x larger",
+        atlas.image.width, atlas.image.height, grid.glyph_width, grid.glyph_height, spread, factor
+    );
+    Ok(atlas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic glyph, an 'L' with a dot, black on a white 6x9 box that
+    /// is itself wrapped in `border` more pixels of background, the way
+    /// some SDL_ttf builds pad their surfaces without moving the origin.
+    fn padded_glyph(border: u32) -> GrayImage {
+        let mut image = GrayImage::new(6 + 2 * border, 9 + 2 * border, 255);
+        for (x, y, w, h) in [(1, 1, 1, 7), (1, 7, 5, 1), (4, 3, 1, 1)] {
+            image.fill_rect(x + border, y + border, w, h, 0);
+        }
+        image
+    }
+
+    /// A white 12x12 glyph box with `image` drawn from the pen at its left
+    /// edge on a baseline at row 10, from its ink box with `ink_crop` and
+    /// whole without, as `generate` places and blits it.
+    fn cell(image: &GrayImage, ink_crop: bool) -> GrayImage {
+        // The bare glyph's ink, which starts 8 rows above the baseline
+        let metrics = GlyphBox {
+            minx: 1,
+            maxx: 6,
+            miny: 0,
+            maxy: 7,
+            advance: 6,
+        };
+        let (left, top) = (0, -8);
+        let source = if ink_crop {
+            image.ink_bounds().unwrap()
+        } else {
+            (0, 0, image.width, image.height)
+        };
+        let position = place(
+            &metrics,
+            left,
+            top,
+            ink_crop,
+            HAlign::Bearing,
+            Measure::Ink,
+            (12, 12),
+            10,
+        );
+        let mut canvas = Canvas {
+            gray: GrayImage::new(12, 12, 255),
+            subpixel: None,
+        };
+        let at = (position.x_offset, position.y_offset);
+        canvas.blit(image, None, source, (0, 0), (12, 12), at);
+        canvas.gray
+    }
+
+    #[test]
+    fn ink_crop_ignores_padding_around_the_glyph() {
+        let bare = padded_glyph(0);
+        let expected = cell(&bare, true);
+        assert_eq!(expected.ink_bounds(), Some((1, 3, 5, 7)));
+        // Cropped or not, a glyph drawn where its metrics say lands alike
+        assert_eq!(cell(&bare, false), expected);
+        for padded in [padded_glyph(1), padded_glyph(2)] {
+            assert_eq!(cell(&padded, true), expected);
+            // Without the crop the padding moves the glyph
+            assert_ne!(cell(&padded, false), expected);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Reading X11 bitmap fonts in the Glyph Bitmap Distribution Format.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::bitmap_font::BitmapFont;
+use crate::codepage::CP437;
+use crate::failure::{Categorize, Failure};
+use crate::gray::GrayImage;
+
+/// A glyph as the file describes it, before it is placed in the cell.
+struct Record {
+    encoding: i64,
+    /// BBX: width, height, and the offset of its lower left corner from the
+    /// origin.
+    bbx: (u32, u32, i32, i32),
+    rows: Vec<u8>,
+}
+
+/// Read the BDF font at `path`.
+pub fn read_bdf(path: &Path) -> Result<BitmapFont> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .failure(Failure::Font)?;
+    parse_bdf(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+        .failure(Failure::Font)
+}
+
+/// Parse a BDF font into cells the size of its FONTBOUNDINGBOX, with each
+/// glyph placed by its BBX offsets so all share the font's baseline.
+/// Encodings are Unicode for ISO10646 and ISO8859-1 fonts (the two agree
+/// below 256) and CP437 indices for any other charset; unencoded glyphs
+/// are skipped.
+pub fn parse_bdf(text: &str) -> Result<BitmapFont> {
+    let mut bounds: Option<(u32, u32, i32, i32)> = None;
+    let (mut registry, mut charset_encoding) = (String::new(), String::new());
+    let mut records = Vec::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((n, line)) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("FONTBOUNDINGBOX") => bounds = Some(parse_box(words, n)?),
+            Some("CHARSET_REGISTRY") => registry = property(words),
+            Some("CHARSET_ENCODING") => charset_encoding = property(words),
+            Some("STARTCHAR") => records.push(parse_char(&mut lines)?),
+            _ => {}
+        }
+    }
+    let Some((width, height, origin_x, origin_y)) = bounds else {
+        bail!("no FONTBOUNDINGBOX");
+    };
+    if width == 0 || height == 0 {
+        bail!("FONTBOUNDINGBOX is {}x{}", width, height);
+    }
+    // Rows from the top of the cell down to the baseline
+    let ascent = height as i32 + origin_y;
+
+    let unicode_encoded = registry.is_empty()
+        || registry.eq_ignore_ascii_case("ISO10646")
+        || (registry.eq_ignore_ascii_case("ISO8859") && charset_encoding == "1");
+    let mut glyphs = Vec::new();
+    let mut unicode = Vec::new();
+    for record in records {
+        let ch = match u32::try_from(record.encoding) {
+            Ok(code) if unicode_encoded => char::from_u32(code),
+            Ok(code) => CP437.get(code as usize).copied(),
+            Err(_) => None,
+        };
+        let Some(ch) = ch else {
+            continue;
+        };
+        let (w, h, x_offset, y_offset) = record.bbx;
+        let bitmap = BitmapFont::unpack_glyph(&record.rows, w, h);
+        let left = x_offset - origin_x;
+        let top = ascent - (y_offset + h as i32);
+        let mut glyph = GrayImage::new(width, height, 255);
+        for y in 0..h {
+            for x in 0..w {
+                let (cx, cy) = (left + x as i32, top + y as i32);
+                if (0..width as i32).contains(&cx) && (0..height as i32).contains(&cy) {
+                    glyph.set(cx as u32, cy as u32, bitmap.get(x, y));
+                }
+            }
+        }
+        glyphs.push(glyph);
+        unicode.push(vec![ch]);
+    }
+    Ok(BitmapFont {
+        width,
+        height,
+        baseline: Some(ascent.clamp(0, height as i32) as u32),
+        glyphs,
+        unicode,
+    })
+}
+
+/// Read one STARTCHAR record, up to and including ENDCHAR.
+fn parse_char<'a>(lines: &mut impl Iterator<Item = (usize, &'a str)>) -> Result<Record> {
+    let mut encoding = -1;
+    let mut bbx = None;
+    let mut rows = Vec::new();
+    while let Some((n, line)) = lines.next() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("ENCODING") => {
+                encoding = words
+                    .next()
+                    .and_then(|w| w.parse().ok())
+                    .with_context(|| format!("line {}: bad ENCODING", n + 1))?;
+            }
+            Some("BBX") => bbx = Some(parse_box(words, n)?),
+            Some("BITMAP") => {
+                let (w, h, _, _) =
+                    bbx.with_context(|| format!("line {}: BITMAP before BBX", n + 1))?;
+                let stride = w.div_ceil(8) as usize;
+                for _ in 0..h {
+                    let (n, row) = lines.next().context("file ends inside a BITMAP")?;
+                    let row = row.trim();
+                    for i in 0..stride {
+                        // Rows may carry more bytes than the width needs
+                        let byte = row.get(i * 2..i * 2 + 2).unwrap_or("00");
+                        rows.push(
+                            u8::from_str_radix(byte, 16)
+                                .with_context(|| format!("line {}: bad bitmap row", n + 1))?,
+                        );
+                    }
+                }
+            }
+            Some("ENDCHAR") => {
+                let bbx = bbx.context("glyph without a BBX")?;
+                return Ok(Record {
+                    encoding,
+                    bbx,
+                    rows,
+                });
+            }
+            _ => {}
+        }
+    }
+    bail!("file ends inside a STARTCHAR record")
+}
+
+/// A string property's value, without its quotes.
+fn property<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    let value = words.collect::<Vec<_>>().join(" ");
+    value.trim_matches('"').to_string()
+}
+
+/// Width, height, and x and y offsets from a FONTBOUNDINGBOX or BBX line.
+fn parse_box<'a>(
+    mut words: impl Iterator<Item = &'a str>,
+    n: usize,
+) -> Result<(u32, u32, i32, i32)> {
+    let mut next = || words.next().and_then(|w| w.parse::<i64>().ok());
+    match (next(), next(), next(), next()) {
+        (Some(w), Some(h), Some(x), Some(y)) if w >= 0 && h >= 0 => {
+            Ok((w as u32, h as u32, x as i32, y as i32))
+        }
+        _ => bail!(
+            "line {}: expected width, height, x offset, and y offset",
+            n + 1
+        ),
+    }
+}
@@ -0,0 +1,121 @@
+//! Fonts that are already bitmaps (PSF console fonts and the like), laid
+//! straight into an atlas instead of being rendered through SDL_ttf.
+
+use anyhow::Result;
+
+use crate::charmap::CharMap;
+use crate::coverage::{Coverage, Entry};
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+use crate::warning::{self, Code, Warning};
+use crate::{Atlas, CellStatus, MissingGlyph};
+
+/// A font of fixed-size glyph images.
+pub struct BitmapFont {
+    pub width: u32,
+    pub height: u32,
+    /// Baseline row from the top of the glyph, when the format records it.
+    pub baseline: Option<u32>,
+    /// Every glyph, `width` x `height`, in the file's order.
+    pub glyphs: Vec<GrayImage>,
+    /// The characters each glyph draws, from the font's Unicode table.
+    /// Empty when the font has no table, in which case glyph `i` is taken
+    /// to be the character at index `i` of the active table.
+    pub unicode: Vec<Vec<char>>,
+}
+
+impl BitmapFont {
+    /// Build a glyph from rows of packed bits, most significant bit first,
+    /// `(width + 7) / 8` bytes per row.
+    pub fn unpack_glyph(rows: &[u8], width: u32, height: u32) -> GrayImage {
+        let stride = width.div_ceil(8) as usize;
+        let mut glyph = GrayImage::new(width, height, 255);
+        for y in 0..height {
+            for x in 0..width {
+                let byte = rows[y as usize * stride + x as usize / 8];
+                if byte & (0x80 >> (x % 8)) != 0 {
+                    glyph.set(x, y, 0);
+                }
+            }
+        }
+        glyph
+    }
+
+    /// The glyph that draws `ch`, which sits at `index` in the active table.
+    fn glyph_for(&self, ch: char, index: usize) -> Option<usize> {
+        let found = if self.unicode.is_empty() {
+            index
+        } else {
+            self.unicode.iter().position(|chars| chars.contains(&ch))?
+        };
+        (found < self.glyphs.len()).then_some(found)
+    }
+
+    /// Lay the glyphs out in `grid`, one cell per character of `charmap`.
+    /// Characters the font doesn't have get `missing`, and glyphs no cell
+    /// asks for are left out with a warning.
+    pub fn to_atlas(&self, charmap: &CharMap, grid: Grid, missing: MissingGlyph) -> Result<Atlas> {
+        let mut image = GrayImage::new(grid.width(), grid.height(), 255);
+        let mut status = vec![CellStatus::Ok; charmap.len()];
+        let mut used = vec![false; self.glyphs.len()];
+        let mut coverage = Coverage::default();
+        for (i, &ch) in charmap.chars().iter().enumerate() {
+            let (x, y) = grid.glyph_origin(i);
+            if let Some(glyph) = self.glyph_for(ch, i) {
+                image.paste(&self.glyphs[glyph], x, y);
+                used[glyph] = true;
+                continue;
+            }
+            coverage.missing.push(Entry::new(i, ch));
+            status[i] = CellStatus::Skipped;
+            let placeholder = match missing {
+                MissingGlyph::Char(replacement) => charmap
+                    .index_of(replacement)
+                    .and_then(|r| self.glyph_for(replacement, r))
+                    .map(|glyph| self.glyphs[glyph].clone()),
+                pattern => pattern.pattern(self.width, self.height),
+            };
+            if let Some(placeholder) = placeholder {
+                image.paste(&placeholder, x, y);
+                coverage.placeholder.push(Entry::new(i, ch));
+                status[i] = CellStatus::Placeholder;
+            }
+        }
+        let unused = used.iter().filter(|&&u| !u).count();
+        if unused > 0 {
+            warning::emit(
+                Warning::new(
+                    Code::FontGlyphsUnused,
+                    format!(
+                        "{} of the font's {} glyphs have no cell in {} and were ignored",
+                        unused,
+                        self.glyphs.len(),
+                        charmap.label()
+                    ),
+                )
+                .data(serde_json::json!({ "unused": unused, "glyphs": self.glyphs.len() })),
+            );
+        }
+        if !coverage.missing.is_empty() {
+            warning::emit(
+                Warning::new(
+                    Code::CharsMissing,
+                    format!(
+                        "{} character(s) are not in the font",
+                        coverage.missing.len()
+                    ),
+                )
+                .data(serde_json::json!({
+                    "indices": coverage.missing.iter().map(|e| e.index).collect::<Vec<_>>(),
+                })),
+            );
+        }
+
+        let mut atlas = Atlas::from_image(image, grid, charmap, self.baseline)?;
+        for (glyph, status) in atlas.glyphs.iter_mut().zip(status) {
+            glyph.status = status;
+        }
+        atlas.coverage = coverage;
+        Ok(atlas)
+    }
+}
@@ -0,0 +1,231 @@
+//! `--output-godot`: the atlas as an AngelCode BMFont text file and page
+//! image, kept to what Godot's importer accepts: the page is named by a bare
+//! relative filename, ids stay within the Basic Multilingual Plane, and
+//! there is no kerning section.
+
+use anyhow::{Result, bail};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::grid::Grid;
+use crate::warning::{self, Code, Warning};
+
+/// What goes into the `.fnt` besides the glyph boxes.
+pub struct FontInfo<'a> {
+    /// Face name for the `info` line.
+    pub face: &'a str,
+    /// File names of the page images, relative to the `.fnt`.
+    pub pages: &'a [&'a str],
+    /// Baseline row, counted from the top of the glyph box.
+    pub baseline: u32,
+    /// Size of each page.
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+}
+
+/// Format the BMFont text for `chars` laid out in `grid`, with `place`
+/// giving each cell's glyph box origin on its page and the page. Characters
+/// above U+FFFF and repeats of an earlier character are left out with a
+/// warning.
+pub fn format_fnt(
+    info: &FontInfo,
+    grid: &Grid,
+    chars: &[char],
+    place: impl Fn(usize) -> (u32, u32, u32),
+) -> Result<String> {
+    let fields = std::iter::once(("face", info.face))
+        .chain(info.pages.iter().map(|&page| ("page file", page)));
+    for (field, value) in fields {
+        if value.contains(['"', '\n', '\r']) {
+            bail!("Error: BMFont {} name '{}' can't be quoted", field, value);
+        }
+    }
+    for page in info.pages {
+        if Path::new(page).components().count() != 1 {
+            bail!("Error: BMFont page '{}' must be a bare filename", page);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    let mut astral = 0;
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch as u32 > 0xFFFF {
+            astral += 1;
+        } else if seen.insert(ch) {
+            entries.push((i, ch));
+        }
+    }
+    if astral > 0 {
+        warning::emit(
+            Warning::new(
+                Code::BmfontCharsDropped,
+                format!(
+                    "{} character(s) above U+FFFF left out of the BMFont file",
+                    astral
+                ),
+            )
+            .data(serde_json::json!({ "above_bmp": astral })),
+        );
+    }
+    if entries.len() + astral < chars.len() {
+        let repeated = chars.len() - astral - entries.len();
+        warning::emit(
+            Warning::new(
+                Code::BmfontCharsDropped,
+                format!(
+                    "{} repeated character(s) left out of the BMFont file",
+                    repeated
+                ),
+            )
+            .data(serde_json::json!({ "repeated": repeated })),
+        );
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "info face=\"{}\" size={} bold=0 italic=0 charset=\"\" unicode=1 stretchH=100 smooth=0 aa=1 padding=0,0,0,0 spacing=0,0",
+        info.face, grid.glyph_height
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "common lineHeight={} base={} scaleW={} scaleH={} pages={} packed=0",
+        grid.glyph_height,
+        info.baseline,
+        info.atlas_width,
+        info.atlas_height,
+        info.pages.len()
+    )
+    .unwrap();
+    for (id, page) in info.pages.iter().enumerate() {
+        writeln!(out, "page id={} file=\"{}\"", id, page).unwrap();
+    }
+    writeln!(out, "chars count={}", entries.len()).unwrap();
+    for (i, ch) in entries {
+        let (x, y, page) = place(i);
+        writeln!(
+            out,
+            "char id={} x={} y={} width={} height={} xoffset=0 yoffset=0 xadvance={} page={} chnl=15",
+            ch as u32, x, y, grid.glyph_width, grid.glyph_height, grid.glyph_width, page
+        )
+        .unwrap();
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Split a BMFont text line into its tag and `key=value` pairs, as the
+    /// documented grammar has them: a value is a quoted string without
+    /// quotes inside, or integers separated by commas.
+    fn parse_line(line: &str) -> (&str, HashMap<&str, &str>) {
+        let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut pairs = HashMap::new();
+        while !rest.is_empty() {
+            let (key, after) = rest.split_once('=').expect("key=value");
+            assert!(
+                !key.is_empty() && key.chars().all(|c| c.is_ascii_alphabetic()),
+                "bad key in {}",
+                line
+            );
+            let (value, after) = match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"').expect("closing quote");
+                    (&after[..end + 2], &quoted[end + 1..])
+                }
+                None => after.split_once(' ').unwrap_or((after, "")),
+            };
+            if !value.starts_with('"') {
+                assert!(
+                    value
+                        .split(',')
+                        .all(|n| n.trim_start_matches('-').parse::<u32>().is_ok()),
+                    "bad value {} in {}",
+                    value,
+                    line
+                );
+            }
+            assert!(pairs.insert(key, value).is_none(), "{} twice", key);
+            rest = after.strip_prefix(' ').unwrap_or(after);
+            assert!(!rest.starts_with(' '), "double space in {}", line);
+        }
+        (tag, pairs)
+    }
+
+    fn info<'a>(pages: &'a [&'a str]) -> FontInfo<'a> {
+        FontInfo {
+            face: "Test Mono",
+            pages,
+            baseline: 12,
+            atlas_width: 16,
+            atlas_height: 32,
+        }
+    }
+
+    #[test]
+    fn fnt_follows_the_bmfont_text_grammar() {
+        let grid = Grid::new(4, 2, false, 8, 16);
+        // A repeat and a character past the BMP, which Godot rejects
+        let chars = ['A', 'B', 'A', '\u{1F600}'];
+        let fnt = format_fnt(&info(&["font.png"]), &grid, &chars, |i| {
+            let (x, y) = grid.glyph_origin(i);
+            (x, y, 0)
+        })
+        .unwrap();
+        assert!(fnt.ends_with('\n'));
+        let lines: Vec<_> = fnt.lines().map(parse_line).collect();
+        let tags: Vec<&str> = lines.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, ["info", "common", "page", "chars", "char", "char"]);
+
+        let required: [(&str, &[&str]); 5] = [
+            ("info", &["face", "size", "unicode", "padding", "spacing"]),
+            (
+                "common",
+                &["lineHeight", "base", "scaleW", "scaleH", "pages", "packed"],
+            ),
+            ("page", &["id", "file"]),
+            ("chars", &["count"]),
+            (
+                "char",
+                &[
+                    "id", "x", "y", "width", "height", "xoffset", "yoffset", "xadvance", "page",
+                    "chnl",
+                ],
+            ),
+        ];
+        for (tag, pairs) in &lines {
+            let (_, keys) = required.iter().find(|(t, _)| t == tag).unwrap();
+            for key in *keys {
+                assert!(pairs.contains_key(key), "{} has no {}", tag, key);
+            }
+        }
+
+        let (_, common) = &lines[1];
+        assert_eq!(common["lineHeight"], "16");
+        assert_eq!(common["base"], "12");
+        assert_eq!(lines[2].1["file"], "\"font.png\"");
+        assert_eq!(lines[3].1["count"], "2");
+        let ids: Vec<&str> = lines[4..].iter().map(|(_, pairs)| pairs["id"]).collect();
+        assert_eq!(ids, ["65", "66"]);
+        assert_eq!((lines[5].1["x"], lines[5].1["y"]), ("8", "0"));
+    }
+
+    #[test]
+    fn pages_must_be_bare_quotable_filenames() {
+        let grid = Grid::new(1, 1, false, 8, 16);
+        let place = |_| (0, 0, 0);
+        for page in ["fonts/font.png", "/tmp/font.png", "font\".png"] {
+            assert!(
+                format_fnt(&info(&[page]), &grid, &['A'], place).is_err(),
+                "{} was accepted",
+                page
+            );
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Programmatic shapes for the CP437 shade, box-drawing, and block range
+//! (176-223), drawn edge to edge so strokes connect between cells.
+
+use std::ops::RangeInclusive;
+
+use crate::codepage::Codepage;
+
+/// CP437 indices that `--synthesize-boxes` draws instead of taking from the
+/// font.
+pub const SYNTHESIZED: RangeInclusive<u8> = 176..=223;
+
+/// The block elements: full, lower half, left half, right half, upper half.
+pub const BLOCKS: RangeInclusive<u8> = 219..=223;
+
+/// The CP437 index whose shape draws `ch`, if it is one of the synthesized
+/// characters. Other codepages place these at different indices (or not at
+/// all), so callers go through the character rather than its index.
+pub fn synthesized_as(ch: char) -> Option<u8> {
+    SYNTHESIZED
+        .into_iter()
+        .find(|&i| Codepage::Cp437.char_at(i) == ch)
+}
+
+/// A filled rectangle in cell coordinates: x, y, width, height.
+pub type CellRect = (u32, u32, u32, u32);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Line {
+    None,
+    Single,
+    Double,
+}
+use Line::{Double as D, None as N, Single as S};
+
+/// Arms of each box-drawing character 179-218: up, down, left, right.
+const BOX_ARMS: [[Line; 4]; 40] = [
+    [S, S, N, N], // 179 │
+    [S, S, S, N], // 180 ┤
+    [S, S, D, N], // 181 ╡
+    [D, D, S, N], // 182 ╢
+    [N, D, S, N], // 183 ╖
+    [N, S, D, N], // 184 ╕
+    [D, D, D, N], // 185 ╣
+    [D, D, N, N], // 186 ║
+    [N, D, D, N], // 187 ╗
+    [D, N, D, N], // 188 ╝
+    [D, N, S, N], // 189 ╜
+    [S, N, D, N], // 190 ╛
+    [N, S, S, N], // 191 ┐
+    [S, N, N, S], // 192 └
+    [S, N, S, S], // 193 ┴
+    [N, S, S, S], // 194 ┬
+    [S, S, N, S], // 195 ├
+    [N, N, S, S], // 196 ─
+    [S, S, S, S], // 197 ┼
+    [S, S, N, D], // 198 ╞
+    [D, D, N, S], // 199 ╟
+    [D, N, N, D], // 200 ╚
+    [N, D, N, D], // 201 ╔
+    [D, N, D, D], // 202 ╩
+    [N, D, D, D], // 203 ╦
+    [D, D, N, D], // 204 ╠
+    [N, N, D, D], // 205 ═
+    [D, D, D, D], // 206 ╬
+    [S, N, D, D], // 207 ╧
+    [D, N, S, S], // 208 ╨
+    [N, S, D, D], // 209 ╤
+    [N, D, S, S], // 210 ╥
+    [D, N, N, S], // 211 ╙
+    [S, N, N, D], // 212 ╘
+    [N, S, N, D], // 213 ╒
+    [N, D, N, S], // 214 ╓
+    [D, D, S, S], // 215 ╫
+    [S, S, D, D], // 216 ╪
+    [S, N, S, N], // 217 ┘
+    [N, S, N, S], // 218 ┌
+];
+
+/// Positions of single and double strokes along one axis of length `len`.
+/// Double strokes keep a 1px gap between their two lines.
+struct Strokes {
+    single: u32,
+    double: [u32; 2],
+    thickness: u32,
+}
+
+impl Strokes {
+    fn new(len: u32, thickness: u32) -> Strokes {
+        let single = (len - thickness.min(len)) / 2;
+        let double_span = 2 * thickness + 1;
+        let first = len.saturating_sub(double_span) / 2;
+        Strokes {
+            single,
+            double: [first, first + thickness + 1],
+            thickness,
+        }
+    }
+
+    fn positions(&self, line: Line) -> Vec<u32> {
+        match line {
+            Line::None => vec![],
+            Line::Single => vec![self.single],
+            Line::Double => self.double.to_vec(),
+        }
+    }
+
+    /// The span the strokes of `line` occupy, used as the junction area that
+    /// perpendicular arms extend into.
+    fn span(&self, line: Line) -> (u32, u32) {
+        match line {
+            Line::None | Line::Single => (self.single, self.single + self.thickness),
+            Line::Double => (self.double[0], self.double[1] + self.thickness),
+        }
+    }
+}
+
+/// Stroke thickness for a cell, growing with the cell width.
+pub fn stroke_thickness(width: u32) -> u32 {
+    (width / 8).max(1)
+}
+
+/// Rectangles that make up index `index` in a `width` x `height` cell, or
+/// `None` when the index isn't one of the synthesized characters.
+pub fn shape(index: u8, width: u32, height: u32) -> Option<Vec<CellRect>> {
+    let half_w = width / 2;
+    let half_h = height / 2;
+    let rects = match index {
+        // Shades as 2x2 ordered dither at 25%, 50%, and 75% coverage
+        176..=178 => {
+            const BAYER: [[u32; 2]; 2] = [[0, 2], [3, 1]];
+            let level = (index - 175) as u32;
+            let mut rects = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    if BAYER[(y % 2) as usize][(x % 2) as usize] < level {
+                        rects.push((x, y, 1, 1));
+                    }
+                }
+            }
+            rects
+        }
+        179..=218 => box_rects(BOX_ARMS[(index - 179) as usize], width, height),
+        // Block elements: halves split at width/2 and height/2, with the extra
+        // pixel of an odd dimension going to the bottom/right half
+        219 => vec![(0, 0, width, height)],
+        220 => vec![(0, half_h, width, height - half_h)],
+        221 => vec![(0, 0, half_w, height)],
+        222 => vec![(half_w, 0, width - half_w, height)],
+        223 => vec![(0, 0, width, half_h)],
+        _ => return None,
+    };
+
+    // Tiny cells can push double strokes past the edge; keep everything
+    // inside the cell so nothing bleeds into a neighbour.
+    Some(
+        rects
+            .into_iter()
+            .filter(|&(x, y, w, h)| x < width && y < height && w > 0 && h > 0)
+            .map(|(x, y, w, h)| (x, y, w.min(width - x), h.min(height - y)))
+            .collect(),
+    )
+}
+
+/// Draw each arm from its cell edge through the junction area, so corners
+/// and tees close up. Double-line junctions come out as a lattice rather
+/// than with open centers, which is fine at terminal sizes.
+fn box_rects(arms: [Line; 4], width: u32, height: u32) -> Vec<CellRect> {
+    let [up, down, left, right] = arms;
+    let thickness = stroke_thickness(width);
+    let columns = Strokes::new(width, thickness);
+    let rows = Strokes::new(height, thickness);
+
+    // Junction extents: how far vertical arms reach into the horizontal
+    // strokes and vice versa.
+    let horizontal = if left == Line::Double || right == Line::Double {
+        Line::Double
+    } else {
+        Line::Single
+    };
+    let vertical = if up == Line::Double || down == Line::Double {
+        Line::Double
+    } else {
+        Line::Single
+    };
+    let (row_start, row_end) = rows.span(horizontal);
+    let (col_start, col_end) = columns.span(vertical);
+
+    let mut rects = Vec::new();
+    for x in columns.positions(up) {
+        rects.push((x, 0, thickness, row_end));
+    }
+    for x in columns.positions(down) {
+        rects.push((x, row_start, thickness, height - row_start));
+    }
+    for y in rows.positions(left) {
+        rects.push((0, y, col_end, thickness));
+    }
+    for y in rows.positions(right) {
+        rects.push((col_start, y, width - col_start, thickness));
+    }
+    rects
+}
@@ -0,0 +1,460 @@
+//! The index-to-character table: a built-in codepage, a `--charset-file`,
+//! `--unicode-range` spans, or the characters of a `--chars-from` text,
+//! optionally patched per index from a `--charmap` file.
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::codepage::{CP437, Codepage};
+use crate::parse_index;
+use crate::warning::{self, Code, Warning};
+
+/// Cell order for `--chars-from` atlases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CharOrder {
+    /// In the order they first appear in the text
+    Appearance,
+    /// By codepoint
+    Sorted,
+}
+
+/// Which character goes in which cell, selected with `--layout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CellLayout {
+    /// Index order: character `i` in cell `i`
+    Sequential,
+    /// libtcod's TCOD layout, 32 columns; characters it has no cell for
+    /// fill its empty cells
+    Tcod,
+}
+
+/// libtcod's `TCOD_CHARMAP_TCOD`, in rows of 32 cells; cells past the end
+/// of a row are empty.
+const TCOD_ROWS: [&str; 5] = [
+    " !\"#$%&'()*+,-./0123456789:;<=>?",
+    "@[\\]^_`{|}~░▒▓│─┼┤┴├┬└┌┐┘▘▝▀▖▚▐▗",
+    "↑↓←→▲▼◄►↕↔☐☑○◉║═╬╣╩╠╦╚╔╗╝",
+    "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+    "abcdefghijklmnopqrstuvwxyz",
+];
+
+impl CellLayout {
+    /// Name as accepted by `--layout`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CellLayout::Sequential => "sequential",
+            CellLayout::Tcod => "tcod",
+        }
+    }
+
+    /// The index of `charmap` that goes in each cell, or `None` when the
+    /// layout leaves every character where it is.
+    pub fn order(self, charmap: &CharMap) -> Result<Option<Vec<usize>>> {
+        match self {
+            CellLayout::Sequential => Ok(None),
+            CellLayout::Tcod => tcod_order(charmap).map(Some),
+        }
+    }
+}
+
+/// Place each TCOD cell's character where `charmap` has it, then fill the
+/// empty cells, and after them the cells whose character the table lacks,
+/// with the leftover indices in order.
+fn tcod_order(charmap: &CharMap) -> Result<Vec<usize>> {
+    if charmap.len() != 256 {
+        bail!(
+            "Error: --layout tcod needs a 256-character table, not {}",
+            charmap.len()
+        );
+    }
+    let mut cells: Vec<Option<usize>> = vec![None; 256];
+    let mut placed = vec![false; 256];
+    let mut unmatched = Vec::new();
+    for (row, chars) in TCOD_ROWS.iter().enumerate() {
+        for (col, ch) in chars.chars().enumerate() {
+            let cell = row * 32 + col;
+            let index = (0..256).find(|&i| !placed[i] && charmap.get(i) == ch);
+            match index {
+                Some(i) => {
+                    cells[cell] = Some(i);
+                    placed[i] = true;
+                }
+                None => unmatched.push(cell),
+            }
+        }
+    }
+    let mut free: Vec<usize> = (0..256)
+        .filter(|&cell| cells[cell].is_none() && !unmatched.contains(&cell))
+        .collect();
+    free.extend(unmatched);
+    let leftovers = (0..256).filter(|&i| !placed[i]);
+    for (cell, i) in free.into_iter().zip(leftovers) {
+        cells[cell] = Some(i);
+    }
+    Ok(cells
+        .into_iter()
+        .map(|i| i.expect("as many leftovers as free cells"))
+        .collect())
+}
+
+/// The character each atlas index renders as, built once at startup so every
+/// stage (measuring, rendering, text encoding) agrees. Codepages and charset
+/// files have 256 entries; Unicode ranges have as many as they span.
+#[derive(Clone, Debug)]
+pub struct CharMap {
+    /// `None` when the table didn't come from a built-in codepage.
+    codepage: Option<Codepage>,
+    /// Where the table came from, for summaries and output headers.
+    label: String,
+    chars: Vec<char>,
+    /// Indices whose character came from the override file, in index order.
+    overrides: Vec<usize>,
+}
+
+impl CharMap {
+    /// The built-in table for `codepage` with no overrides.
+    pub fn new(codepage: Codepage) -> CharMap {
+        CharMap {
+            codepage: Some(codepage),
+            label: codepage.name().to_string(),
+            chars: (0..=u8::MAX).map(|i| codepage.char_at(i)).collect(),
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Fill the blank control-range cells (0-31 and 127) with the CP437
+    /// pictures, as hex viewers show control bytes.
+    pub fn with_control_pictures(mut self) -> CharMap {
+        let len = self.chars.len();
+        for i in (0..32).chain([127]).filter(|&i| i < len) {
+            if self.chars[i] == ' ' {
+                self.chars[i] = CP437[i];
+            }
+        }
+        self
+    }
+
+    /// Every character in each inclusive codepoint range, concatenated in
+    /// order. Surrogates, which aren't characters, are skipped.
+    pub fn from_unicode_ranges(ranges: &[(u32, u32)]) -> Result<CharMap> {
+        let chars: Vec<char> = ranges
+            .iter()
+            .flat_map(|&(start, end)| (start..=end).filter_map(char::from_u32))
+            .collect();
+        if chars.is_empty() {
+            bail!("Unicode ranges select no characters");
+        }
+        let label = ranges
+            .iter()
+            .map(|&(start, end)| format!("U+{:04X}-U+{:04X}", start, end))
+            .collect::<Vec<_>>()
+            .join(",");
+        Ok(CharMap {
+            codepage: None,
+            label,
+            chars,
+            overrides: Vec::new(),
+        })
+    }
+
+    /// Only the characters `path` uses, each once, in `order`. Control
+    /// characters (line breaks, tabs) are layout, not glyphs, and are left out.
+    pub fn from_text_file(path: &Path, order: CharOrder) -> Result<CharMap> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut chars: Vec<char> = Vec::new();
+        for ch in text.chars().filter(|c| !c.is_control()) {
+            if !chars.contains(&ch) {
+                chars.push(ch);
+            }
+        }
+        if order == CharOrder::Sorted {
+            chars.sort_unstable();
+        }
+        if chars.is_empty() {
+            bail!("{} contains no printable characters", path.display());
+        }
+        Ok(CharMap {
+            codepage: None,
+            label: format!("chars from {}", path.display()),
+            chars,
+            overrides: Vec::new(),
+        })
+    }
+
+    /// A fully custom table: exactly 256 characters, one per line or as a
+    /// single string. `\uXXXX` escapes name characters that are awkward
+    /// to type, and `\\` is a literal backslash.
+    pub fn from_charset_file(path: &Path) -> Result<CharMap> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read charset file {}", path.display()))?;
+        let lines = text
+            .lines()
+            .map(unescape)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid charset file {}", path.display()))?;
+
+        // Line breaks only separate characters, so one per line, a single
+        // string, and 16 rows of 16 all read the same way
+        let chars: Vec<char> = lines.into_iter().flatten().collect();
+        if chars.len() != 256 {
+            bail!(
+                "Charset file {} holds {} characters, expected exactly 256",
+                path.display(),
+                chars.len()
+            );
+        }
+        Ok(CharMap {
+            codepage: None,
+            label: "custom".to_string(),
+            chars,
+            overrides: Vec::new(),
+        })
+    }
+
+    /// Layer the overrides in `path` on top of this table. The file maps
+    /// indices (decimal or `0x` hex keys) to single characters and is read as
+    /// JSON when it ends in `.json`, TOML otherwise.
+    pub fn with_overrides(mut self, path: &Path) -> Result<CharMap> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read charmap {}", path.display()))?;
+        let entries: BTreeMap<String, String> = if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+        {
+            serde_json::from_str(&text)
+                .with_context(|| format!("Failed to parse charmap {}", path.display()))?
+        } else {
+            toml::from_str(&text)
+                .with_context(|| format!("Failed to parse charmap {}", path.display()))?
+        };
+
+        for (key, value) in &entries {
+            let index = parse_index(key, self.len())
+                .with_context(|| format!("Invalid charmap key \"{}\"", key))?;
+            let mut chars = value.chars();
+            let (Some(ch), None) = (chars.next(), chars.next()) else {
+                bail!(
+                    "Invalid charmap entry \"{}\": expected exactly one character, got \"{}\"",
+                    key,
+                    value
+                );
+            };
+            if self.overrides.contains(&index) {
+                bail!(
+                    "Invalid charmap key \"{}\": index {} is mapped twice",
+                    key,
+                    index
+                );
+            }
+            self.chars[index] = ch;
+            self.overrides.push(index);
+        }
+        self.overrides.sort_unstable();
+        Ok(self)
+    }
+
+    /// The codepage the table started from, or `None` for a charset file or
+    /// Unicode ranges.
+    pub fn codepage(&self) -> Option<Codepage> {
+        self.codepage
+    }
+
+    /// The codepage name, `custom`, or the Unicode ranges.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Number of atlas indices.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// The character rendered for `index`.
+    pub fn get(&self, index: usize) -> char {
+        self.chars[index]
+    }
+
+    /// All characters in index order.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// The first index that renders as `ch`, if any.
+    pub fn index_of(&self, ch: char) -> Option<usize> {
+        self.chars.iter().position(|&c| c == ch)
+    }
+
+    /// Characters that more than one index renders as, with those indices.
+    pub fn duplicates(&self) -> Vec<(char, Vec<usize>)> {
+        let mut seen: BTreeMap<char, Vec<usize>> = BTreeMap::new();
+        for (i, &ch) in self.chars.iter().enumerate() {
+            seen.entry(ch).or_default().push(i);
+        }
+        seen.into_iter().filter(|(_, at)| at.len() > 1).collect()
+    }
+
+    /// Indices whose character was overridden.
+    pub fn overrides(&self) -> &[usize] {
+        &self.overrides
+    }
+
+    /// The table rearranged so index `c` holds what index `order[c]` did,
+    /// labelled with the `layout` name. It no longer matches its codepage's
+    /// order.
+    pub fn reordered(&self, order: &[usize], layout: &str) -> CharMap {
+        let overrides = (0..order.len())
+            .filter(|&c| self.overrides.contains(&order[c]))
+            .collect();
+        CharMap {
+            codepage: None,
+            label: format!("{}, {} layout", self.label, layout),
+            chars: order.iter().map(|&i| self.chars[i]).collect(),
+            overrides,
+        }
+    }
+}
+
+/// Read a `--remap` file for `charmap`: one line per cell, giving the index
+/// whose character goes there in decimal or `0x` hex, or the character
+/// itself, with `\uXXXX` escapes as in a charset file (a digit must be
+/// escaped to be read as a character). Indices placed twice or not at all
+/// are warned about.
+pub fn read_remap(path: &Path, charmap: &CharMap) -> Result<Vec<usize>> {
+    if charmap.len() != 256 {
+        bail!(
+            "Error: --remap needs a 256-character table, not {}",
+            charmap.len()
+        );
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read remap file {}", path.display()))?;
+    let order = text
+        .lines()
+        .enumerate()
+        .map(|(n, line)| {
+            let entry = || -> Result<usize> {
+                if line.starts_with(|c: char| c.is_ascii_digit()) {
+                    return parse_index(line, charmap.len());
+                }
+                match unescape(line)?.as_slice() {
+                    [ch] => charmap
+                        .index_of(*ch)
+                        .with_context(|| format!("'{}' is not in the table", ch)),
+                    _ => bail!("expected an index or a single character, not '{}'", line),
+                }
+            };
+            entry()
+                .with_context(|| format!("Invalid remap file {}, line {}", path.display(), n + 1))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if order.len() != 256 {
+        bail!(
+            "Remap file {} has {} entries, expected exactly 256",
+            path.display(),
+            order.len()
+        );
+    }
+
+    let mut uses = vec![0; 256];
+    for &i in &order {
+        uses[i] += 1;
+    }
+    let list = |indices: Vec<usize>| {
+        indices
+            .iter()
+            .map(|&i| format!("{} '{}'", i, charmap.get(i)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let repeated: Vec<usize> = (0..256).filter(|&i| uses[i] > 1).collect();
+    if !repeated.is_empty() {
+        warning::emit(
+            Warning::new(
+                Code::RemapRepeated,
+                format!(
+                    "--remap places these indices more than once: {}",
+                    list(repeated.clone())
+                ),
+            )
+            .data(serde_json::json!({ "indices": repeated })),
+        );
+    }
+    let unused: Vec<usize> = (0..256).filter(|&i| uses[i] == 0).collect();
+    if !unused.is_empty() {
+        warning::emit(
+            Warning::new(
+                Code::RemapUnused,
+                format!("--remap leaves these indices out: {}", list(unused.clone())),
+            )
+            .data(serde_json::json!({ "indices": unused })),
+        );
+    }
+    Ok(order)
+}
+
+/// Expand `\uXXXX` and `\\` escapes in one line of a charset file.
+fn unescape(line: &str) -> Result<Vec<char>> {
+    let mut out = Vec::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let escaped = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 4)
+                    .and_then(char::from_u32);
+                match escaped {
+                    Some(ch) => out.push(ch),
+                    None => bail!("Invalid escape \"\\u{}\"", hex),
+                }
+            }
+            other => bail!(
+                "Invalid escape \"\\{}\" (expected \\uXXXX or \\\\)",
+                other.map(String::from).unwrap_or_default()
+            ),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a `--unicode-range` span: two codepoints joined by `-`, each written
+/// as `U+XXXX`, `0xXXXX`, or bare hex.
+pub fn parse_unicode_range(spec: &str) -> Result<(u32, u32)> {
+    let codepoint = |s: &str| {
+        let s = s.trim();
+        let hex = s
+            .strip_prefix("U+")
+            .or_else(|| s.strip_prefix("u+"))
+            .or_else(|| s.strip_prefix("0x"))
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|&c| c <= char::MAX as u32)
+            .with_context(|| format!("Invalid codepoint '{}' in range '{}'", s, spec))
+    };
+    let Some((start, end)) = spec.split_once('-') else {
+        bail!("Invalid range '{}': expected <start>-<end>", spec);
+    };
+    let (start, end) = (codepoint(start)?, codepoint(end)?);
+    if start > end {
+        bail!("Invalid range '{}': start is greater than end", spec);
+    }
+    Ok((start, end))
+}
@@ -0,0 +1,330 @@
+//! Built-in 256-character tables for the codepages the atlas can be laid
+//! out in. Tables are rows of 16 characters, matching the atlas grid; each
+//! codepage pairs a lower half, shared within its family, with its own
+//! upper half.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// A built-in character table, selected with `--codepage`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Codepage {
+    /// IBM PC / US
+    Cp437,
+    /// DOS Latin-1, Western European
+    Cp850,
+    /// DOS Latin-2, Central European
+    Cp852,
+    /// DOS Cyrillic (Russian)
+    Cp866,
+    /// ISO-8859-1, Western European; control codes blank
+    Latin1,
+    /// KOI8-R, Russian; control codes blank
+    Koi8r,
+    /// Commodore 64 upper case and graphics, by PETSCII code
+    PetsciiUnshifted,
+    /// Commodore 64 lower and upper case, by PETSCII code
+    PetsciiShifted,
+}
+
+impl Codepage {
+    /// Lowercase name as accepted by `--codepage`, for summaries and output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Codepage::Cp437 => "cp437",
+            Codepage::Cp850 => "cp850",
+            Codepage::Cp852 => "cp852",
+            Codepage::Cp866 => "cp866",
+            Codepage::Latin1 => "latin1",
+            Codepage::Koi8r => "koi8r",
+            Codepage::PetsciiUnshifted => "petscii-unshifted",
+            Codepage::PetsciiShifted => "petscii-shifted",
+        }
+    }
+
+    /// The IBM codepage number, for consumers that prefer an integer;
+    /// `None` for character sets IBM never numbered.
+    pub fn number(self) -> Option<u32> {
+        match self {
+            Codepage::Cp437 => Some(437),
+            Codepage::Cp850 => Some(850),
+            Codepage::Cp852 => Some(852),
+            Codepage::Cp866 => Some(866),
+            Codepage::Latin1 => Some(819),
+            Codepage::Koi8r => Some(878),
+            Codepage::PetsciiUnshifted | Codepage::PetsciiShifted => None,
+        }
+    }
+
+    /// Whether this is one of the PETSCII tables, which the C64 character
+    /// ROM reorders into screen codes.
+    pub fn is_petscii(self) -> bool {
+        matches!(self, Codepage::PetsciiUnshifted | Codepage::PetsciiShifted)
+    }
+
+    /// The character at `index` in this codepage.
+    pub fn char_at(self, index: u8) -> char {
+        let table = match self {
+            Codepage::Cp437 => &CP437,
+            Codepage::Cp850 => &CP850,
+            Codepage::Cp852 => &CP852,
+            Codepage::Cp866 => &CP866,
+            Codepage::Latin1 => &LATIN1,
+            Codepage::Koi8r => &KOI8R,
+            Codepage::PetsciiUnshifted => &PETSCII_UNSHIFTED,
+            Codepage::PetsciiShifted => &PETSCII_SHIFTED,
+        };
+        table[index as usize]
+    }
+}
+
+/// Every CP437 character in index order, as the atlas lays them out.
+pub const CP437: [char; 256] = table(&LOW, &CP437_HIGH);
+const CP850: [char; 256] = table(&LOW, &CP850_HIGH);
+const CP852: [char; 256] = table(&LOW, &CP852_HIGH);
+const CP866: [char; 256] = table(&LOW, &CP866_HIGH);
+const LATIN1: [char; 256] = table(&ASCII, &LATIN1_HIGH);
+const KOI8R: [char; 256] = table(&ASCII, &KOI8R_HIGH);
+const PETSCII_UNSHIFTED: [char; 256] = table(&PETSCII_UNSHIFTED_LOW, &PETSCII_UNSHIFTED_HIGH);
+const PETSCII_SHIFTED: [char; 256] = table(&PETSCII_SHIFTED_LOW, &PETSCII_SHIFTED_HIGH);
+
+/// The CP437 index that renders `c`, for encoding text to address the
+/// atlas. Space also appears at 0 and 255; the ASCII space (0x20) wins.
+pub fn cp437_index(c: char) -> Option<u8> {
+    if c == ' ' {
+        return Some(0x20);
+    }
+    CP437.iter().position(|&x| x == c).map(|i| i as u8)
+}
+
+/// Join a codepage's lower and upper halves into a full table at compile
+/// time.
+const fn table(low: &[&str; 8], high: &[&str; 8]) -> [char; 256] {
+    let mut table = [' '; 256];
+    let mut row = 0;
+    while row < 16 {
+        let bytes = if row < 8 { low[row] } else { high[row - 8] }.as_bytes();
+        let (mut offset, mut col) = (0, 0);
+        while offset < bytes.len() {
+            let (c, len) = decode_utf8(bytes, offset);
+            table[row * 16 + col] = c;
+            offset += len;
+            col += 1;
+        }
+        assert!(col == 16, "codepage rows hold 16 characters");
+        row += 1;
+    }
+    table
+}
+
+/// Decode the UTF-8 sequence at `offset`, returning it and its length.
+/// `str::chars` isn't usable in a const fn, and the input is a valid `str`.
+const fn decode_utf8(bytes: &[u8], offset: usize) -> (char, usize) {
+    let first = bytes[offset] as u32;
+    let (len, mut code) = match first {
+        0x00..=0x7F => (1, first),
+        0xC0..=0xDF => (2, first & 0x1F),
+        0xE0..=0xEF => (3, first & 0x0F),
+        _ => (4, first & 0x07),
+    };
+    let mut i = 1;
+    while i < len {
+        code = (code << 6) | (bytes[offset + i] as u32 & 0x3F);
+        i += 1;
+    }
+    match char::from_u32(code) {
+        Some(c) => (c, len),
+        None => panic!("codepage rows are valid UTF-8"),
+    }
+}
+
+/// Indices 0-127, shared by the DOS codepages: the CP437 pictures for the
+/// control range (index 0 renders as a space), ASCII, and the house at 127.
+const LOW: [&str; 8] = [
+    " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼",
+    "►◄↕‼¶§▬↨↑↓→←∟↔▲▼",
+    " !\"#$%&'()*+,-./",
+    "0123456789:;<=>?",
+    "@ABCDEFGHIJKLMNO",
+    "PQRSTUVWXYZ[\\]^_",
+    "`abcdefghijklmno",
+    "pqrstuvwxyz{|}~⌂",
+];
+
+/// Indices 0-127 of the ISO and KOI8 encodings: ASCII, with the control
+/// codes and DEL blank.
+const ASCII: [&str; 8] = [
+    "                ",
+    "                ",
+    " !\"#$%&'()*+,-./",
+    "0123456789:;<=>?",
+    "@ABCDEFGHIJKLMNO",
+    "PQRSTUVWXYZ[\\]^_",
+    "`abcdefghijklmno",
+    "pqrstuvwxyz{|}~ ",
+];
+
+// Indices 128-255 per codepage. Index 255 is a no-break space everywhere and
+// is drawn as a plain space, as CP437 always has been here.
+
+/// CP437, US.
+const CP437_HIGH: [&str; 8] = [
+    "ÇüéâäàåçêëèïîìÄÅ",
+    "ÉæÆôöòûùÿÖÜ¢£¥₧ƒ",
+    "áíóúñÑªº¿⌐¬½¼¡«»",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧",
+    "╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "αßΓπΣσµτΦΘΩδ∞φε∩",
+    "≡±≥≤⌠⌡÷≈°∙·√ⁿ²■ ",
+];
+
+/// CP850, Western European.
+const CP850_HIGH: [&str; 8] = [
+    "ÇüéâäàåçêëèïîìÄÅ",
+    "ÉæÆôöòûùÿÖÜø£Ø×ƒ",
+    "áíóúñÑªº¿®¬½¼¡«»",
+    "░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤",
+    "ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀",
+    "ÓßÔÒõÕµþÞÚÛÙýÝ¯´",
+    "\u{AD}±‗¾¶§÷¸°¨·¹³²■ ",
+];
+
+/// CP852, Central European.
+const CP852_HIGH: [&str; 8] = [
+    "ÇüéâäůćçłëŐőîŹÄĆ",
+    "ÉĹĺôöĽľŚśÖÜŤťŁ×č",
+    "áíóúĄąŽžĘę¬źČş«»",
+    "░▒▓│┤ÁÂĚŞ╣║╗╝Żż┐",
+    "└┴┬├─┼Ăă╚╔╩╦╠═╬¤",
+    "đĐĎËďŇÍÎě┘┌█▄ŢŮ▀",
+    "ÓßÔŃńňŠšŔÚŕŰýÝţ´",
+    "\u{AD}˝˛ˇ˘§÷¸°¨˙űŘř■ ",
+];
+
+/// CP866, Cyrillic.
+const CP866_HIGH: [&str; 8] = [
+    "АБВГДЕЖЗИЙКЛМНОП",
+    "РСТУФХЦЧШЩЪЫЬЭЮЯ",
+    "абвгдежзийклмноп",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧",
+    "╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "рстуфхцчшщъыьэюя",
+    "ЁёЄєЇїЎў°∙·√№¤■ ",
+];
+
+/// ISO-8859-1, Western European. The C1 controls at 128-159 are blank, and
+/// the no-break space at 160 is a plain space.
+const LATIN1_HIGH: [&str; 8] = [
+    "                ",
+    "                ",
+    " ¡¢£¤¥¦§¨©ª«¬\u{AD}®¯",
+    "°±²³´µ¶·¸¹º»¼½¾¿",
+    "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏ",
+    "ÐÑÒÓÔÕÖ×ØÙÚÛÜÝÞß",
+    "àáâãäåæçèéêëìíîï",
+    "ðñòóôõö÷øùúûüýþÿ",
+];
+
+/// KOI8-R, Russian; the no-break space at 154 is a plain space.
+const KOI8R_HIGH: [&str; 8] = [
+    "─│┌┐└┘├┤┬┴┼▀▄█▌▐",
+    "░▒▓⌠■∙√≈≤≥ ⌡°²·÷",
+    "═║╒ё╓╔╕╖╗╘╙╚╛╜╝╞",
+    "╟╠╡Ё╢╣╤╥╦╧╨╩╪╫╬©",
+    "юабцдефгхийклмно",
+    "пярстужвьызшэщчъ",
+    "ЮАБЦДЕФГХИЙКЛМНО",
+    "ПЯРСТУЖВЬЫЗШЭЩЧЪ",
+];
+
+// The PETSCII tables follow the Unicode 13 mappings for the C64 glyphs,
+// mostly in the Symbols for Legacy Computing block. Control codes are
+// blank, 0xC0-0xDF and 0xE0-0xFE repeat 0x60-0x7F and 0xA0-0xBE, and 0xFF
+// repeats 0x7E, as the C64 draws them.
+
+/// PETSCII 0-127 in upper case and graphics mode.
+const PETSCII_UNSHIFTED_LOW: [&str; 8] = [
+    "                ",
+    "                ",
+    " !\"#$%&'()*+,-./",
+    "0123456789:;<=>?",
+    "@ABCDEFGHIJKLMNO",
+    "PQRSTUVWXYZ[£]↑←",
+    "─♠🭲🭸🭷🭶🭺🭱🭴╮╰╯🭼╲╱🭽",
+    "🭾●🭻♥🭰╭╳○♣🭵♦┼🮌│π◥",
+];
+
+/// PETSCII 128-255 in upper case and graphics mode.
+const PETSCII_UNSHIFTED_HIGH: [&str; 8] = [
+    "                ",
+    "                ",
+    " ▌▄▔▁▏▒▕🮏◤🮇├▗└┐▂",
+    "┌┴┬┤▎▍🮈🮂🮃▃🭿▖▝┘▘▚",
+    "─♠🭲🭸🭷🭶🭺🭱🭴╮╰╯🭼╲╱🭽",
+    "🭾●🭻♥🭰╭╳○♣🭵♦┼🮌│π◥",
+    " ▌▄▔▁▏▒▕🮏◤🮇├▗└┐▂",
+    "┌┴┬┤▎▍🮈🮂🮃▃🭿▖▝┘▘π",
+];
+
+/// PETSCII 0-127 in lower and upper case mode.
+const PETSCII_SHIFTED_LOW: [&str; 8] = [
+    "                ",
+    "                ",
+    " !\"#$%&'()*+,-./",
+    "0123456789:;<=>?",
+    "@abcdefghijklmno",
+    "pqrstuvwxyz[£]↑←",
+    "─ABCDEFGHIJKLMNO",
+    "PQRSTUVWXYZ┼🮌│🮖🮘",
+];
+
+/// PETSCII 128-255 in lower and upper case mode.
+const PETSCII_SHIFTED_HIGH: [&str; 8] = [
+    "                ",
+    "                ",
+    " ▌▄▔▁▏▒▕🮏🮙🮇├▗└┐▂",
+    "┌┴┬┤▎▍🮈🮂🮃▃✓▖▝┘▘▚",
+    "─ABCDEFGHIJKLMNO",
+    "PQRSTUVWXYZ┼🮌│🮖🮘",
+    " ▌▄▔▁▏▒▕🮏🮙🮇├▗└┐▂",
+    "┌┴┬┤▎▍🮈🮂🮃▃✓▖▝┘▘🮖",
+];
+
+/// The PETSCII code whose glyph the C64 character ROM holds at screen code
+/// `screen` (0-127); screen codes 128-255 are the same glyphs reversed.
+pub fn petscii_for_screen_code(screen: u8) -> u8 {
+    match screen & 0x7F {
+        code @ 0x00..=0x1F => code + 0x40,
+        code @ 0x20..=0x3F => code,
+        code @ 0x40..=0x5F => code + 0x20,
+        code => code + 0x40,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_cp437_char_encodes_to_its_own_index() {
+        for (i, &c) in CP437.iter().enumerate() {
+            if c != ' ' {
+                assert_eq!(cp437_index(c), Some(i as u8), "'{}' at {:#04x}", c, i);
+            }
+        }
+    }
+
+    #[test]
+    fn space_encodes_to_0x20_over_0x00_and_0xff() {
+        let spaces: Vec<usize> = (0..256).filter(|&i| CP437[i] == ' ').collect();
+        assert_eq!(spaces, [0x00, 0x20, 0xFF]);
+        assert_eq!(cp437_index(' '), Some(0x20));
+        // The no-break space that 0xFF stands for isn't in the table
+        assert_eq!(cp437_index('\u{A0}'), None);
+        assert_eq!(cp437_index('€'), None);
+    }
+}
@@ -0,0 +1,60 @@
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::Args;
+
+/// Layer the TOML file at `path` under the command line: every key sets the
+/// option of the same name unless that option was given as a flag. Keys may
+/// be spelled with `_` or `-`; unknown keys are errors so a typo can't
+/// silently fall back to the default.
+pub fn apply(path: &Path, args: &Args, matches: &ArgMatches) -> Result<Args> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file: Map<String, Value> =
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+    overlay(args, file, path, |id| {
+        matches.value_source(id) == Some(ValueSource::CommandLine)
+    })
+}
+
+/// `base` with every option named in `options` replaced, except those for
+/// which `keep_base` is true. `origin` names the file in errors.
+pub fn overlay(
+    base: &Args,
+    options: Map<String, Value>,
+    origin: &Path,
+    keep_base: impl Fn(&str) -> bool,
+) -> Result<Args> {
+    // Every option serializes, unset ones as null, so the keys of this
+    // object are exactly the options a file may set.
+    let Value::Object(mut merged) = serde_json::to_value(base)? else {
+        unreachable!("Args serializes as a map");
+    };
+    for (key, value) in options {
+        let id = key.replace('-', "_");
+        if !merged.contains_key(&id) {
+            bail!("Unknown option '{}' in {}", key, origin.display());
+        }
+        if !keep_base(&id) {
+            merged.insert(id, value);
+        }
+    }
+
+    let mut merged: Args = serde_json::from_value(Value::Object(merged))
+        .with_context(|| format!("Invalid option value in {}", origin.display()))?;
+    merged.config = base.config.clone();
+    merged.preset = base.preset;
+    merged.print_config = base.print_config;
+    merged.manifest = base.manifest.clone();
+    merged.fail_fast = base.fail_fast;
+    merged.watch = base.watch;
+    Ok(merged)
+}
+
+/// The effective options as a config file, for `--print-config`.
+pub fn to_toml(args: &Args) -> Result<String> {
+    toml::to_string(args).context("Failed to serialize configuration")
+}
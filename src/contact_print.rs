@@ -0,0 +1,179 @@
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+
+/// Spacing around labels and between variant cells.
+const MARGIN: u32 = 2;
+/// Gutter brightness, distinct from both the white cell background and ink.
+const GUTTER: u8 = 160;
+/// Threshold offsets shown next to the default (brightness < 128) result.
+const THRESHOLD_DELTA: u8 = 16;
+
+/// 3x5 digit glyphs, one 3-bit row pattern per line (MSB = leftmost pixel).
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const DIGIT_WIDTH: u32 = 3;
+const DIGIT_HEIGHT: u32 = 5;
+
+/// Draw `value` as a `digits`-wide zero-padded label in black.
+pub fn draw_label(image: &mut GrayImage, value: u32, digits: usize, x: u32, y: u32) {
+    let text = format!("{:0digits$}", value);
+    for (n, digit) in text.bytes().enumerate() {
+        let rows = DIGITS[(digit - b'0') as usize];
+        let left = x + n as u32 * (DIGIT_WIDTH + 1);
+        for (dy, row) in rows.iter().enumerate() {
+            for dx in 0..DIGIT_WIDTH {
+                if row & (1 << (DIGIT_WIDTH - 1 - dx)) != 0 {
+                    image.fill_rect(left + dx, y + dy as u32, 1, 1, 0);
+                }
+            }
+        }
+    }
+}
+
+/// Binary ink mask of a cell: pixels darker than `threshold`.
+fn threshold(cell: &GrayImage, threshold: u8) -> Vec<bool> {
+    cell.pixels.iter().map(|&v| v < threshold).collect()
+}
+
+/// Grow (`grow == true`) or shrink a mask by one pixel in the 4-neighborhood.
+/// Pixels outside the cell count as background.
+fn morph(mask: &[bool], width: u32, height: u32, grow: bool) -> Vec<bool> {
+    let at = |x: i64, y: i64| -> bool {
+        if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+            false
+        } else {
+            mask[(y * width as i64 + x) as usize]
+        }
+    };
+    let mut out = Vec::with_capacity(mask.len());
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let neighbours = [at(x - 1, y), at(x + 1, y), at(x, y - 1), at(x, y + 1)];
+            out.push(if grow {
+                at(x, y) || neighbours.iter().any(|&n| n)
+            } else {
+                at(x, y) && neighbours.iter().all(|&n| n)
+            });
+        }
+    }
+    out
+}
+
+/// The binary variants of a cell, in display order after the raw render:
+/// default threshold, threshold -16, threshold +16, weight +1, weight -1.
+fn binary_variants(cell: &GrayImage) -> [Vec<bool>; 5] {
+    let base = threshold(cell, 128);
+    [
+        base.clone(),
+        threshold(cell, 128 - THRESHOLD_DELTA),
+        threshold(cell, 128 + THRESHOLD_DELTA),
+        morph(&base, cell.width, cell.height, true),
+        morph(&base, cell.width, cell.height, false),
+    ]
+}
+
+/// Largest number of pixels by which any variant differs from the default
+/// threshold result. Glyphs that are sensitive to tuning score high.
+fn divergence(variants: &[Vec<bool>]) -> usize {
+    let base = &variants[0];
+    variants[1..]
+        .iter()
+        .map(|v| v.iter().zip(base).filter(|(a, b)| a != b).count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Indices ordered by descending divergence, ties kept in index order.
+fn order_by_divergence(scores: &[usize]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].cmp(&scores[a]));
+    order
+}
+
+/// Build the contact print: one row per glyph holding its index label, the
+/// raw antialiased render, and each binary variant, with the glyphs whose
+/// variants disagree most sorted to the top. The first `count` cells of
+/// `grid` are read from the atlas.
+pub fn build_contact_print(atlas: &GrayImage, grid: &Grid, count: usize) -> GrayImage {
+    let (cell_width, cell_height) = (grid.glyph_width, grid.glyph_height);
+    let cells: Vec<GrayImage> = (0..count)
+        .map(|i| {
+            let (x, y) = grid.glyph_origin(i);
+            atlas.crop(x, y, cell_width, cell_height)
+        })
+        .collect();
+    // Labels are at least three digits so CP437 sheets keep their layout
+    let digits = (count.saturating_sub(1)).to_string().len().max(3);
+    let label_width = digits as u32 * (DIGIT_WIDTH + 1);
+    let variants: Vec<[Vec<bool>; 5]> = cells.iter().map(binary_variants).collect();
+    let scores: Vec<usize> = variants.iter().map(|v| divergence(v)).collect();
+
+    let columns = 1 + variants[0].len() as u32;
+    let row_height = cell_height.max(DIGIT_HEIGHT) + MARGIN;
+    let width = MARGIN + label_width + MARGIN + columns * (cell_width + MARGIN);
+    let height = MARGIN + cells.len() as u32 * row_height;
+    let mut sheet = GrayImage::new(width, height, GUTTER);
+
+    for (row, &i) in order_by_divergence(&scores).iter().enumerate() {
+        let y = MARGIN + row as u32 * row_height;
+        draw_label(&mut sheet, i as u32, digits, MARGIN, y);
+
+        let mut x = MARGIN + label_width + MARGIN;
+        sheet.paste(&cells[i], x, y);
+        for mask in &variants[i] {
+            x += cell_width + MARGIN;
+            let rendered = GrayImage {
+                width: cell_width,
+                height: cell_height,
+                pixels: mask.iter().map(|&ink| if ink { 0 } else { 255 }).collect(),
+            };
+            sheet.paste(&rendered, x, y);
+        }
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheet_rows_sort_the_most_divergent_cells_first() {
+        // Three 4x6 cells side by side: blank, solid ink, and a gray just
+        // under the threshold that --16 and +16 judge differently
+        let grid = Grid::new(3, 3, false, 4, 6);
+        let mut atlas = GrayImage::new(12, 6, 255);
+        atlas.fill_rect(4, 0, 4, 6, 0);
+        atlas.fill_rect(8, 0, 4, 6, 120);
+
+        let sheet = build_contact_print(&atlas, &grid, 3);
+        // Margin, a three-digit label, margin, then six columns of cell
+        // and margin; each row is the cell height and a margin
+        assert_eq!((sheet.width, sheet.height), (2 + 12 + 2 + 6 * 6, 2 + 3 * 8));
+
+        // Gray flips all 24 pixels, solid ink loses its 16 edge pixels to
+        // thinning, and blank never changes
+        let raw = |row: u32| sheet.crop(16, 2 + row * 8, 4, 6);
+        assert_eq!(raw(0), atlas.crop(8, 0, 4, 6));
+        assert_eq!(raw(1), atlas.crop(4, 0, 4, 6));
+        assert_eq!(raw(2), atlas.crop(0, 0, 4, 6));
+
+        let mut label = GrayImage::new(12, 5, GUTTER);
+        draw_label(&mut label, 2, 3, 0, 0);
+        assert_eq!(sheet.crop(2, 2, 12, 5), label);
+
+        // The lower threshold, the second variant, leaves the gray blank
+        let lower = sheet.crop(16 + 2 * 6, 2, 4, 6);
+        assert_eq!(lower, GrayImage::new(4, 6, 255));
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+/// A cell that landed in one of the coverage categories.
+#[derive(Serialize)]
+pub struct Entry {
+    pub index: usize,
+    pub char: String,
+}
+
+impl Entry {
+    pub fn new(index: usize, ch: char) -> Entry {
+        Entry {
+            index,
+            char: ch.to_string(),
+        }
+    }
+}
+
+/// Which cells the font failed to represent, gathered while blitting.
+#[derive(Default, Serialize)]
+pub struct Coverage {
+    /// No font in the chain has a glyph (no metrics, or only .notdef).
+    pub missing: Vec<Entry>,
+    /// Glyphs with an empty ink box, other than whitespace and controls,
+    /// which are expected to be blank.
+    pub zero_sized: Vec<Entry>,
+    /// Glyphs whose ink extends past the cell.
+    pub oversized: Vec<Entry>,
+    /// Missing cells filled with the `--missing-glyph` placeholder; each
+    /// is also listed under `missing`.
+    pub placeholder: Vec<Entry>,
+}
+
+impl Coverage {
+    /// One `category: indices` line per non-empty category.
+    pub fn summary(&self) -> Vec<String> {
+        [
+            ("missing", &self.missing),
+            ("zero-sized", &self.zero_sized),
+            ("oversized", &self.oversized),
+            ("placeholder", &self.placeholder),
+        ]
+        .into_iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(name, entries)| {
+            let list = entries
+                .iter()
+                .map(|e| format!("{} '{}'", e.index, e.char))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} {}: {}", entries.len(), name, list)
+        })
+        .collect()
+    }
+}
+
+/// The `--coverage-report` document.
+#[derive(Serialize)]
+pub struct CoverageReport<'a> {
+    pub font: &'a Path,
+    pub charset: &'a str,
+    pub total: usize,
+    #[serde(flatten)]
+    pub coverage: &'a Coverage,
+    /// Missing indices excused by `--check-allow-missing`.
+    pub allowed_missing: Vec<usize>,
+    /// Whether `--check` would pass.
+    pub passed: bool,
+}
+
+/// Write `report` as pretty-printed JSON.
+pub fn write_coverage_report(path: &Path, report: &CoverageReport) -> Result<()> {
+    let json =
+        serde_json::to_string_pretty(report).context("Failed to serialize coverage report")?;
+    std::fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,87 @@
+//! `--emit-css`: a stylesheet that uses the atlas as a CSS sprite sheet,
+//! one class per cell whose background position shows that glyph.
+
+use anyhow::{Context, Result, bail};
+use std::fmt::Write as _;
+use std::path::{Component, Path, PathBuf};
+
+use mycp437generator::grid::Grid;
+
+/// Check that `prefix` can start a CSS class name as it is, unescaped.
+pub fn check_prefix(prefix: &str) -> Result<()> {
+    let valid = prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        && prefix.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+    if !valid {
+        bail!(
+            "Error: --css-prefix '{}' must be letters, digits, '-' and '_', starting with a letter or '_'",
+            prefix
+        );
+    }
+    Ok(())
+}
+
+/// `target` as a URL relative to the directory `from`.
+fn relative_url(target: &Path, from: &Path) -> Result<String> {
+    let target = std::path::absolute(target)
+        .with_context(|| format!("Failed to resolve {}", target.display()))?;
+    let from = std::path::absolute(from)
+        .with_context(|| format!("Failed to resolve {}", from.display()))?;
+    let (target, from): (Vec<Component>, Vec<Component>) =
+        (target.components().collect(), from.components().collect());
+    let common = target.iter().zip(&from).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    relative.extend(&target[common..]);
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Ok(parts.join("/"))
+}
+
+/// The stylesheet for `count` cells laid out in `grid`, saved at `css` and
+/// pointing at the atlas PNG at `png`. Each class covers the glyph box.
+pub fn stylesheet(
+    prefix: &str,
+    png: &Path,
+    css: &Path,
+    grid: &Grid,
+    count: usize,
+) -> Result<String> {
+    let from = css
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let url = relative_url(png, from)?;
+    if url.contains(['"', '\\', '\n']) {
+        bail!("Error: --emit-css can't quote the path '{}' in a URL", url);
+    }
+
+    let mut out = String::new();
+    writeln!(out, ":root {{").unwrap();
+    writeln!(out, "  --{}-cell-width: {}px;", prefix, grid.glyph_width).unwrap();
+    writeln!(out, "  --{}-cell-height: {}px;", prefix, grid.glyph_height).unwrap();
+    writeln!(out, "  --{}-stride-x: {}px;", prefix, grid.stride_x()).unwrap();
+    writeln!(out, "  --{}-stride-y: {}px;", prefix, grid.stride_y()).unwrap();
+    writeln!(out, "}}\n").unwrap();
+    writeln!(out, "[class^=\"{0}-\"], [class*=\" {0}-\"] {{", prefix).unwrap();
+    writeln!(out, "  display: inline-block;").unwrap();
+    writeln!(out, "  background-image: url(\"{}\");", url).unwrap();
+    writeln!(out, "  background-repeat: no-repeat;").unwrap();
+    writeln!(out, "  image-rendering: pixelated;").unwrap();
+    writeln!(out, "}}\n").unwrap();
+    for i in 0..count {
+        let (x, y) = grid.glyph_origin(i);
+        writeln!(
+            out,
+            ".{}-{:#04x} {{ background-position: -{}px -{}px; width: {}px; height: {}px; }}",
+            prefix, i, x, y, grid.glyph_width, grid.glyph_height
+        )
+        .unwrap();
+    }
+    Ok(out)
+}
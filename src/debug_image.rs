@@ -0,0 +1,84 @@
+use anyhow::Result;
+use sdl3::pixels::PixelFormat;
+use sdl3::surface::Surface;
+
+use crate::CellStatus;
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+
+const GRID_LINE: [u8; 3] = [255, 0, 255];
+const BASELINE: [u8; 3] = [0, 255, 255];
+/// Background of cells whose glyph was skipped, clipped, or replaced.
+const FLAGGED: [u8; 3] = [255, 160, 160];
+/// Background of cells drawn from `--override-dir` images.
+const OVERRIDDEN: [u8; 3] = [160, 255, 160];
+/// Background of cells shrunk by `--overflow scale`.
+const SCALED: [u8; 3] = [255, 230, 140];
+
+/// Copy of the atlas for eyeballing placement: each cell is framed by
+/// 1-pixel magenta lines (inserted between cells, so no glyph pixels are
+/// covered), the first row below the baseline is cyan wherever it is
+/// background, cells that were skipped, clipped, or given a placeholder get
+/// a red background, cells shrunk to fit a yellow one, and cells drawn
+/// from override images a green one.
+/// `baseline` is measured from the top of the glyph box.
+pub fn build_debug_image(
+    atlas: &GrayImage,
+    grid: &Grid,
+    baseline: u32,
+    status: &[CellStatus],
+) -> Result<Surface<'static>> {
+    let (stride_x, stride_y) = (grid.stride_x(), grid.stride_y());
+    let width = grid.columns * (stride_x + 1) + 1;
+    let height = grid.rows * (stride_y + 1) + 1;
+    let mut pixels = vec![GRID_LINE; (width * height) as usize];
+
+    for col in 0..grid.columns {
+        for row in 0..grid.rows {
+            let index = if grid.column_major {
+                col * grid.rows + row
+            } else {
+                row * grid.columns + col
+            } as usize;
+            let background = match status.get(index) {
+                Some(CellStatus::Skipped | CellStatus::Clipped | CellStatus::Placeholder) => {
+                    Some(FLAGGED)
+                }
+                Some(CellStatus::Overridden) => Some(OVERRIDDEN),
+                Some(CellStatus::Scaled) => Some(SCALED),
+                _ => None,
+            };
+            for y in 0..stride_y {
+                for x in 0..stride_x {
+                    let value = atlas.get(col * stride_x + x, row * stride_y + y);
+                    let on_baseline = y == grid.padding + baseline
+                        && (grid.padding..grid.padding + grid.glyph_width).contains(&x);
+                    let rgb = if value < 128 {
+                        [value; 3]
+                    } else if on_baseline {
+                        BASELINE
+                    } else if let Some(rgb) = background {
+                        rgb
+                    } else {
+                        [value; 3]
+                    };
+                    let out_x = col * (stride_x + 1) + 1 + x;
+                    let out_y = row * (stride_y + 1) + 1 + y;
+                    pixels[(out_y * width + out_x) as usize] = rgb;
+                }
+            }
+        }
+    }
+
+    let mut surface = Surface::new(width, height, PixelFormat::RGB24)?;
+    let pitch = surface.pitch() as usize;
+    surface.with_lock_mut(|data: &mut [u8]| {
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let offset = y * pitch + x * 3;
+                data[offset..offset + 3].copy_from_slice(&pixels[y * width as usize + x]);
+            }
+        }
+    });
+    Ok(surface)
+}
@@ -0,0 +1,167 @@
+//! `--diff`: compare the atlas against a reference, a PNG or an earlier
+//! hex dump, cell by cell on the thresholded bits.
+
+use anyhow::{Context, Result, bail};
+#[cfg(feature = "backend-sdl")]
+use sdl3::pixels::PixelFormat;
+#[cfg(feature = "backend-sdl")]
+use sdl3::surface::Surface;
+use std::path::Path;
+
+use crate::charmap::CharMap;
+use crate::image_io;
+use mycp437generator::gray::GrayImage;
+use mycp437generator::grid::Grid;
+use mycp437generator::{Atlas, INK_THRESHOLD, hex_dump, log};
+
+/// What the atlas is compared against.
+pub struct Reference {
+    pub image: GrayImage,
+    /// The grid a hex dump records; a PNG is assumed to share the atlas's.
+    pub grid: Option<Grid>,
+}
+
+impl Reference {
+    pub fn from_png(path: &Path) -> Result<Reference> {
+        Ok(Reference {
+            image: image_io::load(path)
+                .with_context(|| format!("Failed to load {}", path.display()))?,
+            grid: None,
+        })
+    }
+
+    pub fn from_hex_dump(path: &Path) -> Result<Reference> {
+        let dump = hex_dump::read_hex_dump(path)?;
+        Ok(Reference {
+            image: dump.to_image(),
+            grid: Some(dump.grid),
+        })
+    }
+}
+
+/// A cell whose bits differ from the reference.
+pub struct GlyphDiff {
+    pub index: usize,
+    pub ch: char,
+    pub pixels: u32,
+}
+
+/// Why two atlases can't be compared cell by cell, or `None` when they can.
+fn structural_difference(atlas: &Atlas, reference: &Reference) -> Option<String> {
+    let (image, other) = (&atlas.image, &reference.image);
+    if (image.width, image.height) != (other.width, other.height) {
+        return Some(format!(
+            "the atlas is {}x{} but the reference is {}x{}",
+            image.width, image.height, other.width, other.height
+        ));
+    }
+    let grid = &atlas.grid;
+    let other = reference.grid.as_ref()?;
+    let shape = |g: &Grid| {
+        (
+            g.columns,
+            g.rows,
+            g.column_major,
+            g.stride_x(),
+            g.stride_y(),
+        )
+    };
+    if shape(grid) != shape(other) {
+        return Some(format!(
+            "the atlas has {}x{} cells of {}x{} but the reference {}x{} cells of {}x{}",
+            grid.columns,
+            grid.rows,
+            grid.stride_x(),
+            grid.stride_y(),
+            other.columns,
+            other.rows,
+            other.stride_x(),
+            other.stride_y()
+        ));
+    }
+    None
+}
+
+fn is_ink(value: u8) -> bool {
+    value < INK_THRESHOLD
+}
+
+/// Every cell of `atlas` whose bits differ from `reference`, padding
+/// included. Fails on a structural difference.
+pub fn compare(atlas: &Atlas, charmap: &CharMap, reference: &Reference) -> Result<Vec<GlyphDiff>> {
+    if let Some(difference) = structural_difference(atlas, reference) {
+        bail!("Error: structural difference: {}", difference);
+    }
+    let grid = &atlas.grid;
+    let mut diffs = Vec::new();
+    for (index, &ch) in charmap.chars().iter().enumerate() {
+        let (x0, y0) = grid.origin(index);
+        let mut pixels = 0;
+        for y in y0..y0 + grid.cell_height() {
+            for x in x0..x0 + grid.cell_width() {
+                if is_ink(atlas.image.get(x, y)) != is_ink(reference.image.get(x, y)) {
+                    pixels += 1;
+                }
+            }
+        }
+        if pixels > 0 {
+            diffs.push(GlyphDiff { index, ch, pixels });
+        }
+    }
+    Ok(diffs)
+}
+
+/// Log one line per differing glyph and a total, and fail when any glyph
+/// differs by more than `tolerance` pixels.
+pub fn report(diffs: &[GlyphDiff], count: usize, tolerance: u32) -> Result<()> {
+    for diff in diffs {
+        log!(
+            "  {:>3} U+{:04X} {:?} differs by {} px",
+            diff.index,
+            diff.ch as u32,
+            diff.ch,
+            diff.pixels
+        );
+    }
+    let beyond = diffs.iter().filter(|d| d.pixels > tolerance).count();
+    log!(
+        "Diff: {} of {} glyphs differ, {} by more than {} pixels",
+        diffs.len(),
+        count,
+        beyond,
+        tolerance
+    );
+    if beyond > 0 {
+        bail!(
+            "Error: {} glyphs differ from the reference by more than --diff-tolerance {}",
+            beyond,
+            tolerance
+        );
+    }
+    Ok(())
+}
+
+/// The atlas in gray with every pixel whose bit differs from `reference`
+/// in red. The images must be the same size.
+#[cfg(feature = "backend-sdl")]
+pub fn diff_surface(atlas: &GrayImage, reference: &GrayImage) -> Result<Surface<'static>> {
+    let mut surface = Surface::new(atlas.width, atlas.height, PixelFormat::RGBA32)?;
+    let pitch = surface.pitch() as usize;
+    surface.with_lock_mut(|data: &mut [u8]| {
+        for y in 0..atlas.height {
+            for x in 0..atlas.width {
+                let value = atlas.get(x, y);
+                let rgba = if is_ink(value) != is_ink(reference.get(x, y)) {
+                    [255, 0, 0, 255]
+                } else {
+                    // Faded so the red stands out against unchanged ink
+                    let faded = 128 + value / 2;
+                    [faded, faded, faded, 255]
+                };
+                let offset = y as usize * pitch + x as usize * 4;
+                data[offset..offset + 4].copy_from_slice(&rgba);
+            }
+        }
+    });
+    Ok(surface)
+}
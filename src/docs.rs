@@ -0,0 +1,48 @@
+//! `completions` and `manpage`: shell completion scripts and a roff man
+//! page, both generated from the command line's own definition so they
+//! never fall behind it.
+
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::io::Write as _;
+use std::path::Path;
+
+use mycp437generator::failure::{Categorize, Failure};
+
+/// Write the completion script for `shell` to stdout, or into `dir` under
+/// the name that shell looks for.
+pub fn completions(mut command: Command, shell: Shell, dir: Option<&Path>) -> Result<()> {
+    let name = command.get_name().to_string();
+    match dir {
+        Some(dir) => {
+            let path = clap_complete::generate_to(shell, &mut command, name, dir)
+                .with_context(|| format!("Failed to write completions into {}", dir.display()))
+                .failure(Failure::Output)?;
+            println!("Completions saved to {}", path.display());
+        }
+        None => clap_complete::generate(shell, &mut command, name, &mut std::io::stdout()),
+    }
+    Ok(())
+}
+
+/// Write the man page to stdout, or one page per command into `dir`.
+pub fn manpage(command: Command, dir: Option<&Path>) -> Result<()> {
+    match dir {
+        Some(dir) => {
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("Failed to write man pages into {}", dir.display()))
+                .failure(Failure::Output)?;
+            println!("Man pages saved to {}", dir.display());
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            clap_mangen::Man::new(command)
+                .render(&mut stdout)
+                .and_then(|()| stdout.flush())
+                .context("Failed to write man page to stdout")
+                .failure(Failure::Output)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! What kind of failure ended a run, which picks its exit status. Errors
+//! are tagged with a `Failure` where they arise, which leaves their
+//! messages as they were; untagged errors are argument validation, which
+//! most bail paths are.
+
+use anyhow::{Error, Result};
+use std::fmt;
+
+/// A category of failure, each with its own exit status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Invalid options or option files
+    Usage,
+    /// A font that can't be found, read, or parsed
+    Font,
+    /// No size of the font gives the requested atlas
+    Sizing,
+    /// The glyphs didn't render as they must
+    Render,
+    /// An output that couldn't be written
+    Output,
+    /// `--strict` or `--deny-warnings` objections
+    Strict,
+}
+
+impl Failure {
+    /// The process exit status.
+    pub fn code(self) -> u8 {
+        match self {
+            Failure::Usage => 2,
+            Failure::Font => 3,
+            Failure::Sizing => 4,
+            Failure::Render => 5,
+            Failure::Output => 6,
+            Failure::Strict => 7,
+        }
+    }
+
+    /// The category `error` was last tagged with, or `Usage` for an
+    /// untagged error.
+    pub fn of(error: &Error) -> Failure {
+        error
+            .downcast_ref::<Categorized>()
+            .map_or(Failure::Usage, |c| c.failure)
+    }
+}
+
+/// An error and its category; shown exactly as the error is.
+struct Categorized {
+    failure: Failure,
+    error: Error,
+}
+
+impl fmt::Display for Categorized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Debug for Categorized {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for Categorized {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Tag the error of a `Result` with its category.
+pub trait Categorize<T> {
+    fn failure(self, failure: Failure) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> Categorize<T> for std::result::Result<T, E> {
+    fn failure(self, failure: Failure) -> Result<T> {
+        self.map_err(|error| {
+            Error::new(Categorized {
+                failure,
+                error: error.into(),
+            })
+        })
+    }
+}
@@ -0,0 +1,123 @@
+//! `--emit-pico8` and `--emit-tic80`: the atlas as a custom font for the
+//! fantasy consoles, whose glyphs are at most 8x8 pixels. CP437 index `i`
+//! becomes character code `i`.
+
+use anyhow::{Result, bail};
+use std::fmt::Write as _;
+
+use crate::grid::Grid;
+use mycp437generator::{Atlas, INK_THRESHOLD};
+
+/// The largest glyph box either console draws.
+const MAX_GLYPH: u32 = 8;
+/// PICO-8 keeps its custom font at 0x5600: an 8-byte header, then 8 bytes
+/// for each of characters 1-255.
+const PICO8_FONT_LEN: usize = 2048;
+/// TIC-80 `font()` draws from the foreground sprites; this palette index
+/// (white in the default palette) is the ink.
+const TIC80_INK: u8 = 12;
+
+/// Fail with the --font-width to ask for when the glyph box is larger than
+/// a console draws.
+fn check_glyph_box(grid: &Grid, console: &str) -> Result<()> {
+    let (width, height) = (grid.glyph_width, grid.glyph_height);
+    if width <= MAX_GLYPH && height <= MAX_GLYPH {
+        return Ok(());
+    }
+    // Glyph boxes grow with the cell width, so shrink that by the worse ratio
+    let fit = grid.cell_width() as u64 * MAX_GLYPH as u64 / width.max(height) as u64;
+    bail!(
+        "Error: {} glyphs are at most {}x{}, but these are {}x{}; try --font-width {}",
+        console,
+        MAX_GLYPH,
+        MAX_GLYPH,
+        width,
+        height,
+        fit.max(1)
+    );
+}
+
+/// The 8 rows of glyph box `index`, one byte each, leftmost pixel in bit 0.
+fn glyph_rows(atlas: &Atlas, index: usize) -> [u8; 8] {
+    let grid = &atlas.grid;
+    let (left, top) = grid.glyph_origin(index);
+    let mut rows = [0u8; 8];
+    for (y, row) in rows.iter_mut().enumerate().take(grid.glyph_height as usize) {
+        for x in 0..grid.glyph_width {
+            if atlas.image.get(left + x, top + y as u32) < INK_THRESHOLD {
+                *row |= 1 << x;
+            }
+        }
+    }
+    rows
+}
+
+/// A PICO-8 Lua line that pokes the glyphs in as the custom font, for
+/// `print` to use after `\14`. Characters advance by the cell width.
+pub fn format_pico8(atlas: &Atlas, count: usize) -> Result<String> {
+    let grid = &atlas.grid;
+    check_glyph_box(grid, "PICO-8")?;
+    let (advance, line) = (grid.cell_width(), grid.cell_height());
+    if advance > 255 || line > 255 {
+        bail!(
+            "Error: PICO-8 font cells are at most 255 pixels, not {}x{}",
+            advance,
+            line
+        );
+    }
+    let mut font = vec![0u8; PICO8_FONT_LEN];
+    // Width below 128, width from 128, height, draw offset x and y
+    font[..5].copy_from_slice(&[advance as u8, advance as u8, line as u8, 0, 0]);
+    for index in 1..count.min(256) {
+        font[index * 8..index * 8 + 8].copy_from_slice(&glyph_rows(atlas, index));
+    }
+
+    let mut escaped = String::new();
+    for byte in &font {
+        write!(escaped, "\\x{:02x}", byte).unwrap();
+    }
+    let mut out = String::new();
+    writeln!(
+        out,
+        "-- custom font, {}x{} glyphs in {}x{} cells; print \"\\14\" to switch to it",
+        grid.glyph_width, grid.glyph_height, advance, line
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "poke(0x5600,ord(\"{}\",1,{}))",
+        escaped, PICO8_FONT_LEN
+    )
+    .unwrap();
+    Ok(out)
+}
+
+/// A TIC-80 cart `<SPRITES>` section holding glyph `i` in foreground
+/// sprite `i`, as `font()` expects, ink in palette index 12 on 0.
+pub fn format_tic80(atlas: &Atlas, count: usize) -> Result<String> {
+    let grid = &atlas.grid;
+    check_glyph_box(grid, "TIC-80")?;
+    let mut out = String::new();
+    writeln!(out, "-- <SPRITES>").unwrap();
+    for index in 0..count.min(256) {
+        let rows = glyph_rows(atlas, index);
+        if rows == [0; 8] {
+            continue;
+        }
+        let pixels: String = rows
+            .iter()
+            .flat_map(|&row| (0..8).map(move |x| if row & (1 << x) != 0 { TIC80_INK } else { 0 }))
+            .map(|color| format!("{:x}", color))
+            .collect();
+        writeln!(out, "-- {:03}:{}", index, pixels).unwrap();
+    }
+    writeln!(out, "-- </SPRITES>").unwrap();
+    writeln!(
+        out,
+        "-- font(text, x, y, 0, {}, {}) draws with these",
+        grid.cell_width(),
+        grid.cell_height()
+    )
+    .unwrap();
+    Ok(out)
+}
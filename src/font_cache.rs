@@ -0,0 +1,99 @@
+use anyhow::{Context, Result, anyhow};
+use sdl3::ttf::{Font, FontStyle, Sdl3TtfContext};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::failure::{Categorize, Failure};
+
+/// Fonts are keyed by file, face index, and point size (as raw bits, so the
+/// key is `Eq`).
+type FontKey = (PathBuf, u32, u32);
+
+/// Loaded fonts shared across the size search, final render, and any later
+/// generation in the same process, so SDL_ttf isn't asked to open the same
+/// file over and over. Least recently used fonts are dropped once more than
+/// `capacity` are open. Every font is opened in the same style.
+pub struct FontCache<'ttf> {
+    ttf: &'ttf Sdl3TtfContext,
+    capacity: usize,
+    style: FontStyle,
+    /// Most recently used entries are at the back.
+    entries: VecDeque<(FontKey, Rc<Font<'ttf, 'static>>)>,
+    pub hits: usize,
+    pub loads: usize,
+}
+
+impl<'ttf> FontCache<'ttf> {
+    pub fn new(ttf: &'ttf Sdl3TtfContext, capacity: usize, style: FontStyle) -> FontCache<'ttf> {
+        FontCache {
+            ttf,
+            capacity: capacity.max(1),
+            style,
+            entries: VecDeque::new(),
+            hits: 0,
+            loads: 0,
+        }
+    }
+
+    /// Fetch face `face` of a font at `point_size`, loading it with hinting
+    /// disabled on a miss. Face 0 is the only one in a plain .ttf; .ttc
+    /// collections hold several.
+    pub fn get(
+        &mut self,
+        path: &Path,
+        face: u32,
+        point_size: f32,
+    ) -> Result<Rc<Font<'ttf, 'static>>> {
+        let key = (path.to_path_buf(), face, point_size.to_bits());
+        if let Some(pos) = self.entries.iter().position(|(k, _)| *k == key) {
+            let entry = self.entries.remove(pos).expect("position is in range");
+            let font = Rc::clone(&entry.1);
+            self.entries.push_back(entry);
+            self.hits += 1;
+            return Ok(font);
+        }
+
+        let mut font = match self.ttf.load_font_at_index(path, face, point_size) {
+            Ok(font) => font,
+            Err(e) if face > 0 => {
+                // Face 0 always exists, and knows how many faces there are
+                if let Ok(first) = self.ttf.load_font(path, point_size) {
+                    let count = first.num_faces();
+                    if face >= count {
+                        return Err(anyhow!(
+                            "Error: --face-index {} is out of range; {} has {} face(s) (0-{})",
+                            face,
+                            path.display(),
+                            count,
+                            count.saturating_sub(1)
+                        ))
+                        .failure(Failure::Font);
+                    }
+                }
+                return Err(e)
+                    .with_context(|| format!("Failed to load face {} of {}", face, path.display()))
+                    .failure(Failure::Font);
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to load font {}", path.display()))
+                    .failure(Failure::Font);
+            }
+        };
+        font.set_hinting(sdl3::ttf::Hinting::NONE);
+        font.set_style(self.style);
+        let font = Rc::new(font);
+        self.loads += 1;
+
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((key, Rc::clone(&font)));
+        Ok(font)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
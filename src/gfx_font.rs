@@ -0,0 +1,191 @@
+//! `--emit-gfx`: an Adafruit GFX `GFXfont` header. Each glyph's bitmap is
+//! cropped to its ink and placed from the font's metrics, so the font keeps
+//! proportional spacing on the display.
+
+use anyhow::{Result, bail};
+use std::fmt::Write as _;
+
+use crate::charmap::CharMap;
+use crate::gray::GrayImage;
+use mycp437generator::{Atlas, INK_THRESHOLD};
+
+/// One `GFXglyph` entry.
+struct Glyph {
+    offset: usize,
+    width: u32,
+    height: u32,
+    advance: i32,
+    x_offset: i32,
+    y_offset: i32,
+}
+
+/// Format the header for the characters `first..=last`, read from `atlas`
+/// cells by their place in `charmap` and spaced by `pen_and_advance`.
+pub fn format_gfx(
+    atlas: &Atlas,
+    charmap: &CharMap,
+    (first, last): (u32, u32),
+    scale: u32,
+    name: &str,
+) -> Result<String> {
+    if last > 0xFFFF {
+        bail!(
+            "Error: GFX fonts stop at U+FFFF; --gfx-range ends at U+{:04X}",
+            last
+        );
+    }
+    let grid = &atlas.grid;
+    let mut bitmap: Vec<u8> = Vec::new();
+    let mut glyphs = Vec::new();
+    for code in first..=last {
+        let index = char::from_u32(code).and_then(|ch| charmap.index_of(ch));
+        let Some(index) = index else {
+            glyphs.push(Glyph {
+                offset: bitmap.len(),
+                width: 0,
+                height: 0,
+                advance: grid.glyph_width as i32,
+                x_offset: 0,
+                y_offset: 0,
+            });
+            continue;
+        };
+        let (left, top) = grid.glyph_origin(index);
+        let cell = atlas
+            .image
+            .crop(left, top, grid.glyph_width, grid.glyph_height);
+        let (pen_x, advance) = pen_and_advance(atlas, index, scale);
+        let offset = bitmap.len();
+        let glyph = match cell.ink_bounds() {
+            Some((x, y, width, height)) => {
+                pack_bits(&cell, (x, y, width, height), &mut bitmap);
+                Glyph {
+                    offset,
+                    width,
+                    height,
+                    advance,
+                    x_offset: x as i32 - pen_x,
+                    y_offset: y as i32 - atlas.baseline as i32,
+                }
+            }
+            None => Glyph {
+                offset,
+                width: 0,
+                height: 0,
+                advance,
+                x_offset: 0,
+                y_offset: 0,
+            },
+        };
+        glyphs.push(glyph);
+    }
+
+    if bitmap.len() > 0xFFFF {
+        bail!(
+            "Error: the GFX bitmap is {} bytes, more than a 16-bit offset reaches",
+            bitmap.len()
+        );
+    }
+    for (code, glyph) in (first..).zip(&glyphs) {
+        if glyph.width > 255
+            || glyph.height > 255
+            || !(0..=255).contains(&glyph.advance)
+            || !(-128..=127).contains(&glyph.x_offset)
+            || !(-128..=127).contains(&glyph.y_offset)
+        {
+            bail!(
+                "Error: U+{:04X} doesn't fit a GFXglyph ({}x{}, advance {}, offset {},{})",
+                code,
+                glyph.width,
+                glyph.height,
+                glyph.advance,
+                glyph.x_offset,
+                glyph.y_offset
+            );
+        }
+    }
+    if bitmap.is_empty() {
+        // A zero-length array doesn't compile
+        bitmap.push(0);
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by mycp437generator. Do not edit.").unwrap();
+    writeln!(
+        out,
+        "// Include after Adafruit_GFX.h. U+{:04X}-U+{:04X}, {}px line.",
+        first, last, grid.glyph_height
+    )
+    .unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "const uint8_t {}Bitmaps[] PROGMEM = {{", name).unwrap();
+    for line in bitmap.chunks(12) {
+        let bytes: Vec<String> = line.iter().map(|b| format!("0x{:02X},", b)).collect();
+        writeln!(out, "  {}", bytes.join(" ")).unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "const GFXglyph {}Glyphs[] PROGMEM = {{", name).unwrap();
+    for (code, glyph) in (first..).zip(&glyphs) {
+        let label = match char::from_u32(code) {
+            Some(ch) if !ch.is_control() && ch != '\\' => format!(" '{}'", ch),
+            _ => String::new(),
+        };
+        writeln!(
+            out,
+            "  {{ {:5}, {:3}, {:3}, {:3}, {:4}, {:4} }}, // 0x{:02X}{}",
+            glyph.offset,
+            glyph.width,
+            glyph.height,
+            glyph.advance,
+            glyph.x_offset,
+            glyph.y_offset,
+            code,
+            label
+        )
+        .unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "const GFXfont {name} PROGMEM = {{(uint8_t *){name}Bitmaps, (GFXglyph *){name}Glyphs, 0x{:02X}, 0x{:02X}, {}}};",
+        first, last, grid.glyph_height
+    )
+    .unwrap();
+    Ok(out)
+}
+
+/// Where the pen stood in glyph box `index`, from its left edge, and how
+/// far it advances, from the glyph metrics scaled by `scale` as the atlas
+/// was. Cells with no font metrics (synthesized, overridden, or
+/// bitmap-font glyphs) start at the edge and advance by the box width.
+pub fn pen_and_advance(atlas: &Atlas, index: usize, scale: u32) -> (i32, i32) {
+    match &atlas.glyphs[index].placement {
+        Some(p) => (p.pen_x * scale as i32, p.advance * scale as i32),
+        None => (0, atlas.grid.glyph_width as i32),
+    }
+}
+
+/// Append the pixels of `rect` in `cell` as one run of bits, most
+/// significant first, with no row padding and the last byte zero-filled.
+fn pack_bits(cell: &GrayImage, (x, y, width, height): (u32, u32, u32, u32), out: &mut Vec<u8>) {
+    let mut byte = 0u8;
+    let mut bits = 0;
+    for dy in 0..height {
+        for dx in 0..width {
+            byte <<= 1;
+            if cell.get(x + dx, y + dy) < INK_THRESHOLD {
+                byte |= 1;
+            }
+            bits += 1;
+            if bits == 8 {
+                out.push(byte);
+                (byte, bits) = (0, 0);
+            }
+        }
+    }
+    if bits > 0 {
+        out.push(byte << (8 - bits));
+    }
+}
@@ -0,0 +1,126 @@
+//! `--output-dir`: one cell-sized PNG per character, cut from the finished
+//! atlas, with a `manifest.json` listing the files.
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::charmap::CharMap;
+use crate::image_io;
+use crate::save_atomically;
+use mycp437generator::Atlas;
+
+/// How `--output-dir` names the per-glyph files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NameBy {
+    /// The atlas index, zero-padded: `000.png`
+    Index,
+    /// The character's codepoint: `U+263A.png`
+    Codepoint,
+}
+
+/// `manifest.json`: the cell size and every file written.
+#[derive(Serialize)]
+struct Manifest {
+    cell_width: u32,
+    cell_height: u32,
+    glyphs: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    file: String,
+    index: usize,
+    codepoint: String,
+}
+
+/// Options for `write_glyph_dir`.
+pub struct DirOptions {
+    pub name_by: NameBy,
+    /// Overwrite files already in the directory.
+    pub force: bool,
+    /// Leave out cells with no drawn pixel at all.
+    pub skip_empty: bool,
+}
+
+/// Write every cell of `atlas`, padding included, into `dir` (created if
+/// missing). Returns how many files were written.
+pub fn write_glyph_dir(
+    dir: &Path,
+    atlas: &Atlas,
+    charmap: &CharMap,
+    options: &DirOptions,
+) -> Result<usize> {
+    let grid = &atlas.grid;
+    let digits = (charmap.len().saturating_sub(1)).to_string().len().max(3);
+    let names: Vec<String> = charmap
+        .chars()
+        .iter()
+        .enumerate()
+        .map(|(i, &ch)| match options.name_by {
+            NameBy::Index => format!("{:0digits$}.png", i),
+            NameBy::Codepoint => format!("U+{:04X}.png", ch as u32),
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    for (i, name) in names.iter().enumerate() {
+        if !seen.insert(name) {
+            bail!(
+                "Error: index {} would also be written as {}; use --name-by index for tables that repeat characters",
+                i,
+                name
+            );
+        }
+    }
+
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let manifest_path = dir.join("manifest.json");
+    if !options.force {
+        let taken = names
+            .iter()
+            .map(|name| dir.join(name))
+            .chain([manifest_path.clone()])
+            .find(|path| path.exists());
+        if let Some(path) = taken {
+            bail!(
+                "Error: {} already exists; pass --force to overwrite",
+                path.display()
+            );
+        }
+    }
+
+    let mut glyphs = Vec::new();
+    for (i, name) in names.into_iter().enumerate() {
+        let (x, y) = grid.origin(i);
+        let cell = atlas
+            .image
+            .crop(x, y, grid.cell_width(), grid.cell_height());
+        if options.skip_empty && cell.pixels.iter().all(|&v| v == 255) {
+            continue;
+        }
+        let path = dir.join(&name);
+        save_atomically(&path, options.force, |tmp| {
+            image_io::save(&cell, tmp).with_context(|| format!("Failed to save {}", name))
+        })?;
+        glyphs.push(Entry {
+            file: name,
+            index: i,
+            codepoint: format!("U+{:04X}", charmap.get(i) as u32),
+        });
+    }
+
+    let written = glyphs.len();
+    let manifest = Manifest {
+        cell_width: grid.cell_width(),
+        cell_height: grid.cell_height(),
+        glyphs,
+    };
+    let json = serde_json::to_string_pretty(&manifest).context("Failed to serialize manifest")?;
+    save_atomically(&manifest_path, options.force, |tmp| {
+        std::fs::write(tmp, json + "\n").context("Failed to save glyph manifest")
+    })?;
+    Ok(written)
+}
@@ -0,0 +1,467 @@
+use anyhow::{Context, Result, bail};
+#[cfg(feature = "backend-sdl")]
+use sdl3::pixels::{Color, PixelFormat};
+#[cfg(feature = "backend-sdl")]
+use sdl3::surface::Surface;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Copy any surface into RGB24, the one layout the pixel readers index.
+/// SDL's converter resolves palettized surfaces (which the shaded renderer
+/// hands back) through their palette and reads packed formats by their
+/// masks. Formats with alpha are instead composited over white, since
+/// dropping the alpha would turn transparent black pixels into ink.
+#[cfg(feature = "backend-sdl")]
+pub fn to_rgb24(surface: &Surface) -> Result<Surface<'static>> {
+    let format = surface.pixel_format();
+    if !format.supports_alpha() {
+        return surface
+            .convert_format(PixelFormat::RGB24)
+            .with_context(|| format!("Failed to convert {:?} surface to RGB24", format));
+    }
+    let mut rgb = Surface::new(surface.width(), surface.height(), PixelFormat::RGB24)?;
+    rgb.fill_rect(None, Color::RGB(255, 255, 255))?;
+    // Surfaces with an alpha channel blend by default
+    surface
+        .blit(None, &mut rgb, None)
+        .with_context(|| format!("Failed to composite {:?} surface onto RGB24", format))?;
+    Ok(rgb)
+}
+
+/// Rec. 709 luminance of an RGB pixel: green counts most, blue least, and
+/// a gray pixel keeps its value.
+pub fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    ((2126 * r as u32 + 7152 * g as u32 + 722 * b as u32 + 5000) / 10000) as u8
+}
+
+/// An 8-bit brightness buffer (0 = black ink, 255 = white background),
+/// detached from SDL so post-processing passes can work on plain slices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrayImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    /// A `width` x `height` image filled with a single brightness.
+    pub fn new(width: u32, height: u32, fill: u8) -> GrayImage {
+        GrayImage {
+            width,
+            height,
+            pixels: vec![fill; width as usize * height as usize],
+        }
+    }
+
+    /// Copy a surface's pixels out as brightness, by the same `luminance`
+    /// the hex dump threshold uses.
+    #[cfg(feature = "backend-sdl")]
+    pub fn from_surface(surface: &Surface) -> Result<GrayImage> {
+        let rgb = to_rgb24(surface)?;
+        let width = rgb.width();
+        let height = rgb.height();
+        let pitch = rgb.pitch() as usize;
+        let pixels = rgb.with_lock(|data: &[u8]| {
+            let mut pixels = Vec::with_capacity(width as usize * height as usize);
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let offset = y * pitch + x * 3;
+                    pixels.push(luminance(data[offset], data[offset + 1], data[offset + 2]));
+                }
+            }
+            pixels
+        });
+        Ok(GrayImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Build an RGB24 surface with each channel set to the brightness.
+    #[cfg(feature = "backend-sdl")]
+    pub fn to_surface(&self) -> Result<Surface<'static>> {
+        let mut surface = Surface::new(self.width, self.height, PixelFormat::RGB24)?;
+        let pitch = surface.pitch() as usize;
+        surface.with_lock_mut(|data: &mut [u8]| {
+            for y in 0..self.height as usize {
+                for x in 0..self.width as usize {
+                    let value = self.pixels[y * self.width as usize + x];
+                    let offset = y * pitch + x * 3;
+                    data[offset..offset + 3].fill(value);
+                }
+            }
+        });
+        Ok(surface)
+    }
+
+    /// Build an RGBA32 surface in `ink` over `background`, blending the two
+    /// by brightness. With no background the ink is drawn at full color and
+    /// the darkness becomes its alpha, for engines that tint glyphs and
+    /// blend them over the scene.
+    #[cfg(feature = "backend-sdl")]
+    pub fn to_color_surface(
+        &self,
+        ink: [u8; 3],
+        background: Option<[u8; 3]>,
+    ) -> Result<Surface<'static>> {
+        let mut surface = Surface::new(self.width, self.height, PixelFormat::RGBA32)?;
+        let pitch = surface.pitch() as usize;
+        surface.with_lock_mut(|data: &mut [u8]| {
+            for y in 0..self.height as usize {
+                for x in 0..self.width as usize {
+                    let value = self.pixels[y * self.width as usize + x] as u32;
+                    let rgba = match background {
+                        Some(bg) => {
+                            let mix = |i: usize| {
+                                ((ink[i] as u32 * (255 - value) + bg[i] as u32 * value) / 255) as u8
+                            };
+                            [mix(0), mix(1), mix(2), 255]
+                        }
+                        None => [ink[0], ink[1], ink[2], 255 - value as u8],
+                    };
+                    let offset = y * pitch + x * 4;
+                    data[offset..offset + 4].copy_from_slice(&rgba);
+                }
+            }
+        });
+        Ok(surface)
+    }
+
+    /// Save as a single-channel 8-bit PNG of the darkness, 255 for full ink
+    /// and 0 for background, the way engines sample coverage and distance
+    /// textures. SDL would write RGB, three times the texture memory.
+    pub fn save_ink_png(&self, path: &Path) -> Result<()> {
+        let ink: Vec<u8> = self.pixels.iter().map(|&v| 255 - v).collect();
+        write_gray_png(path, self.width, self.height, &ink)
+    }
+
+    /// Save as a single-channel 8-bit PNG of the brightness, without SDL.
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        write_gray_png(path, self.width, self.height, &self.pixels)
+    }
+
+    /// Encode as a single-channel 8-bit PNG of the brightness, in memory.
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+        encode_gray_png(&mut png, self.width, self.height, &self.pixels)
+            .context("Failed to encode PNG")?;
+        Ok(png)
+    }
+
+    /// Left, top, width, and height of the ink, if there is any.
+    pub fn ink_bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        let mut bounds: Option<(u32, u32, u32, u32)> = None;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get(x, y) < crate::INK_THRESHOLD {
+                    let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x, y));
+                    bounds = Some((x0.min(x), y0.min(y), x1.max(x), y1.max(y)));
+                }
+            }
+        }
+        bounds.map(|(x0, y0, x1, y1)| (x0, y0, x1 - x0 + 1, y1 - y0 + 1))
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: u8) {
+        self.pixels[(y * self.width + x) as usize] = value;
+    }
+
+    /// Copy out a `width` x `height` region starting at (`x`, `y`).
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> GrayImage {
+        let mut out = GrayImage::new(width, height, 255);
+        for dy in 0..height {
+            for dx in 0..width {
+                out.set(dx, dy, self.get(x + dx, y + dy));
+            }
+        }
+        out
+    }
+
+    /// Paste `src` with its top-left corner at (`x`, `y`), clipping to bounds.
+    pub fn paste(&mut self, src: &GrayImage, x: u32, y: u32) {
+        for sy in 0..src.height.min(self.height.saturating_sub(y)) {
+            for sx in 0..src.width.min(self.width.saturating_sub(x)) {
+                self.set(x + sx, y + sy, src.get(sx, sy));
+            }
+        }
+    }
+
+    /// Resample to `width` x `height` by averaging a 4x4 grid of samples per
+    /// output pixel, which is adequate for shrinking glyphs a little.
+    pub fn resize(&self, width: u32, height: u32) -> GrayImage {
+        const SAMPLES: u32 = 4;
+        let mut out = GrayImage::new(width, height, 255);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = 0u32;
+                for sy in 0..SAMPLES {
+                    for sx in 0..SAMPLES {
+                        let src_x = ((x * SAMPLES + sx) * self.width) / (width * SAMPLES);
+                        let src_y = ((y * SAMPLES + sy) * self.height) / (height * SAMPLES);
+                        sum += self.get(src_x, src_y) as u32;
+                    }
+                }
+                out.set(x, y, (sum / (SAMPLES * SAMPLES)) as u8);
+            }
+        }
+        out
+    }
+
+    /// `resize` averaging in linear light: decoded with `gamma` first and
+    /// encoded again after, so thin strokes don't fade as they shrink.
+    pub fn resize_linear(&self, width: u32, height: u32, gamma: f32) -> GrayImage {
+        if gamma == 1.0 {
+            return self.resize(width, height);
+        }
+        let mut linear = self.clone();
+        linear.linearize(gamma);
+        let mut out = linear.resize(width, height);
+        out.linearize(1.0 / gamma);
+        out
+    }
+
+    /// Enlarge by an integer `factor`, replicating each pixel into a
+    /// `factor` x `factor` block so edges stay hard.
+    pub fn upscale(&self, factor: u32) -> GrayImage {
+        let mut out = GrayImage::new(self.width * factor, self.height * factor, 255);
+        for y in 0..out.height {
+            for x in 0..out.width {
+                out.set(x, y, self.get(x / factor, y / factor));
+            }
+        }
+        out
+    }
+
+    /// Threshold to pure ink and background, then repeat each column
+    /// `across` times and each row `down` times.
+    pub fn stretch_bits(&self, across: u32, down: u32) -> GrayImage {
+        let mut out = GrayImage::new(self.width * across, self.height * down, 255);
+        for y in 0..out.height {
+            for x in 0..out.width {
+                if self.get(x / across, y / down) < crate::INK_THRESHOLD {
+                    out.set(x, y, 0);
+                }
+            }
+        }
+        out
+    }
+
+    /// Decode every pixel as gamma-encoded with exponent `gamma`, so mid
+    /// grays darken (for `gamma` > 1) as a display shows them and a
+    /// threshold judges them in linear light. 1.0 changes nothing.
+    pub fn linearize(&mut self, gamma: f32) {
+        let table: Vec<u8> = (0..=255u32)
+            .map(|v| ((v as f32 / 255.0).powf(gamma) * 255.0).round() as u8)
+            .collect();
+        for pixel in &mut self.pixels {
+            *pixel = table[*pixel as usize];
+        }
+    }
+
+    /// Fill a rectangle, clipping to bounds.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, value: u8) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.set(px, py, value);
+            }
+        }
+    }
+}
+
+/// An 8-bit RGB buffer (0 = full ink in that channel), for atlases whose
+/// channels differ: a `--render-mode lcd` atlas holds one coverage per
+/// subpixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbImage {
+    pub width: u32,
+    pub height: u32,
+    /// Red, green, and blue of each pixel in turn.
+    pub pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    /// A `width` x `height` image filled with a single color.
+    pub fn new(width: u32, height: u32, fill: [u8; 3]) -> RgbImage {
+        RgbImage {
+            width,
+            height,
+            pixels: fill.repeat(width as usize * height as usize),
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 3] {
+        let at = (y * self.width + x) as usize * 3;
+        [self.pixels[at], self.pixels[at + 1], self.pixels[at + 2]]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, pixel: [u8; 3]) {
+        let at = (y * self.width + x) as usize * 3;
+        self.pixels[at..at + 3].copy_from_slice(&pixel);
+    }
+
+    /// Fill a rectangle, clipping to bounds.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, pixel: [u8; 3]) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.set(px, py, pixel);
+            }
+        }
+    }
+
+    /// Copy a surface's pixels out, in any format `to_rgb24` reads.
+    #[cfg(feature = "backend-sdl")]
+    pub fn from_surface(surface: &Surface) -> Result<RgbImage> {
+        let rgb = to_rgb24(surface)?;
+        let (width, height) = (rgb.width(), rgb.height());
+        let pitch = rgb.pitch() as usize;
+        let row = width as usize * 3;
+        let pixels = rgb.with_lock(|data: &[u8]| {
+            (0..height as usize)
+                .flat_map(|y| &data[y * pitch..y * pitch + row])
+                .copied()
+                .collect()
+        });
+        Ok(RgbImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// The image in the top left of a `width` x `height` white one.
+    pub fn padded(&self, width: u32, height: u32) -> RgbImage {
+        let mut pixels = vec![255; width as usize * height as usize * 3];
+        let row = self.width as usize * 3;
+        for y in 0..self.height as usize {
+            let to = y * width as usize * 3;
+            pixels[to..to + row].copy_from_slice(&self.pixels[y * row..(y + 1) * row]);
+        }
+        RgbImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Exchange the red and blue channels, for panels whose subpixels run
+    /// blue to red.
+    pub fn swap_red_blue(&mut self) {
+        for pixel in self.pixels.chunks_mut(3) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    /// Build an RGB24 surface in `ink` over `background`, blending each
+    /// channel by its own coverage.
+    #[cfg(feature = "backend-sdl")]
+    pub fn to_color_surface(&self, ink: [u8; 3], background: [u8; 3]) -> Result<Surface<'static>> {
+        let mut surface = Surface::new(self.width, self.height, PixelFormat::RGB24)?;
+        let pitch = surface.pitch() as usize;
+        surface.with_lock_mut(|data: &mut [u8]| {
+            for y in 0..self.height as usize {
+                for x in 0..self.width as usize {
+                    for c in 0..3 {
+                        let value = self.pixels[(y * self.width as usize + x) * 3 + c] as u32;
+                        data[y * pitch + x * 3 + c] = ((ink[c] as u32 * (255 - value)
+                            + background[c] as u32 * value)
+                            / 255) as u8;
+                    }
+                }
+            }
+        });
+        Ok(surface)
+    }
+}
+
+/// Write `pixels`, one byte each, as an 8-bit grayscale PNG.
+fn write_gray_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    encode_gray_png(BufWriter::new(file), width, height, pixels)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn encode_gray_png(
+    out: impl Write,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()
+}
+
+/// Parse an `RRGGBB` hex color, with or without a leading `#`.
+pub fn parse_color(spec: &str) -> Result<[u8; 3]> {
+    let hex = spec.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i * 2..i * 2 + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(1), channel(2)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => bail!("Invalid color '{}' (expected RRGGBB hex)", spec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A row of `values`, one pixel tall.
+    fn row(values: &[u8]) -> GrayImage {
+        let mut image = GrayImage::new(values.len() as u32, 1, 255);
+        for (x, &value) in values.iter().enumerate() {
+            image.set(x as u32, 0, value);
+        }
+        image
+    }
+
+    fn values(image: &GrayImage) -> Vec<u8> {
+        (0..image.width).map(|x| image.get(x, 0)).collect()
+    }
+
+    #[test]
+    fn luminance_weighs_green_over_red_over_blue() {
+        for value in [0, 1, 127, 128, 254, 255] {
+            assert_eq!(luminance(value, value, value), value);
+        }
+        // The old channel mean made all three 85, ink at the 128 threshold
+        assert_eq!(luminance(0, 255, 0), 182);
+        assert_eq!(luminance(255, 0, 0), 54);
+        assert_eq!(luminance(0, 0, 255), 18);
+    }
+
+    #[test]
+    fn gamma_widens_an_antialiased_stroke_at_the_threshold() {
+        // One dark pixel with antialiasing fading out on either side
+        let stroke = [255, 200, 150, 60, 150, 200, 255];
+        let ink = |image: &GrayImage| values(image).iter().filter(|&&v| v < 128).count();
+
+        let mut before = row(&stroke);
+        before.linearize(1.0);
+        assert_eq!(values(&before), stroke);
+        assert_eq!(ink(&before), 1);
+
+        let mut after = row(&stroke);
+        after.linearize(2.2);
+        assert_eq!(values(&after), [255, 149, 79, 11, 79, 149, 255]);
+        assert_eq!(ink(&after), 3);
+    }
+
+    #[test]
+    fn linear_resize_without_gamma_is_the_plain_resize() {
+        let image = row(&[0, 255, 255, 255, 0, 0, 128, 255]);
+        assert_eq!(image.resize_linear(2, 1, 1.0), image.resize(2, 1));
+    }
+}
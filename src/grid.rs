@@ -0,0 +1,112 @@
+/// How atlas indices map to cells: the grid shape, fill order, and the
+/// spacing around each glyph.
+///
+/// Each cell is the glyph box with `padding` background pixels on every
+/// side; cells are `gutter` pixels apart. Every cell is followed by its
+/// gutter, the last ones included, so a cell's origin is always
+/// `index * stride` along each axis.
+#[derive(Clone, Copy, Debug)]
+pub struct Grid {
+    pub columns: u32,
+    pub rows: u32,
+    /// Fill down each column before moving right, instead of across rows.
+    pub column_major: bool,
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    pub padding: u32,
+    pub gutter: u32,
+}
+
+impl Grid {
+    /// A grid `columns` wide with enough rows for `count` cells. A final
+    /// partial row (or column, when column-major) is left as background.
+    pub fn new(
+        count: usize,
+        columns: u32,
+        column_major: bool,
+        glyph_width: u32,
+        glyph_height: u32,
+    ) -> Grid {
+        Grid {
+            columns,
+            rows: (count as u32).div_ceil(columns),
+            column_major,
+            glyph_width,
+            glyph_height,
+            padding: 0,
+            gutter: 0,
+        }
+    }
+
+    /// The same layout with every pixel measurement multiplied by `factor`,
+    /// matching an atlas upscaled by that much.
+    pub fn scaled(&self, factor: u32) -> Grid {
+        Grid {
+            glyph_width: self.glyph_width * factor,
+            glyph_height: self.glyph_height * factor,
+            padding: self.padding * factor,
+            gutter: self.gutter * factor,
+            ..*self
+        }
+    }
+
+    /// The same layout with glyph boxes `across` times wider and `down`
+    /// times taller; padding and gutters keep their size.
+    pub fn stretched(&self, across: u32, down: u32) -> Grid {
+        Grid {
+            glyph_width: self.glyph_width * across,
+            glyph_height: self.glyph_height * down,
+            ..*self
+        }
+    }
+
+    /// Cell size including padding, excluding the gutter.
+    pub fn cell_width(&self) -> u32 {
+        self.glyph_width + 2 * self.padding
+    }
+
+    pub fn cell_height(&self) -> u32 {
+        self.glyph_height + 2 * self.padding
+    }
+
+    /// Distance between the origins of neighbouring cells.
+    pub fn stride_x(&self) -> u32 {
+        self.cell_width() + self.gutter
+    }
+
+    pub fn stride_y(&self) -> u32 {
+        self.cell_height() + self.gutter
+    }
+
+    /// Column and row of cell `index`.
+    pub fn cell(&self, index: usize) -> (u32, u32) {
+        let index = index as u32;
+        if self.column_major {
+            (index / self.rows, index % self.rows)
+        } else {
+            (index % self.columns, index / self.columns)
+        }
+    }
+
+    /// Top-left pixel of cell `index`, padding included.
+    pub fn origin(&self, index: usize) -> (u32, u32) {
+        let (col, row) = self.cell(index);
+        (col * self.stride_x(), row * self.stride_y())
+    }
+
+    /// Top-left pixel of the glyph box inside cell `index`.
+    pub fn glyph_origin(&self, index: usize) -> (u32, u32) {
+        let (x, y) = self.origin(index);
+        (x + self.padding, y + self.padding)
+    }
+
+    /// Atlas width in pixels.
+    pub fn width(&self) -> u32 {
+        self.columns * self.stride_x()
+    }
+
+    /// Atlas height in pixels.
+    pub fn height(&self) -> u32 {
+        self.rows * self.stride_y()
+    }
+}
@@ -0,0 +1,202 @@
+//! Reading back the `--hex-dump` text: the header comments give the image
+//! and grid shape, the `//!LONGVAR` block the packed scanlines.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+use crate::packing::{BitOrder, PackedBitmap, WordSize};
+
+/// A dump read back: its scanlines and the grid its header describes.
+pub struct HexDump {
+    pub bitmap: PackedBitmap,
+    pub grid: Grid,
+}
+
+impl HexDump {
+    /// The bitmap as an atlas image, ink black on white.
+    pub fn to_image(&self) -> GrayImage {
+        let mut image = GrayImage::new(self.bitmap.width, self.bitmap.height, 255);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                if self.bitmap.get(x, y) {
+                    image.set(x, y, 0);
+                }
+            }
+        }
+        image
+    }
+}
+
+pub fn read_hex_dump(path: &Path) -> Result<HexDump> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_hex_dump(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// The numbers of `text` split on anything that isn't a digit.
+fn numbers(text: &str) -> Vec<u32> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Parse dump text as `format_hex_dump` writes it. The grid comes from the
+/// header comments, with the `#define`s filling in a missing cell size.
+pub fn parse_hex_dump(text: &str) -> Result<HexDump> {
+    let mut size = None;
+    let mut padded_width = None;
+    let mut layout = None;
+    let mut cell = None;
+    let mut glyph_box = None;
+    let mut word_bits = None;
+    let mut words: Vec<u32> = Vec::new();
+    let mut in_data = false;
+    let mut data_end = None;
+    let (mut define_width, mut define_height) = (None, None);
+
+    for (n, line) in text.lines().enumerate() {
+        let number = n + 1;
+        let line = line.trim();
+        if in_data {
+            if line == "//!ENDLONGVAR" {
+                in_data = false;
+                data_end = Some(number);
+                continue;
+            }
+            let Some(values) = line.strip_prefix("//!") else {
+                bail!(
+                    "Error: line {}: expected hex words or //!ENDLONGVAR",
+                    number
+                );
+            };
+            for value in values.split_whitespace() {
+                let digits = value.strip_prefix("0x").unwrap_or(value);
+                let word = u32::from_str_radix(digits, 16).map_err(|_| {
+                    anyhow::anyhow!("Error: line {}: '{}' is not a hex word", number, value)
+                })?;
+                words.push(word);
+            }
+            continue;
+        }
+        let field = |prefix: &str, count: usize| -> Result<Option<Vec<u32>>> {
+            match line.strip_prefix(prefix) {
+                Some(rest) => {
+                    let values = numbers(rest);
+                    if values.len() < count {
+                        bail!("Error: line {}: can't read '{}'", number, line);
+                    }
+                    Ok(Some(values))
+                }
+                None => Ok(None),
+            }
+        };
+        if let Some(v) = field("// Pixel dimensions:", 2)? {
+            size = Some((v[0], v[1]));
+        } else if let Some(v) = field("// Padded scanline width", 1)? {
+            padded_width = Some(v[0]);
+        } else if let Some(v) = field("// Character grid:", 2)? {
+            layout = Some((v[0], v[1], line.contains("column-major")));
+        } else if let Some(v) = field("// Character cell:", 2)? {
+            cell = Some((v[0], v[1]));
+        } else if let Some(v) = field("// Glyph box:", 5)? {
+            // width, height, padding twice, gutter
+            glyph_box = Some((v[0], v[1], v[2], v[4]));
+        } else if let Some(v) = field("// Packing:", 1)? {
+            word_bits = Some(v[0]);
+        } else if line.starts_with("//!LONGVAR") {
+            in_data = true;
+        } else if let Some(rest) = line.strip_prefix("#define font_") {
+            let value = numbers(rest.split_once(' ').map_or("", |(_, v)| v))
+                .first()
+                .copied();
+            let key = rest.split_whitespace().next().unwrap_or("");
+            if key.ends_with("_glyph_width") || key.ends_with("_glyph_height") {
+                continue;
+            }
+            if key.ends_with("_width") {
+                define_width = value;
+            } else if key.ends_with("_height") {
+                define_height = value;
+            }
+        }
+    }
+
+    if in_data {
+        bail!("Error: the //!LONGVAR block never ends with //!ENDLONGVAR");
+    }
+    let Some((width, height)) = size else {
+        bail!("Error: no '// Pixel dimensions' line");
+    };
+    let Some((columns, rows, column_major)) = layout else {
+        bail!("Error: no '// Character grid' line");
+    };
+    let word_size = match word_bits {
+        Some(8) => WordSize::Bits8,
+        Some(16) => WordSize::Bits16,
+        Some(32) | None => WordSize::Bits32,
+        Some(bits) => bail!("Error: unknown {}-bit packing", bits),
+    };
+    let bits = word_size.bits();
+    let padded_width = padded_width.unwrap_or(width.div_ceil(bits) * bits);
+    if padded_width < width || !padded_width.is_multiple_of(bits) {
+        bail!(
+            "Error: a padded scanline width of {} doesn't fit {}px in {}-bit words",
+            padded_width,
+            width,
+            bits
+        );
+    }
+    let (cell_width, cell_height) = match (cell, define_width.zip(define_height)) {
+        (Some(cell), _) | (None, Some(cell)) => cell,
+        (None, None) => bail!("Error: no '// Character cell' line or font_*_width/height defines"),
+    };
+    let (glyph_width, glyph_height, padding, gutter) =
+        glyph_box.unwrap_or((cell_width, cell_height, 0, 0));
+    if glyph_width + 2 * padding + gutter != cell_width
+        || glyph_height + 2 * padding + gutter != cell_height
+    {
+        bail!(
+            "Error: a {}x{} glyph box with padding {} and gutter {} doesn't make a {}x{} cell",
+            glyph_width,
+            glyph_height,
+            padding,
+            gutter,
+            cell_width,
+            cell_height
+        );
+    }
+    let expected = (padded_width / bits * height) as usize;
+    if words.len() != expected {
+        bail!(
+            "Error: line {}: {} words for {} rows of {} bits, expected {}",
+            data_end.unwrap_or(0),
+            words.len(),
+            height,
+            padded_width,
+            expected
+        );
+    }
+
+    let grid = Grid {
+        columns,
+        rows,
+        column_major,
+        glyph_width,
+        glyph_height,
+        padding,
+        gutter,
+    };
+    Ok(HexDump {
+        bitmap: PackedBitmap {
+            words,
+            word_size,
+            bit_order: BitOrder::LsbFirst,
+            width,
+            height,
+            padded_width,
+        },
+        grid,
+    })
+}
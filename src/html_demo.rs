@@ -0,0 +1,210 @@
+//! `--emit-html`: one self-contained page for showing a font around, with
+//! the atlas embedded as a data URI, every cell cropped out of it by CSS,
+//! a sample paragraph drawn from it, and the sizing metrics on top.
+
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::base64;
+use mycp437generator::Atlas;
+use mycp437generator::charmap::CharMap;
+
+/// Shown when no --sample-text is given.
+const DEFAULT_SAMPLE: &str =
+    "The quick brown fox jumps over the lazy dog.\n0123456789 !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Displayed glyph height the page zooms up to, in CSS pixels.
+const TARGET_HEIGHT: u32 = 24;
+
+/// The default sample as lines of cell indices, leaving out characters
+/// the table doesn't have.
+pub fn default_sample(charmap: &CharMap) -> Vec<Vec<u8>> {
+    DEFAULT_SAMPLE
+        .lines()
+        .map(|line| {
+            line.chars()
+                .filter_map(|ch| charmap.index_of(ch))
+                .filter_map(|i| u8::try_from(i).ok())
+                .collect()
+        })
+        .collect()
+}
+
+/// Escape `text` for HTML content and attribute values.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            _ => ch.to_string(),
+        })
+        .collect()
+}
+
+/// The page for `atlas`, rendered from `font` with the characters of
+/// `charmap`, with `sample` lines of cell indices drawn from it.
+pub fn page(font: &Path, atlas: &Atlas, charmap: &CharMap, sample: &[Vec<u8>]) -> Result<String> {
+    let grid = &atlas.grid;
+    let zoom = TARGET_HEIGHT.div_ceil(grid.glyph_height).clamp(1, 8);
+    let title = escape(
+        &font
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+    );
+
+    let mut metrics = vec![
+        ("Font", escape(&font.display().to_string())),
+        ("Size", format!("{:.4}pt", atlas.font_size)),
+    ];
+    if let Some(line) = &atlas.line {
+        metrics.push((
+            "Line",
+            format!(
+                "ascent {}, descent {}, height {}",
+                line.ascent,
+                line.descent,
+                line.ascent - line.descent
+            ),
+        ));
+    }
+    if let Some((widest, requested)) = atlas.width_fit {
+        metrics.push((
+            "Width",
+            format!("widest glyph {}px, {}px requested", widest, requested),
+        ));
+    }
+    metrics.extend([
+        (
+            "Cell",
+            format!("{}x{}", grid.cell_width(), grid.cell_height()),
+        ),
+        (
+            "Glyph box",
+            format!(
+                "{}x{}, padding {}, gutter {}, stride {}x{}",
+                grid.glyph_width,
+                grid.glyph_height,
+                grid.padding,
+                grid.gutter,
+                grid.stride_x(),
+                grid.stride_y()
+            ),
+        ),
+        (
+            "Baseline",
+            format!("row {} of the glyph box", atlas.baseline),
+        ),
+        (
+            "Characters",
+            format!(
+                "{} ({}), grid {}x{}{}",
+                charmap.len(),
+                escape(charmap.label()),
+                grid.columns,
+                grid.rows,
+                if grid.column_major {
+                    ", column-major"
+                } else {
+                    ""
+                }
+            ),
+        ),
+        (
+            "Atlas",
+            format!(
+                "{}x{} (content {}x{})",
+                atlas.image.width, atlas.image.height, atlas.content_width, atlas.content_height
+            ),
+        ),
+    ]);
+    for line in atlas.coverage.summary() {
+        metrics.push(("Coverage", escape(&line)));
+    }
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">"
+    )
+    .unwrap();
+    writeln!(out, "<title>{}</title>\n<style>", title).unwrap();
+    writeln!(
+        out,
+        "body {{ font-family: sans-serif; margin: 2em; }}\n\
+         dt {{ font-weight: bold; float: left; clear: left; width: 7em; }}\n\
+         dd {{ margin-left: 8em; }}\n\
+         td, th {{ padding: 2px 8px; text-align: left; }}\n\
+         .cell, .glyph {{ image-rendering: pixelated; display: inline-block; vertical-align: top; \
+         background-image: url(data:image/png;base64,{}); background-size: {}px {}px; }}\n\
+         .cell {{ width: {}px; height: {}px; outline: 1px solid #ccc; }}\n\
+         .glyph {{ width: {}px; height: {}px; }}",
+        base64::encode(&atlas.image.to_png()?),
+        atlas.image.width * zoom,
+        atlas.image.height * zoom,
+        grid.cell_width() * zoom,
+        grid.cell_height() * zoom,
+        grid.glyph_width * zoom,
+        grid.glyph_height * zoom
+    )
+    .unwrap();
+    writeln!(out, "</style>\n</head>\n<body>\n<h1>{}</h1>\n<dl>", title).unwrap();
+    for (name, value) in &metrics {
+        writeln!(out, "<dt>{}</dt><dd>{}</dd>", name, value).unwrap();
+    }
+    writeln!(out, "</dl>").unwrap();
+
+    writeln!(out, "<h2>Sample</h2>\n<div>").unwrap();
+    for line in sample {
+        let glyphs: String = line
+            .iter()
+            .map(|&index| {
+                let (x, y) = grid.glyph_origin(index as usize);
+                format!(
+                    "<span class=\"glyph\" style=\"background-position: -{}px -{}px\"></span>",
+                    x * zoom,
+                    y * zoom
+                )
+            })
+            .collect();
+        writeln!(out, "<div>{}</div>", glyphs).unwrap();
+    }
+    writeln!(out, "</div>").unwrap();
+
+    writeln!(
+        out,
+        "<h2>Cells</h2>\n<table>\n<tr><th>Index</th><th>Character</th><th>Codepoint</th><th>Cell</th></tr>"
+    )
+    .unwrap();
+    for (i, &ch) in charmap.chars().iter().enumerate() {
+        let (x, y) = grid.origin(i);
+        let shown = if ch.is_control() {
+            String::new()
+        } else {
+            escape(&ch.to_string())
+        };
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>U+{:04X}</td><td><span class=\"cell\" style=\"background-position: -{}px -{}px\"></span></td></tr>",
+            i,
+            shown,
+            ch as u32,
+            x * zoom,
+            y * zoom
+        )
+        .unwrap();
+    }
+    writeln!(out, "</table>").unwrap();
+
+    writeln!(
+        out,
+        "<h2>Atlas</h2>\n<div class=\"glyph\" style=\"width: {}px; height: {}px\"></div>\n</body>\n</html>",
+        atlas.image.width * zoom,
+        atlas.image.height * zoom
+    )
+    .unwrap();
+    Ok(out)
+}
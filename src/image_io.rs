@@ -0,0 +1,77 @@
+//! Reading and writing the atlas's images: through SDL_image in builds with
+//! the `backend-sdl` feature, and as far as the png crate goes without it.
+
+use anyhow::Result;
+#[cfg(not(feature = "backend-sdl"))]
+use anyhow::bail;
+#[cfg(feature = "backend-sdl")]
+use sdl3::image::{LoadSurface, SaveSurface};
+#[cfg(feature = "backend-sdl")]
+use sdl3::surface::Surface;
+use std::path::Path;
+
+use mycp437generator::gray::{GrayImage, RgbImage};
+
+/// Read the image at `path`, in any format SDL_image loads, as brightness.
+#[cfg(feature = "backend-sdl")]
+pub fn load(path: &Path) -> Result<GrayImage> {
+    GrayImage::from_surface(&Surface::from_file(path)?)
+}
+
+#[cfg(not(feature = "backend-sdl"))]
+pub fn load(_path: &Path) -> Result<GrayImage> {
+    bail!("Error: this build has no SDL to read images with; rebuild with --features backend-sdl")
+}
+
+/// Save `image` as a PNG: RGB through SDL, or single-channel by the png
+/// crate without it. Either holds the same pixels.
+#[cfg(feature = "backend-sdl")]
+pub fn save(image: &GrayImage, path: &Path) -> Result<()> {
+    Ok(image.to_surface()?.save(path)?)
+}
+
+#[cfg(not(feature = "backend-sdl"))]
+pub fn save(image: &GrayImage, path: &Path) -> Result<()> {
+    image.save_png(path)
+}
+
+/// Save `image` as a PNG in `ink` over `background`, or with the darkness
+/// as alpha when there is no background.
+#[cfg(feature = "backend-sdl")]
+pub fn save_colored(
+    image: &GrayImage,
+    ink: [u8; 3],
+    background: Option<[u8; 3]>,
+    path: &Path,
+) -> Result<()> {
+    Ok(image.to_color_surface(ink, background)?.save(path)?)
+}
+
+#[cfg(not(feature = "backend-sdl"))]
+pub fn save_colored(
+    _image: &GrayImage,
+    _ink: [u8; 3],
+    _background: Option<[u8; 3]>,
+    _path: &Path,
+) -> Result<()> {
+    bail!("Error: this build has no SDL to color PNGs with; rebuild with --features backend-sdl")
+}
+
+/// Save a `--render-mode lcd` atlas as a PNG in `ink` over `background`,
+/// each channel blended by its own coverage.
+#[cfg(feature = "backend-sdl")]
+pub fn save_lcd(image: &RgbImage, ink: [u8; 3], background: [u8; 3], path: &Path) -> Result<()> {
+    Ok(image.to_color_surface(ink, background)?.save(path)?)
+}
+
+#[cfg(not(feature = "backend-sdl"))]
+pub fn save_lcd(
+    _image: &RgbImage,
+    _ink: [u8; 3],
+    _background: [u8; 3],
+    _path: &Path,
+) -> Result<()> {
+    bail!(
+        "Error: this build has no SDL to save subpixel atlases with; rebuild with --features backend-sdl"
+    )
+}
@@ -0,0 +1,948 @@
+//! Build CP437-style font atlases from TrueType fonts: size a font so its
+//! widest glyph fills the requested cell width, render every character of a
+//! table into a grid, and hand back the pixels with the layout and metrics
+//! needed to address them.
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use log::info;
+#[cfg(feature = "backend-sdl")]
+use sdl3::Sdl;
+#[cfg(feature = "backend-sdl")]
+use sdl3::ttf::{FontStyle, Sdl3TtfContext};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+#[cfg(feature = "backend-sdl")]
+use std::rc::Rc;
+
+pub mod backend;
+pub mod bdf;
+pub mod bitmap_font;
+mod boxes;
+pub mod charmap;
+pub mod codepage;
+pub mod coverage;
+pub mod failure;
+#[cfg(feature = "backend-sdl")]
+mod font_cache;
+pub mod gray;
+pub mod grid;
+pub mod hex_dump;
+mod morphology;
+mod overrides;
+pub mod packing;
+mod pixel_art;
+pub mod psf;
+#[cfg(feature = "backend-rust")]
+pub mod rust_backend;
+mod sdf;
+#[cfg(feature = "backend-sdl")]
+pub mod sdl_backend;
+pub mod vga_rom;
+pub mod warning;
+
+pub use codepage::{CP437, cp437_index};
+
+use backend::{GlyphBox, LineMetrics};
+use charmap::CharMap;
+use coverage::Coverage;
+use failure::{Categorize, Failure};
+#[cfg(feature = "backend-sdl")]
+use font_cache::FontCache;
+use gray::{GrayImage, RgbImage};
+use grid::Grid;
+#[cfg(feature = "backend-sdl")]
+use sdl_backend::SdlBackend;
+
+thread_local! {
+    /// Label put in front of this thread's log lines, if any.
+    static LOG_PREFIX: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Label every following log line on this thread, e.g. with the name of the
+/// batch job being generated, so interleaved logs stay readable.
+pub fn set_log_prefix(prefix: Option<String>) {
+    LOG_PREFIX.set(prefix);
+}
+
+/// This thread's log prefix, for loggers to put in front of each record.
+pub fn log_prefix() -> Option<String> {
+    LOG_PREFIX.with_borrow(Clone::clone)
+}
+
+#[doc(hidden)]
+pub fn log_line(line: fmt::Arguments) {
+    LOG_PREFIX.with_borrow(|prefix| match prefix {
+        Some(prefix) => eprintln!("[{}] {}", prefix, line),
+        None => eprintln!("{}", line),
+    })
+}
+
+/// `eprintln!` with the current log prefix in front, for reports a flag
+/// asked for, which show at every verbosity. Diagnostics go through the
+/// `log` crate's leveled macros instead.
+#[macro_export]
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::log_line(format_args!($($arg)*))
+    };
+}
+
+/// Which glyph metric defines a character's width.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Measure {
+    /// Ink extent (`maxx`); glyphs are centered in the cell
+    Ink,
+    /// Advance width; glyphs are placed by their bearing like a text renderer
+    Advance,
+}
+
+impl Measure {
+    fn width(self, glyph: &GlyphBox) -> i32 {
+        match self {
+            Measure::Ink => glyph.maxx,
+            Measure::Advance => glyph.advance,
+        }
+    }
+}
+
+/// Where the line sits in a `cell_height` cell taller or shorter than the
+/// font's own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VAlign {
+    /// Baseline at the descent above the bottom, or at `--baseline`
+    Baseline,
+    /// The line's top at the top of the cell
+    Top,
+    /// The line centered in the cell
+    Center,
+    /// The line's bottom at the bottom of the cell
+    Bottom,
+}
+
+/// Policy for glyphs that are larger than the cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    /// Cut off whatever falls outside the cell
+    Clip,
+    /// Shrink the glyph, preserving its aspect ratio, until it fits
+    Scale,
+    /// Abort, listing the offending characters
+    Error,
+}
+
+/// Where a glyph sits across its cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HAlign {
+    /// Centered as `--measure` decides: the advance box, or with
+    /// `--ink-crop` the ink
+    Center,
+    /// Ink flush with the cell's left edge, as classic fonts drew '.'
+    Left,
+    /// Pen at the cell's left edge and the ink at its bearing (minx), as
+    /// a text shaper places it, so box-drawing strokes line up
+    Bearing,
+}
+
+/// Which way `--embolden` and `--thin` move the edges of strokes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmboldenDir {
+    /// Every direction, by a disc of the given radius
+    Both,
+    /// Right edges only, like classic synthetic bold
+    #[value(alias = "h")]
+    #[serde(alias = "h")]
+    Horizontal,
+}
+
+/// How `--scale` enlarges the finished atlas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScaleAlgorithm {
+    /// Replicate pixels; keeps antialiasing as it is
+    Nearest,
+    /// Scale2x over the thresholded atlas; --scale 2, 4, 8, or 16
+    Scale2x,
+    /// Scale3x over the thresholded atlas; --scale 3 or 9
+    Scale3x,
+}
+
+/// How SDL_ttf draws each glyph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenderMode {
+    /// Antialiased, black on white
+    Shaded,
+    /// One bit per pixel, with no antialiasing at all
+    Solid,
+    /// Subpixel antialiasing for LCD panels, one coverage per color
+    /// channel, kept in an RGB atlas
+    Lcd,
+}
+
+/// Order of the subpixels across an LCD panel's pixels, for
+/// `--render-mode lcd`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LcdOrder {
+    /// Red on the left, as on most panels
+    Rgb,
+    /// Blue on the left
+    Bgr,
+}
+
+/// How `--override-dir` images are fitted into the glyph box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverrideFit {
+    /// Keep the drawn pixels, centering them and clipping any excess
+    Center,
+    /// Resample to the glyph box
+    Stretch,
+}
+
+/// What fills a cell whose glyph no font could render.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MissingGlyph {
+    /// Leave the cell as background
+    Blank,
+    /// An unfilled rectangle inset 1px from the glyph box edges
+    Box,
+    /// A one-pixel checkerboard over the whole glyph box
+    Checker,
+    /// Render this character instead
+    Char(char),
+}
+
+impl MissingGlyph {
+    /// Parse a `--missing-glyph` value: `blank`, `box`, `checker`, or
+    /// `char:<c>` with a single character after the colon.
+    pub fn parse(s: &str) -> Result<MissingGlyph> {
+        match s {
+            "blank" => return Ok(MissingGlyph::Blank),
+            "box" => return Ok(MissingGlyph::Box),
+            "checker" => return Ok(MissingGlyph::Checker),
+            _ => {}
+        }
+        let mut chars = s.strip_prefix("char:").unwrap_or_default().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(MissingGlyph::Char(c)),
+            _ => bail!(
+                "Invalid --missing-glyph '{}' (expected blank, box, checker, or char:<c>)",
+                s
+            ),
+        }
+    }
+
+    /// The pattern drawn into a `width` x `height` glyph box, for the kinds
+    /// that are drawn rather than rendered.
+    pub(crate) fn pattern(self, width: u32, height: u32) -> Option<GrayImage> {
+        let mut image = GrayImage::new(width, height, 255);
+        match self {
+            MissingGlyph::Blank | MissingGlyph::Char(_) => return None,
+            MissingGlyph::Box => {
+                let (right, bottom) = (width.saturating_sub(2), height.saturating_sub(2));
+                for y in 1..=bottom {
+                    for x in 1..=right {
+                        if x == 1 || x == right || y == 1 || y == bottom {
+                            image.set(x, y, 0);
+                        }
+                    }
+                }
+            }
+            MissingGlyph::Checker => {
+                for y in 0..height {
+                    for x in (y % 2..width).step_by(2) {
+                        image.set(x, y, 0);
+                    }
+                }
+            }
+        }
+        Some(image)
+    }
+}
+
+/// What happened to each cell during the blit, for the debug image and the
+/// JSON sidecar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CellStatus {
+    /// Drawn in full (or intentionally blank)
+    Ok,
+    /// Nothing drawn: the glyph is missing or was rejected
+    Skipped,
+    /// Drawn, but part of the ink fell outside the cell
+    Clipped,
+    /// The `--missing-glyph` placeholder stands in for a glyph no font has
+    Placeholder,
+    /// Drawn from an `--override-dir` image instead of a font
+    Overridden,
+    /// Drawn shrunk by `--overflow scale` to fit the cell
+    Scaled,
+}
+
+/// Parse an index set spec into a membership table over `len` indices.
+/// Accepts `all`, `ascii` (32-126), or a comma-separated list of indices and
+/// inclusive ranges, each written in decimal or `0x` hex (e.g. `32-126,0xB0-0xDF`).
+pub fn parse_index_set(spec: &str, len: usize) -> Result<Vec<bool>> {
+    let mut set = vec![false; len];
+    match spec.trim() {
+        "all" => return Ok(vec![true; len]),
+        "ascii" => {
+            set[32.min(len)..127.min(len)].fill(true);
+            return Ok(set);
+        }
+        _ => {}
+    }
+
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (parse_index(a, len)?, parse_index(b, len)?),
+            None => {
+                let i = parse_index(part, len)?;
+                (i, i)
+            }
+        };
+        if start > end {
+            bail!("Invalid range '{}': start is greater than end", part);
+        }
+        set[start..=end].fill(true);
+    }
+
+    if !set.contains(&true) {
+        bail!("Index set '{}' selects no characters", spec);
+    }
+    Ok(set)
+}
+
+/// The `(dx, dy)` each of `len` cells is nudged by, from `--nudge` specs
+/// `<range>=<dx>,<dy>`. Where ranges overlap the later spec wins.
+pub fn parse_nudges(specs: &[String], len: usize) -> Result<Vec<(i32, i32)>> {
+    let mut nudges = vec![(0, 0); len];
+    for spec in specs {
+        let parsed = spec.split_once('=').and_then(|(range, offset)| {
+            let (dx, dy) = offset.split_once(',')?;
+            Some((range, dx.trim().parse().ok()?, dy.trim().parse().ok()?))
+        });
+        let Some((range, dx, dy)) = parsed else {
+            bail!(
+                "Error: invalid --nudge '{}' (expected <range>=<dx>,<dy>)",
+                spec
+            );
+        };
+        let set = parse_index_set(range, len)
+            .with_context(|| format!("Invalid --nudge range in '{}'", spec))?;
+        for (nudge, selected) in nudges.iter_mut().zip(set) {
+            if selected {
+                *nudge = (dx, dy);
+            }
+        }
+    }
+    Ok(nudges)
+}
+
+/// The axis tag and value of a `--variation` spec `<tag>=<value>`, such as
+/// `wght=550`. Tags are the four ASCII characters of the font's `fvar`
+/// table; shorter ones are padded with spaces, as OpenType does.
+pub fn parse_variation(spec: &str) -> Result<([u8; 4], f32)> {
+    let parsed = spec.split_once('=').and_then(|(tag, value)| {
+        let tag = tag.trim();
+        let value: f32 = value.trim().parse().ok()?;
+        let valid = (1..=4).contains(&tag.len())
+            && tag.bytes().all(|b| b.is_ascii_graphic())
+            && value.is_finite();
+        valid.then(|| {
+            let mut bytes = *b"    ";
+            bytes[..tag.len()].copy_from_slice(tag.as_bytes());
+            (bytes, value)
+        })
+    });
+    parsed.ok_or_else(|| {
+        anyhow!(
+            "Error: invalid --variation '{}' (expected <tag>=<value>, e.g. wght=550)",
+            spec
+        )
+    })
+}
+
+/// The `HAlign` of each of `len` cells: `default`, except where a
+/// `--halign-range` spec `<range>=<policy>` covers it; later specs win.
+pub fn parse_halign_ranges(default: HAlign, specs: &[String], len: usize) -> Result<Vec<HAlign>> {
+    let mut aligns = vec![default; len];
+    for spec in specs {
+        let parsed = spec
+            .split_once('=')
+            .and_then(|(range, policy)| Some((range, HAlign::from_str(policy.trim(), true).ok()?)));
+        let Some((range, align)) = parsed else {
+            bail!(
+                "Error: invalid --halign-range '{}' (expected <range>=center|left|bearing)",
+                spec
+            );
+        };
+        let set = parse_index_set(range, len)
+            .with_context(|| format!("Invalid --halign-range range in '{}'", spec))?;
+        for (a, selected) in aligns.iter_mut().zip(set) {
+            if selected {
+                *a = align;
+            }
+        }
+    }
+    Ok(aligns)
+}
+
+/// Parse a single character index below `len` in decimal or `0x` hex.
+pub fn parse_index(s: &str, len: usize) -> Result<usize> {
+    let s = s.trim();
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => s.parse::<usize>().ok(),
+    };
+    parsed
+        .filter(|&i| i < len)
+        .with_context(|| format!("Invalid character index '{}' (expected 0-{})", s, len - 1))
+}
+
+/// Brightness below which a pixel counts as ink: anything darker than
+/// mid-gray.
+pub const INK_THRESHOLD: u8 = 128;
+
+/// Everything that shapes an atlas. `new` fills in the command-line
+/// defaults; adjust the public fields from there.
+#[derive(Clone, Debug)]
+pub struct AtlasConfig {
+    pub font_path: PathBuf,
+    /// Cell width in pixels, padding included.
+    pub cell_width: u32,
+    /// Cell height in pixels, padding included, in place of the font's
+    /// line height.
+    pub cell_height: Option<u32>,
+    /// Where the line sits in a `cell_height` cell.
+    pub valign: VAlign,
+    /// Baseline row, counted from the top of the glyph box, for
+    /// `VAlign::Baseline` in a `cell_height` cell.
+    pub baseline_row: Option<u32>,
+    /// The characters to render, one per cell.
+    pub charmap: CharMap,
+    pub measure: Measure,
+    /// Indices that take part in the width measurement, in the syntax of
+    /// `parse_index_set`.
+    pub measure_set: String,
+    /// Blit only each glyph's tight ink box, placed from the font metrics.
+    pub ink_crop: bool,
+    pub render_mode: RenderMode,
+    /// Subpixel order for `RenderMode::Lcd`.
+    pub lcd_order: LcdOrder,
+    pub overflow: Overflow,
+    /// Draw box-drawing and block characters geometrically.
+    pub synthesize_boxes: bool,
+    /// Redraw block elements geometrically after the font pass.
+    pub fix_blocks: bool,
+    /// Face of `font_path` to use when it is a collection (.ttc).
+    pub face_index: u32,
+    /// Render with SDL_ttf's bold style, synthesized for a regular face.
+    pub bold: bool,
+    /// Render at this point size instead of searching for the one that
+    /// fills `cell_width`, which then only sets a minimum glyph width.
+    pub font_size: Option<f32>,
+    /// Fonts tried in order for characters the primary font lacks.
+    pub fallback_fonts: Vec<PathBuf>,
+    /// Font that draws the indices in `secondary_set` in place of the
+    /// primary, sized to the primary's line height.
+    pub secondary_font: Option<PathBuf>,
+    /// Indices taken from `secondary_font`, in the syntax of
+    /// `parse_index_set`.
+    pub secondary_set: String,
+    /// Pixels every glyph is moved down from the font's baseline (up when
+    /// negative).
+    pub baseline_shift: i32,
+    /// Per-range offsets applied after placement, each `<range>=<dx>,<dy>`
+    /// with the range in the syntax of `parse_index_set`.
+    pub nudges: Vec<String>,
+    /// Variable font axis settings, each `<tag>=<value>`, applied before
+    /// the size search. Only the rust backend can set them.
+    pub variations: Vec<String>,
+    /// Horizontal placement of glyphs outside every `halign_ranges` entry.
+    pub halign: HAlign,
+    /// Per-range placements, each `<range>=<policy>`.
+    pub halign_ranges: Vec<String>,
+    /// Placeholder for cells no font could render.
+    pub missing_glyph: MissingGlyph,
+    /// Directory of PNGs, named by index, that replace individual glyphs.
+    pub override_dir: Option<PathBuf>,
+    pub override_fit: OverrideFit,
+    pub columns: u32,
+    pub column_major: bool,
+    pub padding: u32,
+    pub gutter: u32,
+    pub scale: u32,
+    pub scale_algorithm: ScaleAlgorithm,
+    /// Repeat each column of every glyph box this many times, after
+    /// thresholding and before `scale`.
+    pub stretch_width: u32,
+    /// Repeat each row of every glyph box this many times.
+    pub stretch_height: u32,
+    /// Thicken the thresholded strokes of font glyphs by this many pixels.
+    pub embolden: u32,
+    /// Erode the thresholded strokes of font glyphs by this many pixels.
+    pub thin: u32,
+    pub embolden_dir: EmboldenDir,
+    /// Exponent decoding rendered coverage before it is thresholded or
+    /// downsampled; 1.0 leaves it as rendered.
+    pub gamma: f32,
+    /// Pad the atlas to power-of-two dimensions.
+    pub pot: bool,
+    /// Fail when more than this fraction of printable ASCII renders blank.
+    pub max_blank_fraction: f32,
+    pub allow_blank_atlas: bool,
+    pub font_cache_size: usize,
+    /// Refuse to build an atlas wider or taller than this, after scaling
+    /// and padding.
+    pub max_atlas_dimension: u32,
+    /// Build a signed distance field, clamped to this many pixels either
+    /// side of the glyph edge, in place of the rendered coverage.
+    pub sdf_spread: Option<u32>,
+    /// How many times larger the distance field is rendered before it is
+    /// averaged down into the cells.
+    pub sdf_supersample: u32,
+    /// Stop once the font and cell are sized: the atlas comes back blank,
+    /// at the size it would have, with every glyph skipped.
+    pub layout_only: bool,
+    /// Let the atlas run past `max_atlas_dimension` in height, for the
+    /// caller to split into pages of whole rows.
+    pub paged: bool,
+}
+
+impl AtlasConfig {
+    pub fn new(font_path: impl Into<PathBuf>, cell_width: u32, charmap: CharMap) -> AtlasConfig {
+        AtlasConfig {
+            font_path: font_path.into(),
+            cell_width,
+            cell_height: None,
+            valign: VAlign::Baseline,
+            baseline_row: None,
+            charmap,
+            measure: Measure::Ink,
+            measure_set: "all".to_string(),
+            ink_crop: false,
+            render_mode: RenderMode::Shaded,
+            lcd_order: LcdOrder::Rgb,
+            overflow: Overflow::Clip,
+            synthesize_boxes: false,
+            fix_blocks: false,
+            face_index: 0,
+            bold: false,
+            font_size: None,
+            fallback_fonts: Vec::new(),
+            secondary_font: None,
+            secondary_set: String::new(),
+            baseline_shift: 0,
+            nudges: Vec::new(),
+            variations: Vec::new(),
+            halign: HAlign::Center,
+            halign_ranges: Vec::new(),
+            missing_glyph: MissingGlyph::Blank,
+            override_dir: None,
+            override_fit: OverrideFit::Center,
+            columns: 16,
+            column_major: false,
+            padding: 0,
+            gutter: 0,
+            scale: 1,
+            scale_algorithm: ScaleAlgorithm::Nearest,
+            stretch_width: 1,
+            stretch_height: 1,
+            embolden: 0,
+            thin: 0,
+            embolden_dir: EmboldenDir::Both,
+            gamma: 1.0,
+            pot: false,
+            max_blank_fraction: 0.5,
+            allow_blank_atlas: false,
+            font_cache_size: 4,
+            max_atlas_dimension: 16384,
+            sdf_spread: None,
+            sdf_supersample: 8,
+            layout_only: false,
+            paged: false,
+        }
+    }
+}
+
+/// Where a glyph from the font was placed: its metrics and the offset of
+/// the blitted surface from the glyph box origin, before any scaling.
+#[derive(Clone, Copy, Debug)]
+pub struct Placement {
+    pub minx: i32,
+    pub maxx: i32,
+    pub miny: i32,
+    pub maxy: i32,
+    pub advance: i32,
+    /// Pen position the glyph was drawn from, from the glyph box's left.
+    pub pen_x: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    /// Size of what was blitted: the rendered surface, or with `ink_crop`
+    /// its ink box.
+    pub surface_width: u32,
+    pub surface_height: u32,
+}
+
+/// One cell of the finished atlas.
+#[derive(Clone, Debug)]
+pub struct GlyphInfo {
+    pub ch: char,
+    /// Glyph box in the finished atlas: left, top, width, height.
+    pub rect: (u32, u32, u32, u32),
+    pub status: CellStatus,
+    /// Drawn geometrically rather than taken from a font.
+    pub synthesized: bool,
+    /// Taken from a `--fallback-font` rather than the primary or secondary
+    /// font.
+    pub fallback: bool,
+    /// `None` for cells nothing was blitted into from a font.
+    pub placement: Option<Placement>,
+}
+
+/// Font cache counters, for profiling.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheStats {
+    pub open: usize,
+    pub loads: usize,
+    pub hits: usize,
+}
+
+/// A finished atlas.
+pub struct Atlas {
+    /// The pixels, one byte each (0 is ink, 255 background), in rows of
+    /// `image.width` bytes with no padding between them.
+    pub image: GrayImage,
+    pub grid: Grid,
+    /// The part of `image` the grid covers; smaller when padded with `pot`.
+    pub content_width: u32,
+    pub content_height: u32,
+    pub font_size: f32,
+    /// Baseline row, counted from the top of each glyph box.
+    pub baseline: u32,
+    /// The primary font's metrics at `font_size`, before any scaling; `None`
+    /// when the atlas wasn't rendered from a font.
+    pub line: Option<LineMetrics>,
+    /// The widest measured glyph at `font_size` and the glyph width the size
+    /// search aimed for, before any scaling; `None` when the atlas wasn't
+    /// rendered from a font.
+    pub width_fit: Option<(u32, u32)>,
+    /// One entry per character of the config's charmap.
+    pub glyphs: Vec<GlyphInfo>,
+    pub coverage: Coverage,
+    pub cache_stats: CacheStats,
+    /// For `RenderMode::Lcd`, the coverage of each subpixel, laid out as
+    /// `image`, which holds its luminance.
+    pub subpixel: Option<RgbImage>,
+}
+
+impl Atlas {
+    /// An atlas read back from an image laid out as `grid`, such as a
+    /// generated atlas touched up by hand, with one cell per character of
+    /// `charmap`. The image must be exactly the grid's size, or that size
+    /// padded out to powers of two. `baseline` is unknown for arbitrary
+    /// images; without it the debug image draws no baseline.
+    pub fn from_image(
+        image: GrayImage,
+        grid: Grid,
+        charmap: &CharMap,
+        baseline: Option<u32>,
+    ) -> Result<Atlas> {
+        if ((grid.columns * grid.rows) as usize) < charmap.len() {
+            bail!(
+                "Error: a {}x{} grid has no room for {} characters",
+                grid.columns,
+                grid.rows,
+                charmap.len()
+            );
+        }
+        let (stride_x, stride_y) = (grid.stride_x(), grid.stride_y());
+        if !image.width.is_multiple_of(stride_x) || !image.height.is_multiple_of(stride_y) {
+            bail!(
+                "Error: the {}x{} image is not a whole number of {}x{} cells",
+                image.width,
+                image.height,
+                stride_x,
+                stride_y
+            );
+        }
+        let (content_width, content_height) = (grid.width(), grid.height());
+        let exact = (image.width, image.height) == (content_width, content_height);
+        let padded = (image.width, image.height)
+            == (
+                content_width.next_power_of_two(),
+                content_height.next_power_of_two(),
+            );
+        if !exact && !padded {
+            bail!(
+                "Error: the image holds {}x{} cells, but {} characters in {} columns need {}x{}",
+                image.width / stride_x,
+                image.height / stride_y,
+                charmap.len(),
+                grid.columns,
+                grid.columns,
+                grid.rows
+            );
+        }
+        let glyphs = charmap
+            .chars()
+            .iter()
+            .enumerate()
+            .map(|(i, &ch)| {
+                let (x, y) = grid.glyph_origin(i);
+                GlyphInfo {
+                    ch,
+                    rect: (x, y, grid.glyph_width, grid.glyph_height),
+                    status: CellStatus::Ok,
+                    synthesized: false,
+                    fallback: false,
+                    placement: None,
+                }
+            })
+            .collect();
+        Ok(Atlas {
+            image,
+            grid,
+            content_width,
+            content_height,
+            font_size: 0.0,
+            // Past the last row of the glyph box, where nothing is drawn
+            baseline: baseline.unwrap_or(grid.glyph_height),
+            line: None,
+            width_fit: None,
+            glyphs,
+            coverage: Coverage::default(),
+            cache_stats: CacheStats {
+                open: 0,
+                loads: 0,
+                hits: 0,
+            },
+            subpixel: None,
+        })
+    }
+
+    pub fn status(&self) -> Vec<CellStatus> {
+        self.glyphs.iter().map(|g| g.status).collect()
+    }
+}
+
+/// Build an atlas with a `Generator` of its own. Callers building several
+/// atlases should keep one `Generator` and call `Generator::generate`.
+#[cfg(feature = "backend-sdl")]
+pub fn generate_atlas(config: &AtlasConfig) -> Result<Atlas> {
+    Generator::new()?.generate(config)
+}
+
+/// Holds SDL and SDL_ttf initialized for as long as it lives. Atlases own
+/// their pixels, so they stay valid after it is dropped, and a new
+/// generator can be created afterwards.
+#[cfg(feature = "backend-sdl")]
+pub struct Generator {
+    sdl: Sdl,
+    ttf: Sdl3TtfContext,
+}
+
+#[cfg(feature = "backend-sdl")]
+impl Generator {
+    pub fn new() -> Result<Generator> {
+        let sdl = sdl3::init().failure(Failure::Render)?;
+        let ttf = sdl3::ttf::init()
+            .context("Failed to initialize SDL2_ttf")
+            .failure(Failure::Render)?;
+        Ok(Generator { sdl, ttf })
+    }
+
+    pub fn sdl(&self) -> &Sdl {
+        &self.sdl
+    }
+
+    /// Size the font, render every character of `config.charmap`, and lay
+    /// out, scale, and pad the atlas.
+    pub fn generate(&self, config: &AtlasConfig) -> Result<Atlas> {
+        if !config.variations.is_empty() {
+            bail!(
+                "Error: SDL_ttf can't set variable font axes, so --variation needs --backend rust"
+            );
+        }
+        // Font handles are shared through the cache rather than reopened for
+        // every size probe.
+        let style = if config.bold {
+            FontStyle::BOLD
+        } else {
+            FontStyle::NORMAL
+        };
+        let cache = Rc::new(RefCell::new(FontCache::new(
+            &self.ttf,
+            config.font_cache_size,
+            style,
+        )));
+        backend::generate(config, &|path, face| {
+            let font = SdlBackend::open(Rc::clone(&cache), path, face, config.render_mode)?;
+            Ok(Box::new(font))
+        })
+    }
+
+    /// Render `config` and its bold variant at the same point size, in
+    /// glyph boxes large enough for either, so the bold glyphs sit in
+    /// matching cells without clipping.
+    pub fn generate_with_bold(&self, config: &AtlasConfig) -> Result<(Atlas, Atlas)> {
+        backend::generate_with_bold(config, |config| self.generate(config))
+    }
+}
+
+/// Fail unless an atlas whose grid covers `width` x `height` pixels stays
+/// within `--max-atlas-dimension` once `--scale` and `--pot` are applied;
+/// a `paged` atlas's height need only fit the blit coordinates. The
+/// arithmetic is done in u64 so huge cells can't wrap on the way.
+fn check_atlas_size(width: u64, height: u64, config: &AtlasConfig) -> Result<()> {
+    let scale = config.scale as u64;
+    let (mut width, mut height) = (width * scale, height * scale);
+    if config.pot {
+        width = width.next_power_of_two();
+        height = height.next_power_of_two();
+    }
+    let limit = config.max_atlas_dimension as u64;
+    let height_limit = if config.paged { i32::MAX as u64 } else { limit };
+    if width > limit || height > height_limit {
+        return Err(anyhow!(
+            "Error: the atlas would be {}x{} pixels, over the {}px limit; use a smaller --font-width, --scale, or more --columns, or raise --max-atlas-dimension",
+            width,
+            height,
+            limit
+        ))
+        .failure(Failure::Sizing);
+    }
+    Ok(())
+}
+
+fn check_stretch(config: &AtlasConfig) -> Result<()> {
+    for (factor, option) in [
+        (config.stretch_width, "--stretch-width"),
+        (config.stretch_height, "--stretch-height"),
+    ] {
+        if !(1..=16).contains(&factor) {
+            bail!("Error: {} must be between 1 and 16, got {}", option, factor);
+        }
+    }
+    Ok(())
+}
+
+/// The glyph box height and the baseline row in it, for a font whose line
+/// is `line_height` tall with the given ascent and (negative) descent: the
+/// font's own line, unless `cell_height` forces the box.
+fn vertical_layout(
+    config: &AtlasConfig,
+    line_height: u32,
+    ascent: i32,
+    descent: i32,
+) -> Result<(u32, i32)> {
+    let Some(cell_height) = config.cell_height else {
+        return Ok((line_height, ascent));
+    };
+    let Some(height) = cell_height
+        .checked_sub(2 * config.padding)
+        .filter(|&h| h > 0)
+    else {
+        bail!(
+            "Error: --padding {} leaves no room for glyphs in a {}px tall cell",
+            config.padding,
+            cell_height
+        );
+    };
+    let slack = height as i32 - line_height as i32;
+    let row = match config.valign {
+        VAlign::Baseline => match config.baseline_row {
+            Some(row) if row > height => {
+                bail!(
+                    "Error: --baseline {} is below the {}px glyph box",
+                    row,
+                    height
+                )
+            }
+            Some(row) => row as i32,
+            None => height as i32 + descent,
+        },
+        VAlign::Top => ascent,
+        VAlign::Center => ascent + slack / 2,
+        VAlign::Bottom => ascent + slack,
+    };
+    if height != line_height {
+        info!(
+            "Cell height forced to {}px (the font's line is {}px); baseline at row {}",
+            height, line_height, row
+        );
+    }
+    Ok((height, row))
+}
+
+fn check_gamma(config: &AtlasConfig) -> Result<()> {
+    if !(config.gamma.is_finite() && config.gamma > 0.0) {
+        bail!(
+            "Error: --gamma must be a positive number, got {}",
+            config.gamma
+        );
+    }
+    Ok(())
+}
+
+/// Threshold the first `count` glyph boxes of `image`, laid out as `grid`,
+/// and repeat their columns and rows by the config's stretch factors into
+/// a new atlas with the same padding and gutters. Repeating the edge
+/// pixels keeps box-drawing strokes meeting across cells.
+fn stretch_glyphs(
+    image: &GrayImage,
+    grid: &Grid,
+    count: usize,
+    config: &AtlasConfig,
+) -> (GrayImage, Grid) {
+    let (across, down) = (config.stretch_width, config.stretch_height);
+    let stretched = grid.stretched(across, down);
+    let mut out = GrayImage::new(stretched.width(), stretched.height(), 255);
+    for index in 0..count {
+        let (x, y) = grid.glyph_origin(index);
+        let glyph = image.crop(x, y, grid.glyph_width, grid.glyph_height);
+        let (x, y) = stretched.glyph_origin(index);
+        out.paste(&glyph.stretch_bits(across, down), x, y);
+    }
+    info!(
+        "Stretched {}x{}: glyph box {}x{}",
+        across, down, stretched.glyph_width, stretched.glyph_height
+    );
+    (out, stretched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variation_specs_parse_to_padded_tags() {
+        assert_eq!(parse_variation("wght=550").unwrap(), (*b"wght", 550.0));
+        assert_eq!(parse_variation(" slnt = -10 ").unwrap(), (*b"slnt", -10.0));
+        assert_eq!(parse_variation("ab=1.5").unwrap(), (*b"ab  ", 1.5));
+    }
+
+    #[test]
+    fn malformed_variation_specs_are_rejected() {
+        for spec in [
+            "wght",
+            "=550",
+            "toolong=1",
+            "wght=heavy",
+            "wght=inf",
+            "w t=1",
+        ] {
+            assert!(parse_variation(spec).is_err(), "{} was accepted", spec);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Leveled diagnostics through the `log` crate: `--quiet`, the default,
+//! `-v`, and `-vv` show errors, warnings, progress, and per-glyph detail,
+//! as text or, with `--log-format json`, one JSON object per line.
+
+use clap::ValueEnum;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::io::Write as _;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// How log records are written to stderr.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Plain lines, warnings marked `Warning:`
+    Text,
+    /// `{"level": ..., "job": ..., "message": ...}` per line
+    Json,
+}
+
+/// Warnings logged so far, shown or not, for `--deny-warnings`.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts this crate's warnings before handing records to env_logger.
+struct CountingLogger {
+    inner: env_logger::Logger,
+}
+
+impl Log for CountingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() == Level::Warn || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() == Level::Warn && record.target().starts_with(env!("CARGO_CRATE_NAME")) {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+        }
+        if self.inner.matches(record) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// The most detailed level shown for `--quiet` and a `-v` count.
+pub fn level(quiet: bool, verbose: u8) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, _) => LevelFilter::Debug,
+    }
+}
+
+/// Install the logger, once, before anything logs. Other crates' records
+/// only show when they are errors.
+pub fn init(level: LevelFilter, format: LogFormat) {
+    let mut builder = env_logger::Builder::new();
+    builder
+        .filter_level(LevelFilter::Error)
+        .filter_module(env!("CARGO_CRATE_NAME"), level);
+    match format {
+        LogFormat::Text => builder.format(|buf, record| {
+            let prefix = mycp437generator::log_prefix()
+                .map(|p| format!("[{}] ", p))
+                .unwrap_or_default();
+            let marker = match record.level() {
+                Level::Warn => "Warning: ",
+                _ => "",
+            };
+            writeln!(buf, "{}{}{}", prefix, marker, record.args())
+        }),
+        LogFormat::Json => builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "level": record.level().as_str().to_lowercase(),
+                "job": mycp437generator::log_prefix(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{}", line)
+        }),
+    };
+    let logger = CountingLogger {
+        inner: builder.build(),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        // Warnings always reach the logger, even with --quiet, to be counted
+        log::set_max_level(level.max(LevelFilter::Warn));
+    }
+}
+
+/// How many warnings this crate has logged.
+pub fn warnings() -> usize {
+    WARNINGS.load(Ordering::Relaxed)
+}
@@ -0,0 +1,67 @@
+//! `--output-lovefont`: a LÖVE ImageFont strip, the glyphs in one row with
+//! separator columns between them, for `love.graphics.newImageFont`.
+
+use anyhow::Result;
+use sdl3::pixels::PixelFormat;
+use sdl3::surface::Surface;
+
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+
+/// Separator color. The atlas is grayscale, so no glyph pixel can match it.
+const SEPARATOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Lay out the glyphs at `indices` left to right, with a separator column
+/// before each and after the last. LÖVE tints the glyphs when drawing, so
+/// ink comes out white with the atlas darkness as its alpha.
+pub fn build_strip(atlas: &GrayImage, grid: &Grid, indices: &[usize]) -> Result<Surface<'static>> {
+    let glyph_width = grid.glyph_width;
+    let width = indices.len() as u32 * (glyph_width + 1) + 1;
+    let height = grid.glyph_height;
+    let mut pixels = vec![SEPARATOR; (width * height) as usize];
+    for (n, &i) in indices.iter().enumerate() {
+        let (left, top) = grid.glyph_origin(i);
+        let out_left = n as u32 * (glyph_width + 1) + 1;
+        for y in 0..height {
+            for x in 0..glyph_width {
+                let ink = 255 - atlas.get(left + x, top + y);
+                pixels[(y * width + out_left + x) as usize] = [255, 255, 255, ink];
+            }
+        }
+    }
+
+    let mut surface = Surface::new(width, height, PixelFormat::RGBA32)?;
+    let pitch = surface.pitch() as usize;
+    surface.with_lock_mut(|data: &mut [u8]| {
+        for y in 0..height as usize {
+            for x in 0..width as usize {
+                let offset = y * pitch + x * 4;
+                data[offset..offset + 4].copy_from_slice(&pixels[y * width as usize + x]);
+            }
+        }
+    });
+    Ok(surface)
+}
+
+/// The glyph string to pass to `newImageFont`, as a quoted Lua literal.
+pub fn lua_glyph_string(chars: impl IntoIterator<Item = char>) -> String {
+    let mut out = String::from("\"");
+    for ch in chars {
+        match ch {
+            '"' | '\\' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            // LuaJIT has no \u escape, so spell control characters as bytes
+            ch if ch.is_control() => {
+                let mut utf8 = [0; 4];
+                for byte in ch.encode_utf8(&mut utf8).bytes() {
+                    out.push_str(&format!("\\{:03}", byte));
+                }
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
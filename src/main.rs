@@ -1,509 +1,3250 @@
-use anyhow::{Context, Result, bail};
-use clap::Parser;
+use anyhow::{Context, Result, anyhow, bail};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use log::info;
+use logging::LogFormat;
+#[cfg(feature = "backend-sdl")]
+use mycp437generator::Generator;
+use mycp437generator::backend::{self, Backend};
+use mycp437generator::bdf;
+use mycp437generator::bitmap_font::BitmapFont;
+use mycp437generator::charmap::{self, CellLayout, CharMap, CharOrder};
+use mycp437generator::codepage::Codepage;
+use mycp437generator::failure::{Categorize, Failure};
+use mycp437generator::gray::{self, GrayImage};
+use mycp437generator::grid::{self, Grid};
+use mycp437generator::hex_dump;
+use mycp437generator::packing::{self, BitOrder, PackedBitmap, WordSize};
+use mycp437generator::psf;
+use mycp437generator::vga_rom;
+use mycp437generator::warning::{self, Code, Warning};
+use mycp437generator::{
+    Atlas, AtlasConfig, CellStatus, EmboldenDir, HAlign, INK_THRESHOLD, LcdOrder, Measure,
+    MissingGlyph, Overflow, OverrideFit, RenderMode, ScaleAlgorithm, VAlign, coverage, log,
+    parse_index, parse_index_set, set_log_prefix,
+};
+#[cfg(feature = "backend-sdl")]
 use sdl3::image::SaveSurface;
-use sdl3::pixels::Color;
-use sdl3::rect::Rect;
-use sdl3::surface::Surface;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "backend-sdl")]
+use std::cell::OnceCell;
+use std::ffi::OsString;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Instant;
 
-#[derive(Parser, Debug)]
+mod asm_font;
+mod autofit;
+mod base64;
+mod bmfont;
+mod config;
+mod contact_print;
+mod css_sprites;
+#[cfg(feature = "backend-sdl")]
+mod debug_image;
+mod diff;
+mod docs;
+mod fantasy_console;
+mod gfx_font;
+mod glyph_dir;
+mod html_demo;
+mod image_io;
+mod logging;
+#[cfg(feature = "backend-sdl")]
+mod love_font;
+mod manifest;
+mod metrics_report;
+mod pages;
+mod preset;
+#[cfg(feature = "backend-sdl")]
+mod preview;
+mod provenance;
+mod rust_embed;
+mod sample;
+mod sidecar;
+mod strict;
+mod strips;
+mod system_fonts;
+mod terminal;
+#[cfg(feature = "backend-sdl")]
+mod terminal_graphics;
+mod text;
+mod tiles;
+mod trim;
+mod u8g2_font;
+mod variants;
+mod watch;
+
+use manifest::Job;
+use variants::{VariantLayout, Variants};
+
+#[derive(Parser)]
 #[command(name = "mycp437generator")]
 #[command(about = "Generate a CP437 font atlas from a TTF file")]
+#[command(args_conflicts_with_subcommands = true)]
+#[command(after_help = "Exit status:
+  0  success
+  2  invalid arguments or option files
+  3  a font that can't be found, read, or parsed
+  4  no font size gives the requested atlas
+  5  the glyphs didn't render as required (blank atlas, --overflow error, --check)
+  6  an output that couldn't be written
+  7  --strict or --deny-warnings violations")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// With no subcommand, the options of `generate`
+    #[command(flatten)]
+    generate: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate an atlas; the default when no subcommand is given
+    Generate(Box<Args>),
+
+    /// Print a completion script for a shell
+    Completions {
+        shell: clap_complete::Shell,
+
+        /// Write the script into this directory instead of stdout
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+
+    /// Print the man page, in roff
+    Manpage {
+        /// Write a page per command into this directory instead of stdout
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(clap::Args, Debug, Serialize, Deserialize)]
 struct Args {
-    /// Path to the TTF font file
+    /// TOML file of option defaults; keys are the long option names
+    /// (`font_path`, `hex_dump`, ...) and flags given here win
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+
+    /// Print the effective options, config file included, as TOML and exit
+    #[arg(long)]
+    #[serde(skip)]
+    print_config: bool,
+
+    /// Generate every job in this TOML (`[[job]]` tables) or JSON (array)
+    /// file, each taking --config keys layered over the other options
+    #[arg(long)]
+    #[serde(skip)]
+    manifest: Option<PathBuf>,
+
+    /// Stop a --manifest run at the first failing job
+    #[arg(long, requires = "manifest")]
+    #[serde(skip)]
+    fail_fast: bool,
+
+    /// Defaults for a target program, under any config file or flag: `df`
+    /// for Dwarf Fortress and REXPaint tilesets, `tcod` for libtcod fonts
+    #[arg(long, value_enum)]
+    #[serde(skip)]
+    preset: Option<preset::Preset>,
+
+    /// After generating, keep running and regenerate whenever the font,
+    /// config, manifest, or another input file changes
+    #[arg(long)]
+    #[serde(skip)]
+    watch: bool,
+
+    /// Path to the TTF font file (same as --font-path)
+    #[arg(value_name = "FONT")]
+    font: Option<PathBuf>,
+
+    /// Width of each character cell in pixels (same as --font-width)
+    #[arg(value_name = "WIDTH")]
+    width: Option<u32>,
+
+    /// Path to the TTF font file; wins over the positional FONT
+    #[arg(long)]
+    font_path: Option<PathBuf>,
+
+    /// Read the atlas from this PNG instead of rendering a font, e.g. one
+    /// touched up by hand, and write the requested outputs from it. Its
+    /// layout comes from --input-sidecar, or from --font-width (the cell
+    /// width), --cell-height, --padding, --gutter, and --columns
+    #[arg(long, conflicts_with_all = ["font", "font_path", "font_name"])]
+    input_atlas: Option<PathBuf>,
+
+    /// Build the atlas from this PSF1 or PSF2 console font instead of
+    /// rendering a TTF; its Unicode table, if any, places the glyphs
+    #[arg(long, conflicts_with_all = ["font", "font_path", "font_name", "input_atlas"])]
+    input_psf: Option<PathBuf>,
+
+    /// Build the atlas from this BDF bitmap font instead of rendering a TTF;
+    /// cells are its FONTBOUNDINGBOX and glyphs keep its baseline
+    #[arg(long, conflicts_with_all = ["font", "font_path", "font_name", "input_atlas", "input_psf"])]
+    input_bdf: Option<PathBuf>,
+
+    /// Build the atlas from this raw VGA ROM dump: 256 glyphs of 8-pixel
+    /// rows, one byte per row, leftmost pixel in the top bit
+    #[arg(long, conflicts_with_all = ["font", "font_path", "font_name", "input_atlas", "input_psf", "input_bdf"])]
+    input_raw: Option<PathBuf>,
+
+    /// Rebuild the atlas from a --hex-dump this tool wrote earlier; the
+    /// grid comes from the dump's header comments
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["font", "font_path", "font_name", "input_atlas", "input_psf", "input_bdf", "input_raw"])]
+    from_hex: Option<PathBuf>,
+
+    /// Rows per glyph in the --input-raw dump; inferred as the file size
+    /// over 256 when omitted
+    #[arg(long, requires = "input_raw")]
+    raw_height: Option<u32>,
+
+    /// JSON sidecar written alongside the --input-atlas image
+    #[arg(long, requires = "input_atlas", conflicts_with = "cell_height")]
+    input_sidecar: Option<PathBuf>,
+
+    /// Cell height in pixels, padding included: the layout of an
+    /// --input-atlas, or a height forced on a font's cells in place of its
+    /// line height, which --valign positions the line in and which taller
+    /// glyphs overflow per --overflow
+    #[arg(long)]
+    cell_height: Option<u32>,
+
+    /// Where the font's line sits in a --cell-height cell: `baseline` (at
+    /// the descent above the bottom, or --baseline), `top`, `center`, or
+    /// `bottom`
+    #[arg(long, value_enum, default_value_t = VAlign::Baseline, requires = "cell_height")]
+    valign: VAlign,
+
+    /// Baseline row, from the top of the glyph box, for --valign baseline
+    #[arg(long, value_name = "PX", requires = "cell_height")]
+    baseline: Option<u32>,
+
+    /// Installed font family to use instead of a file, e.g.
+    /// "DejaVu Sans Mono"; the file it resolves to is logged
+    #[arg(long, conflicts_with_all = ["font", "font_path"])]
+    font_name: Option<String>,
+
+    /// Style of the --font-name face, e.g. Bold, Light Italic
+    #[arg(long, requires = "font_name")]
+    font_style: Option<String>,
+
+    /// Width of each character cell in pixels; wins over the positional WIDTH
+    #[arg(long)]
+    font_width: Option<u32>,
+
+    /// Render at each of these cell widths (e.g. `8,12,16`) and stack the
+    /// grids top to bottom in one PNG; the --json sidecar records each
+    /// strip's offset and cells, and --hex-dump writes a section per size
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["width", "font_width", "input_atlas", "input_psf", "input_bdf", "input_raw", "from_hex"])]
+    sizes: Vec<u32>,
+
+    /// Choose the widest cell whose atlas fits this texture budget, e.g.
+    /// `256x256`, instead of taking a width; the atlas is padded out to the
+    /// budget, the slack left as a margin right of and below the grid
+    #[arg(long, value_name = "WxH", conflicts_with_all = ["width", "font_width", "sizes", "pot", "with_bold", "with_inverse", "input_atlas", "input_psf", "input_bdf", "input_raw", "from_hex"])]
+    atlas_size: Option<String>,
+
+    /// Also render every character in the font's bold style, in a second
+    /// grid placed by --variant-layout; both share one cell size large
+    /// enough for either
+    #[arg(long, conflicts_with_all = ["sizes", "input_atlas", "input_psf", "input_bdf", "input_raw", "from_hex"])]
+    with_bold: bool,
+
+    /// Also write every cell inverted edge to edge, padding included, for
+    /// reverse video, as a further grid placed by --variant-layout
+    #[arg(long, conflicts_with = "sizes")]
+    with_inverse: bool,
+
+    /// Where variant grids go: `below` the regular one, or `beside` it
+    #[arg(long, value_enum, default_value_t = VariantLayout::Below)]
+    variant_layout: VariantLayout,
+
+    /// Output PNG file path. Without it the PNG goes to a name in the
+    /// current directory chosen by --output-naming, unless --hex-dump or
+    /// --preview was asked for instead
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Default PNG name: `font` for `<font-stem>_<width>x<height>.png`,
+    /// `size` for `<width>x<height>.png`
+    #[arg(long, value_enum, default_value_t = OutputNaming::Font)]
+    output_naming: OutputNaming,
+
+    /// Ink color of the PNG, as RRGGBB hex
+    #[arg(long, default_value = "000000")]
+    foreground: String,
+
+    /// Background color of the PNG, as RRGGBB hex, or `transparent` to
+    /// carry the ink's coverage in the alpha channel instead
+    #[arg(long, default_value = "ffffff")]
+    background: String,
+
+    /// Also write the atlas as a single-channel 8-bit PNG of coverage, 255
+    /// for full ink and 0 for background, taken from the antialiased render
+    #[arg(long)]
+    output_gray: Option<PathBuf>,
+
+    /// Also write each cell as its own PNG into this directory (created if
+    /// missing), with a manifest.json listing the files and their indices
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
+
+    /// --output-dir file names: `index` (`000.png`) or `codepoint`
+    /// (`U+263A.png`)
+    #[arg(long, value_enum, default_value_t = glyph_dir::NameBy::Index, requires = "output_dir")]
+    name_by: glyph_dir::NameBy,
+
+    /// Overwrite output files that already exist instead of refusing to
+    #[arg(long)]
+    force: bool,
+
+    /// Leave cells with no drawn pixel out of --output-dir
+    #[arg(long, requires = "output_dir")]
+    skip_empty: bool,
+
+    /// Dump hex bitmap to stdout, its macros named by --name; combine with
+    /// --output to save the PNG too. A value (`--hex-dump=NAME`, or
+    /// `--hex-dump NAME` when NAME isn't a file) is the deprecated way to
+    /// give the name, so a FONT positional after the flag stays the font
+    #[arg(
+        long,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        value_name = "NAME"
+    )]
+    hex_dump: Option<String>,
+
+    /// Log only errors
+    #[arg(short, long, conflicts_with_all = ["verbose", "debug"])]
+    quiet: bool,
+
+    /// Log more: -v adds progress and the sizes chosen, -vv every glyph's
+    /// metrics and placement
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Same as -vv
+    #[arg(long)]
+    debug: bool,
+
+    /// How log lines are written to stderr: `text`, or `json`, one object
+    /// per line with `level`, `job`, and `message`
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
+    /// Exit with an error after the run when any warning was logged
+    #[arg(long)]
+    deny_warnings: bool,
+
+    /// Write every warning of the run to this file as a JSON array of
+    /// `{code, index, char, message, data}` objects, with stable codes
+    /// such as `GLYPH_CLIPPED`; skipped, placeholder and fallback glyphs
+    /// and a missed width are included too
+    #[arg(long, value_name = "PATH")]
+    report: Option<PathBuf>,
+
+    /// Atlas indices that participate in the width measurement:
+    /// `all`, `ascii`, or a list of indices/ranges such as `32-126,176-223`
+    #[arg(long, default_value = "all")]
+    measure_set: String,
+
+    /// Glyph quantity used for the size search and cell width:
+    /// `ink` (rightmost inked pixel) or `advance` (advance width)
+    #[arg(long, value_enum, default_value_t = Measure::Ink)]
+    measure: Measure,
+
+    /// Blit only each glyph's tight ink box and place it from the font
+    /// metrics, ignoring any padding the renderer adds around the glyph
+    #[arg(long)]
+    ink_crop: bool,
+
+    /// How glyphs are drawn: `shaded` (antialiased), `solid` (no
+    /// antialiasing) or `lcd` (subpixel, saved as an RGB atlas whose
+    /// channels each carry one subpixel's coverage, for drawing 1:1 on an
+    /// LCD panel)
+    #[arg(long, value_enum, default_value_t = RenderMode::Shaded)]
+    render_mode: RenderMode,
+
+    /// Subpixel order of the panel for `--render-mode lcd`: `rgb` or `bgr`.
+    /// SDL_ttf applies FreeType's default LCD filter and offers no choice
+    /// of others
+    #[arg(long, value_enum, default_value_t = LcdOrder::Rgb)]
+    lcd_order: LcdOrder,
+
+    /// Glyph renderer for TrueType fonts: `sdl`, or `rust` (pure Rust, in
+    /// builds with the `backend-rust` feature). Both size and lay out the
+    /// atlas the same way; --render-mode lcd and --with-bold need sdl.
+    /// Builds without the `backend-sdl` feature default to rust
+    #[arg(long, value_enum, default_value_t = BackendKind::DEFAULT)]
+    backend: BackendKind,
+
+    /// Save a signed distance field instead of coverage: the PNG holds the
+    /// distance to each glyph's edge, 255 deep inside and 0 far outside,
+    /// clamped to this many pixels. --padding is raised to at least this
+    #[arg(long, value_name = "SPREAD", conflicts_with_all = ["hex_dump", "input_atlas", "input_psf", "input_bdf", "input_raw", "from_hex"])]
+    sdf: Option<u32>,
+
+    /// How many times larger --sdf renders the glyphs before measuring the
+    /// distances and averaging them down
+    #[arg(long, default_value_t = 8, requires = "sdf")]
+    sdf_supersample: u32,
+
+    /// Also write a diagnostic PNG showing every glyph's raw render next to
+    /// its threshold and weight variants, most tuning-sensitive glyphs first
+    #[arg(long)]
+    contact_print: Option<PathBuf>,
+
+    /// Also write every glyph's font metrics and placement (bounds,
+    /// advance, rendered size, offsets, clipping) after a row of the font's
+    /// size, ascent, descent, and cell size: JSON for a .json path, else CSV
+    #[arg(long)]
+    metrics_report: Option<PathBuf>,
+
+    /// Compare the atlas cell by cell against this PNG, log every glyph
+    /// whose thresholded bits differ, and fail past --diff-tolerance
+    #[arg(long, value_name = "PNG", conflicts_with = "diff_hex")]
+    diff: Option<PathBuf>,
+
+    /// Like --diff, against a hex dump this tool wrote earlier
+    #[arg(long, value_name = "PATH")]
+    diff_hex: Option<PathBuf>,
+
+    /// Differing pixels a glyph may have before --diff fails
+    #[arg(long, value_name = "PIXELS", default_value_t = 0)]
+    diff_tolerance: u32,
+
+    /// Also write the atlas with the pixels that differ from the --diff
+    /// reference in red
+    #[arg(long)]
+    diff_image: Option<PathBuf>,
+
+    /// Maximum number of loaded font handles kept open for reuse
+    #[arg(long, default_value_t = 4)]
+    font_cache_size: usize,
+
+    /// Largest atlas width or height, in pixels after --scale and --pot, to
+    /// attempt building. A taller atlas is split into pages of whole rows,
+    /// saved as name.0.png, name.1.png, ...
+    #[arg(long, default_value_t = 16384)]
+    max_atlas_dimension: u32,
+
+    /// Print timing, font cache, and memory usage when the run finishes
+    #[arg(long)]
+    profile: bool,
+
+    /// Worker threads for the pixel passes (bit packing, distance fields);
+    /// 0 uses one per core. Set once per process, so manifest jobs can't
+    /// change it
+    #[arg(long, default_value_t = 0)]
+    jobs: usize,
+
+    /// What to do with glyphs whose ink doesn't fit the cell:
+    /// `clip` it, `scale` it down to fit, or abort with an `error`. Scaled
+    /// glyphs are still placed by --halign and --nudge
+    #[arg(long, value_enum, default_value_t = Overflow::Clip)]
+    overflow: Overflow,
+
+    /// Draw the shade, box-drawing, and block characters (176-223) directly
+    /// instead of taking them from the font, so lines connect across cells
+    #[arg(long)]
+    synthesize_boxes: bool,
+
+    /// Replace the block elements (219-223) with exact full/half-cell fills
+    /// so adjacent blocks never show seams
+    #[arg(long)]
+    fix_blocks: bool,
+
+    /// Also write a Rust source file with the compressed packed atlas,
+    /// cell constants, and a no_std-friendly `decompress()`
+    #[arg(long)]
+    rust_embedded: Option<PathBuf>,
+
+    /// Also write the first 256 glyphs as a raw VGA ROM dump, each glyph's
+    /// rows in turn with the leftmost pixel in the top bit
+    #[arg(long)]
+    output_raw: Option<PathBuf>,
+
+    /// Bits per row in the --output-raw dump, 8 or 16; glyphs must fit
+    #[arg(long, default_value_t = 8, requires = "output_raw")]
+    raw_width: u32,
+
+    /// Also write a LÖVE ImageFont PNG: the --lovefont-set glyphs in one
+    /// row between magenta separator columns. The glyph string to pass to
+    /// `love.graphics.newImageFont` is printed
+    #[arg(long)]
+    output_lovefont: Option<PathBuf>,
+
+    /// Atlas indices that go in the --output-lovefont strip, in order:
+    /// `all`, `ascii`, or a list of indices/ranges
+    #[arg(long, default_value = "32-126", requires = "output_lovefont")]
+    lovefont_set: String,
+
+    /// Also write a BMFont text file `<BASENAME>.fnt` and its page
+    /// `<BASENAME>.png` (white ink on transparent) for Godot's BitmapFont
+    /// importer; ids are the table's Unicode codepoints
+    #[arg(long, value_name = "BASENAME")]
+    output_godot: Option<PathBuf>,
+
+    /// Also write an Adafruit GFX font header (`GFXfont`) for the
+    /// --gfx-range characters, each cropped to its ink and spaced by the
+    /// font's advances
     #[arg(long)]
-    font_path: PathBuf,
+    emit_gfx: Option<PathBuf>,
+
+    /// Codepoints in the --emit-gfx font, as `<first>-<last>` in hex
+    #[arg(long, default_value = "20-7E", requires = "emit_gfx")]
+    gfx_range: String,
+
+    /// Also write a u8g2 font (C source, bdfconv's run-length encoded
+    /// format) of every printable character in the table
+    #[arg(long)]
+    emit_u8g2: Option<PathBuf>,
+
+    /// Also write a PICO-8 Lua line that pokes the glyphs (at most 8x8) in
+    /// as the custom font, CP437 index = character code
+    #[arg(long)]
+    emit_pico8: Option<PathBuf>,
+
+    /// Also write a TIC-80 cart `<SPRITES>` section holding the glyphs (at
+    /// most 8x8) as foreground sprites, for `font()`
+    #[arg(long)]
+    emit_tic80: Option<PathBuf>,
+
+    /// Also write the glyphs as NES CHR-ROM tiles: per 8x8 tile, 8 bytes of
+    /// plane 0 holding the glyph, then 8 zero bytes of plane 1
+    #[arg(long)]
+    output_chr: Option<PathBuf>,
+
+    /// Tiles per --output-chr glyph: `1x1` for 8x8 glyphs, or `2x2` to cut
+    /// 16x16 glyphs into top left, top right, bottom left, bottom right
+    #[arg(long, value_enum, default_value_t = tiles::TileSplit::One, requires = "output_chr")]
+    chr_tiles: tiles::TileSplit,
+
+    /// Pad the --output-chr file with empty tiles to a multiple of 8 KiB
+    #[arg(long, requires = "output_chr")]
+    chr_pad: bool,
+
+    /// Also write a Commodore 64 character ROM: 2 KiB of 8x8 glyphs, 8
+    /// bytes each with the leftmost pixel in the top bit, in screen code
+    /// order with the reversed glyphs in the upper half. Needs a
+    /// --codepage petscii-* table
+    #[arg(long)]
+    output_c64: Option<PathBuf>,
+
+    /// Also write the glyphs as Game Boy tiles: per 8x8 tile, each row's
+    /// plane 0 byte (the glyph) followed by its zero plane 1 byte
+    #[arg(long)]
+    output_gb: Option<PathBuf>,
+
+    /// Tiles per --output-gb glyph, as for --chr-tiles
+    #[arg(long, value_enum, default_value_t = tiles::TileSplit::One, requires = "output_gb")]
+    gb_tiles: tiles::TileSplit,
+
+    /// Also write the glyphs as assembler source: `rgbds` the --output-gb
+    /// tiles, next to them (`.asm`); `nasm`, `masm`, or `gas` every glyph's
+    /// rows, MSB-first, as bytes for glyphs up to 8 wide and as words or
+    /// doublewords past that, under a label with size equates
+    #[arg(long, value_enum)]
+    emit_asm: Option<tiles::AsmSyntax>,
+
+    /// Where to write the --emit-asm source; defaults to the --name with
+    /// `.asm` (`.s` for gas) in the current directory, or next to the
+    /// --output-gb tiles for rgbds
+    #[arg(long, requires = "emit_asm")]
+    asm_output: Option<PathBuf>,
+
+    /// C/Rust identifier naming the symbols of every text output (--hex-dump,
+    /// --emit-asm, --emit-gfx, --emit-u8g2); defaults to the font's file
+    /// name
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Leave out the comment footer that text outputs (--hex-dump,
+    /// --rust-embedded, --emit-*) end with, naming the tool version, font
+    /// hash, options, and packed-data CRC32, so the files are byte-stable
+    /// across versions of the tool
+    #[arg(long)]
+    no_provenance: bool,
+
+    /// Face to use from a font collection (.ttc); 0 is the first
+    #[arg(long, default_value_t = 0, conflicts_with = "font_name")]
+    face_index: u32,
+
+    /// Set a variable font axis before the size search, as `<tag>=<value>`
+    /// (e.g. `wght=550`, `wdth=75`, `slnt=-10`, or any four-character
+    /// tag). Repeatable. Only --backend rust applies it: SDL_ttf has no way
+    /// to set axes, so the default SDL backend refuses the option rather
+    /// than render the default instance
+    #[arg(long, value_name = "TAG=VALUE")]
+    variation: Vec<String>,
+
+    /// Font to take glyphs from when the primary font lacks them; repeat to
+    /// build a chain that is tried in order
+    #[arg(long)]
+    fallback_font: Vec<PathBuf>,
+
+    /// Font that draws the --secondary-range indices instead of the primary,
+    /// sized to the primary's cell height and aligned to its own baseline
+    #[arg(long)]
+    secondary_font: Option<PathBuf>,
+
+    /// Index range taken from --secondary-font, as `start-end` in decimal or
+    /// 0x hex (e.g. 0xB0-0xDF). Repeatable
+    #[arg(long)]
+    secondary_range: Vec<String>,
+
+    /// Built-in character table the atlas is laid out in
+    #[arg(long, value_enum, default_value_t = Codepage::Cp437)]
+    codepage: Codepage,
+
+    /// Draw the blank control codes (0-31 and 127) of the table, as in
+    /// latin1 and koi8r, with the CP437 pictures
+    #[arg(long)]
+    control_pictures: bool,
+
+    /// File of exactly 256 characters (one per line or a single string,
+    /// `\uXXXX` escapes allowed) replacing the codepage table entirely
+    #[arg(long, conflicts_with = "codepage")]
+    charset_file: Option<PathBuf>,
+
+    /// Build the atlas from a Unicode span such as `U+2500-U+257F` instead
+    /// of a 256-entry codepage; repeat to concatenate spans in order
+    #[arg(long, conflicts_with_all = ["codepage", "charset_file"])]
+    unicode_range: Vec<String>,
+
+    /// Build the atlas from just the characters used in this text file, and
+    /// emit a character-to-index remap table with the output
+    #[arg(long, conflicts_with_all = ["codepage", "charset_file", "unicode_range"])]
+    chars_from: Option<PathBuf>,
+
+    /// Cell order for --chars-from
+    #[arg(long, value_enum, default_value_t = CharOrder::Appearance)]
+    chars_order: CharOrder,
+
+    /// Which character goes in which cell: `sequential` (index order) or
+    /// `tcod` (libtcod's TCOD layout); the sidecar records the order used
+    #[arg(long, value_enum, default_value_t = CellLayout::Sequential)]
+    layout: CellLayout,
+
+    /// Put characters in cells by a file of 256 lines, each the index (or
+    /// the character) that goes in that cell, for an engine's own tileset
+    /// order; repeated or left-out indices are warned about
+    #[arg(long, conflicts_with = "layout")]
+    remap: Option<PathBuf>,
+
+    /// Grid width in cells; rows are added as needed
+    #[arg(long, default_value_t = 16)]
+    columns: u32,
+
+    /// Fill the grid down each column before moving right
+    #[arg(long)]
+    column_major: bool,
+
+    /// Background border inside every cell, taken out of the cell width so
+    /// the glyph box shrinks by twice this much
+    #[arg(long, default_value_t = 0)]
+    padding: u32,
+
+    /// Extra background pixels after every cell, horizontally and vertically
+    #[arg(long, default_value_t = 0)]
+    gutter: u32,
+
+    /// Also write a copy of the atlas with cell grid lines, baselines, and
+    /// skipped or clipped cells highlighted
+    #[arg(long)]
+    debug_image: Option<PathBuf>,
+
+    /// Show the finished atlas in a window (z: zoom, g: grid, Esc/q: close).
+    /// The PNG is only saved as well when --output is given
+    #[arg(long)]
+    preview: bool,
+
+    /// Print the thresholded atlas to stderr as `braille` (2x4 pixels per
+    /// character, the default) or `half-block` (1x2), in bands as wide as
+    /// $COLUMNS (default 80)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "braille")]
+    preview_terminal: Option<terminal::TerminalStyle>,
+
+    /// Draw the finished atlas in the terminal at full resolution and in the
+    /// PNG's colors, with the kitty graphics protocol. A terminal that
+    /// $TERM or $TERM_PROGRAM doesn't name as supporting it gets the
+    /// --preview-terminal text preview instead
+    #[arg(long)]
+    preview_terminal_graphics: bool,
+
+    /// Draw --preview-terminal-graphics as Sixel, for terminals without
+    /// kitty graphics
+    #[arg(long, requires = "preview_terminal_graphics")]
+    sixel: bool,
+
+    /// Upscale the finished atlas by this integer factor (1-16) with
+    /// nearest-neighbor pixel replication
+    #[arg(long, default_value_t = 1)]
+    scale: u32,
+
+    /// Upscaling method: smooth diagonals with the Scale2x/Scale3x
+    /// pixel-art filters, which output pure black and white
+    #[arg(long, value_enum, default_value_t = ScaleAlgorithm::Nearest)]
+    scale_algorithm: ScaleAlgorithm,
+
+    /// Make each glyph this many times wider (1-16) by repeating the
+    /// columns of its thresholded bitmap, before --scale; the cell size
+    /// and every output report the stretched width
+    #[arg(long, default_value_t = 1)]
+    stretch_width: u32,
+
+    /// Make each glyph this many times taller (1-16) by repeating rows,
+    /// e.g. an 8x16 VGA-style font from an 8x8 design
+    #[arg(long, default_value_t = 1)]
+    stretch_height: u32,
+
+    /// Thicken each font glyph's thresholded strokes by this many pixels,
+    /// so hairlines survive small sizes; glyphs that grow out of the cell
+    /// follow --overflow
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    embolden: u32,
+
+    /// Thin each font glyph's thresholded strokes by this many pixels
+    #[arg(long, value_name = "PX", default_value_t = 0)]
+    thin: u32,
+
+    /// Which way --embolden and --thin move stroke edges: `both`, or `h`
+    /// (right edges only, like classic synthetic bold)
+    #[arg(long, value_enum, default_value_t = EmboldenDir::Both)]
+    embolden_dir: EmboldenDir,
+
+    /// Decode rendered coverage with this gamma before thresholding and
+    /// downsampling, as a display shows it: above 1.0 antialiased edges
+    /// darken and count as ink more readily, thickening strokes; below
+    /// 1.0 they lighten, thinning strokes
+    #[arg(long, default_value_t = 1.0)]
+    gamma: f32,
+
+    /// Move every glyph this many pixels down from the font's baseline,
+    /// or up when negative
+    #[arg(
+        long,
+        value_name = "PX",
+        default_value_t = 0,
+        allow_negative_numbers = true
+    )]
+    baseline_shift: i32,
+
+    /// Move the glyphs of an index range by whole pixels after placement,
+    /// as `<range>=<dx>,<dy>` (e.g. `0xB0-0xDF=0,1`); anything pushed out
+    /// of the cell is clipped. Repeatable, later ranges win
+    #[arg(long, value_name = "RANGE=DX,DY")]
+    nudge: Vec<String>,
+
+    /// Where glyphs sit across the cell: `center` (as --measure decides),
+    /// `left` (ink flush left), or `bearing` (at the font's left side
+    /// bearing, as text is set)
+    #[arg(long, value_enum, default_value_t = HAlign::Center)]
+    halign: HAlign,
+
+    /// --halign for an index range, as `<range>=<policy>` (e.g.
+    /// `0xB3-0xDA=bearing`). Repeatable, later ranges win
+    #[arg(long, value_name = "RANGE=POLICY")]
+    halign_range: Vec<String>,
+
+    /// Pad the atlas with background up to power-of-two dimensions
+    #[arg(long)]
+    pot: bool,
+
+    /// Also write a JSON sidecar describing the atlas geometry and which
+    /// character each cell holds
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// TOML or JSON file of per-index character overrides layered on the
+    /// codepage table, e.g. `{ "1": "♿", "255": "·" }`
+    #[arg(long)]
+    charmap: Option<PathBuf>,
+
+    /// Write nothing; report missing, zero-sized, and oversized glyphs and
+    /// fail if there are any. Blank whitespace and controls are expected
+    #[arg(long)]
+    check: bool,
+
+    /// After rendering, report how many columns and rows along each edge
+    /// of the glyph box no glyph ever draws in, and which box-drawing
+    /// glyphs keep an edge from being trimmed
+    #[arg(long)]
+    trim_report: bool,
+
+    /// Shrink the glyph box to the columns and rows some glyph draws in,
+    /// keeping the glyphs' places relative to each other; the smaller
+    /// cells go into every output. Box-drawing and block glyphs that reach
+    /// an edge keep it
+    #[arg(long, conflicts_with_all = ["sizes", "atlas_size", "with_bold", "sdf", "dry_run"])]
+    trim: bool,
+
+    /// Write nothing and render no glyphs; size the font and the cells and
+    /// print the resulting metrics and atlas size (as JSON with
+    /// --log-format json)
+    #[arg(long, conflicts_with_all = ["sizes", "with_bold", "input_atlas", "from_hex", "input_psf", "input_bdf", "input_raw"])]
+    dry_run: bool,
+
+    /// Missing glyphs that don't fail --check: indices/ranges such as `0-31,127`
+    #[arg(long)]
+    check_allow_missing: Option<String>,
+
+    /// Write the --check findings as JSON
+    #[arg(long)]
+    coverage_report: Option<PathBuf>,
+
+    /// Fail the run if any glyph was skipped, clipped, scaled to fit,
+    /// given a placeholder, or taken from a fallback font, or the size
+    /// search missed the requested width; all are listed at the end
+    #[arg(long)]
+    strict: bool,
+
+    /// Kinds of --strict violation to tolerate, such as `clipped,fallback`
+    #[arg(long, value_enum, value_delimiter = ',', requires = "strict")]
+    strict_allow: Vec<strict::Violation>,
+
+    /// What to draw in cells no font can render: `blank`, an inset `box`, a
+    /// `checker` dither, or `char:<c>` to render another character instead
+    #[arg(long, default_value = "blank")]
+    missing_glyph: String,
+
+    /// Directory of hand-drawn PNGs that replace individual glyphs, each
+    /// named after its index in hex or decimal (`0x01.png`, `001.png`)
+    #[arg(long)]
+    override_dir: Option<PathBuf>,
+
+    /// How override images are fitted to the glyph box: `center` them as
+    /// drawn, clipping any excess, or `stretch` them to fill it
+    #[arg(long, value_enum, default_value_t = OverrideFit::Center)]
+    override_fit: OverrideFit,
+
+    /// Compose this text from the finished atlas as a check of spacing and
+    /// baselines, for --sample-output or --emit-html; `\n` (backslash, n)
+    /// starts a new line
+    #[arg(long)]
+    sample_text: Option<String>,
+
+    /// Where to save the --sample-text composition as PNG
+    #[arg(long, requires = "sample_text")]
+    sample_output: Option<PathBuf>,
+
+    /// Also write a self-contained HTML page showing the font: the metrics,
+    /// a sample paragraph (--sample-text, or a pangram), and every cell with
+    /// its index and character, all drawn from the embedded atlas
+    #[arg(long)]
+    emit_html: Option<PathBuf>,
+
+    /// Also write a stylesheet using the --output PNG as a CSS sprite sheet:
+    /// a class per cell, named by prefix and hex index (.cp437-0x41), and
+    /// the cell size as custom properties on :root
+    #[arg(long)]
+    emit_css: Option<PathBuf>,
+
+    /// Class and custom property prefix for --emit-css
+    #[arg(long, default_value = "cp437", requires = "emit_css")]
+    css_prefix: String,
+
+    /// Print a cell to stderr as ASCII art with its metrics and offsets;
+    /// takes a decimal or 0x hex index, or a literal character. Repeatable
+    #[arg(long)]
+    show_glyph: Vec<String>,
+
+    #[command(flatten)]
+    #[serde(flatten)]
+    text_policy: text::TextPolicy,
+
+    /// Fail when more than this fraction of printable ASCII glyphs render blank
+    #[arg(long, default_value_t = 0.5)]
+    max_blank_fraction: f32,
+
+    /// Keep going even when the blank-render check fails
+    #[arg(long)]
+    allow_blank_atlas: bool,
+}
+
+/// Resolve a `--show-glyph` value: a decimal or `0x` hex index, or a single
+/// character looked up in the active table. A lone digit is an index.
+fn parse_glyph_spec(spec: &str, charmap: &CharMap) -> Result<usize> {
+    let mut chars = spec.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && !c.is_ascii_digit()
+    {
+        return charmap
+            .index_of(c)
+            .with_context(|| format!("'{}' is not in the character set ({})", c, charmap.label()));
+    }
+    parse_index(spec, charmap.len())
+}
+
+/// Print the glyph box of cell `index` to stderr, `#` for ink and `.` for
+/// background, thresholded the same way as the hex dump.
+fn print_glyph_art(atlas: &GrayImage, grid: &Grid, index: usize) {
+    let (left, top) = grid.glyph_origin(index);
+    for y in 0..grid.glyph_height {
+        let row: String = (0..grid.glyph_width)
+            .map(|x| {
+                if atlas.get(left + x, top + y) < 128 {
+                    '#'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        log!("{}", row);
+    }
+}
+
+/// Format a packed atlas as the hex dump: a `//!LONGVAR` block of the words
+/// plus the macros a shader needs to address cells. Scanlines are padded to
+/// a whole word so that font_bitmask can use (x % 32) directly without
+/// needing the Y coordinate. A paged atlas, `rows_per_page` grid rows to
+/// each of `pages`, gets a numbered block and macros per page.
+fn format_hex_dump(
+    pages: &[PackedBitmap],
+    rows_per_page: u32,
+    grid: &Grid,
+    charmap: &CharMap,
+    with_remap: bool,
+    variants: Option<&Variants>,
+    name: &str,
+) -> String {
+    // Shaders address cells by stride, so that is what the macros call the
+    // character size; the whole atlas is packed, padding and gutters included
+    let (char_width, char_height) = (grid.stride_x(), grid.stride_y());
+    let packed = &pages[0];
+    let padded_width = packed.padded_width;
+    let word_bits = packed.word_size.bits();
+    // Data names, numbered only when there is more than one page
+    let names: Vec<String> = match pages.len() {
+        1 => vec![name.to_string()],
+        count => (0..count)
+            .map(|page| format!("{}_{}", name, page))
+            .collect(),
+    };
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "// Pixel dimensions: {} wide x {} tall{}",
+        packed.width,
+        packed.height,
+        if pages.len() > 1 {
+            format!(" per page, {} pages of {} rows", pages.len(), rows_per_page)
+        } else {
+            String::new()
+        }
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "// Padded scanline width (map_w for shader): {}",
+        padded_width
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "// Character grid: {}x{}{}{}",
+        grid.columns,
+        grid.rows,
+        if grid.column_major {
+            " (column-major)"
+        } else {
+            ""
+        },
+        // The packed words cover every variant, so say how many cells
+        variants.map_or_else(String::new, |v| {
+            let (across, down) = v.combined_grid(grid.columns, grid.rows);
+            format!(" per variant, {}x{} in all", across, down)
+        })
+    )
+    .unwrap();
+    if let Some(variants) = variants {
+        writeln!(
+            out,
+            "// Variants: {}",
+            variants.describe(grid.columns, grid.rows)
+        )
+        .unwrap();
+    }
+    writeln!(out, "// Codepage: {}", charmap.label()).unwrap();
+    writeln!(
+        out,
+        "// Character cell: {}x{} pixels",
+        char_width, char_height
+    )
+    .unwrap();
+    if grid.padding > 0 || grid.gutter > 0 {
+        writeln!(
+            out,
+            "// Glyph box: {}x{} pixels at +{},+{} in each cell (gutter {})",
+            grid.glyph_width, grid.glyph_height, grid.padding, grid.padding, grid.gutter
+        )
+        .unwrap();
+    }
+    writeln!(out, "// Packing: per-row, {}-bit aligned", word_bits).unwrap();
+    writeln!(out).unwrap();
+
+    for (packed, data_name) in pages.iter().zip(&names) {
+        writeln!(out, "//!LONGVAR uint[] font_data_{}", data_name).unwrap();
+        // Print 8 values per line for readability
+        for (i, value) in packed.words.iter().enumerate() {
+            if i % 8 == 0 {
+                if i > 0 {
+                    writeln!(out).unwrap();
+                }
+                out.push_str("//!  ");
+            } else {
+                out.push(' ');
+            }
+            write!(
+                out,
+                "0x{:0digits$X} ",
+                value,
+                digits = word_bits as usize / 4
+            )
+            .unwrap();
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "//!ENDLONGVAR").unwrap();
+    }
+    writeln!(out, "#define font_{}_width ({})", name, char_width).unwrap();
+    writeln!(out, "#define font_{}_height ({})", name, char_height).unwrap();
+    writeln!(
+        out,
+        "#define font_{}_glyph_width ({})",
+        name, grid.glyph_width
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "#define font_{}_glyph_height ({})",
+        name, grid.glyph_height
+    )
+    .unwrap();
+    writeln!(out, "#define font_{}_padding ({})", name, grid.padding).unwrap();
+    if pages.len() > 1 {
+        // A cell's page is its row over rows_per_page
+        writeln!(out, "#define font_{}_pages ({})", name, pages.len()).unwrap();
+        writeln!(
+            out,
+            "#define font_{}_rows_per_page ({})",
+            name, rows_per_page
+        )
+        .unwrap();
+    }
+    if let Some(variants) = variants {
+        // Added to a glyph's position to reach it in the next variant
+        writeln!(
+            out,
+            "#define font_{}_variant_stride_x ({})",
+            name, variants.stride_x
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "#define font_{}_variant_stride_y ({})",
+            name, variants.stride_y
+        )
+        .unwrap();
+    }
+    if let Some(codepage) = charmap.codepage()
+        && let Some(number) = codepage.number()
+    {
+        writeln!(
+            out,
+            "#define font_{}_codepage ({}) // {}",
+            name,
+            number,
+            codepage.name()
+        )
+        .unwrap();
+    }
+    for name in &names {
+        writeln!(
+            out,
+            "#define font_{name}(uv,pos,txt,start,len) (fontstr(uv,pos,txt,start,len,{char_width},{char_height},{padded_width},{name}))"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "#define multiline_{name}(uv,pos,txt,starts,lens) multiline_font((uv), (pos), (txt), (starts), (lens), {char_width}, {char_height}, {padded_width}, {name})"
+        )
+        .unwrap();
+    }
+    if with_remap {
+        // Strings must be encoded with these indices rather than codepoints
+        writeln!(out, "// Remap (character -> atlas index):").unwrap();
+        for (i, &ch) in charmap.chars().iter().enumerate() {
+            writeln!(out, "//   U+{:04X} '{}' -> {}", ch as u32, ch, i).unwrap();
+        }
+    }
+    out
+}
+
+fn main() -> ExitCode {
+    match run_cli() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            ExitCode::from(Failure::of(&e).code())
+        }
+    }
+}
+
+/// `args` with the deprecated `--hex-dump NAME` joined into
+/// `--hex-dump=NAME`, which is all clap accepts so that a FONT positional
+/// after the flag isn't taken for a name. A following word counts as a
+/// name when it could be one and isn't a file.
+fn join_legacy_hex_dump(args: Vec<OsString>) -> Vec<OsString> {
+    let mut joined = Vec::with_capacity(args.len());
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            joined.push(arg);
+            joined.extend(args);
+            break;
+        }
+        let name = args
+            .peek()
+            .and_then(|next| next.to_str())
+            .filter(|next| {
+                next.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && !Path::new(next).is_file()
+            })
+            .map(str::to_owned);
+        match name {
+            Some(name) if arg == "--hex-dump" => {
+                args.next();
+                joined.push(format!("--hex-dump={}", name).into());
+            }
+            _ => joined.push(arg),
+        }
+    }
+    joined
+}
+
+/// Everything `main` does, leaving the exit status to it.
+fn run_cli() -> Result<()> {
+    let matches =
+        Cli::command().get_matches_from(join_legacy_hex_dump(std::env::args_os().collect()));
+    let matches = match Cli::from_arg_matches(&matches)
+        .unwrap_or_else(|e| e.exit())
+        .command
+    {
+        Some(Command::Completions { shell, dir }) => {
+            return docs::completions(Cli::command(), shell, dir.as_deref());
+        }
+        Some(Command::Manpage { dir }) => return docs::manpage(Cli::command(), dir.as_deref()),
+        Some(Command::Generate(_)) => matches
+            .subcommand_matches("generate")
+            .expect("generate was parsed")
+            .clone(),
+        None => matches,
+    };
+    // Read up front so a broken config or manifest fails before SDL starts
+    let (args, jobs) = load_options(&matches)?;
+    if args.print_config {
+        print!("{}", config::to_toml(&args)?);
+        return Ok(());
+    }
+    let verbose = if args.debug { 2 } else { args.verbose };
+    logging::init(logging::level(args.quiet, verbose), args.log_format);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs)
+        .build_global()
+        .context("Failed to start worker threads")?;
+    let renderer = Renderer::default();
+
+    let result = run(&args, jobs.as_deref(), &renderer);
+    if !args.watch {
+        let violations = strict::report();
+        // Written even when the run failed, to show what led up to it
+        if let Some(path) = &args.report {
+            save_report(path, args.force)?;
+        }
+        result?;
+        if violations > 0 {
+            return Err(anyhow!("Error: {} --strict violation(s)", violations))
+                .failure(Failure::Strict);
+        }
+        let warnings = logging::warnings();
+        if args.deny_warnings && warnings > 0 {
+            return Err(anyhow!(
+                "Error: {} warning(s) logged and --deny-warnings is set",
+                warnings
+            ))
+            .failure(Failure::Strict);
+        }
+        return Ok(());
+    }
+    if let Err(e) = result {
+        eprintln!("{:#}", e);
+    }
+    strict::report();
+    if let Some(path) = &args.report {
+        save_report(path, args.force)?;
+    }
+
+    // Options are re-read on every change, so edits to the config or
+    // manifest (including which files they point at) take effect too
+    let mut watcher = watch::Watcher::new(watched_paths(&args, jobs.as_deref()));
+    eprintln!(
+        "Watching {} file(s) for changes; press Ctrl-C to stop",
+        watcher.len()
+    );
+    loop {
+        watcher.wait();
+        eprintln!("[{}] Change detected, regenerating", watch::timestamp());
+        let result = load_options(&matches).and_then(|(mut args, mut jobs)| {
+            watcher = watch::Watcher::new(watched_paths(&args, jobs.as_deref()));
+            // Rebuilds replace the files the first run wrote
+            args.force = true;
+            for (_, job) in jobs.iter_mut().flatten() {
+                job.force = true;
+            }
+            run(&args, jobs.as_deref(), &renderer)
+        });
+        match result {
+            Ok(()) => eprintln!("[{}] Regenerated", watch::timestamp()),
+            Err(e) => eprintln!("[{}] {:#}", watch::timestamp(), e),
+        }
+        strict::report();
+        // Each rebuild replaces the last one's report
+        if let Some(path) = &args.report
+            && let Err(e) = save_report(path, true)
+        {
+            eprintln!("[{}] {:#}", watch::timestamp(), e);
+        }
+    }
+}
+
+/// Write the warnings collected so far to the --report file.
+fn save_report(path: &Path, force: bool) -> Result<()> {
+    let warnings = warning::take();
+    save_atomically(path, force, |tmp| warning::write_report(tmp, &warnings))?;
+    info!(
+        "Report of {} warning(s) saved to {}",
+        warnings.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Parse the command line and layer in the config file and manifest jobs.
+fn load_options(matches: &ArgMatches) -> Result<(Args, Option<Vec<Job>>)> {
+    let mut args = Args::from_arg_matches(matches).unwrap_or_else(|e| e.exit());
+    if let Some(preset) = args.preset {
+        let origin = PathBuf::from(format!("--preset {}", preset.name()));
+        args = config::overlay(&args, preset.options(), &origin, |id| {
+            matches.value_source(id) == Some(ValueSource::CommandLine)
+        })?;
+    }
+    if let Some(path) = &args.config {
+        args = config::apply(path, &args, matches)?;
+    }
+    let jobs = match &args.manifest {
+        Some(path) => Some(manifest::load(path, &args)?),
+        None => None,
+    };
+    Ok((args, jobs))
+}
+
+/// Generate the single atlas `args` describes, or every manifest job.
+fn run(args: &Args, jobs: Option<&[Job]>, renderer: &Renderer) -> Result<()> {
+    match jobs {
+        Some(jobs) => run_manifest(jobs, args.fail_fast, renderer),
+        None => generate(args, renderer),
+    }
+}
+
+/// Every input file whose modification should trigger a `--watch` rebuild.
+fn watched_paths(args: &Args, jobs: Option<&[Job]>) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = args.config.iter().chain(&args.manifest).cloned().collect();
+    let inputs = match jobs {
+        Some(jobs) => jobs.iter().map(|(_, job)| job).collect(),
+        None => vec![args],
+    };
+    for input in inputs {
+        paths.extend(input.font_path.clone().or_else(|| input.font.clone()));
+        paths.extend(input.fallback_font.iter().cloned());
+        paths.extend(input.secondary_font.clone());
+        paths.extend(input.input_atlas.clone());
+        paths.extend(input.input_sidecar.clone());
+        paths.extend(input.input_psf.clone());
+        paths.extend(input.input_bdf.clone());
+        paths.extend(input.input_raw.clone());
+        paths.extend(input.from_hex.clone());
+        // Edits to an override image change its mtime, not the directory's
+        if let Some(dir) = &input.override_dir {
+            paths.push(dir.clone());
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                paths.extend(entries.flatten().map(|e| e.path()));
+            }
+        }
+        paths.extend(
+            [&input.charset_file, &input.chars_from, &input.charmap]
+                .into_iter()
+                .flatten()
+                .cloned(),
+        );
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+/// Generate each named job in turn within the one SDL/TTF context. A failed
+/// job is logged and counted; the rest still run unless `fail_fast` is set.
+fn run_manifest(jobs: &[Job], fail_fast: bool, renderer: &Renderer) -> Result<()> {
+    let mut failed = 0;
+    // The run exits as the first failed job would have
+    let mut first_failure = None;
+    for (name, job) in jobs {
+        set_log_prefix(Some(name.clone()));
+        let result = generate(job, renderer);
+        if let Err(e) = &result {
+            log!("Failed: {:#}", e);
+            failed += 1;
+            first_failure.get_or_insert(Failure::of(e));
+        }
+        set_log_prefix(None);
+        if result.is_err() && fail_fast {
+            break;
+        }
+    }
+    if let Some(failure) = first_failure {
+        return Err(anyhow!("Error: {} of {} jobs failed", failed, jobs.len())).failure(failure);
+    }
+    eprintln!("All {} jobs succeeded", jobs.len());
+    Ok(())
+}
+
+/// The font, face, and rendering options `args` ask for.
+fn atlas_config(args: &Args, charmap: &CharMap) -> Result<AtlasConfig> {
+    let (font_path, face_index) = match (
+        &args.font_name,
+        args.font_path.clone().or_else(|| args.font.clone()),
+    ) {
+        (Some(name), _) => {
+            let (path, index) =
+                system_fonts::resolve(name, args.font_style.as_deref()).failure(Failure::Font)?;
+            info!(
+                "Font '{}' resolved to {} (face {})",
+                name,
+                path.display(),
+                index
+            );
+            (path, index)
+        }
+        (None, Some(path)) => (path, args.face_index),
+        (None, None) => bail!(
+            "Error: a font is required, either as the first argument, via --font-path, or via --font-name"
+        ),
+    };
+    // With --sizes the width is set again for each strip, and with
+    // --atlas-size narrowed from the widest the budget could hold
+    let budget_width = match &args.atlas_size {
+        Some(spec) => Some(autofit::parse_budget(spec)?.0 / args.columns.max(1)),
+        None => None,
+    };
+    let Some(cell_width) = args
+        .font_width
+        .or(args.width)
+        .or(args.sizes.first().copied())
+        .or(budget_width)
+    else {
+        bail!("Error: a cell width is required, either as the second argument or via --font-width");
+    };
+
+    let mut config = AtlasConfig::new(font_path, cell_width, charmap.clone());
+    config.measure = args.measure;
+    config.measure_set = args.measure_set.clone();
+    config.ink_crop = args.ink_crop;
+    config.render_mode = args.render_mode;
+    config.lcd_order = args.lcd_order;
+    config.sdf_spread = args.sdf;
+    config.sdf_supersample = args.sdf_supersample;
+    // --check reports oversized glyphs as coverage rather than failing on
+    // them, and writes nothing, so how they are drawn doesn't matter
+    config.overflow = if args.check {
+        Overflow::Clip
+    } else {
+        args.overflow
+    };
+    config.synthesize_boxes = args.synthesize_boxes;
+    config.fix_blocks = args.fix_blocks;
+    config.face_index = face_index;
+    config.fallback_fonts = args.fallback_font.clone();
+    config.secondary_font = args.secondary_font.clone();
+    config.secondary_set = args.secondary_range.join(",");
+    config.missing_glyph = MissingGlyph::parse(&args.missing_glyph)?;
+    config.override_dir = args.override_dir.clone();
+    config.override_fit = args.override_fit;
+    config.columns = args.columns;
+    config.column_major = args.column_major;
+    config.padding = args.padding;
+    config.gutter = args.gutter;
+    config.scale = args.scale;
+    config.scale_algorithm = args.scale_algorithm;
+    config.stretch_width = args.stretch_width;
+    config.stretch_height = args.stretch_height;
+    config.embolden = args.embolden;
+    config.thin = args.thin;
+    config.embolden_dir = args.embolden_dir;
+    config.gamma = args.gamma;
+    config.baseline_shift = args.baseline_shift;
+    config.cell_height = args.cell_height;
+    config.valign = args.valign;
+    config.baseline_row = args.baseline;
+    config.nudges = args.nudge.clone();
+    config.variations = args.variation.clone();
+    config.halign = args.halign;
+    config.halign_ranges = args.halign_range.clone();
+    config.pot = args.pot;
+    config.max_blank_fraction = args.max_blank_fraction;
+    config.allow_blank_atlas = args.allow_blank_atlas;
+    config.font_cache_size = args.font_cache_size;
+    config.max_atlas_dimension = args.max_atlas_dimension;
+    // Too tall an atlas is split into pages, except for --sizes, which
+    // stacks strips of its own
+    config.paged = args.sizes.is_empty();
+    Ok(config)
+}
+
+/// Lay out an already-bitmap font's glyphs in the grid the --columns,
+/// --padding, and --gutter options describe, one cell per character of
+/// `charmap`. A font without a Unicode table draws `unordered`, the table
+/// before --layout moved its characters.
+fn bitmap_font_atlas(
+    mut font: BitmapFont,
+    args: &Args,
+    charmap: &CharMap,
+    unordered: &CharMap,
+) -> Result<Atlas> {
+    if font.unicode.is_empty() && args.layout != CellLayout::Sequential {
+        font.unicode = (0..font.glyphs.len().min(unordered.len()))
+            .map(|i| vec![unordered.get(i)])
+            .collect();
+    }
+    if args.columns == 0 {
+        bail!("Error: --columns must be at least 1");
+    }
+    let grid = Grid {
+        padding: args.padding,
+        gutter: args.gutter,
+        ..Grid::new(
+            charmap.len(),
+            args.columns,
+            args.column_major,
+            font.width,
+            font.height,
+        )
+    };
+    let missing = MissingGlyph::parse(&args.missing_glyph)?;
+    let atlas = font.to_atlas(charmap, grid, missing)?;
+    info!(
+        "Atlas: {}x{}, grid {}x{}",
+        atlas.image.width, atlas.image.height, grid.columns, grid.rows
+    );
+    Ok(atlas)
+}
+
+/// Load `path` as an atlas laid out as the sidecar or cell options in
+/// `args` describe, with a cell per character of `charmap`.
+fn read_input_atlas(path: &Path, args: &Args, charmap: &CharMap) -> Result<Atlas> {
+    let (grid, baseline) = match &args.input_sidecar {
+        Some(sidecar) => {
+            let layout = sidecar::read_layout(sidecar)?;
+            (layout.grid(), layout.baseline)
+        }
+        None => {
+            let (Some(cell_width), Some(cell_height)) =
+                (args.font_width.or(args.width), args.cell_height)
+            else {
+                bail!(
+                    "Error: --input-atlas needs --input-sidecar, or --font-width and --cell-height"
+                );
+            };
+            if args.columns == 0 {
+                bail!("Error: --columns must be at least 1");
+            }
+            let inner = |cell: u32| cell.checked_sub(2 * args.padding).filter(|&v| v > 0);
+            let (Some(glyph_width), Some(glyph_height)) = (inner(cell_width), inner(cell_height))
+            else {
+                bail!(
+                    "Error: --padding {} leaves no room for glyphs in a {}x{} cell",
+                    args.padding,
+                    cell_width,
+                    cell_height
+                );
+            };
+            let grid = Grid {
+                padding: args.padding,
+                gutter: args.gutter,
+                ..Grid::new(
+                    charmap.len(),
+                    args.columns,
+                    args.column_major,
+                    glyph_width,
+                    glyph_height,
+                )
+            };
+            (grid, None)
+        }
+    };
+    let image =
+        image_io::load(path).with_context(|| format!("Failed to load atlas {}", path.display()))?;
+    let atlas = Atlas::from_image(image, grid, charmap, baseline)
+        .with_context(|| format!("{} doesn't match the expected layout", path.display()))?;
+    info!(
+        "Atlas: {}x{} read from {}, grid {}x{}",
+        atlas.image.width,
+        atlas.image.height,
+        path.display(),
+        grid.columns,
+        grid.rows
+    );
+    Ok(atlas)
+}
+
+/// Colors of the atlas PNG: the ink, and the background or `None` for a
+/// transparent one.
+type PngColors = ([u8; 3], Option<[u8; 3]>);
+
+/// The --foreground and --background colors.
+fn png_colors(args: &Args) -> Result<PngColors> {
+    Ok((
+        gray::parse_color(&args.foreground).context("Invalid --foreground")?,
+        match args.background.as_str() {
+            "transparent" => None,
+            spec => Some(gray::parse_color(spec).context("Invalid --background")?),
+        },
+    ))
+}
+
+/// Refuse the options only SDL can serve, in a build without it, before
+/// anything is read or rendered.
+#[cfg(not(feature = "backend-sdl"))]
+fn refuse_sdl_options(args: &Args) -> Result<()> {
+    let needs_sdl = [
+        (args.backend == BackendKind::Sdl, "--backend sdl"),
+        (args.with_bold, "--with-bold"),
+        (args.preview, "--preview"),
+        (
+            args.preview_terminal_graphics,
+            "--preview-terminal-graphics",
+        ),
+        (args.input_atlas.is_some(), "--input-atlas"),
+        (args.override_dir.is_some(), "--override-dir"),
+        (args.diff.is_some(), "--diff"),
+        (args.diff_image.is_some(), "--diff-image"),
+        (args.debug_image.is_some(), "--debug-image"),
+        (args.output_lovefont.is_some(), "--output-lovefont"),
+        (args.output_godot.is_some(), "--output-godot"),
+        (
+            png_colors(args)? != ([0; 3], Some([255; 3])),
+            "--foreground and --background",
+        ),
+    ];
+    if let Some((_, option)) = needs_sdl.iter().find(|(used, _)| *used) {
+        bail!(
+            "Error: {} needs SDL, which this build leaves out; rebuild with --features backend-sdl",
+            option
+        );
+    }
+    Ok(())
+}
+
+/// Refuse options that can't be used together, before anything is read or
+/// rendered. Config files bypass clap's conflicts, so they are checked here
+/// too, along with the combinations clap can't express.
+fn validate_config_conflicts(args: &Args) -> Result<()> {
+    let font_path_given = args.font_path.is_some() || args.font.is_some();
+    if args.font_name.is_some() && font_path_given {
+        bail!("Error: --font-name can't be combined with a font path");
+    }
+    if args.font_style.is_some() && args.font_name.is_none() {
+        bail!("Error: --font-style only applies to --font-name");
+    }
+    if args.remap.is_some() && args.layout != CellLayout::Sequential {
+        bail!("Error: --remap and --layout both place the cells; use one");
+    }
+    if args.emit_asm == Some(tiles::AsmSyntax::Rgbds) && args.output_gb.is_none() {
+        bail!("Error: --emit-asm rgbds writes the --output-gb tiles, so needs --output-gb");
+    }
+    if args.emit_css.is_some() && args.output.is_none() {
+        bail!("Error: --emit-css needs --output, the PNG its stylesheet points at");
+    }
+    if args.diff_image.is_some() && args.diff.is_none() && args.diff_hex.is_none() {
+        bail!("Error: --diff-image needs a reference from --diff or --diff-hex");
+    }
+    let bitmap_input = args.input_atlas.is_some()
+        || args.input_psf.is_some()
+        || args.input_bdf.is_some()
+        || args.input_raw.is_some()
+        || args.from_hex.is_some();
+    let png_colors = png_colors(args)?;
+
+    if args.sdf.is_some() {
+        if args.hex_dump.is_some() {
+            bail!(
+                "Error: --hex-dump keeps one bit per pixel, which throws a distance field away; drop --hex-dump or --sdf"
+            );
+        }
+        if bitmap_input {
+            bail!("Error: --sdf renders from a TrueType font, not an --input-* bitmap");
+        }
+        if png_colors != ([0; 3], Some([255; 3])) {
+            bail!(
+                "Error: --sdf PNGs are single-channel, so --foreground and --background don't apply"
+            );
+        }
+    }
+    if args.trim || args.trim_report {
+        let unsupported = [
+            (!args.sizes.is_empty(), "--sizes"),
+            (args.dry_run, "--dry-run"),
+            (args.trim && args.atlas_size.is_some(), "--atlas-size"),
+            (args.trim && args.with_bold, "--with-bold"),
+            (args.trim && args.sdf.is_some(), "--sdf"),
+            (
+                args.trim && args.render_mode == RenderMode::Lcd,
+                "--render-mode lcd",
+            ),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!(
+                "Error: --{} can't be combined with {}",
+                if args.trim { "trim" } else { "trim-report" },
+                option
+            );
+        }
+    }
+    if args.render_mode == RenderMode::Lcd {
+        let unsupported = [
+            (args.hex_dump.is_some(), "--hex-dump"),
+            (!args.sizes.is_empty(), "--sizes"),
+            (args.with_bold, "--with-bold"),
+            (args.with_inverse, "--with-inverse"),
+            (args.input_atlas.is_some(), "--input-atlas"),
+            (args.from_hex.is_some(), "--from-hex"),
+            (args.input_psf.is_some(), "--input-psf"),
+            (args.input_bdf.is_some(), "--input-bdf"),
+            (args.input_raw.is_some(), "--input-raw"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!(
+                "Error: --render-mode lcd makes an RGB atlas with no one-bit or stacked form, so it can't be combined with {}",
+                option
+            );
+        }
+        if png_colors.1.is_none() {
+            bail!(
+                "Error: --render-mode lcd blends each subpixel against the background, which can't be transparent"
+            );
+        }
+    }
+    if args.dry_run {
+        let unsupported = [
+            (!args.sizes.is_empty(), "--sizes"),
+            (args.with_bold, "--with-bold"),
+            (args.input_atlas.is_some(), "--input-atlas"),
+            (args.from_hex.is_some(), "--from-hex"),
+            (args.input_psf.is_some(), "--input-psf"),
+            (args.input_bdf.is_some(), "--input-bdf"),
+            (args.input_raw.is_some(), "--input-raw"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!("Error: --dry-run only sizes a TTF font, not {}", option);
+        }
+    }
+    if args.atlas_size.is_some() {
+        let unsupported = [
+            (args.pot, "--pot"),
+            (args.with_bold, "--with-bold"),
+            (args.with_inverse, "--with-inverse"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!("Error: --atlas-size can't be combined with {}", option);
+        }
+    }
+    Ok(())
+}
+
+/// The character table of a run, in cell order.
+struct CharTable {
+    charmap: CharMap,
+    /// The table before --remap or --layout moved its cells.
+    unordered: CharMap,
+    /// The index of `unordered` in each cell, when the cells were moved.
+    layout_order: Option<Vec<usize>>,
+    layout_name: &'static str,
+}
+
+/// The character table `args` select, patched and reordered as asked.
+fn char_table(args: &Args) -> Result<CharTable> {
+    let mut charmap = if !args.unicode_range.is_empty() {
+        let ranges = args
+            .unicode_range
+            .iter()
+            .map(|spec| charmap::parse_unicode_range(spec))
+            .collect::<Result<Vec<_>>>()
+            .context("Invalid --unicode-range")?;
+        CharMap::from_unicode_ranges(&ranges)?
+    } else if let Some(path) = &args.chars_from {
+        CharMap::from_text_file(path, args.chars_order)?
+    } else if let Some(path) = &args.charset_file {
+        CharMap::from_charset_file(path)?
+    } else {
+        CharMap::new(args.codepage)
+    };
+    if args.control_pictures {
+        charmap = charmap.with_control_pictures();
+    }
+    if let Some(path) = &args.charmap {
+        charmap = charmap.with_overrides(path)?;
+    }
+    // Everything downstream indexes cells, so the table itself is reordered
+    let unordered = charmap.clone();
+    let (layout_order, layout_name) = match &args.remap {
+        Some(path) => (Some(charmap::read_remap(path, &charmap)?), "remap"),
+        None => (args.layout.order(&charmap)?, args.layout.name()),
+    };
+    if let Some(order) = &layout_order {
+        charmap = charmap.reordered(order, layout_name);
+    }
+    if args.charset_file.is_some() {
+        // Legitimate in sets like PETSCII that repeat glyphs, but often a typo
+        for (ch, indices) in charmap.duplicates() {
+            let list = indices
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            warning::emit(
+                Warning::new(
+                    Code::DuplicateChar,
+                    format!("'{}' appears at indices {}", ch, list),
+                )
+                .data(serde_json::json!({ "char": ch, "indices": indices })),
+            );
+        }
+    }
+    Ok(CharTable {
+        charmap,
+        unordered,
+        layout_order,
+        layout_name,
+    })
+}
+
+/// The atlas a run starts from, before trimming and variants.
+struct Source {
+    /// The font or bitmap file it was made from.
+    path: PathBuf,
+    atlas: Atlas,
+    /// The --with-bold atlas.
+    bold: Option<Atlas>,
+    /// Set when a backend other than SDL drew the atlas, to save it as well.
+    pure_backend: Option<Box<dyn Backend>>,
+}
+
+/// Read the --input-* bitmap `args` name, or render the font.
+fn read_source(args: &Args, renderer: &Renderer, table: &CharTable) -> Result<Source> {
+    let charmap = &table.charmap;
+    let bitmap = |path: &PathBuf, atlas| Source {
+        path: path.clone(),
+        atlas,
+        bold: None,
+        pure_backend: None,
+    };
+    if let Some(path) = &args.input_atlas {
+        return Ok(bitmap(path, read_input_atlas(path, args, charmap)?));
+    }
+    if let Some(path) = &args.from_hex {
+        let dump = hex_dump::read_hex_dump(path)?;
+        info!(
+            "Hex dump {}: {}x{} pixels, {}x{} cells of {}x{}",
+            path.display(),
+            dump.bitmap.width,
+            dump.bitmap.height,
+            dump.grid.columns,
+            dump.grid.rows,
+            dump.grid.stride_x(),
+            dump.grid.stride_y()
+        );
+        let atlas = Atlas::from_image(dump.to_image(), dump.grid, charmap, None)
+            .with_context(|| format!("{} doesn't fit the character table", path.display()))?;
+        return Ok(bitmap(path, atlas));
+    }
+    if let Some(path) = &args.input_psf {
+        let font = psf::read_psf(path)?;
+        info!(
+            "PSF font {}: {} glyphs of {}x{}{}",
+            path.display(),
+            font.glyphs.len(),
+            font.width,
+            font.height,
+            if font.unicode.is_empty() {
+                ""
+            } else {
+                ", placed by its Unicode table"
+            }
+        );
+        let atlas = bitmap_font_atlas(font, args, charmap, &table.unordered)?;
+        return Ok(bitmap(path, atlas));
+    }
+    if let Some(path) = &args.input_bdf {
+        let font = bdf::read_bdf(path)?;
+        info!(
+            "BDF font {}: {} glyphs in a {}x{} bounding box",
+            path.display(),
+            font.glyphs.len(),
+            font.width,
+            font.height
+        );
+        let atlas = bitmap_font_atlas(font, args, charmap, &table.unordered)?;
+        return Ok(bitmap(path, atlas));
+    }
+    if let Some(path) = &args.input_raw {
+        let font = vga_rom::read_rom(path, args.raw_height)?;
+        info!(
+            "Raw ROM font {}: {} glyphs of {}x{}",
+            path.display(),
+            font.glyphs.len(),
+            font.width,
+            font.height
+        );
+        let atlas = bitmap_font_atlas(font, args, charmap, &table.unordered)?;
+        return Ok(bitmap(path, atlas));
+    }
+
+    let mut config = atlas_config(args, charmap)?;
+    let budget = fit_atlas_size(args, renderer, &mut config)?;
+    let (mut atlas, bold, pure_backend) = if args.with_bold {
+        if args.backend != BackendKind::Sdl {
+            bail!("Error: --with-bold needs the SDL backend");
+        }
+        let (regular, bolded) =
+            backend::generate_with_bold(&config, |config| renderer.generate(config))?;
+        (regular, Some(bolded), None)
+    } else {
+        let (atlas, pure_backend) = render_font(args, renderer, &config)?;
+        (atlas, None, pure_backend)
+    };
+    if let Some((width, height)) = budget {
+        log!(
+            "Auto-fit: {}px cells ({}x{} with padding and gutter) fill {}x{} of the {}x{} budget; {}x{} pixels unused, left as a margin right of and below the grid",
+            config.cell_width,
+            atlas.grid.stride_x(),
+            atlas.grid.stride_y(),
+            atlas.content_width,
+            atlas.content_height,
+            width,
+            height,
+            width - atlas.content_width,
+            height - atlas.content_height
+        );
+        autofit::pad(&mut atlas, (width, height));
+    }
+    Ok(Source {
+        path: config.font_path,
+        atlas,
+        bold,
+        pure_backend,
+    })
+}
+
+/// Print the metrics and a text rendering of each --show-glyph cell.
+fn show_glyphs(atlas: &Atlas, indices: &[usize]) {
+    for &i in indices {
+        let glyph = &atlas.glyphs[i];
+        log!("Glyph '{}' (index {}):", glyph.ch, i);
+        match &glyph.placement {
+            Some(p) => log!(
+                "  minx={} maxx={} miny={} maxy={} advance={}, x_offset={} y_offset={}",
+                p.minx,
+                p.maxx,
+                p.miny,
+                p.maxy,
+                p.advance,
+                p.x_offset,
+                p.y_offset
+            ),
+            None if glyph.synthesized => log!("  synthesized"),
+            None => log!("  nothing blitted from the font"),
+        }
+        print_glyph_art(&atlas.image, &atlas.grid, i);
+    }
+}
+
+/// Write the --coverage-report, and with --check fail unless every cell
+/// not excused by `allow_missing` has its glyph.
+fn check_coverage(
+    args: &Args,
+    atlas: &Atlas,
+    charmap: &CharMap,
+    source_path: &Path,
+    allow_missing: &[bool],
+) -> Result<()> {
+    let coverage = &atlas.coverage;
+    let excused = coverage
+        .missing
+        .iter()
+        .filter(|e| allow_missing[e.index])
+        .count();
+    let failures =
+        coverage.missing.len() - excused + coverage.zero_sized.len() + coverage.oversized.len();
+    if let Some(path) = &args.coverage_report {
+        save_atomically(path, args.force, |tmp| {
+            coverage::write_coverage_report(
+                tmp,
+                &coverage::CoverageReport {
+                    font: source_path,
+                    charset: charmap.label(),
+                    total: charmap.len(),
+                    coverage,
+                    allowed_missing: (0..charmap.len()).filter(|&i| allow_missing[i]).collect(),
+                    passed: failures == 0,
+                },
+            )
+        })?;
+        info!("Coverage report saved to {}", path.display());
+    }
+    if !args.check {
+        return Ok(());
+    }
+    for line in coverage.summary() {
+        log!("Coverage: {}", line);
+    }
+    if failures > 0 {
+        return Err(anyhow!(
+            "Error: coverage check failed for {} of {} characters",
+            failures,
+            charmap.len()
+        ))
+        .failure(Failure::Render);
+    }
+    log!(
+        "Coverage check passed: {} characters ({} missing allowed)",
+        charmap.len(),
+        excused
+    );
+    Ok(())
+}
+
+/// Build the atlas described by `args` and write every requested output.
+fn generate(args: &Args, renderer: &Renderer) -> Result<()> {
+    let started = Instant::now();
+    validate_config_conflicts(args)?;
+    #[cfg(not(feature = "backend-sdl"))]
+    refuse_sdl_options(args)?;
+    for spec in &args.variation {
+        mycp437generator::parse_variation(spec)?;
+    }
+
+    let table = char_table(args)?;
+    let charmap = &table.charmap;
+    if args.emit_css.is_some() {
+        css_sprites::check_prefix(&args.css_prefix)?;
+    }
+    // Encoded up front so a character outside the set fails before rendering
+    let sample_lines = match &args.sample_text {
+        Some(sample) => {
+            let sample = sample.replace("\\n", "\n");
+            let bytes = text::encode_cp437(&sample, charmap).context("Invalid --sample-text")?;
+            Some(text::layout(&bytes, &args.text_policy))
+        }
+        None => None,
+    };
 
-    /// Width of each character cell in pixels
-    #[arg(long)]
-    font_width: u32,
+    let allow_missing = match &args.check_allow_missing {
+        Some(spec) => {
+            parse_index_set(spec, charmap.len()).context("Invalid --check-allow-missing")?
+        }
+        None => vec![false; charmap.len()],
+    };
 
-    /// Output PNG file path
-    /// Ignored if --hex-dump is provided
-    #[arg(long)]
-    output: Option<PathBuf>,
+    #[cfg(feature = "backend-sdl")]
+    let lovefont_indices: Vec<usize> = match &args.output_lovefont {
+        Some(_) => parse_index_set(&args.lovefont_set, charmap.len())
+            .context("Invalid --lovefont-set")?
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &selected)| selected.then_some(i))
+            .collect(),
+        None => Vec::new(),
+    };
 
-    /// Dump hex bitmap to console instead of saving image
-    #[arg(long)]
-    hex_dump: Option<String>,
+    // A bad name fails before anything is rendered
+    let given_name = given_name(args)?;
+    let png_colors = png_colors(args)?;
 
-    /// Enable debug output
-    #[arg(long)]
-    debug: bool,
-}
+    let show_glyph = args
+        .show_glyph
+        .iter()
+        .map(|spec| parse_glyph_spec(spec, charmap))
+        .collect::<Result<Vec<_>>>()
+        .context("Invalid --show-glyph")?;
 
-// CP437 character mapping
-fn get_cp437_char(index: u8) -> char {
-    match index {
-        0 => ' ',                  // Null - render as space
-        1 => '☺',                  // White smiling face
-        2 => '☻',                  // Black smiling face
-        3 => '♥',                  // Heart
-        4 => '♦',                  // Diamond
-        5 => '♣',                  // Club
-        6 => '♠',                  // Spade
-        7 => '•',                  // Bullet
-        8 => '◘',                  // Inverse bullet
-        9 => '○',                  // White circle
-        10 => '◙',                 // Inverse white circle
-        11 => '♂',                 // Male sign
-        12 => '♀',                 // Female sign
-        13 => '♪',                 // Eighth note
-        14 => '♫',                 // Beamed eighth notes
-        15 => '☼',                 // White sun with rays
-        16 => '►',                 // Black right-pointing pointer
-        17 => '◄',                 // Black left-pointing pointer
-        18 => '↕',                 // Up down arrow
-        19 => '‼',                 // Double exclamation mark
-        20 => '¶',                 // Pilcrow sign
-        21 => '§',                 // Section sign
-        22 => '▬',                 // Black rectangle
-        23 => '↨',                 // Up down arrow with base
-        24 => '↑',                 // Upwards arrow
-        25 => '↓',                 // Downwards arrow
-        26 => '→',                 // Rightwards arrow
-        27 => '←',                 // Leftwards arrow
-        28 => '∟',                 // Right angle
-        29 => '↔',                 // Left right arrow
-        30 => '▲',                 // Black up-pointing triangle
-        31 => '▼',                 // Black down-pointing triangle
-        32..=126 => index as char, // Standard ASCII
-        127 => '⌂',                // House
-        128 => 'Ç',
-        129 => 'ü',
-        130 => 'é',
-        131 => 'â',
-        132 => 'ä',
-        133 => 'à',
-        134 => 'å',
-        135 => 'ç',
-        136 => 'ê',
-        137 => 'ë',
-        138 => 'è',
-        139 => 'ï',
-        140 => 'î',
-        141 => 'ì',
-        142 => 'Ä',
-        143 => 'Å',
-        144 => 'É',
-        145 => 'æ',
-        146 => 'Æ',
-        147 => 'ô',
-        148 => 'ö',
-        149 => 'ò',
-        150 => 'û',
-        151 => 'ù',
-        152 => 'ÿ',
-        153 => 'Ö',
-        154 => 'Ü',
-        155 => '¢',
-        156 => '£',
-        157 => '¥',
-        158 => '₧',
-        159 => 'ƒ',
-        160 => 'á',
-        161 => 'í',
-        162 => 'ó',
-        163 => 'ú',
-        164 => 'ñ',
-        165 => 'Ñ',
-        166 => 'ª',
-        167 => 'º',
-        168 => '¿',
-        169 => '⌐',
-        170 => '¬',
-        171 => '½',
-        172 => '¼',
-        173 => '¡',
-        174 => '«',
-        175 => '»',
-        176 => '░',
-        177 => '▒',
-        178 => '▓',
-        179 => '│',
-        180 => '┤',
-        181 => '╡',
-        182 => '╢',
-        183 => '╖',
-        184 => '╕',
-        185 => '╣',
-        186 => '║',
-        187 => '╗',
-        188 => '╝',
-        189 => '╜',
-        190 => '╛',
-        191 => '┐',
-        192 => '└',
-        193 => '┴',
-        194 => '┬',
-        195 => '├',
-        196 => '─',
-        197 => '┼',
-        198 => '╞',
-        199 => '╟',
-        200 => '╚',
-        201 => '╔',
-        202 => '╩',
-        203 => '╦',
-        204 => '╠',
-        205 => '═',
-        206 => '╬',
-        207 => '╧',
-        208 => '╨',
-        209 => '╤',
-        210 => '╥',
-        211 => '╙',
-        212 => '╘',
-        213 => '╒',
-        214 => '╓',
-        215 => '╫',
-        216 => '╪',
-        217 => '┘',
-        218 => '┌',
-        219 => '█',
-        220 => '▄',
-        221 => '▌',
-        222 => '▐',
-        223 => '▀',
-        224 => 'α',
-        225 => 'ß',
-        226 => 'Γ',
-        227 => 'π',
-        228 => 'Σ',
-        229 => 'σ',
-        230 => 'µ',
-        231 => 'τ',
-        232 => 'Φ',
-        233 => 'Θ',
-        234 => 'Ω',
-        235 => 'δ',
-        236 => '∞',
-        237 => 'φ',
-        238 => 'ε',
-        239 => '∩',
-        240 => '≡',
-        241 => '±',
-        242 => '≥',
-        243 => '≤',
-        244 => '⌠',
-        245 => '⌡',
-        246 => '÷',
-        247 => '≈',
-        248 => '°',
-        249 => '∙',
-        250 => '·',
-        251 => '√',
-        252 => 'ⁿ',
-        253 => '²',
-        254 => '■',
-        255 => ' ', // Non-breaking space
-    }
-}
-/// Convert a surface to hex dump format
-/// Each scanline is padded to a 32-bit boundary so that font_bitmask
-/// can use (x % 32) directly without needing the Y coordinate.
-fn dump_surface_as_hex<T: AsRef<str>>(
-    surface: &Surface,
-    char_width: u32,
-    char_height: u32,
-    name: T,
-) -> Result<()> {
-    let width = surface.width();
-    let height = surface.height();
-    // Padded width: round up to the next multiple of 32
-    let padded_width = ((width + 31) / 32) * 32;
+    if args.dry_run {
+        let mut config = atlas_config(args, charmap)?;
+        let budget = fit_atlas_size(args, renderer, &mut config)?;
+        config.layout_only = true;
+        let (mut atlas, _) = render_font(args, renderer, &config)?;
+        if let Some(budget) = budget {
+            autofit::pad(&mut atlas, budget);
+        }
+        return print_dry_run(args, &config.font_path, &atlas);
+    }
+    if !args.sizes.is_empty() {
+        return generate_stack(args, renderer, charmap, given_name, png_colors);
+    }
 
-    println!("// Pixel dimensions: {} wide x {} tall", width, height);
-    println!(
-        "// Padded scanline width (map_w for shader): {}",
-        padded_width
+    let Source {
+        path: source_path,
+        atlas: mut built,
+        bold,
+        pure_backend,
+    } = read_source(args, renderer, &table)?;
+    if args.trim || args.trim_report {
+        let trimmed = trim::measure(&built);
+        if args.trim_report {
+            trim::report(&trimmed, &built);
+        }
+        if args.trim
+            && let Some(margins) = trimmed.margins
+        {
+            trim::apply(&mut built, margins, args.pot);
+            info!(
+                "Trimmed glyph box: {}x{}",
+                built.grid.glyph_width, built.grid.glyph_height
+            );
+        }
+    }
+    let mut extras = Vec::new();
+    if let Some(bold) = bold {
+        extras.push(("bold", bold.image));
+    }
+    if args.with_inverse {
+        extras.push(("inverse", variants::inverse(&built)));
+    }
+    let variants = (!extras.is_empty())
+        .then(|| variants::append(&mut built, extras, args.variant_layout, args.pot));
+
+    show_glyphs(&built, &show_glyph);
+    warning::record_cells(&built);
+    if args.strict {
+        strict::check(&built, &args.strict_allow);
+    }
+    check_coverage(args, &built, charmap, &source_path, &allow_missing)?;
+    if args.check {
+        return Ok(());
+    }
+
+    let pages = pages::split(&built, args.max_atlas_dimension, args.pot)?;
+    if let Some(pages) = &pages {
+        let unsupported = [
+            (variants.is_some(), "--with-bold or --with-inverse"),
+            (args.output_gray.is_some(), "--output-gray"),
+            (args.rust_embedded.is_some(), "--rust-embedded"),
+        ];
+        if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+            bail!(
+                "Error: the atlas needs {} pages to stay within --max-atlas-dimension {}, which {} doesn't support",
+                pages.images.len(),
+                args.max_atlas_dimension,
+                option
+            );
+        }
+        info!(
+            "Atlas split into {} pages of {} rows, {}x{} each",
+            pages.images.len(),
+            pages.rows_per_page,
+            pages.images[0].width,
+            pages.images[0].height
+        );
+    }
+
+    let packed = packing::pack_bits(
+        &built.image,
+        INK_THRESHOLD,
+        WordSize::Bits32,
+        BitOrder::LsbFirst,
     );
-    println!("// Character grid: 16x16");
-    println!("// Character cell: {}x{} pixels", char_width, char_height);
-    println!("// Packing: per-row, 32-bit aligned");
-    println!();
-
-    // Lock the surface to access pixel data
-    surface.with_lock(|pixels: &[u8]| {
-        let pitch = surface.pitch() as usize;
-        let bytes_per_pixel = surface.pixel_format().bytes_per_pixel() as usize;
-
-        let mut all_values: Vec<u32> = Vec::new();
-
-        for y in 0..height as usize {
-            // Collect bits for this scanline
-            let mut bits: Vec<bool> = Vec::with_capacity(padded_width as usize);
-
-            for x in 0..width as usize {
-                let pixel_offset = y * pitch + x * bytes_per_pixel;
-
-                let is_filled = if pixel_offset + 2 < pixels.len() {
-                    let r = pixels[pixel_offset];
-                    let g = pixels[pixel_offset + 1];
-                    let b = pixels[pixel_offset + 2];
-                    let brightness = (r as u32 + g as u32 + b as u32) / 3;
-                    brightness < 128
-                } else {
-                    false
-                };
+    let text_outputs = [
+        args.hex_dump.is_some(),
+        args.rust_embedded.is_some(),
+        args.emit_gfx.is_some(),
+        args.emit_u8g2.is_some(),
+        args.emit_pico8.is_some(),
+        args.emit_tic80.is_some(),
+        args.emit_asm.is_some(),
+        args.emit_css.is_some(),
+        args.emit_html.is_some(),
+    ];
+    let provenance = if !args.no_provenance && text_outputs.contains(&true) {
+        Some(provenance::Provenance::new(
+            args,
+            &source_path,
+            charmap.label(),
+            &packed.words,
+        )?)
+    } else {
+        None
+    };
+    let out = Outputs {
+        args,
+        atlas: &built,
+        table: &table,
+        source_path: &source_path,
+        name: atlas_name(given_name, &source_path),
+        pages,
+        variants,
+        packed,
+        provenance,
+        png_colors,
+    };
 
-                bits.push(is_filled);
-            }
+    write_diagnostics(&out, sample_lines.as_deref())?;
+    if let Some(path) = &args.emit_html {
+        write_html_demo(&out, path, sample_lines.as_deref())?;
+    }
+    #[cfg(feature = "backend-sdl")]
+    if let Some(path) = &args.output_lovefont {
+        write_lovefont(&out, path, &lovefont_indices)?;
+    }
+    if let Some(base) = &args.output_godot {
+        write_godot(&out, base)?;
+    }
+    show_terminal_previews(&out)?;
+    write_rom_formats(&out)?;
+    if let Some(syntax) = args.emit_asm
+        && syntax != tiles::AsmSyntax::Rgbds
+    {
+        write_asm(&out, syntax)?;
+    }
+    write_font_sources(&out)?;
+    if let Some(path) = &args.json {
+        write_json_sidecar(&out, path)?;
+    }
+    if args.hex_dump.is_some() {
+        write_hex_dump(&out)?;
+    }
+    if args.output.is_some() || (args.hex_dump.is_none() && !args.preview) {
+        write_atlas_pngs(&out, pure_backend.as_deref())?;
+    }
+    compare_with_reference(&out)?;
 
-            // Pad to 32-bit boundary with zeros
-            while bits.len() < padded_width as usize {
-                bits.push(false);
-            }
+    // Shown last so every file is already written when the window opens
+    #[cfg(feature = "backend-sdl")]
+    if args.preview {
+        let surface = built.image.to_surface()?;
+        preview::show(renderer.generator()?.sdl(), &surface, &built.grid)?;
+    }
 
-            // Pack into 32-bit integers
-            for chunk in bits.chunks(32) {
-                let mut value: u32 = 0;
-                for (i, &bit) in chunk.iter().enumerate() {
-                    if bit {
-                        value |= 1 << i;
-                    }
-                }
-                all_values.push(value);
-            }
-        }
+    if args.profile {
+        log!(
+            "Profile: {:.3}s elapsed, font cache {} open / {} loads / {} hits, resident memory {}",
+            started.elapsed().as_secs_f64(),
+            built.cache_stats.open,
+            built.cache_stats.loads,
+            built.cache_stats.hits,
+            resident_memory_kib().map_or("unavailable".to_string(), |kib| format!("{} KiB", kib))
+        );
+    }
 
-        println!("//!LONGVAR uint[] font_data_{}", name.as_ref());
-        // Print 8 values per line for readability
-        for (i, value) in all_values.iter().enumerate() {
-            if i % 8 == 0 {
-                if i > 0 {
-                    println!();
-                }
-                print!("//!  ");
-            } else {
-                print!(" ");
+    Ok(())
+}
+
+/// What every output of a run is written from.
+struct Outputs<'a> {
+    args: &'a Args,
+    atlas: &'a Atlas,
+    table: &'a CharTable,
+    source_path: &'a Path,
+    /// Symbol name for the text outputs.
+    name: String,
+    /// Set when the atlas is split to stay within --max-atlas-dimension.
+    pages: Option<pages::Pages>,
+    variants: Option<Variants>,
+    /// The bits the hex dump, embedded Rust, and terminal previews show.
+    packed: PackedBitmap,
+    provenance: Option<provenance::Provenance>,
+    png_colors: PngColors,
+}
+
+impl Outputs<'_> {
+    fn charmap(&self) -> &CharMap {
+        &self.table.charmap
+    }
+
+    /// The provenance footer for a text output, empty with --no-provenance.
+    fn footer(&self, comment: provenance::Comment) -> String {
+        self.provenance
+            .as_ref()
+            .map_or_else(String::new, |p| p.footer(comment))
+    }
+}
+
+/// The images for inspecting the atlas: --output-gray, --output-dir,
+/// --contact-print, --metrics-report, --debug-image, and --sample-output.
+fn write_diagnostics(out: &Outputs, sample_lines: Option<&[Vec<u8>]>) -> Result<()> {
+    let (args, built, charmap) = (out.args, out.atlas, out.charmap());
+    let grid = &built.grid;
+    if let Some(path) = &args.output_gray {
+        save_atomically(path, args.force, |tmp| built.image.save_ink_png(tmp))?;
+        info!("Grayscale coverage PNG saved to {}", path.display());
+    }
+
+    if let Some(dir) = &args.output_dir {
+        let options = glyph_dir::DirOptions {
+            name_by: args.name_by,
+            force: args.force,
+            skip_empty: args.skip_empty,
+        };
+        let written = glyph_dir::write_glyph_dir(dir, built, charmap, &options)?;
+        info!("{} glyph images saved to {}", written, dir.display());
+    }
+
+    if let Some(path) = &args.contact_print {
+        let sheet = contact_print::build_contact_print(&built.image, grid, charmap.len());
+        save_atomically(path, args.force, |tmp| {
+            image_io::save(&sheet, tmp).context("Failed to save contact print")
+        })?;
+        info!("Contact print saved to {}", path.display());
+    }
+
+    if let Some(path) = &args.metrics_report {
+        let report = metrics_report::format_metrics_report(path, built)?;
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &report).context("Failed to save metrics report")
+        })?;
+        info!("Metrics report saved to {}", path.display());
+    }
+
+    #[cfg(feature = "backend-sdl")]
+    if let Some(path) = &args.debug_image {
+        let image =
+            debug_image::build_debug_image(&built.image, grid, built.baseline, &built.status())?;
+        save_atomically(path, args.force, |tmp| {
+            image.save(tmp).context("Failed to save debug image")
+        })?;
+        info!("Debug image saved to {}", path.display());
+    }
+
+    if let (Some(lines), Some(path)) = (sample_lines, &args.sample_output) {
+        let sample = sample::compose(&built.image, grid, charmap.len(), lines)?;
+        save_atomically(path, args.force, |tmp| {
+            image_io::save(&sample, tmp).context("Failed to save sample text")
+        })?;
+        info!("Sample text saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// --emit-html: a page setting `sample_lines`, or every character, in the
+/// atlas.
+fn write_html_demo(out: &Outputs, path: &Path, sample_lines: Option<&[Vec<u8>]>) -> Result<()> {
+    let sample = match sample_lines {
+        Some(lines) => lines.to_vec(),
+        None => html_demo::default_sample(out.charmap()),
+    };
+    let page = html_demo::page(out.source_path, out.atlas, out.charmap(), &sample)?
+        + &out.footer(provenance::Comment::Block("<!--", "-->"));
+    save_atomically(path, out.args.force, |tmp| {
+        std::fs::write(tmp, &page).context("Failed to save HTML page")
+    })?;
+    info!("HTML page saved to {}", path.display());
+    Ok(())
+}
+
+/// --output-lovefont: the glyphs at `indices` as a LÖVE ImageFont strip.
+#[cfg(feature = "backend-sdl")]
+fn write_lovefont(out: &Outputs, path: &Path, indices: &[usize]) -> Result<()> {
+    let strip = love_font::build_strip(&out.atlas.image, &out.atlas.grid, indices)?;
+    save_atomically(path, out.args.force, |tmp| {
+        strip.save(tmp).context("Failed to save LÖVE ImageFont")
+    })?;
+    log!("LÖVE ImageFont saved to {}, glyph string:", path.display());
+    log!(
+        "{}",
+        love_font::lua_glyph_string(indices.iter().map(|&i| out.charmap().chars()[i]))
+    );
+    Ok(())
+}
+
+/// --output-godot: `<base>.fnt` and its PNG pages.
+fn write_godot(out: &Outputs, base: &Path) -> Result<()> {
+    let (built, grid) = (out.atlas, &out.atlas.grid);
+    let mut fnt_path = base.as_os_str().to_owned();
+    fnt_path.push(".fnt");
+    let fnt_path = PathBuf::from(fnt_path);
+    let png_path = fnt_path.with_extension("png");
+    let (page_paths, page_images): (Vec<PathBuf>, Vec<&GrayImage>) = match &out.pages {
+        Some(pages) => pages
+            .images
+            .iter()
+            .enumerate()
+            .map(|(page, image)| (pages::page_path(&png_path, page), image))
+            .unzip(),
+        None => (vec![png_path], vec![&built.image]),
+    };
+    let page_names = page_paths
+        .iter()
+        .map(|path| path.file_name().and_then(|name| name.to_str()))
+        .collect::<Option<Vec<_>>>()
+        .context("Error: --output-godot needs a UTF-8 file name")?;
+    let face = out
+        .source_path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let (page_width, page_height) = (page_images[0].width, page_images[0].height);
+    let fnt = bmfont::format_fnt(
+        &bmfont::FontInfo {
+            face: &face,
+            pages: &page_names,
+            baseline: built.baseline,
+            atlas_width: page_width,
+            atlas_height: page_height,
+        },
+        grid,
+        out.charmap().chars(),
+        |i| match &out.pages {
+            Some(pages) => {
+                let (x, y) = pages.glyph_origin(grid, i);
+                (x, y, pages.page_of(grid, i))
             }
-            print!("0x{:08X} ", value);
-        }
-        println!();
-        println!("//!ENDLONGVAR");
-        println!("#define font_{}_width ({})", name.as_ref(), char_width);
-        println!("#define font_{}_height ({})", name.as_ref(), char_height);
-        println!("#define font_{}(uv,pos,txt,start,len) (fontstr(uv,pos,txt,start,len,{char_width},{char_height},{padded_width},{}))", name.as_ref(), name.as_ref());
-        println!("#define multiline_{}(uv,pos,txt,starts,lens) multiline_font((uv), (pos), (txt), (starts), (lens), {char_width}, {char_height}, {padded_width}, {})", name.as_ref(), name.as_ref())});
+            None => {
+                let (x, y) = grid.glyph_origin(i);
+                (x, y, 0)
+            }
+        },
+    )?;
+    for (path, image) in page_paths.iter().zip(&page_images) {
+        save_atomically(path, out.args.force, |tmp| {
+            image_io::save_colored(image, [255; 3], None, tmp).context("Failed to save BMFont page")
+        })?;
+    }
+    save_atomically(&fnt_path, out.args.force, |tmp| {
+        std::fs::write(tmp, &fnt).context("Failed to save BMFont file")
+    })?;
+    info!(
+        "Godot BMFont saved to {} and {}",
+        fnt_path.display(),
+        page_paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(())
+}
 
+/// --preview-terminal and --preview-terminal-graphics, drawn on stderr.
+fn show_terminal_previews(out: &Outputs) -> Result<()> {
+    let args = out.args;
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(80);
+    if let Some(style) = args.preview_terminal {
+        eprint!("{}", terminal::render(&out.packed, style, columns));
+    }
+    #[cfg(feature = "backend-sdl")]
+    if args.preview_terminal_graphics {
+        show_terminal_graphics(out, columns)?;
+    }
     Ok(())
 }
-fn main() -> Result<()> {
-    let args = Args::parse();
-
-    if args.output.is_none() && args.hex_dump.is_none() {
-        bail!("Error: either --output or --hex-dump must be provided");
-    }
-
-    // Initialize SDL3
-    let _sdl_context = sdl3::init()?;
-
-    // Initialize SDL3 TTF
-    let ttf_context = sdl3::ttf::init().context("Failed to initialize SDL2_ttf")?;
-
-    // --- Step 1: Find the right font size ---
-    // Iterate font sizes until the widest CP437 glyph's width == font_width.
-    let mut font_size = 1.0_f32;
-    let mut max_width: u32 = 0;
-    for iteration in 1..128 {
-        font_size = iteration as f32; // Start with integer sizes for faster convergence
-        let mut font = ttf_context
-            .load_font(&args.font_path, font_size)
-            .context("Failed to load font")?;
-        font.set_hinting(sdl3::ttf::Hinting::NONE);
-
-        // Find the widest glyph across all 256 CP437 characters
-        max_width = 0;
-        for i in 0..=u8::MAX {
-            let ch = get_cp437_char(i);
-            let metrics = match font.find_glyph_metrics(ch) {
-                Some(m) => m,
-                None => continue, // Character not in font, skip
-            };
-            max_width = max_width.max(metrics.maxx as u32);
+
+/// --preview-terminal-graphics, falling back to a text preview in
+/// terminals that can't draw the protocol.
+#[cfg(feature = "backend-sdl")]
+fn show_terminal_graphics(out: &Outputs, columns: u32) -> Result<()> {
+    let args = out.args;
+    let protocol = if args.sixel {
+        terminal_graphics::Protocol::Sixel
+    } else {
+        terminal_graphics::Protocol::Kitty
+    };
+    if protocol.supported() {
+        // The plain black-on-white atlas is RGB24; colored ones RGBA32
+        let surface = match out.png_colors {
+            ([0, 0, 0], Some([255, 255, 255])) => out.atlas.image.to_surface()?,
+            (ink, background) => out.atlas.image.to_color_surface(ink, background)?,
+        };
+        terminal_graphics::show(&protocol.encode(&surface)?)?;
+    } else {
+        let term = std::env::var("TERM").unwrap_or_default();
+        warning::emit(
+            Warning::new(
+                Code::TerminalGraphics,
+                format!(
+                    "TERM={} doesn't look like a terminal that draws {} graphics; showing a text preview instead",
+                    term,
+                    protocol.name()
+                ),
+            )
+            .data(serde_json::json!({ "term": term, "protocol": protocol.name() })),
+        );
+        if args.preview_terminal.is_none() {
+            eprint!(
+                "{}",
+                terminal::render(&out.packed, terminal::TerminalStyle::Braille, columns)
+            );
         }
+    }
+    Ok(())
+}
 
-        if max_width >= args.font_width {
-            eprintln!(
-                "Iteration {}: font_size={:.4}pt, max_width={} == font_width={} — done",
-                iteration, font_size, max_width, args.font_width
+/// The binary glyph ROMs and tile sets: --output-raw, --output-chr,
+/// --output-c64, and --output-gb with its RGBDS source.
+fn write_rom_formats(out: &Outputs) -> Result<()> {
+    let (args, built, charmap) = (out.args, out.atlas, out.charmap());
+    let grid = &built.grid;
+    if let Some(path) = &args.output_raw {
+        if !matches!(args.raw_width, 8 | 16) {
+            bail!("Error: --raw-width must be 8 or 16");
+        }
+        if charmap.len() != vga_rom::GLYPHS {
+            bail!(
+                "Error: --output-raw needs a {}-character table, not {}",
+                vga_rom::GLYPHS,
+                charmap.len()
             );
-            break;
         }
-        eprintln!(
-            "Iteration {}: font_size={:.4}pt, max_width={} < font_width={}",
-            iteration, font_size, max_width, args.font_width
+        let rom = vga_rom::encode_rom(&built.image, grid, args.raw_width / 8)?;
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &rom).context("Failed to save raw ROM dump")
+        })?;
+        info!(
+            "Raw ROM dump saved to {}: 256 glyphs of {} rows, {}",
+            path.display(),
+            grid.glyph_height,
+            if grid.glyph_width < args.raw_width {
+                format!(
+                    "{}-pixel glyphs left-aligned in {}-bit rows with the right {} bits zero",
+                    grid.glyph_width,
+                    args.raw_width,
+                    args.raw_width - grid.glyph_width
+                )
+            } else {
+                format!("{} bits per row", args.raw_width)
+            }
         );
     }
 
-    // --- Step 2: Load final font, derive cell dimensions ---
-    let mut font = ttf_context
-        .load_font(&args.font_path, font_size)
-        .context("Failed to load font with adjusted size")?;
-    font.set_hinting(sdl3::ttf::Hinting::NONE);
-
-    let font_width = max_width;
-
-    // --- Step 3: Render all 256 glyphs, find true cell height, build atlas ---
-    // shaded() produces surfaces where baseline is at font.ascent() from top,
-    // so blitting all at y=0 gives automatic baseline alignment.
-    // First pass: render all chars and find the max surface height.
-    let mut rendered: Vec<(u8, char, Option<Surface>)> = Vec::with_capacity(256);
+    if let Some(path) = &args.output_chr {
+        let chr = tiles::encode_chr(
+            &built.image,
+            grid,
+            charmap.len(),
+            args.chr_tiles,
+            args.chr_pad,
+        )?;
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &chr).context("Failed to save CHR tiles")
+        })?;
+        info!("CHR tiles saved to {}: {} bytes", path.display(), chr.len());
+    }
 
-    let cp437_all_string = (0..=u8::MAX).map(|i| get_cp437_char(i)).collect::<String>();
-    let texture = match font
-        .render(&cp437_all_string)
-        .shaded(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255))
-    {
-        Ok(s) => s,
-        Err(e) => {
+    if let Some(path) = &args.output_c64 {
+        if !charmap.codepage().is_some_and(Codepage::is_petscii) {
             bail!(
-                "Warning: failed to render all CP437 chars in one string: {}",
-                e
+                "Error: --output-c64 needs --codepage petscii-unshifted or petscii-shifted, unmodified"
             );
         }
-    };
-    let font_height = texture.height();
-    // If no glyphs rendered, fall back to font.height()
-    if font_height == 0 {
-        bail!(
-            "Error: all rendered glyphs have zero height. This likely means the font size is too small or the font file is invalid."
-        );
+        let rom = tiles::encode_c64(&built.image, grid)?;
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &rom).context("Failed to save C64 character ROM")
+        })?;
+        info!("C64 character ROM saved to {}", path.display());
     }
 
-    for i in 0..=u8::MAX {
-        let ch = get_cp437_char(i);
-        let surface = font
-            .render(&ch.to_string())
-            .shaded(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255))
-            .ok();
-        rendered.push((i, ch, surface));
+    if let Some(path) = &args.output_gb {
+        let gb = tiles::encode_gb(&built.image, grid, charmap.len(), args.gb_tiles)?;
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &gb).context("Failed to save Game Boy tiles")
+        })?;
+        info!(
+            "Game Boy tiles saved to {}: {} bytes",
+            path.display(),
+            gb.len()
+        );
+        if let Some(tiles::AsmSyntax::Rgbds) = args.emit_asm {
+            let asm_path = args
+                .asm_output
+                .clone()
+                .unwrap_or_else(|| path.with_extension("asm"));
+            save_atomically(&asm_path, args.force, |tmp| {
+                let source = tiles::format_rgbds(&gb, &out.name)
+                    + &out.footer(provenance::Comment::Line(";"));
+                std::fs::write(tmp, source).context("Failed to save RGBDS source")
+            })?;
+            info!(
+                "RGBDS source saved to {} as {}",
+                asm_path.display(),
+                out.name
+            );
+        }
     }
+    Ok(())
+}
 
-    eprintln!(
-        "Final: font_size={:.4}pt, ascent={}, descent={}, height={}, max_width={}",
-        font_size,
-        font.ascent(),
-        font.descent(),
-        font.height(),
-        max_width
-    );
-    eprintln!(
-        "Cell: {}x{} (width specified, height derived)",
-        font_width, font_height
+/// --emit-asm for the assemblers other than RGBDS, whose source goes with
+/// the --output-gb tiles.
+fn write_asm(out: &Outputs, syntax: tiles::AsmSyntax) -> Result<()> {
+    let path = out.args.asm_output.clone().unwrap_or_else(|| {
+        let extension = if syntax == tiles::AsmSyntax::Gas {
+            "s"
+        } else {
+            "asm"
+        };
+        PathBuf::from(format!("{}.{}", out.name, extension))
+    });
+    let marker = if syntax == tiles::AsmSyntax::Gas {
+        "#"
+    } else {
+        ";"
+    };
+    let source = asm_font::format_asm(syntax, out.atlas, &out.name)?
+        + &out.footer(provenance::Comment::Line(marker));
+    save_atomically(&path, out.args.force, |tmp| {
+        std::fs::write(tmp, &source).context("Failed to save assembler source")
+    })?;
+    info!(
+        "Assembler source saved to {} as {}",
+        path.display(),
+        out.name
     );
+    Ok(())
+}
 
-    // --- Step 3: Render each character individually into a 16x16 grid atlas ---
-    let atlas_width = font_width * 16;
-    let atlas_height = font_height * 16;
+/// The fonts written as source code: --rust-embedded, --emit-gfx,
+/// --emit-u8g2, --emit-pico8, and --emit-tic80.
+fn write_font_sources(out: &Outputs) -> Result<()> {
+    let (args, built, charmap, name) = (out.args, out.atlas, out.charmap(), &out.name);
+    if let Some(path) = &args.rust_embedded {
+        save_atomically(path, args.force, |tmp| {
+            rust_embed::write_rust_embedded(
+                tmp,
+                &rust_embed::EmbeddedAtlas {
+                    words: &out.packed.words,
+                    atlas_width: built.image.width,
+                    atlas_height: built.image.height,
+                    map_width: out.packed.padded_width,
+                    grid: &built.grid,
+                    footer: &out.footer(provenance::Comment::Line("//")),
+                },
+            )
+        })?;
+        info!("Rust embedded atlas saved to {}", path.display());
+    }
 
-    let mut atlas = Surface::new(atlas_width, atlas_height, sdl3::pixels::PixelFormat::RGB24)?;
+    // Pen positions and advances come from the font, before any stretching
+    let horizontal_scale = args.scale * args.stretch_width;
+    if let Some(path) = &args.emit_gfx {
+        let range = charmap::parse_unicode_range(&args.gfx_range).context("Invalid --gfx-range")?;
+        let header = gfx_font::format_gfx(built, charmap, range, horizontal_scale, name)?
+            + &out.footer(provenance::Comment::Line("//"));
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &header).context("Failed to save GFX font header")
+        })?;
+        info!("GFX font header saved to {} as {}", path.display(), name);
+    }
 
-    // Fill with solid white background
-    atlas
-        .fill_rect(None, Color::RGB(255, 255, 255))
-        .context("unable to fill rect")?;
+    if let Some(path) = &args.emit_u8g2 {
+        let source = u8g2_font::format_u8g2(built, charmap, horizontal_scale, name)?
+            + &out.footer(provenance::Comment::Line("//"));
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &source).context("Failed to save u8g2 font")
+        })?;
+        info!("u8g2 font saved to {} as {}", path.display(), name);
+    }
 
-    // Second pass: blit all pre-rendered surfaces into the atlas.
-    // Since shaded() places the baseline at font.ascent() from the top of every
-    // surface, blitting at y=0 in each cell keeps all glyphs baseline-aligned.
-    for &(i, ch, ref surface_opt) in &rendered {
-        let char_surface = match surface_opt {
-            Some(s) => s,
-            None => {
-                if args.debug {
-                    eprintln!("Skipping '{}' (index {}) — not in font", ch, i);
-                }
-                continue;
-            }
-        };
+    if let Some(path) = &args.emit_pico8 {
+        let lua = fantasy_console::format_pico8(built, charmap.len())?
+            + &out.footer(provenance::Comment::Line("--"));
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &lua).context("Failed to save PICO-8 font")
+        })?;
+        info!("PICO-8 font saved to {}", path.display());
+    }
 
-        let col = i % 16;
-        let row = i / 16;
-        let cell_x = col as i32 * font_width as i32;
-        let cell_y = row as i32 * font_height as i32;
+    if let Some(path) = &args.emit_tic80 {
+        let sprites = fantasy_console::format_tic80(built, charmap.len())?
+            + &out.footer(provenance::Comment::Line("--"));
+        save_atomically(path, args.force, |tmp| {
+            std::fs::write(tmp, &sprites).context("Failed to save TIC-80 sprites")
+        })?;
+        info!("TIC-80 font sprites saved to {}", path.display());
+    }
+    Ok(())
+}
 
-        // Horizontal: center glyph in cell
-        let x_offset = ((font_width as i32 - char_surface.width() as i32) / 2).max(0);
+/// --json: the layout of the atlas for code that loads it.
+fn write_json_sidecar(out: &Outputs, path: &Path) -> Result<()> {
+    let (args, built, table) = (out.args, out.atlas, out.table);
+    let (grid, charmap) = (&built.grid, &table.charmap);
+    let status = built.status();
+    save_atomically(path, args.force, |tmp| {
+        sidecar::write_sidecar(
+            tmp,
+            &sidecar::Sidecar {
+                font: out.source_path,
+                font_size: built.font_size,
+                cell_width: grid.stride_x(),
+                cell_height: grid.stride_y(),
+                glyph_width: grid.glyph_width,
+                glyph_height: grid.glyph_height,
+                padding: grid.padding,
+                gutter: grid.gutter,
+                baseline: built.baseline,
+                columns: grid.columns,
+                rows: grid.rows,
+                column_major: grid.column_major,
+                atlas_width: built.image.width,
+                atlas_height: built.image.height,
+                content_width: built.content_width,
+                content_height: built.content_height,
+                charset: charmap.label(),
+                chars: charmap.chars().iter().map(char::to_string).collect(),
+                status: &status,
+                layout: table.layout_name,
+                layout_order: table.layout_order.as_deref(),
+                layout_inverse: table.layout_order.as_ref().map(|order| {
+                    (0..table.unordered.len())
+                        .map(|i| order.iter().position(|&source| source == i))
+                        .collect()
+                }),
+                baseline_shift: args.baseline_shift,
+                nudge: &args.nudge,
+                variation: &args.variation,
+                variants: out.variants.as_ref(),
+                pages: out
+                    .pages
+                    .as_ref()
+                    .map(|pages| pages.layout(grid, charmap.len())),
+                remap: args.chars_from.is_some().then(|| {
+                    charmap
+                        .chars()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, ch)| (ch.to_string(), i))
+                        .collect()
+                }),
+            },
+        )
+    })?;
+    info!("JSON sidecar saved to {}", path.display());
+    Ok(())
+}
 
-        let metrics = match font.find_glyph_metrics(get_cp437_char(i)) {
-            Some(m) => m,
-            None => {
-                if args.debug {
-                    eprintln!(
-                        "Warning: failed to get metrics for char '{}' (index {}) — skipping",
-                        ch, i
-                    );
-                }
-                continue;
-            }
-        };
+/// --hex-dump: the packed words of every page, written to stdout.
+fn write_hex_dump(out: &Outputs) -> Result<()> {
+    let grid = &out.atlas.grid;
+    let paged_words = match &out.pages {
+        Some(pages) => pages
+            .images
+            .iter()
+            .map(|image| {
+                packing::pack_bits(image, INK_THRESHOLD, WordSize::Bits32, BitOrder::LsbFirst)
+            })
+            .collect(),
+        None => vec![out.packed.clone()],
+    };
+    let rows_per_page = out.pages.as_ref().map_or(grid.rows, |p| p.rows_per_page);
+    let dump = format_hex_dump(
+        &paged_words,
+        rows_per_page,
+        grid,
+        out.charmap(),
+        out.args.chars_from.is_some(),
+        out.variants.as_ref(),
+        &out.name,
+    ) + &out.footer(provenance::Comment::Line("//"));
+    // A closed pipe or full disk is an error, not a panic inside print!
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(dump.as_bytes())
+        .and_then(|()| stdout.flush())
+        .context("Failed to write hex dump to stdout")
+        .failure(Failure::Output)
+}
 
-        if metrics.miny == metrics.maxy || metrics.minx == metrics.maxx {
-            if args.debug {
-                eprintln!(
-                    "Warning: char '{}' (index {}) has a zero dimension (miny == maxy == {} or minx == maxx == {}) — skipping",
-                    ch, i, metrics.miny, metrics.minx
-                );
+/// The atlas PNG, one per page, at --output or its default name, and the
+/// --emit-css stylesheet pointing at it.
+fn write_atlas_pngs(out: &Outputs, pure_backend: Option<&dyn Backend>) -> Result<()> {
+    let (args, built) = (out.args, out.atlas);
+    let grid = &built.grid;
+    let path = args
+        .output
+        .clone()
+        .unwrap_or_else(|| match args.output_naming {
+            OutputNaming::Font => {
+                default_output_path(out.source_path, grid.glyph_width, grid.glyph_height)
+            }
+            OutputNaming::Size => {
+                PathBuf::from(format!("{}x{}.png", grid.glyph_width, grid.glyph_height))
+            }
+        });
+    let saved: Vec<(PathBuf, &GrayImage)> = match &out.pages {
+        Some(pages) => pages
+            .images
+            .iter()
+            .enumerate()
+            .map(|(page, image)| (pages::page_path(&path, page), image))
+            .collect(),
+        None => vec![(path, &built.image)],
+    };
+    if built.subpixel.is_some() && out.pages.is_some() {
+        bail!("Error: --render-mode lcd needs the atlas in a single PNG");
+    }
+    for (path, image) in &saved {
+        match &built.subpixel {
+            Some(lcd) => {
+                let (ink, background) = out.png_colors;
+                let background = background.unwrap_or([255; 3]);
+                save_atomically(path, args.force, |tmp| {
+                    image_io::save_lcd(lcd, ink, background, tmp).context("Failed to save PNG")
+                })?;
             }
-            continue;
+            None => save_atlas_png(path, image, args, out.png_colors, pure_backend)?,
         }
-
-        let y_offset = if char_surface.height() == font_height {
-            0
-        } else if metrics.miny + font.descent() <= 1 {
-            //descent without ascent
-            font_height as i32 - char_surface.height() as i32
+        // Keep stdout clean for a hex dump written alongside
+        if args.hex_dump.is_some() {
+            info!("Font atlas saved to {}", path.display());
         } else {
-            //no ascent or descent
-            0
-        };
+            println!("Font atlas saved to {}", path.display());
+        }
+    }
+    if let (Some(css_path), Some(png_path)) = (&args.emit_css, &args.output) {
+        if out.pages.is_some() {
+            bail!("Error: --emit-css needs the atlas in a single PNG");
+        }
+        let css = css_sprites::stylesheet(
+            &args.css_prefix,
+            png_path,
+            css_path,
+            grid,
+            out.charmap().len(),
+        )? + &out.footer(provenance::Comment::Block("/*", "*/"));
+        save_atomically(css_path, args.force, |tmp| {
+            std::fs::write(tmp, &css).context("Failed to save stylesheet")
+        })?;
+        info!("Stylesheet saved to {}", css_path.display());
+    }
+    Ok(())
+}
 
-        if y_offset + char_surface.height() as i32 > font_height as i32 {
-            eprintln!(
-                "Warning: char '{}' (index {}) has a y_offset={} that causes it to exceed cell height ({} + {} > {})",
-                ch,
-                i,
-                y_offset,
-                y_offset,
-                char_surface.height(),
-                font_height
-            );
+/// --diff or --diff-hex: report the cells that changed from the reference,
+/// with --diff-image showing where.
+fn compare_with_reference(out: &Outputs) -> Result<()> {
+    let args = out.args;
+    let reference = match (&args.diff, &args.diff_hex) {
+        (Some(path), _) => diff::Reference::from_png(path)?,
+        (None, Some(path)) => diff::Reference::from_hex_dump(path)?,
+        (None, None) => return Ok(()),
+    };
+    let diffs = diff::compare(out.atlas, out.charmap(), &reference)?;
+    #[cfg(feature = "backend-sdl")]
+    if let Some(path) = &args.diff_image {
+        let surface = diff::diff_surface(&out.atlas.image, &reference.image)?;
+        save_atomically(path, args.force, |tmp| {
+            surface.save(tmp).context("Failed to save diff image")
+        })?;
+        info!("Diff image saved to {}", path.display());
+    }
+    diff::report(&diffs, out.charmap().len(), args.diff_tolerance)
+}
+
+/// With --atlas-size, set `config`'s cell width to the widest whose atlas
+/// fits the budget, and return the budget.
+fn fit_atlas_size(
+    args: &Args,
+    renderer: &Renderer,
+    config: &mut AtlasConfig,
+) -> Result<Option<(u32, u32)>> {
+    let Some(spec) = &args.atlas_size else {
+        return Ok(None);
+    };
+    let budget = autofit::parse_budget(spec)?;
+    config.cell_width = autofit::fit(
+        |probe| Ok(render_font(args, renderer, probe)?.0),
+        config,
+        budget,
+    )?;
+    Ok(Some(budget))
+}
+
+/// What --dry-run reports.
+#[derive(Serialize)]
+struct DryRun<'a> {
+    font: &'a Path,
+    font_size: f32,
+    ascent: Option<i32>,
+    descent: Option<i32>,
+    cell_width: u32,
+    cell_height: u32,
+    glyph_width: u32,
+    glyph_height: u32,
+    baseline: u32,
+    columns: u32,
+    rows: u32,
+    atlas_width: u32,
+    atlas_height: u32,
+    /// Bits per row of the hex dump's 32-bit words.
+    padded_scanline_width: u32,
+}
+
+/// Print the layout of `atlas`, sized but not drawn, to stdout.
+fn print_dry_run(args: &Args, font: &Path, atlas: &Atlas) -> Result<()> {
+    let grid = &atlas.grid;
+    let report = DryRun {
+        font,
+        font_size: atlas.font_size,
+        ascent: atlas.line.map(|line| line.ascent),
+        descent: atlas.line.map(|line| line.descent),
+        cell_width: grid.cell_width(),
+        cell_height: grid.cell_height(),
+        glyph_width: grid.glyph_width,
+        glyph_height: grid.glyph_height,
+        baseline: atlas.baseline,
+        columns: grid.columns,
+        rows: grid.rows,
+        atlas_width: atlas.image.width,
+        atlas_height: atlas.image.height,
+        padded_scanline_width: atlas.image.width.div_ceil(32) * 32,
+    };
+    if args.log_format == LogFormat::Json {
+        println!(
+            "{}",
+            serde_json::to_string(&report).context("Failed to serialize --dry-run report")?
+        );
+        return Ok(());
+    }
+    println!("Font: {}", font.display());
+    println!("Font size: {:.4}pt", report.font_size);
+    if let (Some(ascent), Some(descent)) = (report.ascent, report.descent) {
+        println!("Ascent: {}, descent: {}", ascent, descent);
+    }
+    println!(
+        "Cell: {}x{} (glyph box {}x{}, baseline row {})",
+        report.cell_width,
+        report.cell_height,
+        report.glyph_width,
+        report.glyph_height,
+        report.baseline
+    );
+    println!("Grid: {}x{}", report.columns, report.rows);
+    println!("Atlas: {}x{}", report.atlas_width, report.atlas_height);
+    println!("Padded scanline width: {}", report.padded_scanline_width);
+    Ok(())
+}
+
+/// Draws fonts with whichever --backend each run asks for. SDL starts the
+/// first time something needs it, so runs that never do, such as every
+/// --backend rust run without --preview, don't initialize it at all.
+#[derive(Default)]
+struct Renderer {
+    #[cfg(feature = "backend-sdl")]
+    generator: OnceCell<Generator>,
+}
+
+impl Renderer {
+    /// The SDL generator, started on first use and kept for later runs.
+    #[cfg(feature = "backend-sdl")]
+    fn generator(&self) -> Result<&Generator> {
+        if let Some(generator) = self.generator.get() {
+            return Ok(generator);
+        }
+        let generator = Generator::new()?;
+        Ok(self.generator.get_or_init(|| generator))
+    }
+
+    /// Render `config` with SDL_ttf.
+    #[cfg(feature = "backend-sdl")]
+    fn generate(&self, config: &AtlasConfig) -> Result<Atlas> {
+        self.generator()?.generate(config)
+    }
+
+    #[cfg(not(feature = "backend-sdl"))]
+    fn generate(&self, _config: &AtlasConfig) -> Result<Atlas> {
+        bail!("Error: this build has no SDL backend; rebuild with --features backend-sdl")
+    }
+}
+
+/// Render `config` with the --backend, handing back the backend too when
+/// it isn't SDL so the atlas can be saved through it.
+fn render_font(
+    args: &Args,
+    renderer: &Renderer,
+    config: &AtlasConfig,
+) -> Result<(Atlas, Option<Box<dyn Backend>>)> {
+    match args.backend {
+        BackendKind::Sdl => Ok((renderer.generate(config)?, None)),
+        BackendKind::Rust => {
+            let pure = open_rust_backend(config, &config.font_path, config.face_index)?;
+            let atlas =
+                backend::generate(config, &|path, face| open_rust_backend(config, path, face))?;
+            Ok((atlas, Some(pure)))
         }
+    }
+}
+
+/// Save `image` as the atlas PNG at `path`: single-channel for --sdf, in
+/// the --foreground and --background colors otherwise, and through `pure`
+/// when that backend drew it.
+fn save_atlas_png(
+    path: &Path,
+    image: &GrayImage,
+    args: &Args,
+    png_colors: PngColors,
+    pure: Option<&dyn Backend>,
+) -> Result<()> {
+    if args.sdf.is_some() {
+        save_atomically(path, args.force, |tmp| image.save_ink_png(tmp))
+    } else if png_colors == ([0; 3], Some([255; 3])) {
+        save_atomically(path, args.force, |tmp| match pure {
+            Some(pure) => pure.save_image(image, tmp),
+            None => image_io::save(image, tmp).context("Failed to save PNG"),
+        })
+    } else {
+        let (ink, background) = png_colors;
+        save_atomically(path, args.force, |tmp| {
+            image_io::save_colored(image, ink, background, tmp).context("Failed to save PNG")
+        })
+    }
+}
 
-        let dst_rect = Rect::new(
-            cell_x + x_offset,
-            cell_y + y_offset,
-            char_surface.width().min(font_width),
-            char_surface.height().min(font_height),
+/// `--sizes`: render the font at every size, stack the strips, and write
+/// the PNG, sidecar, and hex dump; the other outputs take a single grid.
+fn generate_stack(
+    args: &Args,
+    renderer: &Renderer,
+    charmap: &CharMap,
+    given_name: Option<&str>,
+    png_colors: PngColors,
+) -> Result<()> {
+    let unsupported = [
+        (args.check, "--check"),
+        (args.preview, "--preview"),
+        (args.preview_terminal.is_some(), "--preview-terminal"),
+        (
+            args.preview_terminal_graphics,
+            "--preview-terminal-graphics",
+        ),
+        (!args.show_glyph.is_empty(), "--show-glyph"),
+        (args.coverage_report.is_some(), "--coverage-report"),
+        (args.output_gray.is_some(), "--output-gray"),
+        (args.output_dir.is_some(), "--output-dir"),
+        (args.contact_print.is_some(), "--contact-print"),
+        (args.metrics_report.is_some(), "--metrics-report"),
+        (args.debug_image.is_some(), "--debug-image"),
+        (args.sample_output.is_some(), "--sample-output"),
+        (args.emit_html.is_some(), "--emit-html"),
+        (args.emit_css.is_some(), "--emit-css"),
+        (args.emit_asm.is_some(), "--emit-asm"),
+        (args.output_lovefont.is_some(), "--output-lovefont"),
+        (args.output_godot.is_some(), "--output-godot"),
+        (args.rust_embedded.is_some(), "--rust-embedded"),
+        (args.output_raw.is_some(), "--output-raw"),
+        (args.output_chr.is_some(), "--output-chr"),
+        (args.output_gb.is_some(), "--output-gb"),
+        (args.output_c64.is_some(), "--output-c64"),
+        (args.emit_gfx.is_some(), "--emit-gfx"),
+        (args.emit_u8g2.is_some(), "--emit-u8g2"),
+        (args.emit_pico8.is_some(), "--emit-pico8"),
+        (args.emit_tic80.is_some(), "--emit-tic80"),
+        (args.diff.is_some() || args.diff_hex.is_some(), "--diff"),
+    ];
+    if let Some((_, option)) = unsupported.iter().find(|(used, _)| *used) {
+        bail!(
+            "Error: {} takes a single grid and can't be combined with --sizes",
+            option
         );
+    }
+    if args.sizes.contains(&0) {
+        bail!("Error: --sizes must all be at least 1");
+    }
 
-        if args.debug {
-            eprintln!(
-                "{}  miny={}, maxy={}, asc={}, dsc={}, intern={}, tex_height={}, font_height={}, y_offset={}",
-                ch,
-                metrics.miny,
-                metrics.maxy,
-                font.ascent(),
-                font.descent(),
-                font.height(),
-                char_surface.height(),
-                font_height,
-                y_offset
-            );
+    let mut config = atlas_config(args, charmap)?;
+    let mut pure_backend = None;
+    let mut rendered = Vec::with_capacity(args.sizes.len());
+    for &size in &args.sizes {
+        config.cell_width = size;
+        let atlas;
+        (atlas, pure_backend) = render_font(args, renderer, &config)?;
+        info!(
+            "Size {}: {}x{} strip of {}x{} cells",
+            size,
+            atlas.image.width,
+            atlas.image.height,
+            atlas.grid.stride_x(),
+            atlas.grid.stride_y()
+        );
+        warning::record_cells(&atlas);
+        if args.strict {
+            strict::check(&atlas, &args.strict_allow);
         }
-        char_surface.blit(None, &mut atlas, Some(dst_rect))?;
+        rendered.push((size, atlas));
     }
+    let (image, strips) = strips::stack(rendered);
+    let name = atlas_name(given_name, &config.font_path);
 
-    eprintln!("Atlas: {}x{}", atlas_width, atlas_height);
+    if let Some(path) = &args.json {
+        save_atomically(path, args.force, |tmp| {
+            sidecar::write_sidecar(
+                tmp,
+                &sidecar::StackSidecar {
+                    font: &config.font_path,
+                    atlas_width: image.width,
+                    atlas_height: image.height,
+                    charset: charmap.label(),
+                    chars: charmap.chars().iter().map(char::to_string).collect(),
+                    strips: strips.iter().map(strips::Strip::layout).collect(),
+                },
+            )
+        })?;
+        info!("JSON sidecar saved to {}", path.display());
+    }
 
-    if let Some(name) = &args.hex_dump {
-        dump_surface_as_hex(&atlas, font_width, font_height, name)?;
-    } else if let Some(path) = &args.output {
-        atlas.save(path).context("Failed to save PNG")?;
-        println!("Font atlas saved to {}", path.display());
+    if args.hex_dump.is_some() {
+        let mut dump = String::new();
+        let mut words = Vec::new();
+        for strip in &strips {
+            let packed = packing::pack_bits(
+                &strip.atlas.image,
+                INK_THRESHOLD,
+                WordSize::Bits32,
+                BitOrder::LsbFirst,
+            );
+            if !dump.is_empty() {
+                dump.push('\n');
+            }
+            dump.push_str(&format_hex_dump(
+                std::slice::from_ref(&packed),
+                strip.atlas.grid.rows,
+                &strip.atlas.grid,
+                charmap,
+                args.chars_from.is_some(),
+                None,
+                &format!("{}_{}", name, strip.size),
+            ));
+            words.extend(packed.words);
+        }
+        if !args.no_provenance {
+            let provenance =
+                provenance::Provenance::new(args, &config.font_path, charmap.label(), &words)?;
+            dump.push_str(&provenance.footer(provenance::Comment::Line("//")));
+        }
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(dump.as_bytes())
+            .and_then(|()| stdout.flush())
+            .context("Failed to write hex dump to stdout")
+            .failure(Failure::Output)?;
     }
 
+    if args.output.is_some() || args.hex_dump.is_none() {
+        let path = args
+            .output
+            .clone()
+            .unwrap_or_else(|| match args.output_naming {
+                OutputNaming::Font => {
+                    default_output_path(&config.font_path, image.width, image.height)
+                }
+                OutputNaming::Size => {
+                    PathBuf::from(format!("{}x{}.png", image.width, image.height))
+                }
+            });
+        save_atlas_png(&path, &image, args, png_colors, pure_backend.as_deref())?;
+        if args.hex_dump.is_some() {
+            info!("Font atlas saved to {}", path.display());
+        } else {
+            println!("Font atlas saved to {}", path.display());
+        }
+    }
     Ok(())
 }
+
+/// The symbol name outputs use: --name, or one made from the source's file
+/// name.
+fn atlas_name(given_name: Option<&str>, source_path: &Path) -> String {
+    given_name.map_or_else(
+        || {
+            symbol_name(
+                source_path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("font"),
+            )
+        },
+        str::to_string,
+    )
+}
+
+/// Run `write` against a temporary file next to `path`, then rename it into
+/// place, so a failed or interrupted write never leaves a truncated output
+/// behind (or clobbers the previous good one). An existing `path` is only
+/// replaced with `force`.
+fn save_atomically(
+    path: &Path,
+    force: bool,
+    write: impl FnOnce(&Path) -> Result<()>,
+) -> Result<()> {
+    if !force && path.exists() {
+        return Err(anyhow!(
+            "Error: {} already exists; pass --force to overwrite",
+            path.display()
+        ))
+        .failure(Failure::Output);
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp = path.with_file_name(tmp_name);
+    if let Err(e) = write(&tmp) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e).failure(Failure::Output);
+    }
+    std::fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to move {} into place", path.display()))
+        .failure(Failure::Output)
+}
+
+/// The --name to give text outputs, or the one passed the deprecated way
+/// as the --hex-dump value, checked to be a C and Rust identifier. `None`
+/// leaves the default, the font's file name.
+fn given_name(args: &Args) -> Result<Option<&str>> {
+    let legacy = args.hex_dump.as_deref().filter(|name| !name.is_empty());
+    if let Some(old) = legacy {
+        warning::emit(Warning::new(
+            Code::Deprecated,
+            format!(
+                "--hex-dump={} is deprecated; use --hex-dump --name {}",
+                old, old
+            ),
+        ));
+    }
+    let Some(name) = args.name.as_deref().or(legacy) else {
+        return Ok(None);
+    };
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!(
+            "Error: --name '{}' is not an identifier; use letters, digits, and `_`, not starting with a digit",
+            name
+        );
+    }
+    Ok(Some(name))
+}
+
+/// `name` made into an assembler or C symbol: characters other than
+/// letters, digits, and `_` become `_`, and a leading digit gets a `_` in
+/// front.
+fn symbol_name(name: &str) -> String {
+    let mut symbol: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        symbol.insert(0, '_');
+    }
+    symbol
+}
+
+/// Which renderer draws the glyphs of a TrueType font.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum BackendKind {
+    /// SDL_ttf
+    Sdl,
+    /// ab_glyph, without SDL; needs the `backend-rust` feature
+    Rust,
+}
+
+impl BackendKind {
+    #[cfg(feature = "backend-sdl")]
+    const DEFAULT: BackendKind = BackendKind::Sdl;
+    #[cfg(not(feature = "backend-sdl"))]
+    const DEFAULT: BackendKind = BackendKind::Rust;
+}
+
+/// Open face `face` of the font at `path` for `config` with ab_glyph.
+/// --variation names the primary font's axes, so fallback and secondary
+/// fonts draw their default instance.
+#[cfg(feature = "backend-rust")]
+fn open_rust_backend(config: &AtlasConfig, path: &Path, face: u32) -> Result<Box<dyn Backend>> {
+    let variations = if path == config.font_path {
+        &config.variations[..]
+    } else {
+        &[]
+    };
+    let backend = mycp437generator::rust_backend::RustBackend::open(
+        path,
+        face,
+        variations,
+        config.render_mode,
+    )?;
+    Ok(Box::new(backend))
+}
+
+#[cfg(not(feature = "backend-rust"))]
+fn open_rust_backend(_config: &AtlasConfig, _path: &Path, _face: u32) -> Result<Box<dyn Backend>> {
+    bail!("Error: this build has no rust backend; rebuild with --features backend-rust")
+}
+
+/// How the PNG is named when --output isn't given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum OutputNaming {
+    /// `<font-stem>_<width>x<height>.png`
+    Font,
+    /// `<width>x<height>.png`, as Dwarf Fortress tilesets are named
+    Size,
+}
+
+/// `<font-stem>_<width>x<height>.png` in the current directory, with anything
+/// outside `[A-Za-z0-9._-]` in the stem replaced so the name is shell-safe.
+fn default_output_path(font_path: &Path, width: u32, height: u32) -> PathBuf {
+    let stem = font_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut stem: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.is_empty() {
+        stem.push_str("font");
+    }
+    PathBuf::from(format!("{}_{}x{}.png", stem, width, height))
+}
+
+/// Current resident set size, where the platform exposes it cheaply.
+fn resident_memory_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse().ok())
+}
@@ -0,0 +1,59 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::Args;
+use crate::config;
+
+/// A job's log name and its options.
+pub type Job = (String, Args);
+
+/// The TOML form: an array of `[[job]]` tables.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TomlManifest {
+    job: Vec<Map<String, Value>>,
+}
+
+/// Read the jobs in a `--manifest` file: a JSON array of objects, or TOML
+/// `[[job]]` tables. Each job takes the same keys as a `--config` file and
+/// is layered over the command-line options; an optional `name` labels its
+/// log lines and defaults to its position.
+pub fn load(path: &Path, base: &Args) -> Result<Vec<Job>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let jobs: Vec<Map<String, Value>> = if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    } else {
+        toml::from_str::<TomlManifest>(&text)
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+            .job
+    };
+    if jobs.is_empty() {
+        bail!("{} lists no jobs", path.display());
+    }
+
+    jobs.into_iter()
+        .enumerate()
+        .map(|(n, mut options)| {
+            let name = match options.remove("name") {
+                Some(Value::String(name)) => name,
+                Some(other) => bail!(
+                    "Job {} in {} has a non-string name: {}",
+                    n + 1,
+                    path.display(),
+                    other
+                ),
+                None => format!("job {}", n + 1),
+            };
+            let args = config::overlay(base, options, path, |_| false)
+                .with_context(|| format!("Invalid job '{}'", name))?;
+            Ok((name, args))
+        })
+        .collect()
+}
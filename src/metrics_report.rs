@@ -0,0 +1,155 @@
+//! `--metrics-report`: the font metrics and placement of every glyph, as
+//! CSV or JSON, for layout engines that need more than the grid.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::CellStatus;
+use mycp437generator::{Atlas, INK_THRESHOLD};
+
+/// The atlas-wide values, then one record per cell.
+#[derive(Serialize)]
+struct Report {
+    font_size: f32,
+    /// `None` for atlases that weren't rendered from a font.
+    ascent: Option<i32>,
+    descent: Option<i32>,
+    cell_width: u32,
+    cell_height: u32,
+    glyphs: Vec<Record>,
+}
+
+/// One cell. The font fields are `None` where nothing was blitted from a
+/// font; all are in pixels before `--scale`.
+#[derive(Serialize)]
+struct Record {
+    index: usize,
+    codepoint: String,
+    minx: Option<i32>,
+    maxx: Option<i32>,
+    miny: Option<i32>,
+    maxy: Option<i32>,
+    advance: Option<i32>,
+    surface_width: Option<u32>,
+    surface_height: Option<u32>,
+    x_offset: Option<i32>,
+    y_offset: Option<i32>,
+    clipped: bool,
+    /// The share of the glyph box that is ink in the finished atlas.
+    fill_ratio: f32,
+}
+
+/// The share of glyph `index`'s box that is ink.
+fn fill_ratio(atlas: &Atlas, index: usize) -> f32 {
+    let grid = &atlas.grid;
+    let (x0, y0) = grid.glyph_origin(index);
+    let (width, height) = (grid.glyph_width, grid.glyph_height);
+    let mut ink = 0;
+    for y in y0..y0 + height {
+        for x in x0..x0 + width {
+            if atlas.image.get(x, y) < INK_THRESHOLD {
+                ink += 1;
+            }
+        }
+    }
+    ink as f32 / (width * height).max(1) as f32
+}
+
+fn report(atlas: &Atlas) -> Report {
+    let glyphs = atlas
+        .glyphs
+        .iter()
+        .enumerate()
+        .map(|(index, glyph)| {
+            let p = glyph.placement;
+            Record {
+                index,
+                codepoint: format!("U+{:04X}", glyph.ch as u32),
+                minx: p.map(|p| p.minx),
+                maxx: p.map(|p| p.maxx),
+                miny: p.map(|p| p.miny),
+                maxy: p.map(|p| p.maxy),
+                advance: p.map(|p| p.advance),
+                surface_width: p.map(|p| p.surface_width),
+                surface_height: p.map(|p| p.surface_height),
+                x_offset: p.map(|p| p.x_offset),
+                y_offset: p.map(|p| p.y_offset),
+                clipped: glyph.status == CellStatus::Clipped,
+                fill_ratio: fill_ratio(atlas, index),
+            }
+        })
+        .collect();
+    Report {
+        font_size: atlas.font_size,
+        ascent: atlas.line.map(|line| line.ascent),
+        descent: atlas.line.map(|line| line.descent),
+        cell_width: atlas.grid.stride_x(),
+        cell_height: atlas.grid.stride_y(),
+        glyphs,
+    }
+}
+
+/// An empty field for `None`, as spreadsheets expect.
+fn field<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |v| v.to_string())
+}
+
+/// A `#` comment with the atlas-wide values, then a header row and one row
+/// per cell.
+fn format_csv(report: &Report) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "# font_size={:.4},ascent={},descent={},cell_width={},cell_height={}",
+        report.font_size,
+        field(report.ascent),
+        field(report.descent),
+        report.cell_width,
+        report.cell_height
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "index,codepoint,minx,maxx,miny,maxy,advance,surface_width,surface_height,x_offset,y_offset,clipped,fill_ratio"
+    )
+    .unwrap();
+    for r in &report.glyphs {
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{:.4}",
+            r.index,
+            r.codepoint,
+            field(r.minx),
+            field(r.maxx),
+            field(r.miny),
+            field(r.maxy),
+            field(r.advance),
+            field(r.surface_width),
+            field(r.surface_height),
+            field(r.x_offset),
+            field(r.y_offset),
+            r.clipped,
+            r.fill_ratio
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// The report for `atlas` in the format `path` asks for: JSON for a
+/// `.json` extension, CSV otherwise.
+pub fn format_metrics_report(path: &Path, atlas: &Atlas) -> Result<String> {
+    let report = report(atlas);
+    if path
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"))
+    {
+        let json =
+            serde_json::to_string_pretty(&report).context("Failed to serialize metrics report")?;
+        Ok(json + "\n")
+    } else {
+        Ok(format_csv(&report))
+    }
+}
@@ -0,0 +1,102 @@
+//! `--embolden` and `--thin`: growing or eroding each glyph's thresholded
+//! strokes by whole pixels, for fonts whose hairlines vanish at small sizes.
+
+use crate::gray::GrayImage;
+use crate::{EmboldenDir, INK_THRESHOLD, Overflow};
+
+/// Where a pixel's ink spreads to, as offsets from it: a disc of `radius`,
+/// or with `Horizontal` a run to its right, as classic synthetic bold
+/// overprints the glyph shifted sideways.
+fn reach(radius: u32, dir: EmboldenDir) -> Vec<(i32, i32)> {
+    let r = radius as i32;
+    match dir {
+        EmboldenDir::Both => (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx * dx + dy * dy <= r * r)
+            .collect(),
+        EmboldenDir::Horizontal => (0..=r).map(|dx| (dx, 0)).collect(),
+    }
+}
+
+/// Whether (`x`, `y`) is ink; outside the image is background.
+fn is_ink(image: &GrayImage, x: i32, y: i32) -> bool {
+    x >= 0
+        && y >= 0
+        && (x as u32) < image.width
+        && (y as u32) < image.height
+        && image.get(x as u32, y as u32) < INK_THRESHOLD
+}
+
+/// `image` thresholded, with every ink pixel spread over its reach.
+fn dilate(image: &GrayImage, radius: u32, dir: EmboldenDir) -> GrayImage {
+    let reach = reach(radius, dir);
+    let mut out = GrayImage::new(image.width, image.height, 255);
+    for y in 0..image.height as i32 {
+        for x in 0..image.width as i32 {
+            if reach.iter().any(|&(dx, dy)| is_ink(image, x - dx, y - dy)) {
+                out.set(x as u32, y as u32, 0);
+            }
+        }
+    }
+    out
+}
+
+/// Thin the strokes of `image`, a glyph box, by `radius` pixels: it comes
+/// back thresholded, keeping only the ink pixels whose whole reach is ink.
+pub fn thin(image: &GrayImage, radius: u32, dir: EmboldenDir) -> GrayImage {
+    let reach = reach(radius, dir);
+    let mut out = GrayImage::new(image.width, image.height, 255);
+    for y in 0..image.height as i32 {
+        for x in 0..image.width as i32 {
+            if reach.iter().all(|&(dx, dy)| is_ink(image, x + dx, y + dy)) {
+                out.set(x as u32, y as u32, 0);
+            }
+        }
+    }
+    out
+}
+
+/// Thicken the strokes of `glyph`, a glyph box, by `radius` pixels. Ink
+/// that grows past the box is handled by `overflow`: clipped, or the
+/// grown glyph shifted back inside and shrunk if it still doesn't fit.
+/// Also returns whether anything grew past the box; for `Error` the
+/// clipped glyph comes back and the caller aborts.
+pub fn embolden(
+    glyph: &GrayImage,
+    radius: u32,
+    dir: EmboldenDir,
+    overflow: Overflow,
+) -> (GrayImage, bool) {
+    let (width, height) = (glyph.width, glyph.height);
+    let mut margin = GrayImage::new(width + 2 * radius, height + 2 * radius, 255);
+    margin.paste(glyph, radius, radius);
+    let grown = dilate(&margin, radius, dir);
+    let clipped = grown.crop(radius, radius, width, height);
+    let Some((x, y, w, h)) = grown.ink_bounds() else {
+        return (clipped, false);
+    };
+    let fits = x >= radius && y >= radius && x + w <= radius + width && y + h <= radius + height;
+    if fits || overflow != Overflow::Scale {
+        return (clipped, !fits);
+    }
+
+    let ink = grown.crop(x, y, w, h);
+    let ink = if w <= width && h <= height {
+        ink
+    } else {
+        let factor = (width as f32 / w as f32).min(height as f32 / h as f32);
+        let w = ((w as f32 * factor).round() as u32).clamp(1, width);
+        let h = ((h as f32 * factor).round() as u32).clamp(1, height);
+        // Back to pure ink and background, as the rest of the glyph is
+        ink.resize(w, h).stretch_bits(1, 1)
+    };
+    // As close to where the grown ink sat as the box allows
+    let place = |at: u32, size: u32, room: u32| at.saturating_sub(radius).min(room - size);
+    let mut out = GrayImage::new(width, height, 255);
+    out.paste(
+        &ink,
+        place(x, ink.width, width),
+        place(y, ink.height, height),
+    );
+    (out, true)
+}
@@ -0,0 +1,146 @@
+//! Hand-drawn replacements for individual glyphs, loaded from a directory of
+//! PNGs named after the index they replace (`0x01.png`, `001.png`).
+
+use anyhow::{Context, Result, bail};
+#[cfg(feature = "backend-sdl")]
+use sdl3::image::LoadSurface;
+#[cfg(feature = "backend-sdl")]
+use sdl3::surface::Surface;
+use std::path::{Path, PathBuf};
+
+use crate::gray::GrayImage;
+use crate::warning::{self, Code, Warning};
+use crate::{INK_THRESHOLD, OverrideFit, parse_index};
+
+/// A thresholded override image and the file it came from.
+pub struct Override {
+    pub path: PathBuf,
+    pub image: GrayImage,
+}
+
+/// Load every `.png` in `dir` into the slot of the index it is named after,
+/// out of `len`. Other files are ignored; a PNG whose name isn't an index,
+/// or two naming the same index, are errors.
+pub fn load(dir: &Path, len: usize) -> Result<Vec<Option<Override>>> {
+    let mut overrides: Vec<Option<Override>> = (0..len).map(|_| None).collect();
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read override directory {}", dir.display()))?;
+    let mut paths = entries
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .with_context(|| format!("Failed to read override directory {}", dir.display()))?;
+    paths.sort();
+    for path in paths {
+        if !path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        {
+            continue;
+        }
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let index = parse_index(stem, len)
+            .with_context(|| format!("Override {} is not named after an index", path.display()))?;
+        if let Some(previous) = &overrides[index] {
+            bail!(
+                "Error: {} and {} both override index {}",
+                previous.path.display(),
+                path.display(),
+                index
+            );
+        }
+        overrides[index] = Some(Override {
+            image: threshold(&read_png(&path)?),
+            path,
+        });
+    }
+    Ok(overrides)
+}
+
+/// Read the override at `path`, in any format SDL_image loads.
+#[cfg(feature = "backend-sdl")]
+fn read_png(path: &Path) -> Result<GrayImage> {
+    let surface = Surface::from_file(path)
+        .with_context(|| format!("Failed to load override {}", path.display()))?;
+    GrayImage::from_surface(&surface)
+}
+
+#[cfg(not(feature = "backend-sdl"))]
+fn read_png(path: &Path) -> Result<GrayImage> {
+    bail!(
+        "Error: can't load override {}; this build has no SDL to read it with, rebuild with --features backend-sdl",
+        path.display()
+    )
+}
+
+/// Fit an override into a `width` x `height` glyph box. `center` keeps the
+/// pixels as drawn, clipping whatever falls outside the box; `stretch`
+/// resamples to the box and thresholds again.
+pub fn fit(item: &Override, fit: OverrideFit, width: u32, height: u32) -> GrayImage {
+    let image = &item.image;
+    match fit {
+        OverrideFit::Stretch => threshold(&image.resize(width, height)),
+        OverrideFit::Center => {
+            if (image.width, image.height) != (width, height) {
+                warning::emit(
+                    Warning::new(
+                        Code::OverrideResized,
+                        format!(
+                            "override {} is {}x{}, centered in the {}x{} glyph box{}",
+                            item.path.display(),
+                            image.width,
+                            image.height,
+                            width,
+                            height,
+                            if image.width > width || image.height > height {
+                                " and clipped"
+                            } else {
+                                ""
+                            }
+                        ),
+                    )
+                    .data(serde_json::json!({
+                        "path": item.path,
+                        "width": image.width,
+                        "height": image.height,
+                        "box_width": width,
+                        "box_height": height,
+                    })),
+                );
+            }
+            // Crop an oversized image to the middle, then pad it out
+            let (crop_x, crop_y) = (
+                image.width.saturating_sub(width) / 2,
+                image.height.saturating_sub(height) / 2,
+            );
+            let cropped = image.crop(
+                crop_x,
+                crop_y,
+                image.width.min(width),
+                image.height.min(height),
+            );
+            let mut out = GrayImage::new(width, height, 255);
+            out.paste(
+                &cropped,
+                (width - cropped.width) / 2,
+                (height - cropped.height) / 2,
+            );
+            out
+        }
+    }
+}
+
+/// Reduce an image to pure ink and background with the hex dump's rule.
+fn threshold(image: &GrayImage) -> GrayImage {
+    GrayImage {
+        width: image.width,
+        height: image.height,
+        pixels: image
+            .pixels
+            .iter()
+            .map(|&v| if v < INK_THRESHOLD { 0 } else { 255 })
+            .collect(),
+    }
+}
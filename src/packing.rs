@@ -0,0 +1,304 @@
+//! Thresholding an atlas into 1-bit scanlines packed into words, the form
+//! the hex dump, the embedded Rust file, and the terminal preview share.
+
+#[cfg(feature = "backend-sdl")]
+use anyhow::Result;
+use rayon::prelude::*;
+#[cfg(feature = "backend-sdl")]
+use sdl3::surface::Surface;
+
+use crate::gray::GrayImage;
+
+/// Bits per packed word.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WordSize {
+    Bits8,
+    Bits16,
+    Bits32,
+}
+
+impl WordSize {
+    pub fn bits(self) -> u32 {
+        match self {
+            WordSize::Bits8 => 8,
+            WordSize::Bits16 => 16,
+            WordSize::Bits32 => 32,
+        }
+    }
+}
+
+/// Which end of a word holds the leftmost pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Pixel `x` is bit `x % bits`, as the shader macros expect
+    LsbFirst,
+    /// The leftmost pixel is the most significant bit, as in PSF and XBM
+    MsbFirst,
+}
+
+/// A thresholded image, one bit per pixel. Each scanline is padded with
+/// background to a whole number of words, so a pixel's word is found from
+/// `padded_width` alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedBitmap {
+    /// `padded_width / bits` words per scanline, top to bottom; only the low
+    /// `word_size` bits of each are used.
+    pub words: Vec<u32>,
+    pub word_size: WordSize,
+    pub bit_order: BitOrder,
+    pub width: u32,
+    pub height: u32,
+    /// Scanline length in bits: `width` rounded up to a whole word.
+    pub padded_width: u32,
+}
+
+impl PackedBitmap {
+    /// Whether the pixel at (`x`, `y`) is ink; false outside the image.
+    pub fn get(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let bits = self.word_size.bits();
+        let word = self.words[(y * (self.padded_width / bits) + x / bits) as usize];
+        word & self.mask(x % bits) != 0
+    }
+
+    /// The bit for the pixel `offset` places into its word.
+    fn mask(&self, offset: u32) -> u32 {
+        match self.bit_order {
+            BitOrder::LsbFirst => 1 << offset,
+            BitOrder::MsbFirst => 1 << (self.word_size.bits() - 1 - offset),
+        }
+    }
+}
+
+/// Pack `image` with pixels darker than `threshold` as set bits. Scanlines
+/// are packed in parallel on rayon's pool.
+pub fn pack_bits(
+    image: &GrayImage,
+    threshold: u8,
+    word_size: WordSize,
+    bit_order: BitOrder,
+) -> PackedBitmap {
+    let (width, height) = (image.width, image.height);
+    let bits = word_size.bits();
+    let mut packed = PackedBitmap {
+        words: Vec::new(),
+        word_size,
+        bit_order,
+        width,
+        height,
+        padded_width: width.div_ceil(bits) * bits,
+    };
+    let rows: Vec<Vec<u32>> = (0..height)
+        .into_par_iter()
+        .map(|y| {
+            let mut words = Vec::with_capacity((packed.padded_width / bits) as usize);
+            for word_start in (0..packed.padded_width).step_by(bits as usize) {
+                let mut word = 0;
+                for x in word_start..(word_start + bits).min(width) {
+                    if image.get(x, y) < threshold {
+                        word |= packed.mask(x - word_start);
+                    }
+                }
+                words.push(word);
+            }
+            words
+        })
+        .collect();
+    packed.words = rows.concat();
+    packed
+}
+
+/// Pack `surface`, in any pixel format, by the Rec. 709 luminance
+/// `GrayImage::from_surface` reads it as.
+#[cfg(feature = "backend-sdl")]
+pub fn pack_surface_bits(
+    surface: &Surface,
+    threshold: u8,
+    word_size: WordSize,
+    bit_order: BitOrder,
+) -> Result<PackedBitmap> {
+    let image = GrayImage::from_surface(surface)?;
+    Ok(pack_bits(&image, threshold, word_size, bit_order))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_single_pixel_image_packs_its_pixel() {
+        let black = GrayImage::new(1, 1, 0);
+        let white = GrayImage::new(1, 1, 255);
+        let pack =
+            |image: &GrayImage, word_size, bit_order| pack_bits(image, 128, word_size, bit_order);
+
+        let packed = pack(&black, WordSize::Bits32, BitOrder::LsbFirst);
+        assert_eq!(
+            (packed.width, packed.height, packed.padded_width),
+            (1, 1, 32)
+        );
+        assert_eq!(packed.words, [1]);
+        assert!(packed.get(0, 0));
+        assert_eq!(
+            pack(&black, WordSize::Bits8, BitOrder::MsbFirst).words,
+            [0x80]
+        );
+        assert_eq!(
+            pack(&white, WordSize::Bits32, BitOrder::LsbFirst).words,
+            [0]
+        );
+    }
+
+    #[test]
+    fn rows_pack_into_whole_words_in_either_bit_order() {
+        // 10x2: ink at (0, 0), (9, 0), and (8, 1)
+        let mut image = GrayImage::new(10, 2, 255);
+        for (x, y) in [(0, 0), (9, 0), (8, 1)] {
+            image.set(x, y, 0);
+        }
+        let cases = [
+            (
+                WordSize::Bits8,
+                BitOrder::LsbFirst,
+                16,
+                vec![0x01, 0x02, 0x00, 0x01],
+            ),
+            (
+                WordSize::Bits8,
+                BitOrder::MsbFirst,
+                16,
+                vec![0x80, 0x40, 0x00, 0x80],
+            ),
+            (
+                WordSize::Bits16,
+                BitOrder::LsbFirst,
+                16,
+                vec![0x0201, 0x0100],
+            ),
+            (
+                WordSize::Bits16,
+                BitOrder::MsbFirst,
+                16,
+                vec![0x8040, 0x0080],
+            ),
+            (
+                WordSize::Bits32,
+                BitOrder::LsbFirst,
+                32,
+                vec![0x0201, 0x0100],
+            ),
+            (
+                WordSize::Bits32,
+                BitOrder::MsbFirst,
+                32,
+                vec![0x8040_0000, 0x0080_0000],
+            ),
+        ];
+        for (word_size, bit_order, padded_width, words) in cases {
+            let packed = pack_bits(&image, 128, word_size, bit_order);
+            assert_eq!(
+                packed.padded_width, padded_width,
+                "{:?} {:?}",
+                word_size, bit_order
+            );
+            assert_eq!(packed.words, words, "{:?} {:?}", word_size, bit_order);
+            for y in 0..2 {
+                for x in 0..10 {
+                    let ink = matches!((x, y), (0, 0) | (9, 0) | (8, 1));
+                    assert_eq!(packed.get(x, y), ink, "({}, {})", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn only_pixels_darker_than_the_threshold_are_ink() {
+        let mut image = GrayImage::new(3, 1, 255);
+        for (x, value) in [(0, 127), (1, 128), (2, 129)] {
+            image.set(x, 0, value);
+        }
+        let packed = pack_bits(&image, 128, WordSize::Bits8, BitOrder::LsbFirst);
+        assert_eq!(packed.words, [0b001]);
+    }
+
+    /// Surfaces of every pixel format the renderers hand back.
+    #[cfg(feature = "backend-sdl")]
+    mod surfaces {
+        use super::*;
+        use crate::gray;
+        use sdl3::pixels::{Color, Palette, PixelFormat};
+
+        /// An 'X' of ink with gray antialiasing beside each stroke, on white.
+        fn glyph() -> GrayImage {
+            let mut glyph = GrayImage::new(8, 8, 255);
+            for i in 0..8 {
+                glyph.set(i, i, 0);
+                glyph.set(7 - i, i, 0);
+                if i + 1 < 8 && glyph.get(i + 1, i) == 255 {
+                    glyph.set(i + 1, i, 100);
+                }
+            }
+            glyph
+        }
+
+        /// `glyph` as a surface of `format`, writing `pixel(value)` for each
+        /// pixel into its bytes.
+        fn surface(
+            glyph: &GrayImage,
+            format: PixelFormat,
+            pixel: impl Fn(u8) -> Vec<u8>,
+        ) -> Surface<'static> {
+            let mut surface = Surface::new(glyph.width, glyph.height, format).unwrap();
+            let pitch = surface.pitch() as usize;
+            surface.with_lock_mut(|bytes: &mut [u8]| {
+                for y in 0..glyph.height {
+                    for x in 0..glyph.width {
+                        let bytes_of = pixel(glyph.get(x, y));
+                        let at = y as usize * pitch + x as usize * bytes_of.len();
+                        bytes[at..at + bytes_of.len()].copy_from_slice(&bytes_of);
+                    }
+                }
+            });
+            surface
+        }
+
+        #[test]
+        fn every_pixel_format_packs_the_same_glyph_alike() {
+            let glyph = glyph();
+            let rgb = surface(&glyph, PixelFormat::RGB24, |v| vec![v, v, v]);
+            // Background as transparent black, which only reads as white once
+            // composited
+            let argb = surface(&glyph, PixelFormat::ARGB8888, |v| {
+                let argb = if v == 255 {
+                    0
+                } else {
+                    0xFF00_0000 | (u32::from(v) * 0x01_0101)
+                };
+                argb.to_ne_bytes().to_vec()
+            });
+            let mut indexed = surface(&glyph, PixelFormat::INDEX8, |v| vec![v]);
+            let grays: Vec<Color> = (0..=255).map(|v| Color::RGB(v, v, v)).collect();
+            indexed
+                .set_palette(&Palette::with_colors(&grays).unwrap())
+                .unwrap();
+
+            let expected =
+                pack_surface_bits(&rgb, 128, WordSize::Bits8, BitOrder::MsbFirst).unwrap();
+            assert_eq!(expected.words[0], 0b1100_0001);
+            assert_eq!(expected.words[1], 0b0110_0010);
+            for other in [&argb, &indexed] {
+                assert_eq!(
+                    gray::to_rgb24(other).unwrap().pixel_format(),
+                    PixelFormat::RGB24
+                );
+                assert_eq!(GrayImage::from_surface(other).unwrap(), glyph);
+                assert_eq!(
+                    pack_surface_bits(other, 128, WordSize::Bits8, BitOrder::MsbFirst).unwrap(),
+                    expected
+                );
+            }
+        }
+    }
+}
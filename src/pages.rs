@@ -0,0 +1,118 @@
+//! Multi-page atlases: one taller than `--max-atlas-dimension` is split
+//! into pages of whole rows, so no glyph straddles two, saved as
+//! `name.0.png`, `name.1.png`, and so on. An atlas that fits stays a
+//! single texture, written exactly as before.
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use mycp437generator::Atlas;
+use mycp437generator::failure::{Categorize, Failure};
+use mycp437generator::gray::GrayImage;
+use mycp437generator::grid::Grid;
+
+/// The pages of an atlas, top to bottom.
+pub struct Pages {
+    /// Grid rows on each page; the last may have fewer.
+    pub rows_per_page: u32,
+    pub images: Vec<GrayImage>,
+}
+
+impl Pages {
+    /// Page of cell `index`.
+    pub fn page_of(&self, grid: &Grid, index: usize) -> u32 {
+        grid.cell(index).1 / self.rows_per_page
+    }
+
+    /// Top-left pixel of the glyph box of cell `index` on its page.
+    pub fn glyph_origin(&self, grid: &Grid, index: usize) -> (u32, u32) {
+        let (x, y) = grid.glyph_origin(index);
+        let page = self.page_of(grid, index);
+        (x, y - page * self.rows_per_page * grid.stride_y())
+    }
+
+    /// The sidecar's description of the pages.
+    pub fn layout(&self, grid: &Grid, count: usize) -> PageLayout {
+        PageLayout {
+            count: self.images.len() as u32,
+            rows_per_page: self.rows_per_page,
+            page_width: self.images[0].width,
+            page_height: self.images[0].height,
+            page: (0..count).map(|i| self.page_of(grid, i)).collect(),
+        }
+    }
+}
+
+/// How the sidecar's cells are spread over pages. Cell positions restart
+/// at the top of each page.
+#[derive(Serialize)]
+pub struct PageLayout {
+    pub count: u32,
+    pub rows_per_page: u32,
+    /// Size of every page's texture; the last page's grid may be shorter.
+    pub page_width: u32,
+    pub page_height: u32,
+    /// The page of each cell.
+    pub page: Vec<u32>,
+}
+
+/// Split `atlas` into pages no taller than `limit`, each padded to powers
+/// of two with `pot`, or `None` when it fits in one texture.
+pub fn split(atlas: &Atlas, limit: u32, pot: bool) -> Result<Option<Pages>> {
+    let grid = &atlas.grid;
+    let texture_height = |height: u32| {
+        if pot {
+            height.next_power_of_two()
+        } else {
+            height
+        }
+    };
+    if texture_height(atlas.content_height) <= limit {
+        return Ok(None);
+    }
+    let stride = grid.stride_y();
+    let rows_per_page = (1..=grid.rows)
+        .take_while(|&rows| texture_height(rows * stride) <= limit)
+        .last()
+        .ok_or_else(|| {
+            anyhow!(
+                "Error: a single {}px row of cells is over the {}px --max-atlas-dimension",
+                stride,
+                limit
+            )
+        })
+        .failure(Failure::Sizing)?;
+    let images = (0..grid.rows)
+        .step_by(rows_per_page as usize)
+        .map(|first| {
+            let rows = rows_per_page.min(grid.rows - first);
+            let content = atlas
+                .image
+                .crop(0, first * stride, atlas.content_width, rows * stride);
+            let mut page = GrayImage::new(
+                atlas.image.width,
+                texture_height(rows_per_page * stride),
+                255,
+            );
+            page.paste(&content, 0, 0);
+            page
+        })
+        .collect();
+    Ok(Some(Pages {
+        rows_per_page,
+        images,
+    }))
+}
+
+/// `path` with the page number before its extension: `atlas.png` becomes
+/// `atlas.0.png`.
+pub fn page_path(path: &Path, page: usize) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", page));
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
@@ -0,0 +1,171 @@
+//! Scale2x and Scale3x (the EPX family) over a 1-bit ink mask. They only
+//! ever copy existing pixels, so the result stays strictly black and white,
+//! which the packed hex dump relies on.
+
+use crate::gray::GrayImage;
+
+/// A thresholded atlas: `true` is ink.
+pub struct Mask {
+    width: u32,
+    height: u32,
+    bits: Vec<bool>,
+}
+
+impl Mask {
+    /// Threshold at mid-gray, the same rule the hex dump packs with.
+    pub fn from_gray(image: &GrayImage) -> Mask {
+        Mask {
+            width: image.width,
+            height: image.height,
+            bits: image.pixels.iter().map(|&v| v < 128).collect(),
+        }
+    }
+
+    pub fn to_gray(&self) -> GrayImage {
+        GrayImage {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .bits
+                .iter()
+                .map(|&ink| if ink { 0 } else { 255 })
+                .collect(),
+        }
+    }
+
+    /// Pixel at `(x, y)`, with coordinates outside the mask clamped to the
+    /// nearest edge, as the reference implementation does.
+    fn at(&self, x: i64, y: i64) -> bool {
+        let x = x.clamp(0, self.width as i64 - 1) as u32;
+        let y = y.clamp(0, self.height as i64 - 1) as u32;
+        self.bits[(y * self.width + x) as usize]
+    }
+
+    /// The 3x3 neighbourhood of `(x, y)` in reading order.
+    fn neighbourhood(&self, x: u32, y: u32) -> [bool; 9] {
+        let (x, y) = (x as i64, y as i64);
+        let mut out = [false; 9];
+        for (n, slot) in out.iter_mut().enumerate() {
+            *slot = self.at(x + n as i64 % 3 - 1, y + n as i64 / 3 - 1);
+        }
+        out
+    }
+
+    /// Expand every pixel into a `factor` x `factor` block chosen by `kernel`.
+    fn expand(&self, factor: u32, kernel: impl Fn([bool; 9]) -> Vec<bool>) -> Mask {
+        let width = self.width * factor;
+        let mut bits = vec![false; (width * self.height * factor) as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let block = kernel(self.neighbourhood(x, y));
+                for (n, &ink) in block.iter().enumerate() {
+                    let ox = x * factor + n as u32 % factor;
+                    let oy = y * factor + n as u32 / factor;
+                    bits[(oy * width + ox) as usize] = ink;
+                }
+            }
+        }
+        Mask {
+            width,
+            height: self.height * factor,
+            bits,
+        }
+    }
+
+    /// Double the size with Scale2x.
+    pub fn scale2x(&self) -> Mask {
+        self.expand(2, |[_, b, _, d, e, f, _, h, _]| {
+            if b != h && d != f {
+                vec![
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                vec![e; 4]
+            }
+        })
+    }
+
+    /// Triple the size with Scale3x.
+    pub fn scale3x(&self) -> Mask {
+        self.expand(3, |[a, b, c, d, e, f, g, h, i]| {
+            if b != h && d != f {
+                vec![
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) {
+                        b
+                    } else {
+                        e
+                    },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) {
+                        d
+                    } else {
+                        e
+                    },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) {
+                        f
+                    } else {
+                        e
+                    },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) {
+                        h
+                    } else {
+                        e
+                    },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                vec![e; 9]
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mask drawn as whitespace-separated rows of `#` for ink and `.`
+    /// for background.
+    fn mask(drawing: &str) -> Mask {
+        let rows: Vec<&str> = drawing.split_whitespace().collect();
+        Mask {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            bits: rows
+                .iter()
+                .flat_map(|row| row.chars().map(|c| c == '#'))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn scale2x_rounds_the_inside_of_an_l_corner() {
+        let l = mask(
+            "#...
+             #...
+             #...
+             ####",
+        );
+        // As the reference Scale2x draws it: the only pixel added fills
+        // the inner corner, and the outer corner stays square
+        let expected = mask(
+            "##......
+             ##......
+             ##......
+             ##......
+             ##......
+             ###.....
+             ########
+             ########",
+        );
+        let scaled = l.scale2x();
+        assert_eq!((scaled.width, scaled.height), (8, 8));
+        assert_eq!(scaled.bits, expected.bits);
+    }
+}
@@ -0,0 +1,49 @@
+//! `--preset`: named bundles of option defaults for a target program. A
+//! preset sits under the config file and the command line, so anything
+//! given either way still wins.
+
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+
+/// A target selected with `--preset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Dwarf Fortress and REXPaint tilesets: white unantialiased glyphs on
+    /// magenta, saved as `<width>x<height>.png`
+    Df,
+    /// libtcod fonts: the TCOD layout in 32 columns, white on black so the
+    /// brightness reads as alpha
+    Tcod,
+}
+
+impl Preset {
+    /// Name as accepted by `--preset`, for error messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Df => "df",
+            Preset::Tcod => "tcod",
+        }
+    }
+
+    /// The options the preset sets, keyed like a config file.
+    pub fn options(self) -> Map<String, Value> {
+        let options = match self {
+            Preset::Df => json!({
+                "render_mode": "solid",
+                "foreground": "ffffff",
+                "background": "ff00ff",
+                "output_naming": "size",
+            }),
+            Preset::Tcod => json!({
+                "layout": "tcod",
+                "columns": 32,
+                "foreground": "ffffff",
+                "background": "000000",
+            }),
+        };
+        let Value::Object(options) = options else {
+            unreachable!("presets are objects");
+        };
+        options
+    }
+}
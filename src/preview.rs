@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use sdl3::Sdl;
+use sdl3::event::Event;
+use sdl3::keyboard::Keycode;
+use sdl3::pixels::Color;
+use sdl3::render::{FRect, ScaleMode};
+use sdl3::surface::Surface;
+
+use crate::grid::Grid;
+
+const MAX_ZOOM: u32 = 4;
+
+/// Show `atlas` in a window until Esc or q. `z` (or +/-) steps the zoom
+/// between 1x and 4x and `g` toggles a cell grid overlay. Only called for
+/// `--preview`, so headless runs never touch the video subsystem.
+pub fn show(sdl: &Sdl, atlas: &Surface, grid: &Grid) -> Result<()> {
+    let video = sdl
+        .video()
+        .context("Failed to initialize video (is a display available?)")?;
+    let mut zoom = 1;
+    let mut show_grid = false;
+    let window = video
+        .window("mycp437generator preview", atlas.width(), atlas.height())
+        .position_centered()
+        .build()
+        .context("Failed to open preview window")?;
+    let mut canvas = window.into_canvas();
+    let texture_creator = canvas.texture_creator();
+    let mut texture = texture_creator
+        .create_texture_from_surface(atlas)
+        .context("Failed to upload atlas texture")?;
+    // Keep pixels crisp when zoomed
+    texture.set_scale_mode(ScaleMode::Nearest);
+    let mut events = sdl.event_pump().context("Failed to get event pump")?;
+
+    loop {
+        let (width, height) = (atlas.width() * zoom, atlas.height() * zoom);
+        canvas.set_draw_color(Color::RGB(255, 255, 255));
+        canvas.clear();
+        canvas
+            .copy(
+                &texture,
+                None,
+                FRect::new(0.0, 0.0, width as f32, height as f32),
+            )
+            .context("Failed to draw atlas")?;
+        if show_grid {
+            canvas.set_draw_color(Color::RGB(255, 0, 255));
+            let (step_x, step_y) = (grid.stride_x() * zoom, grid.stride_y() * zoom);
+            for col in 1..grid.columns {
+                let x = (col * step_x) as f32;
+                canvas
+                    .fill_rect(FRect::new(x, 0.0, 1.0, (grid.rows * step_y) as f32))
+                    .context("Failed to draw grid")?;
+            }
+            for row in 1..grid.rows {
+                let y = (row * step_y) as f32;
+                canvas
+                    .fill_rect(FRect::new(0.0, y, (grid.columns * step_x) as f32, 1.0))
+                    .context("Failed to draw grid")?;
+            }
+        }
+        canvas.present();
+
+        let new_zoom = match events.wait_event() {
+            Event::Quit { .. } => return Ok(()),
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => match key {
+                Keycode::Escape | Keycode::Q => return Ok(()),
+                Keycode::G => {
+                    show_grid = !show_grid;
+                    zoom
+                }
+                Keycode::Z => zoom % MAX_ZOOM + 1,
+                Keycode::Plus | Keycode::Equals => (zoom + 1).min(MAX_ZOOM),
+                Keycode::Minus => (zoom - 1).max(1),
+                _ => zoom,
+            },
+            _ => zoom,
+        };
+        if new_zoom != zoom {
+            zoom = new_zoom;
+            canvas
+                .window_mut()
+                .set_size(atlas.width() * zoom, atlas.height() * zoom)
+                .context("Failed to resize preview window")?;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! The footer text outputs end with, recording what produced them: the
+//! tool, the font and its hash, every option, and a checksum of the packed
+//! atlas, so a pasted blob can be traced back to its run.
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::{Args, config};
+use mycp437generator::log;
+
+/// How a format marks comments.
+#[derive(Clone, Copy)]
+pub enum Comment {
+    /// A marker in front of every line
+    Line(&'static str),
+    /// An opening and a closing delimiter around the whole footer
+    Block(&'static str, &'static str),
+}
+
+/// The provenance of one run.
+pub struct Provenance {
+    lines: Vec<String>,
+}
+
+impl Provenance {
+    /// Describe a run of `args` on `font` with the `charset` table, whose
+    /// atlas packs into `words`. The checksum is also printed, so a file
+    /// can be checked against a fresh run without diffing.
+    pub fn new(args: &Args, font: &Path, charset: &str, words: &[u32]) -> Result<Provenance> {
+        let sha256 = match std::fs::read(font) {
+            Ok(bytes) => format!("{:x}", Sha256::digest(&bytes)),
+            Err(_) => "unavailable".to_string(),
+        };
+        let mut hasher = crc32fast::Hasher::new();
+        for word in words {
+            hasher.update(&word.to_le_bytes());
+        }
+        let crc = hasher.finalize();
+        log!("Packed atlas CRC32: {:08x}", crc);
+
+        let mut lines = vec![
+            format!(
+                "Generated by {} {}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ),
+            format!(
+                "Font: {} (SHA-256 {})",
+                font.file_name().map_or_else(
+                    || font.display().to_string(),
+                    |n| n.to_string_lossy().into_owned()
+                ),
+                sha256
+            ),
+            format!("Character table: {}", charset),
+            format!(
+                "CRC32 of the packed atlas (32-bit LSB-first words, little-endian): {:08x}",
+                crc
+            ),
+            "Options:".to_string(),
+        ];
+        lines.extend(
+            config::to_toml(args)?
+                .lines()
+                .map(|line| format!("  {}", line)),
+        );
+        Ok(Provenance { lines })
+    }
+
+    /// The footer as `comment`s, after a blank line.
+    pub fn footer(&self, comment: Comment) -> String {
+        let mut out = String::from("\n");
+        match comment {
+            Comment::Line(marker) => {
+                for line in &self.lines {
+                    out.push_str(format!("{} {}", marker, line).trim_end());
+                    out.push('\n');
+                }
+            }
+            Comment::Block(open, close) => {
+                out.push_str(open);
+                out.push('\n');
+                for line in &self.lines {
+                    // Keep option values from ending the comment early
+                    out.push_str(&line.replace(close, ""));
+                    out.push('\n');
+                }
+                out.push_str(close);
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,152 @@
+//! Reading Linux console fonts in the PC Screen Font formats, PSF1 and PSF2.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::bitmap_font::BitmapFont;
+use crate::failure::{Categorize, Failure};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE512: u8 = 0x01;
+const PSF1_MODEHASTAB: u8 = 0x02;
+const PSF1_MODESEQ: u8 = 0x04;
+/// Ends a glyph's entry in the PSF1 Unicode table.
+const PSF1_SEPARATOR: u16 = 0xFFFF;
+/// Starts a combining sequence in the PSF1 Unicode table.
+const PSF1_STARTSEQ: u16 = 0xFFFE;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xFF;
+const PSF2_STARTSEQ: u8 = 0xFE;
+
+/// Read the PSF1 or PSF2 font at `path`.
+pub fn read_psf(path: &Path) -> Result<BitmapFont> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .failure(Failure::Font)?;
+    parse_psf(&bytes)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+        .failure(Failure::Font)
+}
+
+/// Parse a PSF1 or PSF2 font. Combining sequences in the Unicode table are
+/// skipped; only the single characters a glyph draws are kept.
+pub fn parse_psf(bytes: &[u8]) -> Result<BitmapFont> {
+    if bytes.starts_with(&PSF1_MAGIC) {
+        parse_psf1(bytes)
+    } else if bytes.starts_with(&PSF2_MAGIC) {
+        parse_psf2(bytes)
+    } else {
+        bail!("not a PSF font (bad magic number)");
+    }
+}
+
+fn parse_psf1(bytes: &[u8]) -> Result<BitmapFont> {
+    let (mode, height) = match bytes.get(2..4) {
+        Some(&[mode, height]) => (mode, height as u32),
+        _ => bail!("truncated PSF1 header"),
+    };
+    if height == 0 {
+        bail!("PSF1 glyphs are 0 pixels tall");
+    }
+    let count = if mode & PSF1_MODE512 != 0 { 512 } else { 256 };
+    let glyph_bytes = height as usize;
+    let data = glyph_data(bytes, 4, count, glyph_bytes)?;
+    let glyphs = data
+        .chunks(glyph_bytes)
+        .map(|rows| BitmapFont::unpack_glyph(rows, 8, height))
+        .collect();
+
+    let mut unicode = Vec::new();
+    if mode & (PSF1_MODEHASTAB | PSF1_MODESEQ) != 0 {
+        let table: Vec<u16> = bytes[4 + data.len()..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        let mut entries = table.split(|&v| v == PSF1_SEPARATOR);
+        for _ in 0..count {
+            let entry = entries.next().unwrap_or_default();
+            let singles = entry
+                .split(|&v| v == PSF1_STARTSEQ)
+                .next()
+                .unwrap_or_default();
+            unicode.push(
+                singles
+                    .iter()
+                    .filter_map(|&v| char::from_u32(v as u32))
+                    .collect(),
+            );
+        }
+    }
+    Ok(BitmapFont {
+        width: 8,
+        height,
+        baseline: None,
+        glyphs,
+        unicode,
+    })
+}
+
+fn parse_psf2(bytes: &[u8]) -> Result<BitmapFont> {
+    let field = |n: usize| -> Result<u32> {
+        let word = bytes
+            .get(4 + n * 4..8 + n * 4)
+            .context("truncated PSF2 header")?;
+        Ok(u32::from_le_bytes(word.try_into().expect("four bytes")))
+    };
+    let (header_size, flags, count) = (field(1)? as usize, field(2)?, field(3)? as usize);
+    let (glyph_bytes, height, width) = (field(4)? as usize, field(5)?, field(6)?);
+    if width == 0 || height == 0 {
+        bail!("PSF2 glyphs are {}x{}", width, height);
+    }
+    if glyph_bytes < width.div_ceil(8) as usize * height as usize {
+        bail!(
+            "PSF2 glyphs of {} bytes can't hold {}x{} pixels",
+            glyph_bytes,
+            width,
+            height
+        );
+    }
+    let data = glyph_data(bytes, header_size, count, glyph_bytes)?;
+    let glyphs = data
+        .chunks(glyph_bytes)
+        .map(|rows| BitmapFont::unpack_glyph(rows, width, height))
+        .collect();
+
+    let mut unicode = Vec::new();
+    if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+        let mut entries = bytes[header_size + data.len()..].split(|&b| b == PSF2_SEPARATOR);
+        for _ in 0..count {
+            let entry = entries.next().unwrap_or_default();
+            let singles = entry
+                .split(|&b| b == PSF2_STARTSEQ)
+                .next()
+                .unwrap_or_default();
+            let text = String::from_utf8_lossy(singles);
+            unicode.push(text.chars().filter(|&c| c != '\u{FFFD}').collect());
+        }
+    }
+    Ok(BitmapFont {
+        width,
+        height,
+        baseline: None,
+        glyphs,
+        unicode,
+    })
+}
+
+/// The `count` glyphs of `glyph_bytes` each that start at `offset`.
+fn glyph_data(bytes: &[u8], offset: usize, count: usize, glyph_bytes: usize) -> Result<&[u8]> {
+    let len = count
+        .checked_mul(glyph_bytes)
+        .context("PSF glyph table size overflows")?;
+    bytes
+        .get(offset..offset.saturating_add(len))
+        .with_context(|| {
+            format!(
+                "file ends before its {} glyphs of {} bytes",
+                count, glyph_bytes
+            )
+        })
+}
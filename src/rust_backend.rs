@@ -0,0 +1,154 @@
+//! `--backend rust`: glyphs measured and rasterized by ab_glyph, and images
+//! written by the png crate, with no SDL involved.
+
+use ab_glyph::{Font, FontVec, GlyphId, PxScale, ScaleFont, VariableFont, point};
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::backend::{Backend, GlyphBox, LineMetrics, Raster};
+use crate::failure::{Categorize, Failure};
+use crate::gray::GrayImage;
+use crate::{INK_THRESHOLD, RenderMode, parse_variation};
+
+pub struct RustBackend {
+    font: FontVec,
+    render_mode: RenderMode,
+}
+
+impl RustBackend {
+    /// Load face `index` of the font file at `path`, with its variation
+    /// axes set by `variations`, `--variation` specs such as `wght=550`,
+    /// to draw glyphs as `render_mode` asks.
+    pub fn open(
+        path: &Path,
+        index: u32,
+        variations: &[String],
+        render_mode: RenderMode,
+    ) -> Result<RustBackend> {
+        if render_mode == RenderMode::Lcd {
+            bail!("Error: --render-mode lcd needs the SDL backend");
+        }
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .failure(Failure::Font)?;
+        let mut font = FontVec::try_from_vec_and_index(data, index)
+            .with_context(|| format!("Failed to parse font {}", path.display()))
+            .failure(Failure::Font)?;
+        for spec in variations {
+            let (tag, value) = parse_variation(spec)?;
+            if !font.set_variation(&tag, value) {
+                let axes: Vec<String> = font
+                    .variations()
+                    .iter()
+                    .map(|axis| {
+                        format!(
+                            "{} ({} to {})",
+                            String::from_utf8_lossy(&axis.tag).trim_end(),
+                            axis.min_value,
+                            axis.max_value
+                        )
+                    })
+                    .collect();
+                bail!(
+                    "Error: {} has no '{}' axis for --variation; {}",
+                    path.display(),
+                    String::from_utf8_lossy(&tag).trim_end(),
+                    if axes.is_empty() {
+                        "it isn't a variable font".to_string()
+                    } else {
+                        format!("its axes are {}", axes.join(", "))
+                    }
+                );
+            }
+        }
+        Ok(RustBackend { font, render_mode })
+    }
+
+    /// ab_glyph scales by line height; SDL_ttf's point sizes are pixels
+    /// per em at 72 DPI.
+    fn scale(&self, size: f32) -> PxScale {
+        let units_per_em = self.font.units_per_em().unwrap_or(1000.0);
+        PxScale::from(size * self.font.height_unscaled() / units_per_em)
+    }
+
+    fn glyph_id(&self, ch: char) -> Option<GlyphId> {
+        Some(self.font.glyph_id(ch)).filter(|id| id.0 != 0)
+    }
+}
+
+impl Backend for RustBackend {
+    fn line_metrics(&self, size: f32) -> Result<LineMetrics> {
+        let font = self.font.as_scaled(self.scale(size));
+        Ok(LineMetrics {
+            ascent: font.ascent().round() as i32,
+            descent: font.descent().round() as i32,
+        })
+    }
+
+    fn measure_glyph(&self, ch: char, size: f32) -> Result<Option<GlyphBox>> {
+        let Some(id) = self.glyph_id(ch) else {
+            return Ok(None);
+        };
+        let scale = self.scale(size);
+        let advance = self.font.as_scaled(scale).h_advance(id).round() as i32;
+        let glyph = id.with_scale_and_position(scale, point(0.0, 0.0));
+        Ok(Some(match self.font.outline_glyph(glyph) {
+            Some(outline) => {
+                let bounds = outline.px_bounds();
+                GlyphBox {
+                    minx: bounds.min.x as i32,
+                    maxx: bounds.max.x as i32,
+                    miny: -bounds.max.y as i32,
+                    maxy: -bounds.min.y as i32,
+                    advance,
+                }
+            }
+            None => GlyphBox {
+                minx: 0,
+                maxx: 0,
+                miny: 0,
+                maxy: 0,
+                advance,
+            },
+        }))
+    }
+
+    /// The image covers the glyph's ink box; a glyph with no outline, such
+    /// as a space, draws an empty one.
+    fn rasterize_glyph(&self, ch: char, size: f32) -> Result<Option<Raster>> {
+        let Some(id) = self.glyph_id(ch) else {
+            return Ok(None);
+        };
+        let glyph = id.with_scale_and_position(self.scale(size), point(0.0, 0.0));
+        let Some(outline) = self.font.outline_glyph(glyph) else {
+            return Ok(Some(Raster {
+                image: GrayImage::new(0, 0, 255),
+                subpixel: None,
+                left: 0,
+                top: 0,
+            }));
+        };
+        let bounds = outline.px_bounds();
+        let mut image = GrayImage::new(bounds.width() as u32, bounds.height() as u32, 255);
+        outline.draw(|x, y, coverage| {
+            if x < image.width && y < image.height {
+                let mut value = 255 - (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                // Solid glyphs are all ink or none, as SDL_ttf draws them
+                if self.render_mode == RenderMode::Solid {
+                    value = if value < INK_THRESHOLD { 0 } else { 255 };
+                }
+                image.set(x, y, value);
+            }
+        });
+        Ok(Some(Raster {
+            image,
+            subpixel: None,
+            left: bounds.min.x as i32,
+            top: bounds.min.y as i32,
+        }))
+    }
+
+    fn save_image(&self, image: &GrayImage, path: &Path) -> Result<()> {
+        image.save_png(path)
+    }
+}
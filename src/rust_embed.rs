@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::grid::Grid;
+
+/// Header bit marking a run: the next word repeats `header & COUNT_MASK` times.
+/// Without it, the next `header` words are copied literally.
+const RUN_FLAG: u32 = 0x8000_0000;
+const COUNT_MASK: u32 = 0x7FFF_FFFF;
+/// Shortest run worth encoding as a repeat instead of literals.
+const MIN_RUN: usize = 3;
+
+/// Run-length encode packed atlas words. The atlas is mostly background, so
+/// long runs of zero words collapse to two words each.
+pub fn compress(words: &[u32]) -> Vec<u32> {
+    compress_runs(words, COUNT_MASK as usize)
+}
+
+/// `compress`, splitting runs longer than `max_run` into several.
+fn compress_runs(words: &[u32], max_run: usize) -> Vec<u32> {
+    let mut out = Vec::new();
+    let mut literal_start = 0;
+    let mut i = 0;
+    while i < words.len() {
+        let run = words[i..]
+            .iter()
+            .take(max_run)
+            .take_while(|&&w| w == words[i])
+            .count();
+        if run >= MIN_RUN {
+            flush_literals(&mut out, &words[literal_start..i]);
+            out.push(RUN_FLAG | run as u32);
+            out.push(words[i]);
+            i += run;
+            literal_start = i;
+        } else {
+            i += run;
+        }
+    }
+    flush_literals(&mut out, &words[literal_start..]);
+    out
+}
+
+fn flush_literals(out: &mut Vec<u32>, literals: &[u32]) {
+    if !literals.is_empty() {
+        out.push(literals.len() as u32);
+        out.extend_from_slice(literals);
+    }
+}
+
+/// The decoder emitted into the generated file. It is written against
+/// `core` + `alloc` only, so it works in `no_std` game crates.
+const DECODER: &str = r#"/// Expand `PACKED` back into the atlas words (`WORD_COUNT` of them,
+/// `MAP_WIDTH / 32` per scanline, bit i = pixel x % 32 == i).
+pub fn decompress() -> Vec<u32> {
+    let mut out = Vec::with_capacity(WORD_COUNT);
+    let mut i = 0;
+    while i < PACKED.len() {
+        let header = PACKED[i];
+        let count = (header & 0x7FFF_FFFF) as usize;
+        if header & 0x8000_0000 != 0 {
+            out.resize(out.len() + count, PACKED[i + 1]);
+            i += 2;
+        } else {
+            out.extend_from_slice(&PACKED[i + 1..i + 1 + count]);
+            i += 1 + count;
+        }
+    }
+    out
+}
+"#;
+
+/// Geometry written alongside the data as constants.
+pub struct EmbeddedAtlas<'a> {
+    pub words: &'a [u32],
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub map_width: u32,
+    pub grid: &'a Grid,
+    /// Appended after the decoder, such as the provenance footer.
+    pub footer: &'a str,
+}
+
+/// Write a self-contained `.rs` file holding the compressed atlas, its
+/// dimensions, and a `decompress()` that restores the packed words.
+pub fn write_rust_embedded(path: &Path, atlas: &EmbeddedAtlas) -> Result<()> {
+    let src = embedded_source(atlas, &compress(atlas.words));
+    std::fs::write(path, src).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The generated file for `atlas`, with `packed` as its compressed words.
+fn embedded_source(atlas: &EmbeddedAtlas, packed: &[u32]) -> String {
+    let mut src = String::new();
+    writeln!(src, "// Generated by mycp437generator. Do not edit.").unwrap();
+    writeln!(
+        src,
+        "// {} atlas words compressed to {}.",
+        atlas.words.len(),
+        packed.len()
+    )
+    .unwrap();
+    writeln!(src).unwrap();
+    writeln!(src, "extern crate alloc;").unwrap();
+    writeln!(src, "use alloc::vec::Vec;").unwrap();
+    writeln!(src).unwrap();
+    for (name, value) in [
+        ("CELL_WIDTH", atlas.grid.stride_x()),
+        ("CELL_HEIGHT", atlas.grid.stride_y()),
+        ("GLYPH_WIDTH", atlas.grid.glyph_width),
+        ("GLYPH_HEIGHT", atlas.grid.glyph_height),
+        ("PADDING", atlas.grid.padding),
+        ("GRID_COLUMNS", atlas.grid.columns),
+        ("GRID_ROWS", atlas.grid.rows),
+        ("ATLAS_WIDTH", atlas.atlas_width),
+        ("ATLAS_HEIGHT", atlas.atlas_height),
+        ("MAP_WIDTH", atlas.map_width),
+    ] {
+        writeln!(src, "pub const {}: u32 = {};", name, value).unwrap();
+    }
+    writeln!(
+        src,
+        "pub const COLUMN_MAJOR: bool = {};",
+        atlas.grid.column_major
+    )
+    .unwrap();
+    writeln!(src, "pub const WORD_COUNT: usize = {};", atlas.words.len()).unwrap();
+    writeln!(src).unwrap();
+    writeln!(src, "static PACKED: [u32; {}] = [", packed.len()).unwrap();
+    for line in packed.chunks(8) {
+        let words: Vec<String> = line.iter().map(|w| format!("0x{:08X},", w)).collect();
+        writeln!(src, "    {}", words.join(" ")).unwrap();
+    }
+    writeln!(src, "];").unwrap();
+    writeln!(src).unwrap();
+    src.push_str(DECODER);
+    src.push_str(atlas.footer);
+    src
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    /// Build the generated file for `words`, compressed as `packed`, into a
+    /// program that prints what its `decompress()` returns, and run it.
+    fn decompressed(words: &[u32], packed: &[u32]) -> Vec<u32> {
+        let dir = tempfile::tempdir().unwrap();
+        let grid = Grid::new(256, 16, false, 8, 16);
+        let source = embedded_source(
+            &EmbeddedAtlas {
+                words,
+                atlas_width: 128,
+                atlas_height: 256,
+                map_width: 128,
+                grid: &grid,
+                footer: "",
+            },
+            packed,
+        );
+        std::fs::write(dir.path().join("atlas.rs"), source).unwrap();
+        std::fs::write(
+            dir.path().join("main.rs"),
+            "mod atlas {\n    include!(\"atlas.rs\");\n}\n\n\
+             fn main() {\n    for word in atlas::decompress() {\n        println!(\"{}\", word);\n    }\n}\n",
+        )
+        .unwrap();
+        // The decoder must also build without std, as the docs promise
+        std::fs::write(
+            dir.path().join("lib.rs"),
+            "#![no_std]\ninclude!(\"atlas.rs\");\n",
+        )
+        .unwrap();
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        for (input, args) in [
+            ("lib.rs", &["--crate-type", "lib"][..]),
+            ("main.rs", &["-o", "decompress"][..]),
+        ] {
+            let status = Command::new(&rustc)
+                .args(["--edition", "2021", "-A", "warnings"])
+                .arg(input)
+                .args(args)
+                .current_dir(dir.path())
+                .status()
+                .unwrap();
+            assert!(status.success(), "{} didn't build", input);
+        }
+        let output = Command::new(dir.path().join("decompress"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| line.parse().unwrap())
+            .collect()
+    }
+
+    fn round_trip(words: &[u32]) {
+        let packed = compress(words);
+        assert_eq!(decompressed(words, &packed), words);
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(compress(&[]).is_empty());
+        round_trip(&[]);
+    }
+
+    #[test]
+    fn input_without_runs() {
+        let words: Vec<u32> = (0..20).collect();
+        assert_eq!(compress(&words).len(), words.len() + 1);
+        round_trip(&words);
+    }
+
+    #[test]
+    fn runs_and_literals() {
+        let mut words = vec![0; 100];
+        words.extend([1, 2, 2, 3, 0xFFFF_FFFF, 0xFFFF_FFFF, 0xFFFF_FFFF]);
+        words.extend([7; 3]);
+        round_trip(&words);
+    }
+
+    #[test]
+    fn runs_longer_than_the_longest_run() {
+        let mut words = vec![5; 11];
+        words.push(6);
+        let packed = compress_runs(&words, 4);
+        // 4 + 4 + 3 repeats, then the literal
+        assert_eq!(
+            packed,
+            [RUN_FLAG | 4, 5, RUN_FLAG | 4, 5, RUN_FLAG | 3, 5, 1, 6]
+        );
+        assert_eq!(decompressed(&words, &packed), words);
+    }
+}
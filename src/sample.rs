@@ -0,0 +1,38 @@
+use anyhow::{Result, bail};
+
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+
+/// Lay out `lines` of atlas indices the way a consumer would draw them:
+/// glyph boxes side by side and lines `glyph_height` apart, leaving out the
+/// padding and gutter so gaps between box-drawing glyphs show up as-is.
+pub fn compose(
+    atlas: &GrayImage,
+    grid: &Grid,
+    count: usize,
+    lines: &[Vec<u8>],
+) -> Result<GrayImage> {
+    let longest = lines.iter().map(Vec::len).max().unwrap_or(0).max(1) as u32;
+    let (glyph_width, glyph_height) = (grid.glyph_width, grid.glyph_height);
+    let mut out = GrayImage::new(
+        longest * glyph_width,
+        lines.len().max(1) as u32 * glyph_height,
+        255,
+    );
+    for (row, line) in lines.iter().enumerate() {
+        for (col, &index) in line.iter().enumerate() {
+            let index = index as usize;
+            if index >= count {
+                bail!(
+                    "Sample text uses index {} but the atlas has {} cells",
+                    index,
+                    count
+                );
+            }
+            let (x, y) = grid.glyph_origin(index);
+            let glyph = atlas.crop(x, y, glyph_width, glyph_height);
+            out.paste(&glyph, col as u32 * glyph_width, row as u32 * glyph_height);
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,201 @@
+//! Signed distance fields for `--sdf`. The atlas is rendered `factor`
+//! times larger, each cell's exact Euclidean distance transform is taken
+//! at that size, and the field is averaged back down into the final cell.
+
+use rayon::prelude::*;
+
+use crate::backend::LineMetrics;
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+use crate::{Atlas, GlyphInfo, INK_THRESHOLD, Placement};
+
+/// Stand-in for "no feature pixel yet" in the squared distance passes.
+const FAR: f64 = 1e20;
+
+/// Shrink `atlas`, rendered with every measurement multiplied by `factor`,
+/// to a distance field atlas at its intended size. Each pixel holds the
+/// signed distance to the glyph's edge, clamped to `spread` final pixels,
+/// in the atlas's own brightness convention: 0 is `spread` or more inside
+/// the ink, 255 that far outside, and the edge falls on `INK_THRESHOLD`.
+/// Cells are transformed separately, so neighbours never affect each other.
+pub fn distance_field(atlas: Atlas, factor: u32, spread: u32) -> Atlas {
+    let hi = atlas.grid;
+    // The glyph box width is a multiple of the factor by construction; its
+    // height comes from the font, so a final partial row is rounded up
+    let grid = Grid {
+        glyph_width: hi.glyph_width / factor,
+        glyph_height: hi.glyph_height.div_ceil(factor),
+        padding: hi.padding / factor,
+        gutter: hi.gutter / factor,
+        ..hi
+    };
+    let limit = (spread * factor) as f64;
+    // Cells are independent, so they are transformed in parallel
+    let cells: Vec<GrayImage> = (0..atlas.glyphs.len())
+        .into_par_iter()
+        .map(|index| {
+            let (hi_x, hi_y) = hi.origin(index);
+            let cell = atlas
+                .image
+                .crop(hi_x, hi_y, hi.cell_width(), hi.cell_height());
+            shrink_field(&cell, grid.cell_width(), grid.cell_height(), factor, limit)
+        })
+        .collect();
+    let mut image = GrayImage::new(grid.width(), grid.height(), 255);
+    for (index, cell) in cells.iter().enumerate() {
+        let (x, y) = grid.origin(index);
+        image.paste(cell, x, y);
+    }
+
+    let shrink = |v: i32| v.div_euclid(factor as i32);
+    let glyphs = atlas
+        .glyphs
+        .into_iter()
+        .enumerate()
+        .map(|(i, glyph)| {
+            let (x, y) = grid.glyph_origin(i);
+            GlyphInfo {
+                rect: (x, y, grid.glyph_width, grid.glyph_height),
+                placement: glyph.placement.map(|p| Placement {
+                    minx: shrink(p.minx),
+                    maxx: shrink(p.maxx),
+                    miny: shrink(p.miny),
+                    maxy: shrink(p.maxy),
+                    advance: shrink(p.advance),
+                    pen_x: shrink(p.pen_x),
+                    x_offset: shrink(p.x_offset),
+                    y_offset: shrink(p.y_offset),
+                    surface_width: p.surface_width.div_ceil(factor),
+                    surface_height: p.surface_height.div_ceil(factor),
+                }),
+                ..glyph
+            }
+        })
+        .collect();
+    Atlas {
+        image,
+        grid,
+        content_width: grid.width(),
+        content_height: grid.height(),
+        font_size: atlas.font_size / factor as f32,
+        baseline: atlas.baseline / factor,
+        line: atlas.line.map(|line| LineMetrics {
+            ascent: shrink(line.ascent),
+            descent: shrink(line.descent),
+        }),
+        width_fit: atlas
+            .width_fit
+            .map(|(widest, requested)| (widest / factor, requested / factor)),
+        glyphs,
+        coverage: atlas.coverage,
+        cache_stats: atlas.cache_stats,
+        subpixel: None,
+    }
+}
+
+/// The signed distance field of `cell`, averaged down `factor` times into
+/// a `width` x `height` image of brightness. Samples past the cell's edge
+/// count as far outside.
+fn shrink_field(cell: &GrayImage, width: u32, height: u32, factor: u32, limit: f64) -> GrayImage {
+    let field = signed_distances(cell);
+    let mut out = GrayImage::new(width, height, 255);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            for dy in 0..factor {
+                for dx in 0..factor {
+                    let (sx, sy) = (x * factor + dx, y * factor + dy);
+                    sum += if sx < cell.width && sy < cell.height {
+                        field[(sy * cell.width + sx) as usize].clamp(-limit, limit)
+                    } else {
+                        -limit
+                    };
+                }
+            }
+            let distance = sum / (factor * factor) as f64 / limit;
+            let value = 127.5 - distance * 127.5;
+            out.set(x, y, value.round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    out
+}
+
+/// Distance in pixels from each pixel's center to the glyph edge, positive
+/// on ink and negative on background. The edge is taken to run halfway
+/// between an ink pixel and its nearest background pixel.
+fn signed_distances(cell: &GrayImage) -> Vec<f64> {
+    let ink: Vec<bool> = cell.pixels.iter().map(|&v| v < INK_THRESHOLD).collect();
+    let to_ink = distance_transform(&ink, cell.width, cell.height);
+    let background: Vec<bool> = ink.iter().map(|&inked| !inked).collect();
+    let to_background = distance_transform(&background, cell.width, cell.height);
+    ink.iter()
+        .zip(to_ink.iter().zip(&to_background))
+        .map(|(&inked, (&out, &inside))| {
+            if inked {
+                inside.sqrt() - 0.5
+            } else {
+                0.5 - out.sqrt()
+            }
+        })
+        .collect()
+}
+
+/// Squared Euclidean distance from every pixel to the nearest `feature`
+/// pixel (Felzenszwalb and Huttenlocher's separable transform), or `FAR`
+/// when there is none.
+fn distance_transform(feature: &[bool], width: u32, height: u32) -> Vec<f64> {
+    let (width, height) = (width as usize, height as usize);
+    let mut grid: Vec<f64> = feature.iter().map(|&f| if f { 0.0 } else { FAR }).collect();
+    let mut line = vec![0.0; width.max(height)];
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = grid[y * width + x];
+        }
+        let column = transform_line(&line[..height]);
+        for y in 0..height {
+            grid[y * width + x] = column[y];
+        }
+    }
+    for y in 0..height {
+        let row = transform_line(&grid[y * width..(y + 1) * width]);
+        grid[y * width..(y + 1) * width].copy_from_slice(&row);
+    }
+    grid
+}
+
+/// One dimension of the transform: the lower envelope of the parabolas
+/// rooted at each sample.
+fn transform_line(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    let mut out = vec![0.0; n];
+    // Roots of the parabolas on the envelope, and where each takes over
+    let mut roots = vec![0usize; n];
+    let mut bounds = vec![0.0f64; n + 1];
+    let mut k = 0;
+    bounds[0] = -f64::INFINITY;
+    bounds[1] = f64::INFINITY;
+    let intersect = |q: usize, p: usize| {
+        let (q2, p2) = ((q * q) as f64, (p * p) as f64);
+        ((f[q] + q2) - (f[p] + p2)) / (2.0 * (q as f64 - p as f64))
+    };
+    for q in 1..n {
+        let mut s = intersect(q, roots[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersect(q, roots[k]);
+        }
+        k += 1;
+        roots[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    for (q, value) in out.iter_mut().enumerate() {
+        while bounds[k + 1] < q as f64 {
+            k += 1;
+        }
+        let d = q as f64 - roots[k] as f64;
+        *value = d * d + f[roots[k]];
+    }
+    out
+}
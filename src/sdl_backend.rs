@@ -0,0 +1,133 @@
+//! The default backend: glyphs measured and rendered by SDL_ttf, from fonts
+//! shared through a `FontCache`.
+
+use anyhow::{Context, Result};
+use log::info;
+use sdl3::image::SaveSurface;
+use sdl3::pixels::Color;
+use sdl3::surface::Surface;
+use sdl3::ttf::Font;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::backend::{Backend, GlyphBox, LineMetrics, Raster};
+use crate::font_cache::FontCache;
+use crate::gray::{GrayImage, RgbImage};
+use crate::{CacheStats, RenderMode};
+
+impl RenderMode {
+    /// Draw `text` in black; solid surfaces come back keyed, which the
+    /// pixel readers resolve to white.
+    fn render(self, font: &Font, text: &str) -> Result<Surface<'static>, sdl3::Error> {
+        let partial = font.render(text);
+        match self {
+            RenderMode::Shaded => partial.shaded(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255)),
+            RenderMode::Solid => partial.solid(Color::RGB(0, 0, 0)),
+            RenderMode::Lcd => partial.lcd(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255)),
+        }
+    }
+}
+
+/// One face of a font file, loaded at each size it is asked for through a
+/// cache shared with the other fonts of the same atlas.
+pub struct SdlBackend<'ttf> {
+    cache: Rc<RefCell<FontCache<'ttf>>>,
+    path: PathBuf,
+    face: u32,
+    render_mode: RenderMode,
+}
+
+impl<'ttf> SdlBackend<'ttf> {
+    pub fn open(
+        cache: Rc<RefCell<FontCache<'ttf>>>,
+        path: &Path,
+        face: u32,
+        render_mode: RenderMode,
+    ) -> Result<SdlBackend<'ttf>> {
+        let backend = SdlBackend {
+            cache,
+            path: path.to_path_buf(),
+            face,
+            render_mode,
+        };
+        if face > 0 {
+            let font = backend.font(1.0)?;
+            info!(
+                "Face {} of {}: {} {}",
+                face,
+                font.num_faces(),
+                font.face_family_name().unwrap_or_default(),
+                font.face_style_name().unwrap_or_default()
+            );
+        }
+        Ok(backend)
+    }
+
+    fn font(&self, size: f32) -> Result<Rc<Font<'ttf, 'static>>> {
+        self.cache.borrow_mut().get(&self.path, self.face, size)
+    }
+}
+
+impl Backend for SdlBackend<'_> {
+    fn line_metrics(&self, size: f32) -> Result<LineMetrics> {
+        let font = self.font(size)?;
+        Ok(LineMetrics {
+            ascent: font.ascent(),
+            descent: font.descent(),
+        })
+    }
+
+    fn line_height(&self, size: f32) -> Result<i32> {
+        Ok(self.font(size)?.height())
+    }
+
+    /// SDL_ttf sizes a rendered string's surface with this same
+    /// measurement, so it gives that height without rendering the string.
+    fn text_height(&self, text: &str, size: f32) -> Result<u32> {
+        Ok(self.font(size)?.size_of(text)?.1)
+    }
+
+    fn measure_glyph(&self, ch: char, size: f32) -> Result<Option<GlyphBox>> {
+        Ok(self.font(size)?.find_glyph_metrics(ch).map(|m| GlyphBox {
+            minx: m.minx,
+            maxx: m.maxx,
+            miny: m.miny,
+            maxy: m.maxy,
+            advance: m.advance,
+        }))
+    }
+
+    /// The surface starts at the pen, or at minx when the glyph overhangs
+    /// to the left, and has its baseline at the font's ascent from the top.
+    fn rasterize_glyph(&self, ch: char, size: f32) -> Result<Option<Raster>> {
+        let font = self.font(size)?;
+        let Ok(surface) = self.render_mode.render(&font, &ch.to_string()) else {
+            return Ok(None);
+        };
+        let minx = font.find_glyph_metrics(ch).map_or(0, |m| m.minx);
+        let subpixel = match self.render_mode {
+            RenderMode::Lcd => Some(RgbImage::from_surface(&surface)?),
+            _ => None,
+        };
+        Ok(Some(Raster {
+            image: GrayImage::from_surface(&surface)?,
+            subpixel,
+            left: minx.min(0),
+            top: -font.ascent(),
+        }))
+    }
+
+    fn save_image(&self, image: &GrayImage, path: &Path) -> Result<()> {
+        image.to_surface()?.save(path).context("Failed to save PNG")
+    }
+
+    fn cache_stats(&self) -> CacheStats {
+        let cache = self.cache.borrow();
+        CacheStats {
+            open: cache.len(),
+            loads: cache.loads,
+            hits: cache.hits,
+        }
+    }
+}
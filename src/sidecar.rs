@@ -0,0 +1,129 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::CellStatus;
+use crate::grid::Grid;
+use crate::pages::PageLayout;
+use crate::strips::StripLayout;
+use crate::variants::Variants;
+
+/// Machine-readable description of an atlas, written next to the image or
+/// hex dump so consumers don't have to re-derive the layout.
+#[derive(Serialize)]
+pub struct Sidecar<'a> {
+    /// The font, or the image read with `--input-atlas`.
+    pub font: &'a Path,
+    pub font_size: f32,
+    /// Cell stride: the distance between neighbouring cell origins.
+    pub cell_width: u32,
+    pub cell_height: u32,
+    /// The glyph box inside each cell, `padding` pixels from its origin.
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    pub padding: u32,
+    pub gutter: u32,
+    /// Baseline row, counted from the top of the glyph box.
+    pub baseline: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub column_major: bool,
+    /// Texture size, which UVs are relative to.
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    /// The part of the texture the grid covers; smaller than the texture
+    /// when it was padded with `--pot`.
+    pub content_width: u32,
+    pub content_height: u32,
+    /// Codepage name, `custom`, or the Unicode ranges the atlas covers.
+    pub charset: &'a str,
+    /// The character in each cell, indexed row-major from the top left.
+    pub chars: Vec<String>,
+    /// How each cell came out of the blit: `ok`, `skipped`, `clipped`,
+    /// `placeholder`, `overridden`, or `scaled`.
+    pub status: &'a [CellStatus],
+    /// `--layout` name: `sequential`, the layout that moved the cells, or
+    /// `remap` for a `--remap` file.
+    pub layout: &'a str,
+    /// For a non-sequential layout, the index in the original table of the
+    /// character in each cell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout_order: Option<&'a [usize]>,
+    /// The inverse of `layout_order`: for each index of the original table,
+    /// the first cell holding its character, or null if none does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout_inverse: Option<Vec<Option<usize>>>,
+    /// `--baseline-shift` and `--nudge` as given, so the placement can be
+    /// reproduced.
+    pub baseline_shift: i32,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub nudge: &'a [String],
+    /// `--variation` axis settings the font was rendered with.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub variation: &'a [String],
+    /// With `--with-bold` or `--with-inverse`, the variant grids after the regular one that
+    /// the cell fields describe.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variants: Option<&'a Variants>,
+    /// For an atlas split into pages, which page each cell is on; cell
+    /// positions then restart at the top of each page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<PageLayout>,
+    /// Character to cell index, for atlases built from `--chars-from` text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remap: Option<BTreeMap<String, usize>>,
+}
+
+/// Sidecar for a `--sizes` atlas: the shared character table, then each
+/// strip's own grid.
+#[derive(Serialize)]
+pub struct StackSidecar<'a> {
+    pub font: &'a Path,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    pub charset: &'a str,
+    pub chars: Vec<String>,
+    pub strips: Vec<StripLayout>,
+}
+
+/// The layout fields of a sidecar, read back to interpret an atlas image.
+#[derive(Deserialize)]
+pub struct Layout {
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    pub padding: u32,
+    pub gutter: u32,
+    /// Missing from sidecars written before it was recorded.
+    pub baseline: Option<u32>,
+    pub columns: u32,
+    pub rows: u32,
+    pub column_major: bool,
+}
+
+impl Layout {
+    pub fn grid(&self) -> Grid {
+        Grid {
+            columns: self.columns,
+            rows: self.rows,
+            column_major: self.column_major,
+            glyph_width: self.glyph_width,
+            glyph_height: self.glyph_height,
+            padding: self.padding,
+            gutter: self.gutter,
+        }
+    }
+}
+
+/// Read the layout from the sidecar at `path`; other fields are ignored.
+pub fn read_layout(path: &Path) -> Result<Layout> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Write `sidecar` as pretty-printed JSON.
+pub fn write_sidecar(path: &Path, sidecar: &impl Serialize) -> Result<()> {
+    let json = serde_json::to_string_pretty(sidecar).context("Failed to serialize sidecar")?;
+    std::fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,105 @@
+//! `--strict`: everything that keeps an atlas from being exactly what was
+//! asked for, collected over the whole run and reported together at the
+//! end, which then fails the run.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+use mycp437generator::{Atlas, CellStatus};
+
+/// A kind of violation, which `--strict-allow` can waive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Violation {
+    /// Cells left empty: no font has the glyph, or it was rejected
+    Skipped,
+    /// Glyphs with ink cut off at the cell edge
+    Clipped,
+    /// Glyphs shrunk by --overflow scale to fit the cell
+    Scaled,
+    /// Cells given the --missing-glyph placeholder
+    Placeholder,
+    /// Glyphs taken from a --fallback-font
+    Fallback,
+    /// The widest glyph at the chosen size is not the requested width
+    Size,
+}
+
+impl Violation {
+    fn name(self) -> &'static str {
+        match self {
+            Violation::Skipped => "skipped",
+            Violation::Clipped => "clipped",
+            Violation::Scaled => "scaled",
+            Violation::Placeholder => "placeholder",
+            Violation::Fallback => "fallback",
+            Violation::Size => "size",
+        }
+    }
+}
+
+/// Violations found so far, one line each, labelled with their job.
+static VIOLATIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Note every violation in `atlas` outside `allow`.
+pub fn check(atlas: &Atlas, allow: &[Violation]) {
+    let mut found = Vec::new();
+    let cells = [
+        (Violation::Skipped, CellStatus::Skipped),
+        (Violation::Clipped, CellStatus::Clipped),
+        (Violation::Scaled, CellStatus::Scaled),
+        (Violation::Placeholder, CellStatus::Placeholder),
+    ];
+    for (violation, status) in cells {
+        let indices: Vec<usize> = (0..atlas.glyphs.len())
+            .filter(|&i| atlas.glyphs[i].status == status)
+            .collect();
+        found.push((violation, indices));
+    }
+    let fallback = (0..atlas.glyphs.len())
+        .filter(|&i| atlas.glyphs[i].fallback)
+        .collect();
+    found.push((Violation::Fallback, fallback));
+
+    let mut lines: Vec<String> = found
+        .into_iter()
+        .filter(|(violation, indices)| !indices.is_empty() && !allow.contains(violation))
+        .map(|(violation, indices)| {
+            let list = indices
+                .iter()
+                .map(|&i| format!("{} '{}'", i, atlas.glyphs[i].ch))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} {}: {}", indices.len(), violation.name(), list)
+        })
+        .collect();
+    if let Some((widest, requested)) = atlas.width_fit
+        && widest != requested
+        && !allow.contains(&Violation::Size)
+    {
+        lines.push(format!(
+            "size: the widest glyph is {}px, not the requested {}px",
+            widest, requested
+        ));
+    }
+
+    let prefix = mycp437generator::log_prefix()
+        .map(|p| format!("[{}] ", p))
+        .unwrap_or_default();
+    let mut violations = VIOLATIONS.lock().unwrap();
+    violations.extend(lines.into_iter().map(|line| format!("{}{}", prefix, line)));
+}
+
+/// Print and clear the violations noted so far, returning how many there
+/// were.
+pub fn report() -> usize {
+    let violations = std::mem::take(&mut *VIOLATIONS.lock().unwrap());
+    if !violations.is_empty() {
+        eprintln!("--strict found {} violation(s):", violations.len());
+        for line in &violations {
+            eprintln!("  {}", line);
+        }
+    }
+    violations.len()
+}
@@ -0,0 +1,83 @@
+//! `--sizes`: one typeface rendered at several cell widths, each size's
+//! grid a strip stacked under the last in a single texture.
+
+use serde::Serialize;
+
+use mycp437generator::Atlas;
+use mycp437generator::gray::GrayImage;
+
+/// One size's atlas and the row its strip starts at in the stack.
+pub struct Strip {
+    /// The cell width asked for, which names the strip.
+    pub size: u32,
+    pub atlas: Atlas,
+    pub y: u32,
+}
+
+/// Stack `strips` of (size, atlas) top to bottom, left-aligned, in a
+/// texture as wide as the widest; the rest is background.
+pub fn stack(strips: Vec<(u32, Atlas)>) -> (GrayImage, Vec<Strip>) {
+    let width = strips
+        .iter()
+        .map(|(_, atlas)| atlas.image.width)
+        .max()
+        .unwrap_or(0);
+    let height = strips.iter().map(|(_, atlas)| atlas.image.height).sum();
+    let mut image = GrayImage::new(width, height, 255);
+    let mut y = 0;
+    let strips = strips
+        .into_iter()
+        .map(|(size, atlas)| {
+            image.paste(&atlas.image, 0, y);
+            let strip = Strip { size, atlas, y };
+            y += strip.atlas.image.height;
+            strip
+        })
+        .collect();
+    (image, strips)
+}
+
+/// One strip of the sidecar: where it sits and the grid inside it.
+#[derive(Serialize)]
+pub struct StripLayout {
+    pub size: u32,
+    /// First row of the strip in the texture; strips start at column 0.
+    pub y_offset: u32,
+    pub width: u32,
+    pub height: u32,
+    pub font_size: f32,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub glyph_width: u32,
+    pub glyph_height: u32,
+    pub padding: u32,
+    pub gutter: u32,
+    /// Baseline row, counted from the top of the glyph box.
+    pub baseline: u32,
+    pub columns: u32,
+    pub rows: u32,
+    pub column_major: bool,
+}
+
+impl Strip {
+    pub fn layout(&self) -> StripLayout {
+        let grid = &self.atlas.grid;
+        StripLayout {
+            size: self.size,
+            y_offset: self.y,
+            width: self.atlas.image.width,
+            height: self.atlas.image.height,
+            font_size: self.atlas.font_size,
+            cell_width: grid.stride_x(),
+            cell_height: grid.stride_y(),
+            glyph_width: grid.glyph_width,
+            glyph_height: grid.glyph_height,
+            padding: grid.padding,
+            gutter: grid.gutter,
+            baseline: self.atlas.baseline,
+            columns: grid.columns,
+            rows: grid.rows,
+            column_major: grid.column_major,
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! `--font-name`: finding an installed font by family name, through
+//! fontconfig's configuration on Linux and the system font directories
+//! elsewhere.
+
+use anyhow::{Result, bail};
+use fontdb::{Database, Family, Query, Source, Stretch, Style, Weight};
+use std::path::PathBuf;
+
+/// How many installed families a failed lookup suggests.
+const SUGGESTIONS: usize = 5;
+
+/// The file and face index of the installed face of `family` (matched
+/// without regard to case) that best fits `style`, e.g. `Bold Italic`.
+pub fn resolve(family: &str, style: Option<&str>) -> Result<(PathBuf, u32)> {
+    let (weight, slant) = parse_style(style.unwrap_or("Regular"))?;
+    let mut db = Database::new();
+    db.load_system_fonts();
+
+    let mut families: Vec<&str> = db
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.as_str()))
+        .collect();
+    families.sort_unstable();
+    families.dedup();
+
+    let Some(name) = families
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(family))
+    else {
+        let wanted = family.to_lowercase();
+        families.sort_by_key(|name| edit_distance(&name.to_lowercase(), &wanted));
+        let closest = families
+            .iter()
+            .take(SUGGESTIONS)
+            .map(|name| format!("'{}'", name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if closest.is_empty() {
+            bail!(
+                "Error: no installed font family is named '{}', and no fonts were found at all",
+                family
+            );
+        }
+        bail!(
+            "Error: no installed font family is named '{}'; the closest are {}",
+            family,
+            closest
+        );
+    };
+
+    let query = Query {
+        families: &[Family::Name(name)],
+        weight,
+        stretch: Stretch::Normal,
+        style: slant,
+    };
+    let source = db.query(&query).and_then(|id| db.face_source(id));
+    match source {
+        Some((Source::File(path), index)) => Ok((path, index)),
+        Some((Source::SharedFile(path, _), index)) => Ok((path, index)),
+        _ => bail!("Error: font family '{}' has no face on disk", name),
+    }
+}
+
+/// Split a style such as `Bold`, `Light Italic`, or `Regular` into the
+/// weight and slant to ask for.
+fn parse_style(style: &str) -> Result<(Weight, Style)> {
+    let mut weight = Weight::NORMAL;
+    let mut slant = Style::Normal;
+    for word in style.split_whitespace() {
+        match word.to_lowercase().replace('-', "").as_str() {
+            "thin" | "hairline" => weight = Weight::THIN,
+            "extralight" | "ultralight" => weight = Weight::EXTRA_LIGHT,
+            "light" => weight = Weight::LIGHT,
+            "regular" | "normal" | "book" | "roman" => weight = Weight::NORMAL,
+            "medium" => weight = Weight::MEDIUM,
+            "semibold" | "demibold" => weight = Weight::SEMIBOLD,
+            "bold" => weight = Weight::BOLD,
+            "extrabold" | "ultrabold" => weight = Weight::EXTRA_BOLD,
+            "black" | "heavy" => weight = Weight::BLACK,
+            "italic" => slant = Style::Italic,
+            "oblique" => slant = Style::Oblique,
+            _ => bail!(
+                "Invalid --font-style word '{}' (expected a weight such as Light, Regular, or Bold, and/or Italic or Oblique)",
+                word
+            ),
+        }
+    }
+    Ok((weight, slant))
+}
+
+/// Levenshtein distance between `a` and `b`, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,66 @@
+//! Draw the packed atlas bits as Unicode art, so a whole font can be
+//! eyeballed over SSH. One dot per atlas pixel; nothing is downscaled.
+
+use clap::ValueEnum;
+use mycp437generator::packing::PackedBitmap;
+use serde::{Deserialize, Serialize};
+
+/// Which characters `--preview-terminal` draws with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TerminalStyle {
+    /// Braille patterns, 2x4 pixels per character
+    Braille,
+    /// Half blocks, 1x2 pixels per character
+    HalfBlock,
+}
+
+impl TerminalStyle {
+    /// Atlas pixels covered by one character cell.
+    fn dots(self) -> (u32, u32) {
+        match self {
+            TerminalStyle::Braille => (2, 4),
+            TerminalStyle::HalfBlock => (1, 2),
+        }
+    }
+}
+
+/// Braille dot bit for each position in the 2x4 block, indexed [y][x].
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Render `bits` in bands at most `columns` characters wide, each band
+/// headed by the pixel columns it covers.
+pub fn render(bits: &PackedBitmap, style: TerminalStyle, columns: u32) -> String {
+    let (dot_w, dot_h) = style.dots();
+    let band_width = columns.max(1) * dot_w;
+    let mut out = String::new();
+    for band_x in (0..bits.width).step_by(band_width as usize) {
+        let band_end = (band_x + band_width).min(bits.width);
+        out += &format!("x {}-{}\n", band_x, band_end - 1);
+        for y in (0..bits.height).step_by(dot_h as usize) {
+            for x in (band_x..band_end).step_by(dot_w as usize) {
+                out.push(match style {
+                    TerminalStyle::Braille => {
+                        let mut pattern = 0;
+                        for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, dot) in row.iter().enumerate() {
+                                if bits.get(x + dx as u32, y + dy as u32) {
+                                    pattern |= dot;
+                                }
+                            }
+                        }
+                        char::from_u32(0x2800 + pattern).expect("braille block")
+                    }
+                    TerminalStyle::HalfBlock => match (bits.get(x, y), bits.get(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                });
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,207 @@
+//! `--preview-terminal-graphics`: the finished atlas shown in the terminal
+//! at full resolution, through the kitty graphics protocol or Sixel, so the
+//! actual pixels can be checked over SSH without X forwarding.
+
+use anyhow::{Context, Result, bail};
+use sdl3::pixels::PixelFormat;
+use sdl3::surface::Surface;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use crate::base64;
+use mycp437generator::failure::{Categorize, Failure};
+
+/// Base64 bytes per kitty graphics escape, the most the protocol allows.
+const KITTY_CHUNK: usize = 4096;
+
+/// Which graphics protocol to draw with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+impl Protocol {
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Kitty => "kitty",
+            Protocol::Sixel => "Sixel",
+        }
+    }
+
+    /// Whether `$TERM` or `$TERM_PROGRAM` names a terminal known to draw
+    /// the protocol. Only the terminal type crosses SSH, so that is checked
+    /// first.
+    pub fn supported(self) -> bool {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        match self {
+            Protocol::Kitty => {
+                matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+                    || matches!(program.as_str(), "WezTerm" | "ghostty")
+                    || std::env::var_os("KITTY_WINDOW_ID").is_some()
+            }
+            Protocol::Sixel => {
+                term.contains("sixel")
+                    || term.starts_with("foot")
+                    || term.starts_with("mlterm")
+                    || matches!(program.as_str(), "WezTerm" | "iTerm.app" | "mintty")
+            }
+        }
+    }
+
+    /// The escape sequence that draws `surface`.
+    pub fn encode(self, surface: &Surface) -> Result<String> {
+        match self {
+            Protocol::Kitty => kitty(surface),
+            Protocol::Sixel => sixel(surface),
+        }
+    }
+}
+
+/// The pixels of an RGB24 or RGBA32 surface, rows packed without pitch
+/// padding, and the bytes per pixel.
+fn pixels(surface: &Surface) -> Result<(Vec<u8>, usize)> {
+    let format = surface.pixel_format();
+    let depth = if format == PixelFormat::RGB24 {
+        3
+    } else if format == PixelFormat::RGBA32 {
+        4
+    } else {
+        bail!(
+            "Error: can't draw a {:?} surface as terminal graphics",
+            format
+        );
+    };
+    let row = surface.width() as usize * depth;
+    let pitch = surface.pitch() as usize;
+    let height = surface.height() as usize;
+    let data = surface.with_lock(|data: &[u8]| {
+        (0..height)
+            .flat_map(|y| &data[y * pitch..y * pitch + row])
+            .copied()
+            .collect()
+    });
+    Ok((data, depth))
+}
+
+/// Transmit and display the raw pixels in one go, the base64 payload split
+/// over as many escapes as it takes.
+fn kitty(surface: &Surface) -> Result<String> {
+    let (data, depth) = pixels(surface)?;
+    let payload = base64::encode(&data);
+    let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).expect("base64 is ASCII");
+        if i == 0 {
+            write!(
+                out,
+                "\x1b_Ga=T,q=2,f={},s={},v={},m={};{}\x1b\\",
+                depth * 8,
+                surface.width(),
+                surface.height(),
+                more,
+                chunk
+            )
+            .unwrap();
+        } else {
+            write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk).unwrap();
+        }
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+/// Sixel with each channel quantized to six levels, a 216-color palette of
+/// which only the colors used are defined. Pixels under half alpha are
+/// left transparent.
+fn sixel(surface: &Surface) -> Result<String> {
+    let (data, depth) = pixels(surface)?;
+    let (width, height) = (surface.width() as usize, surface.height() as usize);
+    let level = |v: u8| (v as usize * 5 + 127) / 255;
+    let colors: Vec<Option<usize>> = data
+        .chunks(depth)
+        .map(|p| {
+            (depth == 3 || p[3] >= 128).then(|| level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+        })
+        .collect();
+
+    // Transparent background, 1:1 pixel aspect
+    let mut out = format!("\x1bP0;1;0q\"1;1;{};{}", width, height);
+    let percent = |level: usize| level * 100 / 5;
+    for color in colors.iter().flatten().collect::<BTreeSet<_>>() {
+        write!(
+            out,
+            "#{};2;{};{};{}",
+            color,
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        )
+        .unwrap();
+    }
+    for top in (0..height).step_by(6) {
+        let rows = 6.min(height - top);
+        let band = &colors[top * width..(top + rows) * width];
+        for (n, &color) in band
+            .iter()
+            .flatten()
+            .collect::<BTreeSet<_>>()
+            .iter()
+            .enumerate()
+        {
+            if n > 0 {
+                out.push('$');
+            }
+            write!(out, "#{}", color).unwrap();
+            let sixels = (0..width).map(|x| {
+                (0..rows)
+                    .filter(|&dy| band[dy * width + x] == Some(*color))
+                    .fold(0u8, |bits, dy| bits | 1 << dy)
+            });
+            push_runs(&mut out, sixels);
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    Ok(out)
+}
+
+/// Append `sixels` run-length encoded, leaving off the empty run at the end.
+fn push_runs(out: &mut String, sixels: impl Iterator<Item = u8>) {
+    let mut runs: Vec<(u8, usize)> = Vec::new();
+    for bits in sixels {
+        match runs.last_mut() {
+            Some((last, count)) if *last == bits => *count += 1,
+            _ => runs.push((bits, 1)),
+        }
+    }
+    if runs.last().is_some_and(|&(bits, _)| bits == 0) {
+        runs.pop();
+    }
+    for (bits, count) in runs {
+        let ch = (0x3f + bits) as char;
+        if count > 3 {
+            write!(out, "!{}{}", count, ch).unwrap();
+        } else {
+            (0..count).for_each(|_| out.push(ch));
+        }
+    }
+}
+
+/// Write `escape` to the controlling terminal, past any redirection of
+/// stdout and stderr.
+pub fn show(escape: &str) -> Result<()> {
+    let mut tty = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .context("Failed to open the terminal for --preview-terminal-graphics")
+        .failure(Failure::Output)?;
+    tty.write_all(escape.as_bytes())
+        .and_then(|()| tty.flush())
+        .context("Failed to write the terminal graphics preview")
+        .failure(Failure::Output)
+}
@@ -0,0 +1,173 @@
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::charmap::CharMap;
+
+/// How carriage returns are treated when laying out text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrMode {
+    /// Drop them, so CRLF files lay out like LF files
+    Strip,
+    /// Render index 13's glyph (♪), as some retro art expects
+    Glyph,
+}
+
+/// How line feeds are treated when laying out text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LfMode {
+    /// Start a new line
+    Newline,
+    /// Render index 10's glyph (◙)
+    Glyph,
+}
+
+/// Text layout policy shared by every feature that composes text from the
+/// atlas, so tabs, line endings, and stray control bytes behave the same way
+/// everywhere.
+#[derive(clap::Args, Clone, Debug, Serialize, Deserialize)]
+pub struct TextPolicy {
+    /// Tab stop width when laying out text; 0 renders tabs as their glyph
+    #[arg(long, default_value_t = 8)]
+    pub tab_width: usize,
+
+    /// Carriage returns in laid-out text: `strip` or render as a `glyph`
+    #[arg(long, value_enum, default_value_t = CrMode::Strip)]
+    pub cr: CrMode,
+
+    /// Line feeds in laid-out text: `newline` or render as a `glyph`
+    #[arg(long, value_enum, default_value_t = LfMode::Newline)]
+    pub lf: LfMode,
+
+    /// Render every byte's glyph verbatim, ignoring tabs, CR, and LF
+    /// (what ANSI art that draws with control-range pictures relies on)
+    #[arg(long)]
+    pub literal_controls: bool,
+}
+
+/// Split CP437 bytes into lines of glyph indices according to `policy`.
+/// Control bytes other than tab, CR, and LF always render as their glyph.
+pub fn layout(bytes: &[u8], policy: &TextPolicy) -> Vec<Vec<u8>> {
+    let mut lines = vec![Vec::new()];
+    for &byte in bytes {
+        let line = lines.last_mut().expect("always at least one line");
+        if policy.literal_controls {
+            line.push(byte);
+            continue;
+        }
+        match byte {
+            b'\t' if policy.tab_width > 0 => {
+                let next_stop = (line.len() / policy.tab_width + 1) * policy.tab_width;
+                line.resize(next_stop, b' ');
+            }
+            b'\r' if policy.cr == CrMode::Strip => {}
+            b'\n' if policy.lf == LfMode::Newline => lines.push(Vec::new()),
+            _ => line.push(byte),
+        }
+    }
+    lines
+}
+
+/// Encode Unicode text as CP437 bytes. Characters in the C0 control range
+/// (and DEL) keep their byte value so `layout` can interpret them; everything
+/// else must appear in `charmap`.
+pub fn encode_cp437(text: &str, charmap: &CharMap) -> Result<Vec<u8>> {
+    text.chars()
+        .map(|c| match c {
+            '\u{0}'..='\u{1F}' | '\u{7F}' => Ok(c as u8),
+            ' ' => Ok(b' '),
+            _ => match charmap.index_of(c).and_then(|i| u8::try_from(i).ok()) {
+                Some(i) => Ok(i),
+                None => bail!(
+                    "Character '{}' (U+{:04X}) is not in the character set ({})",
+                    c,
+                    c as u32,
+                    charmap.label()
+                ),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The policy the command line defaults to.
+    fn policy() -> TextPolicy {
+        TextPolicy {
+            tab_width: 8,
+            cr: CrMode::Strip,
+            lf: LfMode::Newline,
+            literal_controls: false,
+        }
+    }
+
+    #[test]
+    fn tabs_pad_to_the_next_stop() {
+        let four = TextPolicy {
+            tab_width: 4,
+            ..policy()
+        };
+        assert_eq!(layout(b"a\tb", &four), [b"a   b".to_vec()]);
+        assert_eq!(layout(b"abcd\tb", &four), [b"abcd    b".to_vec()]);
+        assert_eq!(layout(b"\t\tb", &four), [b"        b".to_vec()]);
+        // Stops count from the start of each line
+        assert_eq!(
+            layout(b"abc\nd\te", &four),
+            [b"abc".to_vec(), b"d   e".to_vec()]
+        );
+    }
+
+    #[test]
+    fn a_zero_tab_width_renders_the_tab_glyph() {
+        let glyph = TextPolicy {
+            tab_width: 0,
+            ..policy()
+        };
+        assert_eq!(layout(b"a\tb", &glyph), [vec![b'a', 9, b'b']]);
+    }
+
+    #[test]
+    fn carriage_returns_are_stripped_or_drawn() {
+        assert_eq!(layout(b"a\r\nb", &policy()), [b"a".to_vec(), b"b".to_vec()]);
+        let glyph = TextPolicy {
+            cr: CrMode::Glyph,
+            ..policy()
+        };
+        assert_eq!(layout(b"a\r\nb", &glyph), [vec![b'a', 13], b"b".to_vec()]);
+    }
+
+    #[test]
+    fn line_feeds_break_lines_or_are_drawn() {
+        assert_eq!(
+            layout(b"a\n\nb\n", &policy()),
+            [b"a".to_vec(), vec![], b"b".to_vec(), vec![]]
+        );
+        let glyph = TextPolicy {
+            lf: LfMode::Glyph,
+            ..policy()
+        };
+        assert_eq!(layout(b"a\nb", &glyph), [vec![b'a', 10, b'b']]);
+    }
+
+    #[test]
+    fn literal_controls_draw_every_byte() {
+        let literal = TextPolicy {
+            literal_controls: true,
+            ..policy()
+        };
+        let bytes = b"a\t\r\nb\x01\x1b";
+        assert_eq!(layout(bytes, &literal), [bytes.to_vec()]);
+    }
+
+    #[test]
+    fn other_control_bytes_always_draw_their_glyph() {
+        assert_eq!(
+            layout(b"\x01\x07\x1b\x7f", &policy()),
+            [b"\x01\x07\x1b\x7f".to_vec()]
+        );
+    }
+}
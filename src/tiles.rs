@@ -0,0 +1,218 @@
+//! 8x8 tile output for console homebrew: 2bpp in the NES CHR-ROM and Game
+//! Boy layouts, with glyphs color 1 on color 0, and the C64's 1bpp
+//! character ROM.
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+use mycp437generator::INK_THRESHOLD;
+use mycp437generator::codepage::petscii_for_screen_code;
+
+/// Side of a tile in pixels.
+const TILE: u32 = 8;
+/// CHR-ROM banks are 8 KiB; `--chr-pad` rounds the file up to one.
+const CHR_BANK: usize = 8192;
+
+/// How many tiles each glyph is cut into, selected with `--chr-tiles` or
+/// `--gb-tiles`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+pub enum TileSplit {
+    /// One tile per 8x8 glyph
+    #[value(name = "1x1")]
+    #[serde(rename = "1x1")]
+    One,
+    /// Four tiles per 16x16 glyph: top left, top right, bottom left,
+    /// bottom right
+    #[value(name = "2x2")]
+    #[serde(rename = "2x2")]
+    TwoByTwo,
+}
+
+/// Assembler dialect for `--emit-asm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AsmSyntax {
+    /// RGBDS `db` lines of the --output-gb tiles
+    Rgbds,
+    /// NASM `db`/`dw`/`dd` lines of every glyph
+    Nasm,
+    /// MASM `DB`/`DW`/`DD` lines of every glyph
+    Masm,
+    /// GNU as `.byte`/`.2byte`/`.4byte` lines of every glyph
+    Gas,
+}
+
+impl TileSplit {
+    /// Tiles across and down each glyph.
+    fn tiles(self) -> u32 {
+        match self {
+            TileSplit::One => 1,
+            TileSplit::TwoByTwo => 2,
+        }
+    }
+}
+
+/// Every glyph's tiles in index order, each as its 8 rows of bits with the
+/// leftmost pixel in the top bit. The glyph box must be exactly 8x8 (16x16
+/// for a 2x2 split).
+fn glyph_tiles(
+    image: &GrayImage,
+    grid: &Grid,
+    count: usize,
+    split: TileSplit,
+) -> Result<Vec<[u8; 8]>> {
+    let side = TILE * split.tiles();
+    if (grid.glyph_width, grid.glyph_height) != (side, side) {
+        bail!(
+            "Error: tile output needs {}x{} glyphs, not {}x{}",
+            side,
+            side,
+            grid.glyph_width,
+            grid.glyph_height
+        );
+    }
+    let mut tiles = Vec::with_capacity(count * (split.tiles() * split.tiles()) as usize);
+    for i in 0..count {
+        let (x0, y0) = grid.glyph_origin(i);
+        for ty in 0..split.tiles() {
+            for tx in 0..split.tiles() {
+                let mut tile = [0u8; 8];
+                for (y, row) in tile.iter_mut().enumerate() {
+                    for x in 0..TILE {
+                        let (px, py) = (x0 + tx * TILE + x, y0 + ty * TILE + y as u32);
+                        if image.get(px, py) < INK_THRESHOLD {
+                            *row |= 0x80 >> x;
+                        }
+                    }
+                }
+                tiles.push(tile);
+            }
+        }
+    }
+    Ok(tiles)
+}
+
+/// Encode the first `count` glyphs as NES CHR tiles: 8 bytes of plane 0
+/// (the glyph) then 8 zero bytes of plane 1. With `pad`, empty tiles fill
+/// the file out to a whole number of 8 KiB banks.
+pub fn encode_chr(
+    image: &GrayImage,
+    grid: &Grid,
+    count: usize,
+    split: TileSplit,
+    pad: bool,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for tile in glyph_tiles(image, grid, count, split)? {
+        bytes.extend(tile);
+        bytes.extend([0u8; 8]);
+    }
+    if pad {
+        bytes.resize(bytes.len().next_multiple_of(CHR_BANK), 0);
+    }
+    Ok(bytes)
+}
+
+/// Encode the first `count` glyphs as Game Boy tiles: for each of the 8
+/// rows, the low plane byte (the glyph) then the high plane byte (zero).
+pub fn encode_gb(
+    image: &GrayImage,
+    grid: &Grid,
+    count: usize,
+    split: TileSplit,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for tile in glyph_tiles(image, grid, count, split)? {
+        bytes.extend(tile.iter().flat_map(|&row| [row, 0]));
+    }
+    Ok(bytes)
+}
+
+/// Encode a PETSCII atlas's 8x8 glyphs as a C64 character ROM: screen
+/// codes 0-127, each its 8 rows with the leftmost pixel in the top bit,
+/// then the same glyphs reversed for 128-255.
+pub fn encode_c64(image: &GrayImage, grid: &Grid) -> Result<Vec<u8>> {
+    let tiles = glyph_tiles(image, grid, 256, TileSplit::One)?;
+    let mut bytes = Vec::with_capacity(2048);
+    for reversed in [false, true] {
+        for screen in 0..128u8 {
+            let tile = tiles[petscii_for_screen_code(screen) as usize];
+            bytes.extend(tile.map(|row| if reversed { !row } else { row }));
+        }
+    }
+    Ok(bytes)
+}
+
+/// Format tile data as an RGBDS include: an exported `label`, one `db` line
+/// of 16 bytes per tile, and a `.end` local label after the last.
+pub fn format_rgbds(bytes: &[u8], label: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "; {} tiles, 2bpp", bytes.len() / 16).unwrap();
+    writeln!(out, "{}::", label).unwrap();
+    for tile in bytes.chunks(16) {
+        let values: Vec<String> = tile.iter().map(|b| format!("${:02X}", b)).collect();
+        writeln!(out, "    db {}", values.join(", ")).unwrap();
+    }
+    writeln!(out, ".end").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 'A' from the IBM PC's 8x8 BIOS font, one byte per row.
+    const A: [u8; 8] = [0x30, 0x78, 0xCC, 0xCC, 0xFC, 0xCC, 0xCC, 0x00];
+
+    /// A one-glyph atlas of `rows`, each pixel drawn `scale` times over.
+    fn atlas(rows: &[u8; 8], scale: u32) -> (GrayImage, Grid) {
+        let side = TILE * scale;
+        let mut image = GrayImage::new(side, side, 255);
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..TILE {
+                if row & (0x80 >> x) != 0 {
+                    image.fill_rect(x * scale, y as u32 * scale, scale, scale, 0);
+                }
+            }
+        }
+        (image, Grid::new(1, 1, false, side, side))
+    }
+
+    #[test]
+    fn chr_tiles_hold_the_glyph_in_plane_0() {
+        let (image, grid) = atlas(&A, 1);
+        let chr = encode_chr(&image, &grid, 1, TileSplit::One, false).unwrap();
+        assert_eq!(chr[..8], A);
+        assert_eq!(chr[8..], [0; 8]);
+    }
+
+    #[test]
+    fn split_glyphs_are_tiled_left_to_right_then_down() {
+        let (image, grid) = atlas(&A, 2);
+        let chr = encode_chr(&image, &grid, 1, TileSplit::TwoByTwo, false).unwrap();
+        assert_eq!(chr.len(), 4 * 16);
+        // 'A' drawn at twice the size, cut into quarters
+        let quarters: [[u8; 8]; 4] = [
+            [0x0F, 0x0F, 0x3F, 0x3F, 0xF0, 0xF0, 0xF0, 0xF0],
+            [0x00, 0x00, 0xC0, 0xC0, 0xF0, 0xF0, 0xF0, 0xF0],
+            [0xFF, 0xFF, 0xF0, 0xF0, 0xF0, 0xF0, 0x00, 0x00],
+            [0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0xF0, 0x00, 0x00],
+        ];
+        for (tile, plane0) in quarters.iter().enumerate() {
+            assert_eq!(chr[tile * 16..tile * 16 + 8], plane0[..], "tile {}", tile);
+            assert_eq!(chr[tile * 16 + 8..tile * 16 + 16], [0; 8], "tile {}", tile);
+        }
+    }
+
+    #[test]
+    fn padding_fills_out_a_whole_bank() {
+        let (image, grid) = atlas(&A, 1);
+        let chr = encode_chr(&image, &grid, 1, TileSplit::One, true).unwrap();
+        assert_eq!(chr.len(), CHR_BANK);
+        assert!(chr[16..].iter().all(|&b| b == 0));
+    }
+}
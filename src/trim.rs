@@ -0,0 +1,188 @@
+//! `--trim-report` and `--trim`: the columns and rows of the glyph box
+//! that no glyph ever draws in, and an atlas whose glyph boxes leave them
+//! out. Glyphs keep their places relative to each other, so text set from
+//! the trimmed atlas lines up as before, only tighter.
+
+use mycp437generator::gray::GrayImage;
+use mycp437generator::grid::Grid;
+use mycp437generator::{Atlas, log};
+
+/// Columns and rows never drawn in, from each edge of the glyph box.
+#[derive(Clone, Copy, Debug)]
+pub struct Margins {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+/// What trimming `atlas` would take off.
+pub struct Trim {
+    /// `None` when no glyph has any ink, leaving nothing to trim to.
+    pub margins: Option<Margins>,
+    /// Without the box-drawing and block glyphs, which reach the edge of
+    /// the box on purpose so they join their neighbours.
+    pub without_boxes: Option<Margins>,
+    /// Box-drawing and block glyphs that touch each edge, as
+    /// left, right, top, bottom.
+    pub holding: [Vec<usize>; 4],
+}
+
+/// Box-drawing and block characters, meant to touch their neighbours.
+fn joins_neighbours(ch: char) -> bool {
+    ('\u{2500}'..='\u{259F}').contains(&ch)
+}
+
+/// The bounds of every pixel in `glyph` that isn't plain background, so
+/// trimming never cuts the faintest antialiasing, as left, top, right and
+/// bottom, exclusive.
+fn drawn_bounds(glyph: &GrayImage) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for y in 0..glyph.height {
+        for x in 0..glyph.width {
+            if glyph.get(x, y) < 255 {
+                let (x0, y0, x1, y1) = bounds.unwrap_or((x, y, x + 1, y + 1));
+                bounds = Some((x0.min(x), y0.min(y), x1.max(x + 1), y1.max(y + 1)));
+            }
+        }
+    }
+    bounds
+}
+
+/// Work out what trimming `atlas` would take off.
+pub fn measure(atlas: &Atlas) -> Trim {
+    let grid = &atlas.grid;
+    let (width, height) = (grid.glyph_width, grid.glyph_height);
+    let mut all: Option<(u32, u32, u32, u32)> = None;
+    let mut others: Option<(u32, u32, u32, u32)> = None;
+    let mut holding: [Vec<usize>; 4] = Default::default();
+    let union = |acc: Option<(u32, u32, u32, u32)>, b: (u32, u32, u32, u32)| {
+        Some(match acc {
+            Some(a) => (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)),
+            None => b,
+        })
+    };
+    for (i, glyph) in atlas.glyphs.iter().enumerate() {
+        let (x, y) = grid.glyph_origin(i);
+        let Some(bounds) = drawn_bounds(&atlas.image.crop(x, y, width, height)) else {
+            continue;
+        };
+        all = union(all, bounds);
+        if joins_neighbours(glyph.ch) {
+            let edges = [
+                bounds.0 == 0,
+                bounds.2 == width,
+                bounds.1 == 0,
+                bounds.3 == height,
+            ];
+            for (list, touches) in holding.iter_mut().zip(edges) {
+                if touches {
+                    list.push(i);
+                }
+            }
+        } else {
+            others = union(others, bounds);
+        }
+    }
+    let margins = |bounds: Option<(u32, u32, u32, u32)>| {
+        bounds.map(|(x0, y0, x1, y1)| Margins {
+            left: x0,
+            right: width - x1,
+            top: y0,
+            bottom: height - y1,
+        })
+    };
+    Trim {
+        margins: margins(all),
+        without_boxes: margins(others),
+        holding,
+    }
+}
+
+/// Print what `trim` found for `atlas`.
+pub fn report(trim: &Trim, atlas: &Atlas) {
+    let grid = &atlas.grid;
+    let Some(margins) = trim.margins else {
+        log!("Trim: no glyph has any ink, so there is nothing to trim to");
+        return;
+    };
+    log!(
+        "Trim: no glyph draws in the {} left and {} right column(s) or the {} top and {} bottom row(s) of the {}x{} glyph box; trimmed it would be {}x{}",
+        margins.left,
+        margins.right,
+        margins.top,
+        margins.bottom,
+        grid.glyph_width,
+        grid.glyph_height,
+        grid.glyph_width - margins.left - margins.right,
+        grid.glyph_height - margins.top - margins.bottom
+    );
+    let Some(loose) = trim.without_boxes else {
+        return;
+    };
+    let edges = [
+        ("left", margins.left, loose.left),
+        ("right", margins.right, loose.right),
+        ("top", margins.top, loose.top),
+        ("bottom", margins.bottom, loose.bottom),
+    ];
+    for ((edge, kept, free), holding) in edges.into_iter().zip(&trim.holding) {
+        if free > kept {
+            let list = holding
+                .iter()
+                .map(|&i| format!("{} '{}'", i, atlas.glyphs[i].ch))
+                .collect::<Vec<_>>()
+                .join(", ");
+            log!(
+                "Trim: the {} edge keeps {} more px for box-drawing glyphs that reach it: {}",
+                edge,
+                free - kept,
+                list
+            );
+        }
+    }
+}
+
+/// Shrink every glyph box of `atlas` by `margins`, moving each glyph with
+/// its box, and pad the new atlas to powers of two with `pot`.
+pub fn apply(atlas: &mut Atlas, margins: Margins, pot: bool) {
+    let old = atlas.grid;
+    let grid = Grid {
+        glyph_width: old.glyph_width - margins.left - margins.right,
+        glyph_height: old.glyph_height - margins.top - margins.bottom,
+        ..old
+    };
+    let (width, height) = (grid.width(), grid.height());
+    let (image_width, image_height) = if pot {
+        (width.next_power_of_two(), height.next_power_of_two())
+    } else {
+        (width, height)
+    };
+    let mut image = GrayImage::new(image_width, image_height, 255);
+    for i in 0..atlas.glyphs.len() {
+        let (x, y) = old.glyph_origin(i);
+        let glyph = atlas.image.crop(
+            x + margins.left,
+            y + margins.top,
+            grid.glyph_width,
+            grid.glyph_height,
+        );
+        let (x, y) = grid.glyph_origin(i);
+        image.paste(&glyph, x, y);
+    }
+    let (dx, dy) = (margins.left as i32, margins.top as i32);
+    for (i, glyph) in atlas.glyphs.iter_mut().enumerate() {
+        let (x, y) = grid.glyph_origin(i);
+        glyph.rect = (x, y, grid.glyph_width, grid.glyph_height);
+        if let Some(p) = &mut glyph.placement {
+            p.pen_x -= dx;
+            p.x_offset -= dx;
+            p.y_offset -= dy;
+        }
+    }
+    atlas.image = image;
+    atlas.grid = grid;
+    atlas.content_width = width;
+    atlas.content_height = height;
+    atlas.baseline = atlas.baseline.saturating_sub(margins.top);
+}
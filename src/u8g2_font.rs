@@ -0,0 +1,337 @@
+//! `--emit-u8g2`: a u8g2 font, in the run-length encoded format bdfconv
+//! writes: a 23-byte header, glyphs with codepoints below 256 (reached
+//! through the `A` and `a` jump offsets), then the rest behind a Unicode
+//! lookup table. Glyphs are transparent and proportional, like the
+//! `_tf` fonts bdfconv builds.
+
+use anyhow::{Result, bail};
+use std::fmt::Write as _;
+
+use crate::charmap::CharMap;
+use crate::gfx_font::pen_and_advance;
+use crate::gray::GrayImage;
+use mycp437generator::{Atlas, INK_THRESHOLD};
+
+const HEADER_LEN: usize = 23;
+
+/// A glyph's box, relative to the pen on the baseline with y pointing up,
+/// and its pixels as runs of background and ink.
+struct Glyph {
+    code: u32,
+    width: u32,
+    height: u32,
+    x: i32,
+    y: i32,
+    advance: i32,
+    /// (background, ink) run lengths, row by row across the box.
+    runs: Vec<(u32, u32)>,
+}
+
+/// Bits of LSB-first fields, the order u8g2's decoder reads them in.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+}
+
+impl BitWriter {
+    fn unsigned(&mut self, value: u32, bits: u32) {
+        for n in 0..bits {
+            if self.bit == 0 {
+                self.bytes.push(0);
+            }
+            if value & (1 << n) != 0 {
+                *self.bytes.last_mut().expect("pushed above") |= 1 << self.bit;
+            }
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+
+    /// u8g2 stores signed fields offset by half their range.
+    fn signed(&mut self, value: i32, bits: u32) {
+        self.unsigned((value + (1 << (bits - 1))) as u32, bits);
+    }
+}
+
+/// Format every distinct printable character of `charmap` as a u8g2 font
+/// named `name`, read from the `atlas` cells and spaced by the glyph
+/// metrics (scaled by `scale` as the atlas was).
+pub fn format_u8g2(atlas: &Atlas, charmap: &CharMap, scale: u32, name: &str) -> Result<String> {
+    let grid = &atlas.grid;
+    let mut chars: Vec<(char, usize)> = charmap
+        .chars()
+        .iter()
+        .enumerate()
+        .filter(|&(_, ch)| !ch.is_control() && (*ch as u32) < 0xFFFF)
+        .map(|(i, &ch)| (ch, i))
+        .collect();
+    chars.sort_by_key(|&(ch, i)| (ch, i));
+    chars.dedup_by_key(|&mut (ch, _)| ch);
+
+    let mut glyphs = Vec::new();
+    for (ch, index) in chars {
+        let (left, top) = grid.glyph_origin(index);
+        let cell = atlas
+            .image
+            .crop(left, top, grid.glyph_width, grid.glyph_height);
+        let (pen_x, advance) = pen_and_advance(atlas, index, scale);
+        let glyph = match cell.ink_bounds() {
+            Some((x, y, width, height)) => Glyph {
+                code: ch as u32,
+                width,
+                height,
+                x: x as i32 - pen_x,
+                y: atlas.baseline as i32 - (y + height) as i32,
+                advance,
+                runs: runs(&cell, (x, y, width, height)),
+            },
+            None => Glyph {
+                code: ch as u32,
+                width: 0,
+                height: 0,
+                x: 0,
+                y: 0,
+                advance,
+                runs: Vec::new(),
+            },
+        };
+        glyphs.push(glyph);
+    }
+    if glyphs.is_empty() {
+        bail!("Error: the character table has nothing printable for a u8g2 font");
+    }
+
+    // Field widths wide enough for every glyph
+    let inked = || glyphs.iter().filter(|g| g.width > 0);
+    let bits_width = unsigned_bits(glyphs.iter().map(|g| g.width).max().unwrap_or(0));
+    let bits_height = unsigned_bits(glyphs.iter().map(|g| g.height).max().unwrap_or(0));
+    let bits_x = signed_bits(glyphs.iter().map(|g| g.x));
+    let bits_y = signed_bits(glyphs.iter().map(|g| g.y));
+    let bits_advance = signed_bits(glyphs.iter().map(|g| g.advance));
+    // The decoder reads fields a byte's worth at most
+    if [bits_width, bits_height, bits_x, bits_y, bits_advance]
+        .iter()
+        .any(|&bits| bits > 8)
+    {
+        bail!("Error: the glyphs are too large for u8g2's 8-bit glyph fields");
+    }
+    // The font box spans every glyph's ink
+    let box_left = inked().map(|g| g.x).min().unwrap_or(0);
+    let box_bottom = inked().map(|g| g.y).min().unwrap_or(0);
+    let box_right = inked().map(|g| g.x + g.width as i32).max().unwrap_or(0);
+    let box_top = inked().map(|g| g.y + g.height as i32).max().unwrap_or(0);
+
+    // The run-length field widths that give the smallest font
+    let mut best: Option<(Vec<Vec<u8>>, u32, u32)> = None;
+    for bits_0 in 2..=8 {
+        for bits_1 in 2..=7 {
+            let encoded: Vec<Vec<u8>> = glyphs
+                .iter()
+                .map(|g| {
+                    encode_glyph(
+                        g,
+                        [bits_width, bits_height, bits_x, bits_y, bits_advance],
+                        bits_0,
+                        bits_1,
+                    )
+                })
+                .collect();
+            let size: usize = encoded.iter().map(Vec::len).sum();
+            if best
+                .as_ref()
+                .is_none_or(|(b, _, _)| size < b.iter().map(Vec::len).sum())
+            {
+                best = Some((encoded, bits_0, bits_1));
+            }
+        }
+    }
+    let (encoded, bits_0, bits_1) = best.expect("at least one width pair was tried");
+
+    // Glyph records: 8-bit codes first, each `code, size, bits`, then the
+    // rest as `code_hi, code_lo, size, bits`
+    let mut body = Vec::new();
+    let (mut start_upper, mut start_lower) = (None, None);
+    let records = || glyphs.iter().zip(&encoded);
+    for (glyph, bits) in records().filter(|(g, _)| g.code < 256) {
+        if glyph.code >= 'A' as u32 && start_upper.is_none() {
+            start_upper = Some(body.len());
+        }
+        if glyph.code >= 'a' as u32 && start_lower.is_none() {
+            start_lower = Some(body.len());
+        }
+        push_record(&mut body, glyph.code, &[glyph.code as u8], bits)?;
+    }
+    let end_8bit = body.len();
+    body.extend([0, 0]);
+    let start_unicode = body.len();
+    // One lookup block covering every codepoint: skip the 4-byte table
+    body.extend([0, 4, 0xFF, 0xFF]);
+    for (glyph, bits) in records().filter(|(g, _)| g.code >= 256) {
+        let code = (glyph.code as u16).to_be_bytes();
+        push_record(&mut body, glyph.code, &code, bits)?;
+    }
+    body.extend([0, 0]);
+
+    let glyph_at = |code: char| glyphs.iter().find(|g| g.code == code as u32);
+    let top_of = |code: char| glyph_at(code).map_or(0, |g| g.y + g.height as i32);
+    let bottom_of = |code: char| glyph_at(code).map_or(0, |g| g.y);
+    let offset_field = |offset: Option<usize>| -> Result<[u8; 2]> {
+        let offset = offset.unwrap_or(end_8bit);
+        match u16::try_from(offset) {
+            Ok(offset) => Ok(offset.to_be_bytes()),
+            Err(_) => bail!("Error: the u8g2 font's 8-bit glyphs pass 64 KiB"),
+        }
+    };
+    let mut font = vec![
+        glyphs.len().min(255) as u8,
+        0, // proportional glyph boxes
+        bits_0 as u8,
+        bits_1 as u8,
+        bits_width as u8,
+        bits_height as u8,
+        bits_x as u8,
+        bits_y as u8,
+        bits_advance as u8,
+        signed_byte(box_right - box_left, "font box width")?,
+        signed_byte(box_top - box_bottom, "font box height")?,
+        signed_byte(box_left, "font box x")?,
+        signed_byte(box_bottom, "font box y")?,
+        signed_byte(top_of('A'), "ascent of 'A'")?,
+        signed_byte(bottom_of('g'), "descent of 'g'")?,
+        signed_byte(top_of('('), "ascent of '('")?,
+        signed_byte(bottom_of(')'), "descent of ')'")?,
+    ];
+    font.extend(offset_field(start_upper)?);
+    font.extend(offset_field(start_lower)?);
+    font.extend(offset_field(Some(start_unicode))?);
+    debug_assert_eq!(font.len(), HEADER_LEN);
+    font.extend(body);
+
+    let mut out = String::new();
+    writeln!(out, "/* Generated by mycp437generator. Do not edit.").unwrap();
+    writeln!(
+        out,
+        "   {} glyphs, {} bytes. Use with u8g2.setFont({}). */",
+        glyphs.len(),
+        font.len(),
+        name
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "const uint8_t {name}[{}] U8G2_FONT_SECTION(\"{name}\") = {{",
+        font.len()
+    )
+    .unwrap();
+    for line in font.chunks(16) {
+        let bytes: Vec<String> = line.iter().map(|b| format!("0x{:02X},", b)).collect();
+        writeln!(out, "  {}", bytes.join(" ")).unwrap();
+    }
+    writeln!(out, "}};").unwrap();
+    Ok(out)
+}
+
+/// Append a glyph record: `code` bytes, the record's total size, the bits.
+fn push_record(body: &mut Vec<u8>, code: u32, code_bytes: &[u8], bits: &[u8]) -> Result<()> {
+    let size = code_bytes.len() + 1 + bits.len();
+    let Ok(size) = u8::try_from(size) else {
+        bail!(
+            "Error: U+{:04X} encodes to {} bytes; u8g2 glyphs stop at 255",
+            code,
+            size
+        );
+    };
+    body.extend_from_slice(code_bytes);
+    body.push(size);
+    body.extend_from_slice(bits);
+    Ok(())
+}
+
+/// The glyph's box fields, then its runs. Identical consecutive pairs share
+/// one copy, each repeat flagged by a 1 bit and the last by a 0.
+fn encode_glyph(glyph: &Glyph, field_bits: [u32; 5], bits_0: u32, bits_1: u32) -> Vec<u8> {
+    let [bits_width, bits_height, bits_x, bits_y, bits_advance] = field_bits;
+    let mut out = BitWriter::default();
+    out.unsigned(glyph.width, bits_width);
+    out.unsigned(glyph.height, bits_height);
+    out.signed(glyph.x, bits_x);
+    out.signed(glyph.y, bits_y);
+    out.signed(glyph.advance, bits_advance);
+    if glyph.width == 0 {
+        return out.bytes;
+    }
+
+    let (max_0, max_1) = ((1 << bits_0) - 1, (1 << bits_1) - 1);
+    let mut pairs = Vec::new();
+    for &(mut background, mut ink) in &glyph.runs {
+        while background > max_0 {
+            pairs.push((max_0, 0));
+            background -= max_0;
+        }
+        while ink > max_1 {
+            pairs.push((background, max_1));
+            (background, ink) = (0, ink - max_1);
+        }
+        pairs.push((background, ink));
+    }
+    let mut i = 0;
+    while i < pairs.len() {
+        let (a, b) = pairs[i];
+        out.unsigned(a, bits_0);
+        out.unsigned(b, bits_1);
+        i += 1;
+        while i < pairs.len() && pairs[i] == (a, b) {
+            out.unsigned(1, 1);
+            i += 1;
+        }
+        out.unsigned(0, 1);
+    }
+    out.bytes
+}
+
+/// The pixels of `rect` in `cell`, row by row, as alternating runs of
+/// background and ink, each pair starting with background.
+fn runs(cell: &GrayImage, (x, y, width, height): (u32, u32, u32, u32)) -> Vec<(u32, u32)> {
+    let mut runs = Vec::new();
+    let (mut background, mut ink) = (0, 0);
+    for dy in 0..height {
+        for dx in 0..width {
+            if cell.get(x + dx, y + dy) < INK_THRESHOLD {
+                ink += 1;
+            } else {
+                if ink > 0 {
+                    runs.push((background, ink));
+                    (background, ink) = (0, 0);
+                }
+                background += 1;
+            }
+        }
+    }
+    if background > 0 || ink > 0 {
+        runs.push((background, ink));
+    }
+    runs
+}
+
+/// Bits to hold every value up to `max`.
+fn unsigned_bits(max: u32) -> u32 {
+    u32::BITS - max.leading_zeros()
+}
+
+/// Bits to hold every value of `values` in u8g2's offset form, at least 1.
+fn signed_bits(values: impl Iterator<Item = i32>) -> u32 {
+    let (mut low, mut high) = (0, 0);
+    for v in values {
+        (low, high) = (low.min(v), high.max(v));
+    }
+    (1..32)
+        .find(|&bits| -(1 << (bits - 1)) <= low && high < (1 << (bits - 1)))
+        .unwrap_or(32)
+}
+
+fn signed_byte(value: i32, what: &str) -> Result<u8> {
+    match i8::try_from(value) {
+        Ok(v) => Ok(v as u8),
+        Err(_) => bail!("Error: the u8g2 {} of {} doesn't fit a byte", what, value),
+    }
+}
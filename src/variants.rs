@@ -0,0 +1,121 @@
+//! `--with-bold` and `--with-inverse`: further copies of the grid laid out
+//! below or beside the regular one, a constant stride apart, so a shader
+//! reaches a glyph's variant by adding the stride.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use mycp437generator::Atlas;
+use mycp437generator::gray::GrayImage;
+
+/// Where each variant grid goes relative to the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VariantLayout {
+    /// Under it, as more rows
+    Below,
+    /// To its right, as more columns
+    Beside,
+}
+
+/// How the variant grids sit in the texture.
+#[derive(Serialize)]
+pub struct Variants {
+    pub layout: VariantLayout,
+    /// The variants in texture order, `regular` first.
+    pub names: Vec<&'static str>,
+    /// Pixels from a glyph to the same glyph in the next variant.
+    pub stride_x: u32,
+    pub stride_y: u32,
+}
+
+impl Variants {
+    /// Cells across and down all the variants together.
+    pub fn combined_grid(&self, columns: u32, rows: u32) -> (u32, u32) {
+        let count = self.names.len() as u32;
+        match self.layout {
+            VariantLayout::Below => (columns, rows * count),
+            VariantLayout::Beside => (columns * count, rows),
+        }
+    }
+
+    /// The header line a hex dump describes the variants with.
+    pub fn describe(&self, columns: u32, rows: u32) -> String {
+        let (across, down) = self.combined_grid(columns, rows);
+        format!(
+            "{} {}: {}x{} cells in all, each variant +{},+{} pixels from the last",
+            self.names.join(", "),
+            match self.layout {
+                VariantLayout::Below => "stacked below",
+                VariantLayout::Beside => "side by side",
+            },
+            across,
+            down,
+            self.stride_x,
+            self.stride_y
+        )
+    }
+}
+
+/// `atlas`'s image with every cell inverted edge to edge, padding
+/// included, so the background of a reverse-video glyph fills the cell.
+pub fn inverse(atlas: &Atlas) -> GrayImage {
+    let grid = &atlas.grid;
+    let mut image = atlas.image.clone();
+    for i in 0..atlas.glyphs.len() {
+        let (x0, y0) = grid.origin(i);
+        for y in y0..y0 + grid.cell_height() {
+            for x in x0..x0 + grid.cell_width() {
+                image.set(x, y, 255 - image.get(x, y));
+            }
+        }
+    }
+    image
+}
+
+/// Lay the grids of `extras`, each drawn in `atlas`'s grid, out after the
+/// regular one. `atlas` keeps its grid, which addresses the regular
+/// variant; its image becomes the whole texture, padded again to powers
+/// of two with `pot`.
+pub fn append(
+    atlas: &mut Atlas,
+    extras: Vec<(&'static str, GrayImage)>,
+    layout: VariantLayout,
+    pot: bool,
+) -> Variants {
+    let (width, height) = (atlas.content_width, atlas.content_height);
+    let (stride_x, stride_y) = match layout {
+        VariantLayout::Below => (0, height),
+        VariantLayout::Beside => (width, 0),
+    };
+    let count = extras.len() as u32 + 1;
+    let (content_width, content_height) = match layout {
+        VariantLayout::Below => (width, height * count),
+        VariantLayout::Beside => (width * count, height),
+    };
+    let (texture_width, texture_height) = if pot {
+        (
+            content_width.next_power_of_two(),
+            content_height.next_power_of_two(),
+        )
+    } else {
+        (content_width, content_height)
+    };
+    let mut image = GrayImage::new(texture_width, texture_height, 255);
+    image.paste(&atlas.image.crop(0, 0, width, height), 0, 0);
+    let mut names = vec!["regular"];
+    for (i, (name, extra)) in extras.into_iter().enumerate() {
+        let n = i as u32 + 1;
+        image.paste(&extra.crop(0, 0, width, height), stride_x * n, stride_y * n);
+        names.push(name);
+    }
+    atlas.image = image;
+    atlas.content_width = content_width;
+    atlas.content_height = content_height;
+    Variants {
+        layout,
+        names,
+        stride_x,
+        stride_y,
+    }
+}
@@ -0,0 +1,138 @@
+//! Raw VGA ROM font dumps: 256 glyphs of 8-pixel rows, one byte per row,
+//! most significant bit leftmost, with no header at all.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+
+use crate::INK_THRESHOLD;
+use crate::bitmap_font::BitmapFont;
+use crate::failure::{Categorize, Failure};
+use crate::gray::GrayImage;
+use crate::grid::Grid;
+
+/// Glyphs in a dump.
+pub const GLYPHS: usize = 256;
+
+/// Read the ROM dump at `path`, `height` rows per glyph, or the file size
+/// over 256 when `height` is `None`.
+pub fn read_rom(path: &Path, height: Option<u32>) -> Result<BitmapFont> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .failure(Failure::Font)?;
+    parse_rom(&bytes, height)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+        .failure(Failure::Font)
+}
+
+/// Parse a ROM dump of 8x`height` glyphs. The glyphs carry no Unicode
+/// table, so glyph `i` lands at index `i` of the active table.
+pub fn parse_rom(bytes: &[u8], height: Option<u32>) -> Result<BitmapFont> {
+    let height = match height {
+        Some(height) => height as usize,
+        None if !bytes.is_empty() && bytes.len().is_multiple_of(GLYPHS) => bytes.len() / GLYPHS,
+        None => bail!(
+            "{} bytes is not a whole number of rows for {} glyphs; pass --raw-height",
+            bytes.len(),
+            GLYPHS
+        ),
+    };
+    if height == 0 {
+        bail!("--raw-height must be at least 1");
+    }
+    if bytes.len() != GLYPHS * height {
+        bail!(
+            "{} bytes doesn't hold {} glyphs of {} rows (expected {} bytes)",
+            bytes.len(),
+            GLYPHS,
+            height,
+            GLYPHS * height
+        );
+    }
+    let height = u32::try_from(height).context("glyphs are too tall")?;
+    Ok(BitmapFont {
+        width: 8,
+        height,
+        baseline: None,
+        glyphs: bytes
+            .chunks(height as usize)
+            .map(|rows| BitmapFont::unpack_glyph(rows, 8, height))
+            .collect(),
+        unicode: Vec::new(),
+    })
+}
+
+/// Pack the first 256 glyph boxes of `grid` in `image` into a ROM dump with
+/// `row_bytes` bytes per row, glyph after glyph in index order. Glyphs
+/// narrower than the row sit at its left, with the spare low bits zero.
+pub fn encode_rom(image: &GrayImage, grid: &Grid, row_bytes: u32) -> Result<Vec<u8>> {
+    if grid.glyph_width > row_bytes * 8 {
+        bail!(
+            "Error: {}-pixel glyphs don't fit {}-bit ROM rows",
+            grid.glyph_width,
+            row_bytes * 8
+        );
+    }
+    if grid.columns * grid.rows < GLYPHS as u32 {
+        bail!("Error: a ROM dump needs {} glyphs", GLYPHS);
+    }
+    let mut bytes = Vec::with_capacity(GLYPHS * (grid.glyph_height * row_bytes) as usize);
+    for i in 0..GLYPHS {
+        let (x0, y0) = grid.glyph_origin(i);
+        for y in 0..grid.glyph_height {
+            let mut row = vec![0u8; row_bytes as usize];
+            for x in 0..grid.glyph_width {
+                if image.get(x0 + x, y0 + y) < INK_THRESHOLD {
+                    row[x as usize / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            bytes.extend(row);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MissingGlyph;
+    use crate::charmap::CharMap;
+    use crate::codepage::Codepage;
+
+    /// 256 glyphs of `height` rows, each row different from its glyph's
+    /// others and from every other glyph's first row.
+    fn rom(height: usize) -> Vec<u8> {
+        (0..GLYPHS * height)
+            .map(|n| {
+                let (glyph, row) = (n / height, n % height);
+                (glyph * (row + 1) + row * 29) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn an_imported_dump_exports_unchanged() {
+        for height in [8, 14, 16] {
+            let bytes = rom(height);
+            let font = parse_rom(&bytes, None).unwrap();
+            let grid = Grid::new(GLYPHS, 16, false, 8, height as u32);
+            let atlas = font
+                .to_atlas(&CharMap::new(Codepage::Cp437), grid, MissingGlyph::Blank)
+                .unwrap();
+            assert_eq!(encode_rom(&atlas.image, &grid, 1).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn wide_rows_keep_the_glyph_in_the_high_byte() {
+        let bytes = rom(8);
+        let font = parse_rom(&bytes, Some(8)).unwrap();
+        let grid = Grid::new(GLYPHS, 16, false, 8, 8);
+        let atlas = font
+            .to_atlas(&CharMap::new(Codepage::Cp437), grid, MissingGlyph::Blank)
+            .unwrap();
+        let wide = encode_rom(&atlas.image, &grid, 2).unwrap();
+        let narrow: Vec<u8> = wide.chunks(2).map(|row| row[0]).collect();
+        assert_eq!(narrow, bytes);
+        assert!(wide.chunks(2).all(|row| row[1] == 0));
+    }
+}
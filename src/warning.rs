@@ -0,0 +1,182 @@
+//! Warnings as structured events: each one is logged as before and also
+//! collected with a stable code, the cell it concerns, and the numbers
+//! behind it, for `--report` to write out at the end of the run.
+
+use anyhow::{Context, Result};
+use log::warn;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::{Atlas, CellStatus, log_prefix};
+
+/// What a warning is about. The serialized names are stable, for
+/// pipelines to match on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Code {
+    /// A glyph renders as the font's .notdef box
+    GlyphNotdef,
+    /// No printable ASCII glyph has any ink, written anyway
+    BlankAtlas,
+    /// A glyph outside the measure set is wider than the cell
+    GlyphOversized,
+    /// Part of a glyph's ink was cut off at the cell edge
+    GlyphClipped,
+    /// A glyph reaches below the cell
+    GlyphTooTall,
+    /// A glyph over the cell was shrunk by --overflow scale to fit
+    GlyphScaled,
+    /// No font has the glyph, so the cell was left empty
+    GlyphSkipped,
+    /// The cell got the --missing-glyph placeholder
+    GlyphPlaceholder,
+    /// The glyph came from a --fallback-font
+    GlyphFallback,
+    /// The widest glyph at the chosen size is wider than requested
+    SizeOvershoot,
+    /// The widest glyph at the chosen size is narrower than requested
+    SizeUndershoot,
+    /// Glyphs of a bitmap font have no cell in the table
+    FontGlyphsUnused,
+    /// Characters of the table are not in a bitmap font
+    CharsMissing,
+    /// Characters a BMFont file can't hold
+    BmfontCharsDropped,
+    /// A character appears more than once in the table
+    DuplicateChar,
+    /// A --remap file places a cell more than once
+    RemapRepeated,
+    /// A --remap file leaves cells out
+    RemapUnused,
+    /// A --glyph-override image isn't the size of the glyph box
+    OverrideResized,
+    /// The terminal doesn't look like it draws inline graphics
+    TerminalGraphics,
+    /// A deprecated way of passing an option
+    Deprecated,
+}
+
+/// One warning, as `--report` writes it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Warning {
+    pub code: Code,
+    /// The atlas cell, for warnings about one glyph.
+    pub index: Option<usize>,
+    #[serde(rename = "char")]
+    pub ch: Option<char>,
+    pub message: String,
+    /// The numbers behind the message, which vary by code.
+    pub data: serde_json::Value,
+    /// The batch job it came from, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job: Option<String>,
+}
+
+impl Warning {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Warning {
+            code,
+            index: None,
+            ch: None,
+            message: message.into(),
+            data: serde_json::json!({}),
+            job: log_prefix(),
+        }
+    }
+
+    /// About the glyph in cell `index`.
+    pub fn glyph(mut self, index: usize, ch: char) -> Self {
+        self.index = Some(index);
+        self.ch = Some(ch);
+        self
+    }
+
+    pub fn data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+}
+
+/// Warnings collected so far, across threads.
+static COLLECTED: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+/// Log `warning` and collect it.
+pub fn emit(warning: Warning) {
+    warn!("{}", warning.message);
+    record(warning);
+}
+
+/// Log `summary` once for a list of per-glyph warnings, and collect each.
+pub fn emit_all(summary: impl std::fmt::Display, warnings: Vec<Warning>) {
+    warn!("{}", summary);
+    COLLECTED.lock().unwrap().extend(warnings);
+}
+
+/// Collect `warning` without logging it, for what the logs only show at
+/// a lower level or in the coverage summary.
+pub fn record(warning: Warning) {
+    COLLECTED.lock().unwrap().push(warning);
+}
+
+/// Collect what `atlas` shows only as cell states and sizing: empty and
+/// placeholder cells, fallback glyphs, and a widest glyph off the
+/// requested width. Clipping is collected as it happens.
+pub fn record_cells(atlas: &Atlas) {
+    for (i, glyph) in atlas.glyphs.iter().enumerate() {
+        let (code, what) = match glyph.status {
+            CellStatus::Skipped => (Code::GlyphSkipped, "has no glyph and was left empty"),
+            CellStatus::Placeholder => (
+                Code::GlyphPlaceholder,
+                "has no glyph and was given the --missing-glyph placeholder",
+            ),
+            _ => continue,
+        };
+        record(
+            Warning::new(code, format!("'{}' (index {}) {}", glyph.ch, i, what)).glyph(i, glyph.ch),
+        );
+    }
+    for (i, glyph) in atlas.glyphs.iter().enumerate().filter(|(_, g)| g.fallback) {
+        record(
+            Warning::new(
+                Code::GlyphFallback,
+                format!("'{}' (index {}) comes from a --fallback-font", glyph.ch, i),
+            )
+            .glyph(i, glyph.ch),
+        );
+    }
+    if let Some((widest, requested)) = atlas.width_fit
+        && widest != requested
+    {
+        let code = if widest > requested {
+            Code::SizeOvershoot
+        } else {
+            Code::SizeUndershoot
+        };
+        record(
+            Warning::new(
+                code,
+                format!(
+                    "the widest glyph is {}px, not the requested {}px",
+                    widest, requested
+                ),
+            )
+            .data(serde_json::json!({
+                "widest": widest,
+                "requested": requested,
+                "font_size": atlas.font_size,
+            })),
+        );
+    }
+}
+
+/// Take the warnings collected so far.
+pub fn take() -> Vec<Warning> {
+    std::mem::take(&mut *COLLECTED.lock().unwrap())
+}
+
+/// Write `warnings` to `path` as a JSON array.
+pub fn write_report(path: &Path, warnings: &[Warning]) -> Result<()> {
+    let json = serde_json::to_string_pretty(warnings).context("Failed to serialize --report")?;
+    std::fs::write(path, json + "\n").with_context(|| format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,78 @@
+//! Modification-time polling for `--watch`. Fonts and configs are small and
+//! few, so a handful of `stat` calls a second costs nothing and needs no
+//! platform notification API.
+
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const POLL: Duration = Duration::from_millis(250);
+/// How long the files must stay untouched before a change is acted on.
+const SETTLE: Duration = Duration::from_millis(500);
+
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Watcher {
+    pub fn new(paths: Vec<PathBuf>) -> Watcher {
+        Watcher {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let stamp = modified(&path);
+                    (path, stamp)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Refresh the stamps; true if any file changed, appeared, or vanished.
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+        for (path, stamp) in &mut self.files {
+            let now = modified(path);
+            if now != *stamp {
+                *stamp = now;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Block until a watched file changes, then until the writes settle, so
+    /// an editor saving in several steps triggers a single regeneration.
+    pub fn wait(&mut self) {
+        while !self.poll() {
+            sleep(POLL);
+        }
+        let mut quiet_since = Instant::now();
+        while quiet_since.elapsed() < SETTLE {
+            sleep(POLL);
+            if self.poll() {
+                quiet_since = Instant::now();
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Wall-clock time of day as `HH:MM:SS` UTC, for log lines.
+pub fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600 % 24,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
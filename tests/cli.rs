@@ -0,0 +1,628 @@
+//! The command line, run the way build scripts run it.
+
+mod common;
+
+use assert_cmd::Command;
+use common::{MONO, fixture};
+use mycp437generator::hex_dump::parse_hex_dump;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+
+fn cli() -> Command {
+    Command::cargo_bin("mycp437generator").unwrap()
+}
+
+/// Width and height of the PNG at `path`, from its IHDR chunk.
+fn png_size(path: &Path) -> (u32, u32) {
+    let bytes = std::fs::read(path).unwrap();
+    assert!(
+        bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "{} isn't a PNG",
+        path.display()
+    );
+    let field = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+    (field(16), field(20))
+}
+
+/// Dimensions, color type, and decoded pixels of the PNG at `path`.
+fn png_pixels(path: &Path) -> (u32, u32, png::ColorType, Vec<u8>) {
+    let file = std::fs::File::open(path)
+        .unwrap_or_else(|e| panic!("Failed to open {}: {}", path.display(), e));
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    pixels.truncate(info.buffer_size());
+    (info.width, info.height, info.color_type, pixels)
+}
+
+/// Names of the files in `dir`.
+fn files(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn flags_write_the_atlas_to_output() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("atlas.png");
+    cli()
+        .arg("--font-path")
+        .arg(fixture(MONO))
+        .args(["--font-width", "8", "--output"])
+        .arg(&output)
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    assert_eq!(png_size(&output).0, 16 * 8);
+    assert_eq!(files(dir.path()), ["atlas.png"]);
+}
+
+#[test]
+fn hex_dump_alone_writes_only_to_stdout() {
+    let dir = TempDir::new().unwrap();
+    let assert = cli()
+        .arg("--font-path")
+        .arg(fixture(MONO))
+        .args(["--font-width", "8", "--hex-dump"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let dump = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(
+        dump.starts_with("// Pixel dimensions: 128 wide x "),
+        "{}",
+        dump
+    );
+    assert!(dump.contains("//!LONGVAR uint[] font_data_"));
+    assert!(dump.contains("_width (8)"));
+    assert!(files(dir.path()).is_empty());
+}
+
+#[test]
+fn hex_dump_leaves_a_following_font_positional_alone() {
+    let dir = TempDir::new().unwrap();
+    let assert = cli()
+        .arg("--hex-dump")
+        .arg(fixture(MONO))
+        .arg("8")
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let dump = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(
+        dump.contains("#define font_DejaVuSansMono_width (8)"),
+        "{}",
+        dump
+    );
+    assert!(files(dir.path()).is_empty());
+}
+
+#[test]
+fn hex_dump_takes_a_deprecated_name_after_an_equals_sign() {
+    let dir = TempDir::new().unwrap();
+    let assert = cli()
+        .arg(fixture(MONO))
+        .args(["8", "--hex-dump=tiny"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let dump = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(dump.contains("#define font_tiny_width (8)"), "{}", dump);
+    assert!(stderr(&assert).contains("--hex-dump=tiny is deprecated"));
+}
+
+#[test]
+fn hex_dump_takes_a_deprecated_name_after_a_space() {
+    let assert = cli()
+        .arg("--font-path")
+        .arg(fixture(MONO))
+        .args([
+            "--font-width",
+            "8",
+            "--hex-dump",
+            "myname",
+            "--print-config",
+        ])
+        .assert()
+        .success();
+    let config = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(config.contains("hex_dump = \"myname\""), "{}", config);
+    assert!(
+        !config.lines().any(|line| line.starts_with("font = ")),
+        "{}",
+        config
+    );
+
+    let dir = TempDir::new().unwrap();
+    let assert = cli()
+        .arg("--font-path")
+        .arg(fixture(MONO))
+        .args(["--font-width", "8", "--hex-dump", "myname"])
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let dump = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert!(dump.contains("#define font_myname_width (8)"), "{}", dump);
+    assert!(stderr(&assert).contains("--hex-dump=myname is deprecated"));
+    assert!(files(dir.path()).is_empty());
+}
+
+#[test]
+fn positional_font_and_width_write_the_default_name() {
+    let dir = TempDir::new().unwrap();
+    let assert = cli()
+        .arg(fixture(MONO))
+        .arg("8")
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let written = files(dir.path());
+    assert_eq!(written.len(), 1);
+    let name = &written[0];
+    assert!(
+        name.starts_with("DejaVuSansMono_8x") && name.ends_with(".png"),
+        "{}",
+        name
+    );
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    assert_eq!(stdout.trim(), format!("Font atlas saved to {}", name));
+    assert_eq!(png_size(&dir.path().join(name)).0, 16 * 8);
+}
+
+#[test]
+fn flags_win_over_positionals() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("atlas.png");
+    cli()
+        .arg(fixture(MONO))
+        .arg("8")
+        .args(["--font-width", "12", "--output"])
+        .arg(&output)
+        .assert()
+        .success();
+    assert_eq!(png_size(&output).0, 16 * 12);
+}
+
+/// An atlas of the fixture `font` with 8px cells, written into `dir`
+/// under its default name.
+fn atlas(font: &str, dir: &TempDir) -> Command {
+    let mut cmd = cli();
+    cmd.arg("--font-path")
+        .arg(fixture(font))
+        .args(["--font-width", "8"])
+        .current_dir(dir.path());
+    cmd
+}
+
+fn stderr(assert: &assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+#[test]
+fn exit_2_for_invalid_arguments() {
+    let dir = TempDir::new().unwrap();
+    cli().args(["--font-width", "eight"]).assert().code(2);
+    atlas(MONO, &dir).args(["--padding", "4"]).assert().code(2);
+}
+
+#[test]
+fn exit_3_for_a_missing_font() {
+    let dir = TempDir::new().unwrap();
+    atlas("NoSuchFont.ttf", &dir).assert().code(3);
+}
+
+#[test]
+fn exit_4_when_no_size_gives_the_width() {
+    let dir = TempDir::new().unwrap();
+    // No glyph of Blank.ttf has ink to measure
+    let assert = atlas("Blank.ttf", &dir).assert().code(4);
+    assert!(stderr(&assert).contains("has any width"));
+    // 16 columns of 2000px are over the atlas size limit
+    atlas(MONO, &dir)
+        .args(["--font-width", "2000"])
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn exit_5_for_a_blank_atlas() {
+    let dir = TempDir::new().unwrap();
+    let assert = atlas("Blank.ttf", &dir)
+        .args(["--measure", "advance"])
+        .assert()
+        .code(5);
+    assert!(stderr(&assert).contains("--allow-blank-atlas"));
+}
+
+/// The blank-atlas gate fails a font that renders nothing but background,
+/// and --allow-blank-atlas or a looser --max-blank-fraction lets it through.
+#[test]
+fn blank_renders_trip_the_gate_unless_allowed() {
+    let dir = TempDir::new().unwrap();
+    let assert = atlas("Blank.ttf", &dir)
+        .args(["--measure", "advance"])
+        .assert()
+        .code(5);
+    assert!(stderr(&assert).contains("printable ASCII glyphs rendered blank (100% > 50%)"));
+    assert!(files(dir.path()).is_empty());
+
+    let assert = atlas("Blank.ttf", &dir)
+        .args(["--measure", "advance", "--allow-blank-atlas"])
+        .assert()
+        .success();
+    assert!(stderr(&assert).contains("continuing due to --allow-blank-atlas"));
+    assert_eq!(files(dir.path()).len(), 1);
+
+    atlas("Blank.ttf", &dir)
+        .args([
+            "--measure",
+            "advance",
+            "--max-blank-fraction",
+            "1",
+            "--force",
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn exit_6_for_an_unwritable_output() {
+    let dir = TempDir::new().unwrap();
+    atlas(MONO, &dir)
+        .arg("--output")
+        .arg(dir.path().join("missing/atlas.png"))
+        .assert()
+        .code(6);
+}
+
+#[test]
+fn exit_7_for_strict_and_deny_warnings() {
+    let dir = TempDir::new().unwrap();
+    // A 4px tall cell clips most glyphs
+    atlas(MONO, &dir)
+        .args(["--cell-height", "4", "--strict", "--force"])
+        .assert()
+        .code(7);
+    atlas(MONO, &dir)
+        .args(["--cell-height", "4", "--deny-warnings", "--force"])
+        .assert()
+        .code(7);
+}
+
+/// Build the --rust-embedded file at `path` into a program that prints
+/// what its `decompress()` returns, and run it.
+fn decompressed(path: &Path) -> Vec<u32> {
+    let dir = path.parent().unwrap();
+    std::fs::write(
+        dir.join("main.rs"),
+        "mod atlas {\n    include!(\"atlas.rs\");\n}\n\n\
+         fn main() {\n    for word in atlas::decompress() {\n        println!(\"{}\", word);\n    }\n}\n",
+    )
+    .unwrap();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let status = std::process::Command::new(rustc)
+        .args([
+            "--edition",
+            "2021",
+            "-A",
+            "warnings",
+            "main.rs",
+            "-o",
+            "decompress",
+        ])
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} didn't build", path.display());
+    let output = std::process::Command::new(dir.join("decompress"))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| line.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn rust_embedded_decompresses_to_the_packed_words() {
+    for font in [MONO, "DejaVuSansMono-Bold.ttf"] {
+        for width in ["8", "12", "16"] {
+            let dir = TempDir::new().unwrap();
+            let source = dir.path().join("atlas.rs");
+            let assert = cli()
+                .arg("--font-path")
+                .arg(fixture(font))
+                .args(["--font-width", width, "--hex-dump", "--rust-embedded"])
+                .arg(&source)
+                .current_dir(dir.path())
+                .assert()
+                .success();
+            let dump = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+            let packed = parse_hex_dump(&dump).unwrap().bitmap.words;
+            assert_eq!(decompressed(&source), packed, "{} at {}px", font, width);
+        }
+    }
+}
+
+/// A PSF font's glyph block is the MSB-first rows --output-raw writes, so
+/// putting the font's header and Unicode table back around the raw export
+/// rebuilds the file.
+#[test]
+fn psf_fonts_round_trip_through_the_atlas() {
+    for (name, header, height) in [("Tiny8x8.psf", 4, 8), ("Tiny8x16.psfu", 32, 16)] {
+        let dir = TempDir::new().unwrap();
+        let raw = dir.path().join("font.raw");
+        cli()
+            .arg("--input-psf")
+            .arg(fixture(name))
+            .arg("--output-raw")
+            .arg(&raw)
+            .arg("--output")
+            .arg(dir.path().join("atlas.png"))
+            .current_dir(dir.path())
+            .assert()
+            .success();
+        let psf = std::fs::read(fixture(name)).unwrap();
+        let raw = std::fs::read(&raw).unwrap();
+        assert_eq!(raw.len(), 256 * height, "{}", name);
+        let rebuilt = [&psf[..header], &raw, &psf[header + raw.len()..]].concat();
+        assert!(rebuilt == psf, "{} changed on the way through", name);
+    }
+}
+
+/// A raw ROM dump the tool exported imports and exports again unchanged,
+/// including glyphs narrower than the 8-bit rows.
+#[test]
+fn raw_dumps_round_trip_through_the_atlas() {
+    for width in ["6", "8"] {
+        let dir = TempDir::new().unwrap();
+        let (first, second) = (dir.path().join("first.raw"), dir.path().join("second.raw"));
+        cli()
+            .arg("--font-path")
+            .arg(fixture(MONO))
+            .args(["--font-width", width, "--output-raw"])
+            .arg(&first)
+            .arg("--output")
+            .arg(dir.path().join("font.png"))
+            .current_dir(dir.path())
+            .assert()
+            .success();
+        cli()
+            .arg("--input-raw")
+            .arg(&first)
+            .arg("--output-raw")
+            .arg(&second)
+            .arg("--output")
+            .arg(dir.path().join("raw.png"))
+            .current_dir(dir.path())
+            .assert()
+            .success();
+        let (first, second) = (
+            std::fs::read(first).unwrap(),
+            std::fs::read(second).unwrap(),
+        );
+        assert_eq!(first.len() % 256, 0);
+        assert!(
+            first == second,
+            "{}px glyphs changed on the way through",
+            width
+        );
+    }
+}
+
+/// The cell height comes from measuring the whole table as one string,
+/// where it used to come from rendering that string. The atlases under
+/// tests/fixtures/golden were written before that change, so these must
+/// match them pixel for pixel.
+#[test]
+fn atlases_match_the_goldens_from_before_the_height_was_measured() {
+    let dir = TempDir::new().unwrap();
+    for font_name in [MONO, "DejaVuSansMono-Bold.ttf"] {
+        for width in ["8", "12", "16"] {
+            let stem = font_name.trim_end_matches(".ttf");
+            let golden = fixture(&format!("golden/{}-{}.png", stem, width));
+            assert!(
+                golden.exists(),
+                "{} is missing; record it as tests/fixtures/README.md describes",
+                golden.display()
+            );
+            let output = dir.path().join(format!("{}-{}.png", stem, width));
+            cli()
+                .arg("--font-path")
+                .arg(fixture(font_name))
+                .args(["--font-width", width, "--output"])
+                .arg(&output)
+                .current_dir(dir.path())
+                .assert()
+                .success();
+            assert!(
+                png_pixels(&output) == png_pixels(&golden),
+                "{} at {}px differs from {}",
+                font_name,
+                width,
+                golden.display()
+            );
+        }
+    }
+}
+
+/// A hex dump rebuilt into a PNG and dumped again comes out as it went in.
+#[test]
+fn hex_dumps_round_trip_through_a_png() {
+    let dir = TempDir::new().unwrap();
+    let dump = |args: &mut Command| {
+        let assert = args
+            .args(["--hex-dump", "--name", "mono", "--no-provenance"])
+            .current_dir(dir.path())
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+    };
+    let first = dump(
+        cli()
+            .arg("--font-path")
+            .arg(fixture(MONO))
+            .args(["--font-width", "8"]),
+    );
+    let first_path = dir.path().join("first.txt");
+    std::fs::write(&first_path, &first).unwrap();
+
+    let png = dir.path().join("rebuilt.png");
+    cli()
+        .arg("--from-hex")
+        .arg(&first_path)
+        .arg("--output")
+        .arg(&png)
+        .current_dir(dir.path())
+        .assert()
+        .success();
+
+    let grid = parse_hex_dump(&first).unwrap().grid;
+    let second = dump(
+        cli()
+            .arg("--input-atlas")
+            .arg(&png)
+            .arg("--font-width")
+            .arg(grid.cell_width().to_string())
+            .arg("--cell-height")
+            .arg(grid.cell_height().to_string())
+            .arg("--padding")
+            .arg(grid.padding.to_string())
+            .arg("--gutter")
+            .arg(grid.gutter.to_string()),
+    );
+    assert_eq!(second, first);
+}
+
+/// SDL_ttf and ab_glyph antialias differently, but a font drawn in whole
+/// pixels at its pixel size leaves them nothing to disagree on, so their
+/// dumps of it match to within a few stray pixels.
+#[cfg(feature = "backend-rust")]
+#[test]
+fn backends_dump_a_chunky_font_alike() {
+    let dump = |backend: &str| {
+        let assert = cli()
+            .arg("--font-path")
+            .arg(fixture("Chunky.ttf"))
+            .args(["--font-width", "8", "--backend", backend])
+            .args(["--hex-dump", "--name", "chunky", "--no-provenance"])
+            .assert()
+            .success();
+        let text = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        parse_hex_dump(&text).unwrap().bitmap
+    };
+    let (sdl, rust) = (dump("sdl"), dump("rust"));
+    assert_eq!((sdl.width, sdl.height), (rust.width, rust.height));
+    let pixels = || (0..sdl.height).flat_map(|y| (0..sdl.width).map(move |x| (x, y)));
+    let ink = pixels().filter(|&(x, y)| sdl.get(x, y)).count();
+    let differing = pixels()
+        .filter(|&(x, y)| sdl.get(x, y) != rust.get(x, y))
+        .count();
+    assert!(ink > 0);
+    assert!(
+        differing * 100 <= ink,
+        "{} of {} ink pixels differ between the backends",
+        differing,
+        ink
+    );
+}
+
+/// 'A' from the IBM PC's 8x8 BIOS font, one byte per row.
+const IBM_A: [u8; 8] = [0x30, 0x78, 0xCC, 0xCC, 0xFC, 0xCC, 0xCC, 0x00];
+
+/// A raw ROM in `dir` holding only the IBM 'A', at 0x41.
+fn ibm_a_rom(dir: &TempDir) -> (PathBuf, Vec<u8>) {
+    let mut rom = vec![0u8; 256 * 8];
+    rom[0x41 * 8..0x42 * 8].copy_from_slice(&IBM_A);
+    let path = dir.path().join("ibm-a.rom");
+    std::fs::write(&path, &rom).unwrap();
+    (path, rom)
+}
+
+/// NES CHR tiles are plane 0 then plane 1, eight rows each, leftmost pixel
+/// in the top bit; a glyph of color 1 leaves plane 1 empty. This is what
+/// NES tile editors write for the IBM 'A'.
+#[test]
+fn chr_tiles_encode_a_known_a() {
+    let dir = TempDir::new().unwrap();
+    let (rom_path, _) = ibm_a_rom(&dir);
+    let chr = dir.path().join("font.chr");
+    cli()
+        .arg("--input-raw")
+        .arg(&rom_path)
+        .arg("--output-chr")
+        .arg(&chr)
+        .arg("--output")
+        .arg(dir.path().join("font.png"))
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let chr = std::fs::read(&chr).unwrap();
+    assert_eq!(chr.len(), 256 * 16);
+    let expected: Vec<u8> = IBM_A.iter().copied().chain([0; 8]).collect();
+    assert_eq!(chr[0x41 * 16..0x42 * 16], expected[..]);
+    // Every other tile is empty
+    assert!(
+        chr[..0x41 * 16]
+            .iter()
+            .chain(&chr[0x42 * 16..])
+            .all(|&b| b == 0)
+    );
+}
+
+/// The NASM source --emit-asm writes has the equates, the label, and a
+/// `db` line of eight rows per glyph; where nasm is installed it assembles
+/// back to the ROM it came from.
+#[test]
+fn nasm_source_holds_every_glyph_row() {
+    let dir = TempDir::new().unwrap();
+    let (rom_path, rom) = ibm_a_rom(&dir);
+    let asm = dir.path().join("font.asm");
+    cli()
+        .arg("--input-raw")
+        .arg(&rom_path)
+        .args(["--emit-asm", "nasm", "--name", "font", "--asm-output"])
+        .arg(&asm)
+        .arg("--output")
+        .arg(dir.path().join("font.png"))
+        .current_dir(dir.path())
+        .assert()
+        .success();
+    let source = std::fs::read_to_string(&asm).unwrap();
+    let mut lines = source.lines().filter(|line| !line.starts_with(';'));
+    assert_eq!(lines.next(), Some("font_width equ 8"));
+    assert_eq!(lines.next(), Some("font_height equ 8"));
+    assert_eq!(lines.next(), Some("font_glyphs equ 256"));
+    assert_eq!(lines.next(), Some(""));
+    assert_eq!(lines.next(), Some("font:"));
+    let rows: Vec<Vec<u8>> = lines
+        .map(|line| {
+            let data = line.strip_prefix("    db ").expect("a db line");
+            let (values, _) = data.split_once(" ; ").expect("a comment");
+            values
+                .split(", ")
+                .map(|v| u8::from_str_radix(v.strip_prefix("0x").unwrap(), 16).unwrap())
+                .collect()
+        })
+        .collect();
+    assert_eq!(rows.len(), 256);
+    assert!(rows.iter().all(|glyph| glyph.len() == 8));
+    assert_eq!(rows.concat(), rom);
+
+    let Ok(status) = std::process::Command::new("nasm")
+        .args(["-f", "bin", "font.asm", "-o", "font.bin"])
+        .current_dir(dir.path())
+        .status()
+    else {
+        eprintln!("nasm isn't installed; checked the source's structure only");
+        return;
+    };
+    assert!(status.success(), "nasm rejected the source");
+    assert_eq!(std::fs::read(dir.path().join("font.bin")).unwrap(), rom);
+}
@@ -0,0 +1,52 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use mycp437generator::charmap::CharMap;
+use mycp437generator::codepage::Codepage;
+use mycp437generator::gray::GrayImage;
+use mycp437generator::{Atlas, AtlasConfig};
+
+/// The monospaced font most tests render.
+pub const MONO: &str = "DejaVuSansMono.ttf";
+
+/// Path of `name` in tests/fixtures.
+pub fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// A CP437 atlas of `MONO` with `width` px cells and every other option
+/// at its default.
+pub fn config(width: u32) -> AtlasConfig {
+    AtlasConfig::new(fixture(MONO), width, CharMap::new(Codepage::Cp437))
+}
+
+/// The cell holding `ch`.
+pub fn index(atlas: &Atlas, ch: char) -> usize {
+    atlas
+        .glyphs
+        .iter()
+        .position(|g| g.ch == ch)
+        .unwrap_or_else(|| panic!("'{}' has no cell", ch))
+}
+
+/// The glyph box of cell `index`.
+pub fn glyph(atlas: &Atlas, index: usize) -> GrayImage {
+    let (x, y) = atlas.grid.glyph_origin(index);
+    atlas
+        .image
+        .crop(x, y, atlas.grid.glyph_width, atlas.grid.glyph_height)
+}
+
+/// Left, top, right and bottom of the ink in the glyph box of `ch`, the
+/// last two exclusive.
+pub fn ink(atlas: &Atlas, ch: char) -> (u32, u32, u32, u32) {
+    let (x, y, w, h) = glyph(atlas, index(atlas, ch))
+        .ink_bounds()
+        .unwrap_or_else(|| panic!("'{}' has no ink", ch));
+    (x, y, x + w, y + h)
+}
@@ -0,0 +1,262 @@
+//! Atlases rendered through the library from the fixture fonts.
+//!
+//! SDL may only be initialized from one thread per process, and the
+//! default test harness runs each test on a thread of its own, so these
+//! run one after another on the main thread instead, sharing one
+//! `Generator`; tests of a generator's own lifetime run after it is
+//! dropped. Pass a name, or part of one, to run only the matching tests.
+
+mod common;
+
+use common::{MONO, config, fixture, glyph, index, ink};
+use mycp437generator::{Atlas, CellStatus, Generator, HAlign, INK_THRESHOLD, generate_atlas};
+use sdl3::pixels::Color;
+
+type Test = fn(&Generator);
+
+/// Descenders hang below the shared baseline row without being cut off,
+/// and '_' sits below it.
+fn descenders_sit_below_the_baseline(generator: &Generator) {
+    let atlas = generator.generate(&config(16)).unwrap();
+    let g = index(&atlas, 'g');
+    assert_eq!(atlas.glyphs[g].status, CellStatus::Ok);
+    let (_, _, _, g_bottom) = ink(&atlas, 'g');
+    assert!(
+        g_bottom > atlas.baseline,
+        "'g' ends at row {}, above the baseline at {}",
+        g_bottom,
+        atlas.baseline
+    );
+    let (_, underscore_top, _, _) = ink(&atlas, '_');
+    assert!(
+        underscore_top >= atlas.baseline,
+        "'_' starts at row {}, above the baseline at {}",
+        underscore_top,
+        atlas.baseline
+    );
+
+    // A cell that ends where the 'g' does keeps its whole descender
+    let mut tight = config(16);
+    tight.cell_height = Some(g_bottom);
+    tight.baseline_row = Some(atlas.baseline);
+    let atlas = generator.generate(&tight).unwrap();
+    assert_eq!(atlas.grid.glyph_height, g_bottom);
+    assert_eq!(atlas.glyphs[g].status, CellStatus::Ok);
+    assert_eq!(
+        ink(&atlas, 'g').3,
+        g_bottom,
+        "'g' has no ink in its bottom row"
+    );
+}
+
+/// Decoding coverage with a gamma over 1 darkens antialiased edges before
+/// the ink threshold, so every glyph keeps at least the ink it had.
+fn gamma_thickens_thresholded_glyphs(generator: &Generator) {
+    let before = generator.generate(&config(8)).unwrap();
+    let mut corrected = config(8);
+    corrected.gamma = 2.2;
+    let after = generator.generate(&corrected).unwrap();
+    let ink = |atlas: &Atlas, i: usize| {
+        let glyph = glyph(atlas, i);
+        (0..glyph.height)
+            .flat_map(|y| (0..glyph.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| glyph.get(x, y) < INK_THRESHOLD)
+            .count()
+    };
+    let mut thicker = 0;
+    for i in 0..before.glyphs.len() {
+        let (old, new) = (ink(&before, i), ink(&after, i));
+        assert!(new >= old, "cell {} lost ink: {} -> {}", i, old, new);
+        thicker += usize::from(new > old);
+    }
+    assert!(thicker > 0, "no glyph gained any ink");
+}
+
+/// With --fix-blocks the five block cells fill exactly their share of the
+/// glyph box, corner to corner, at even and odd sizes.
+fn fixed_blocks_reach_their_corners(generator: &Generator) {
+    // Top-left, top-right, bottom-left, bottom-right
+    let blocks = [
+        ('█', 219, [true, true, true, true]),
+        ('▄', 220, [false, false, true, true]),
+        ('▌', 221, [true, false, true, false]),
+        ('▐', 222, [false, true, false, true]),
+        ('▀', 223, [true, true, false, false]),
+    ];
+    for width in [8, 9] {
+        let mut fixed = config(width);
+        fixed.fix_blocks = true;
+        let atlas = generator.generate(&fixed).unwrap();
+        for (ch, expected_index, corners) in blocks {
+            let i = index(&atlas, ch);
+            assert_eq!(i, expected_index);
+            let glyph = glyph(&atlas, i);
+            let (right, bottom) = (glyph.width - 1, glyph.height - 1);
+            let inked = [(0, 0), (right, 0), (0, bottom), (right, bottom)]
+                .map(|(x, y)| glyph.get(x, y) < INK_THRESHOLD);
+            assert_eq!(inked, corners, "'{}' at width {}", ch, width);
+        }
+    }
+}
+
+/// Under `HAlign::Bearing` the vertical stroke of '│' lands in the same
+/// columns as the one through '┼', so the two join.
+fn bearing_lines_up_vertical_strokes(generator: &Generator) {
+    for width in [8, 16] {
+        let mut bearing = config(width);
+        bearing.halign = HAlign::Bearing;
+        let atlas = generator.generate(&bearing).unwrap();
+        let (left, _, right, _) = ink(&atlas, '│');
+        // The top row of '┼' is above its crossbar, so only the stroke
+        let cross = glyph(&atlas, index(&atlas, '┼'));
+        let (_, top, _, _) = ink(&atlas, '┼');
+        let stroke: Vec<u32> = (0..cross.width)
+            .filter(|&x| cross.get(x, top) < INK_THRESHOLD)
+            .collect();
+        assert_eq!(stroke, (left..right).collect::<Vec<_>>(), "width {}", width);
+    }
+}
+
+/// The cell height comes from measuring the whole table as one string,
+/// where it used to come from rendering that string. Every placement
+/// follows from that height, so while the two agree the atlas is the same
+/// pixel for pixel as the rendered string made it.
+fn measured_height_matches_the_rendered_string(generator: &Generator) {
+    let ttf = sdl3::ttf::init().unwrap();
+    for font_name in [MONO, "DejaVuSansMono-Bold.ttf"] {
+        for width in [8, 12, 16] {
+            let mut config = config(width);
+            config.font_path = fixture(font_name);
+            let atlas = generator.generate(&config).unwrap();
+            let mut font = ttf.load_font(fixture(font_name), atlas.font_size).unwrap();
+            font.set_hinting(sdl3::ttf::Hinting::NONE);
+            let all: String = config.charmap.chars().iter().collect();
+            let rendered = font
+                .render(&all)
+                .shaded(Color::RGB(0, 0, 0), Color::RGB(255, 255, 255))
+                .unwrap();
+            let (_, measured) = font.size_of(&all).unwrap();
+            assert_eq!(measured, rendered.height(), "{} at {}px", font_name, width);
+            assert_eq!(atlas.grid.glyph_height, rendered.height());
+        }
+    }
+}
+
+/// A generator renders the same atlas every time it is asked.
+fn generating_twice_gives_the_same_atlas(generator: &Generator) {
+    let first = generator.generate(&config(8)).unwrap();
+    let second = generator.generate(&config(8)).unwrap();
+    assert_eq!(first.image, second.image);
+    assert_eq!(first.font_size, second.font_size);
+    assert_eq!(first.baseline, second.baseline);
+    assert_eq!(first.status(), second.status());
+}
+
+/// Dropping a generator shuts SDL down without stopping `generate_atlas`
+/// from starting it again, and atlases outlive the generator that made
+/// them.
+fn generate_atlas_after_a_generator_is_dropped() {
+    let before = {
+        let generator = Generator::new().unwrap();
+        generator.generate(&config(8)).unwrap()
+    };
+    let after = generate_atlas(&config(8)).unwrap();
+    assert_eq!(before.image, after.image);
+}
+
+/// Resident set size of this process, from `/proc/self/status`.
+#[cfg(feature = "soak")]
+fn resident_memory_kib() -> u64 {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse().ok())
+        .unwrap()
+}
+
+/// Two hundred generations in a row on one generator leave resident
+/// memory where the first few put it, so a long `--watch` session or a
+/// big batch doesn't grow without bound. Long-running, so only with
+/// `--features soak`.
+#[cfg(feature = "soak")]
+fn generations_hold_resident_memory_steady(generator: &Generator) {
+    const GENERATIONS: usize = 200;
+    const WARM_UP: usize = 10;
+    const ALLOWED_GROWTH_KIB: u64 = 32 * 1024;
+    for _ in 0..WARM_UP {
+        generator.generate(&config(16)).unwrap();
+    }
+    let before = resident_memory_kib();
+    for _ in WARM_UP..GENERATIONS {
+        generator.generate(&config(16)).unwrap();
+    }
+    let after = resident_memory_kib();
+    let growth = after.saturating_sub(before);
+    println!("  resident memory {} KiB -> {} KiB", before, after);
+    assert!(
+        growth < ALLOWED_GROWTH_KIB,
+        "resident memory grew by {} KiB over {} generations",
+        growth,
+        GENERATIONS - WARM_UP
+    );
+}
+
+fn main() {
+    let tests: &[(&str, Test)] = &[
+        (
+            "descenders_sit_below_the_baseline",
+            descenders_sit_below_the_baseline,
+        ),
+        (
+            "gamma_thickens_thresholded_glyphs",
+            gamma_thickens_thresholded_glyphs,
+        ),
+        (
+            "fixed_blocks_reach_their_corners",
+            fixed_blocks_reach_their_corners,
+        ),
+        (
+            "bearing_lines_up_vertical_strokes",
+            bearing_lines_up_vertical_strokes,
+        ),
+        (
+            "measured_height_matches_the_rendered_string",
+            measured_height_matches_the_rendered_string,
+        ),
+        (
+            "generating_twice_gives_the_same_atlas",
+            generating_twice_gives_the_same_atlas,
+        ),
+        #[cfg(feature = "soak")]
+        (
+            "generations_hold_resident_memory_steady",
+            generations_hold_resident_memory_steady,
+        ),
+    ];
+    let alone: &[(&str, fn())] = &[(
+        "generate_atlas_after_a_generator_is_dropped",
+        generate_atlas_after_a_generator_is_dropped,
+    )];
+    let filters: Vec<String> = std::env::args()
+        .skip(1)
+        .filter(|a| !a.starts_with('-'))
+        .collect();
+    let selected =
+        |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str()));
+    {
+        let generator = Generator::new().unwrap();
+        for (name, test) in tests {
+            if selected(name) {
+                println!("test {} ...", name);
+                test(&generator);
+            }
+        }
+    }
+    for (name, test) in alone {
+        if selected(name) {
+            println!("test {} ...", name);
+            test();
+        }
+    }
+}